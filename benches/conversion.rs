@@ -0,0 +1,206 @@
+//! Throughput benchmarks for document conversion, schema inference and inserts
+//!
+//! Run with `cargo bench`. Each document shape stresses a different part of
+//! the converter, so optimizations can be measured against a stable baseline.
+
+use bson::{doc, spec::BinarySubtype, Binary, Bson, DateTime, Document};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::collections::HashSet;
+use std::hint::black_box;
+
+use mongo_to_sqlite::converter::{bson_to_sql_value, document_to_sql_values, ConverterOptions, QuoteStyle};
+use mongo_to_sqlite::libsql_client::LibSqlClient;
+use mongo_to_sqlite::schema::{InferenceOptions, SchemaInferrer};
+
+/// Number of documents converted per benchmark iteration
+const BATCH_SIZE: usize = 1000;
+
+/// A document with only scalar fields
+fn flat_document(i: i64) -> Document {
+    doc! {
+        "_id": i,
+        "name": format!("user-{}", i),
+        "email": format!("user{}@example.com", i),
+        "age": (i % 90) as i32,
+        "score": i as f64 * 1.5,
+        "active": i % 2 == 0,
+        "created_at": DateTime::from_millis(1_700_000_000_000 + i),
+    }
+}
+
+/// A document with embedded sub-documents, stored as JSON text
+fn nested_document(i: i64) -> Document {
+    doc! {
+        "_id": i,
+        "profile": {
+            "name": format!("user-{}", i),
+            "address": {
+                "street": format!("{} Main St", i),
+                "city": "Springfield",
+                "geo": { "lat": 39.78, "lng": -89.65 },
+            },
+        },
+        "settings": { "theme": "dark", "notifications": { "email": true, "sms": false } },
+    }
+}
+
+/// A document dominated by arrays of scalars and sub-documents
+fn array_heavy_document(i: i64) -> Document {
+    let tags: Vec<Bson> = (0..20).map(|t| Bson::String(format!("tag-{}", t))).collect();
+    let events: Vec<Bson> = (0..10)
+        .map(|e| Bson::Document(doc! { "type": "click", "at": i + e, "x": e * 10 }))
+        .collect();
+    let scores: Vec<Bson> = (0..50).map(|s| Bson::Int64(i + s)).collect();
+
+    doc! {
+        "_id": i,
+        "tags": tags,
+        "events": events,
+        "scores": scores,
+    }
+}
+
+/// A document carrying binary payloads, including a UUID
+fn binary_heavy_document(i: i64) -> Document {
+    let binary = |subtype, bytes| Bson::Binary(Binary { subtype, bytes });
+
+    doc! {
+        "_id": i,
+        "avatar": binary(BinarySubtype::Generic, vec![(i % 256) as u8; 4096]),
+        "thumbnail": binary(BinarySubtype::Generic, vec![7; 512]),
+        "token": binary(BinarySubtype::Uuid, (0..16).collect()),
+    }
+}
+
+/// Build a batch of documents of one shape
+fn batch(shape: fn(i64) -> Document) -> Vec<Document> {
+    (0..BATCH_SIZE as i64).map(shape).collect()
+}
+
+fn shapes() -> [(&'static str, Vec<Document>); 4] {
+    [
+        ("flat", batch(flat_document)),
+        ("nested", batch(nested_document)),
+        ("array_heavy", batch(array_heavy_document)),
+        ("binary_heavy", batch(binary_heavy_document)),
+    ]
+}
+
+fn bench_document_to_sql_values(c: &mut Criterion) {
+    let mut group = c.benchmark_group("document_to_sql_values");
+    group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+
+    let options = ConverterOptions::default();
+    let inference_options = InferenceOptions::default();
+
+    for (name, documents) in shapes() {
+        let schema = SchemaInferrer::infer_schema(name, &documents, &inference_options).unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(name), &documents, |b, documents| {
+            b.iter(|| {
+                let mut warned_fields = HashSet::new();
+                for doc in documents {
+                    black_box(document_to_sql_values(
+                        doc,
+                        &schema.fields,
+                        &options,
+                        &mut warned_fields,
+                    ));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_bson_to_sql_value(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bson_to_sql_value");
+    group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+
+    for (name, documents) in shapes() {
+        let values: Vec<Bson> = documents
+            .into_iter()
+            .flat_map(|doc| doc.into_iter().map(|(_, value)| value))
+            .collect();
+
+        group.bench_with_input(BenchmarkId::from_parameter(name), &values, |b, values| {
+            b.iter(|| {
+                for value in values {
+                    black_box(bson_to_sql_value(value));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_infer_schema(c: &mut Criterion) {
+    let mut group = c.benchmark_group("infer_schema");
+    group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+
+    let options = InferenceOptions::default();
+
+    for (name, documents) in shapes() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &documents, |b, documents| {
+            b.iter(|| SchemaInferrer::infer_schema(black_box(name), documents, &options).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+/// Insert a batch of flat documents into an in-memory database, comparing a
+/// statement prepared per row with one prepared statement reused for the batch
+fn bench_insert_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_batch");
+    group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let documents = batch(flat_document);
+    let schema = SchemaInferrer::infer_schema("users", &documents, &InferenceOptions::default()).unwrap();
+    let mut warned_fields = HashSet::new();
+    let rows: Vec<Vec<libsql::Value>> = documents
+        .iter()
+        .map(|doc| document_to_sql_values(doc, &schema.fields, &ConverterOptions::default(), &mut warned_fields))
+        .collect();
+    let insert_sql = format!(
+        "INSERT OR REPLACE INTO \"users\" VALUES ({})",
+        vec!["?"; schema.fields.len()].join(", ")
+    );
+
+    let client = runtime.block_on(async {
+        let client = LibSqlClient::new(Some(":memory:")).await.unwrap();
+        client.execute(&schema.to_create_table_sql(QuoteStyle::Ansi)).await.unwrap();
+        client
+    });
+
+    group.bench_function("per_row", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                for values in &rows {
+                    client
+                        .execute_with_params(&insert_sql, libsql::params_from_iter(values.iter().cloned()))
+                        .await
+                        .unwrap();
+                }
+            })
+        })
+    });
+
+    group.bench_function("prepared", |b| {
+        b.iter(|| runtime.block_on(client.execute_prepared(&insert_sql, &rows)).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_document_to_sql_values,
+    bench_bson_to_sql_value,
+    bench_infer_schema,
+    bench_insert_batch
+);
+criterion_main!(benches);
@@ -1,5 +1,28 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, Parser, Subcommand};
 use anyhow::{Result, bail};
+use std::ffi::OsString;
+use std::path::Path;
+
+use crate::converter::{
+    ConverterOptions, DatetimeFormat, DecimalFormat, LegacyUuidEncoding, NonFiniteMode, ObjectIdFormat, QuoteStyle,
+    parse_default_specs, MAX_ROUND_PLACES,
+};
+use crate::computed::parse_computed_specs;
+use crate::export::ExportCompression;
+use crate::filter::parse_post_filter_specs;
+use crate::libsql_client::split_sql_statements;
+use crate::migration::{
+    ContinuationToken, DedupStrategy, InvalidDocumentPolicy, MergeSpec, OversizePolicy, DEFAULT_SQLITE_VARIABLE_LIMIT,
+};
+use crate::mongodb_client::{
+    build_credential, build_sort_document, detect_uri_scheme, parse_pipeline, parse_sample_query,
+    AuthMechanism, AuthSettings,
+};
+use crate::naming::{NamingStyle, MIN_IDENTIFIER_LENGTH};
+use crate::parquet_sink::ParquetCompression;
+use crate::schema::Collation;
+use crate::sqlite_backend::Backend;
 
 /// MongoDB to SQLite migration tool
 ///
@@ -8,11 +31,224 @@ use anyhow::{Result, bail};
 #[derive(Parser, Debug)]
 #[command(name = "mongo-to-sqlite")]
 #[command(author, version, about, long_about = None)]
-pub struct Args {
-    /// MongoDB database name to migrate
-    #[arg(short, long, required = true)]
-    pub database: String,
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Available subcommands
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Migrate collections into SQLite/LibSQL (the default)
+    Migrate(Box<Args>),
+    /// List collections with their document counts and inferred schemas
+    Inspect(InspectArgs),
+    /// Export collections to stdout or a file without migrating
+    Export(ExportArgs),
+    /// Check connectivity to MongoDB and the SQLite/LibSQL target
+    Check(CheckArgs),
+}
+
+/// Subcommand names, used to detect when `migrate` was left implicit
+const SUBCOMMANDS: [&str; 5] = ["migrate", "inspect", "export", "check", "help"];
+
+impl Cli {
+    /// Parse the command line, defaulting to `migrate` when no subcommand is given
+    ///
+    /// This keeps invocations from before subcommands existed (e.g.
+    /// `mongo-to-sqlite -d mydb --all-tables`) working unchanged.
+    ///
+    /// Options from a `--config` file are merged in before parsing.
+    pub fn parse_with_default() -> Self {
+        let args = with_default_subcommand(std::env::args_os().collect());
+        let args = merge_config_file(args).unwrap_or_else(|e| {
+            clap::Error::raw(clap::error::ErrorKind::InvalidValue, format!("{:#}\n", e)).exit()
+        });
+        Self::parse_from(args)
+    }
+}
+
+/// Parse the options of a `--config` TOML file
+///
+/// Keys are long option names; underscores may be used instead of dashes.
+/// `true` turns a flag on and `false` leaves it off, arrays give a
+/// repeatable option several times and other values are passed as text.
+///
+/// # Arguments
+/// * `text` - Contents of the TOML file
+///
+/// # Returns
+/// `(option name, values)` pairs, with no values for flags
+pub fn parse_config(text: &str) -> Result<Vec<(String, Vec<String>)>> {
+    let table: toml::Table = text
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid config file: {}", e))?;
+
+    let mut options = Vec::new();
+    for (key, value) in table {
+        let name = key.replace('_', "-");
+        let values = match value {
+            toml::Value::Boolean(false) => continue,
+            toml::Value::Boolean(true) => Vec::new(),
+            toml::Value::Array(items) => items
+                .into_iter()
+                .map(|item| config_scalar(&name, item))
+                .collect::<Result<_>>()?,
+            other => vec![config_scalar(&name, other)?],
+        };
+        options.push((name, values));
+    }
+
+    Ok(options)
+}
+
+/// Render a single config value as option text
+fn config_scalar(name: &str, value: toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(s) => Ok(s),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        _ => bail!(
+            "Option '{}' in the config file must be a string, number, boolean or an array of those",
+            name
+        ),
+    }
+}
+
+/// Add the options of a `migrate --config` file to the command line
+///
+/// Options given on the command line win over the file, and the file wins
+/// over environment variables and defaults. Options already on the command
+/// line are left out, including every value of repeatable options.
+///
+/// # Arguments
+/// * `args` - Command-line arguments, after `with_default_subcommand`
+///
+/// # Returns
+/// The arguments with the file's options appended, or unchanged without `--config`
+pub fn merge_config_file(args: Vec<OsString>) -> Result<Vec<OsString>> {
+    // Parse leniently: the file may provide required options
+    let command = Cli::command().ignore_errors(true);
+    let Ok(matches) = command.clone().try_get_matches_from(&args) else {
+        return Ok(args);
+    };
+    let Some(("migrate", migrate)) = matches.subcommand() else {
+        return Ok(args);
+    };
+    let Some(path) = migrate.get_one::<String>("config") else {
+        return Ok(args);
+    };
+
+    let text = std::fs::read_to_string(Path::new(path))
+        .map_err(|e| anyhow::anyhow!("Cannot read config file {}: {}", path, e))?;
+    let options = parse_config(&text)?;
+    let migrate_command = command
+        .find_subcommand("migrate")
+        .expect("migrate subcommand is defined");
+
+    let mut merged = args.clone();
+    for (name, values) in options {
+        let Some(arg) = migrate_command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(name.as_str()))
+        else {
+            bail!("Unknown option '{}' in config file {}", name, path);
+        };
+        if name == "config" {
+            bail!("Config file {} cannot name another config file", path);
+        }
+        if migrate.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+
+        if values.is_empty() {
+            merged.push(OsString::from(format!("--{}", name)));
+        }
+        for value in values {
+            // --name=value keeps values starting with '-' from looking like flags
+            merged.push(OsString::from(format!("--{}={}", name, value)));
+        }
+    }
+
+    Ok(merged)
+}
+
+/// Insert the `migrate` subcommand if the arguments don't start with one
+///
+/// Top-level `--help` and `--version` are left alone.
+///
+/// # Arguments
+/// * `args` - Command-line arguments, including the program name
+///
+/// # Returns
+/// The arguments with a subcommand in first position
+pub fn with_default_subcommand(mut args: Vec<OsString>) -> Vec<OsString> {
+    let has_subcommand = args.get(1).is_some_and(|first| {
+        let first = first.to_string_lossy();
+        SUBCOMMANDS.contains(&first.as_ref())
+            || matches!(first.as_ref(), "-h" | "--help" | "-V" | "--version")
+    });
+
+    if !has_subcommand {
+        args.insert(1.min(args.len()), OsString::from("migrate"));
+    }
+
+    args
+}
+
+/// Parse a collection list: one name per line, ignoring blank lines and
+/// `#` comments
+///
+/// # Arguments
+/// * `text` - Contents of a --tables-from-file file
+///
+/// # Returns
+/// The collection names, in file order
+pub fn parse_collection_list(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Read the collection list of --tables-from-file
+///
+/// # Arguments
+/// * `path` - Path of the file
+///
+/// # Returns
+/// The collection names, or an error if the file can't be read or names none
+pub fn read_collection_list(path: &str) -> Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read --tables-from-file {}: {}", path, e))?;
+    let collections = parse_collection_list(&text);
+    if collections.is_empty() {
+        bail!("--tables-from-file {} lists no collections", path);
+    }
+    Ok(collections)
+}
+
+/// Read the statements of a `--post-sql` script
+///
+/// # Arguments
+/// * `path` - Path to the SQL script
+///
+/// # Returns
+/// The statements, or an error if the file can't be read or has none
+pub fn read_post_sql(path: &str) -> Result<Vec<String>> {
+    let script = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read --post-sql {}: {}", path, e))?;
+    let statements = split_sql_statements(&script);
+    if statements.is_empty() {
+        bail!("--post-sql {} has no SQL statements", path);
+    }
+    Ok(statements)
+}
 
+/// MongoDB connection settings shared by all subcommands
+#[derive(clap::Args, Debug, Clone)]
+pub struct ConnectionArgs {
     /// MongoDB connection URI
     /// 
     /// If not specified, will use the MONGODB_URI environment variable,
@@ -20,6 +256,258 @@ pub struct Args {
     #[arg(long, env = "MONGODB_URI", default_value = "mongodb://localhost:27017")]
     pub mongodb_uri: String,
 
+    /// Path to a PEM-encoded CA certificate file for MongoDB TLS connections
+    ///
+    /// Useful for Atlas or self-hosted clusters using a private CA
+    #[arg(long)]
+    pub tls_ca_file: Option<String>,
+
+    /// Accept invalid TLS certificates from the MongoDB server
+    ///
+    /// Use with caution! This disables certificate validation and should
+    /// only be used for testing.
+    #[arg(long)]
+    pub tls_allow_invalid_certs: bool,
+
+    /// Path to a PEM file with the client certificate and private key
+    ///
+    /// Presented to the server during the TLS handshake; required by
+    /// --auth-mechanism MONGODB-X509 unless the URI sets tlsCertificateKeyFile.
+    #[arg(long, value_name = "PATH")]
+    pub tls_cert_key_file: Option<String>,
+
+    /// Authentication mechanism: SCRAM-SHA-256, MONGODB-X509 or MONGODB-AWS
+    ///
+    /// Overrides any credentials in the URI. SCRAM-SHA-256 needs
+    /// --auth-username and --auth-password; MONGODB-X509 needs a client
+    /// certificate; MONGODB-AWS uses --auth-username/--auth-password as the
+    /// access key id and secret, or the AWS environment when they are omitted.
+    #[arg(long, value_name = "MECHANISM")]
+    pub auth_mechanism: Option<String>,
+
+    /// Username for --auth-mechanism (the access key id for MONGODB-AWS)
+    #[arg(long, env = "MONGODB_USERNAME")]
+    pub auth_username: Option<String>,
+
+    /// Password for --auth-mechanism (the secret access key for MONGODB-AWS)
+    ///
+    /// Prefer the environment variable, which stays out of the process list.
+    #[arg(long, env = "MONGODB_PASSWORD", hide_env_values = true)]
+    pub auth_password: Option<String>,
+
+    /// Database holding the user for SCRAM-SHA-256 [default: the URI's database or admin]
+    #[arg(long, value_name = "DATABASE", requires = "auth_mechanism")]
+    pub auth_source: Option<String>,
+
+    /// Session token of temporary AWS credentials, for MONGODB-AWS
+    #[arg(long, value_name = "TOKEN", requires = "auth_mechanism")]
+    pub aws_session_token: Option<String>,
+
+    /// Server-side time limit for MongoDB sampling, counts and reads, in milliseconds
+    ///
+    /// The server aborts operations that exceed it (maxTimeMS), so a busy
+    /// cluster fails fast instead of hanging. For a collection read the limit
+    /// covers the server's work across all batches, not the time spent
+    /// inserting into SQLite.
+    #[arg(long, value_name = "MS")]
+    pub mongo_max_time_ms: Option<u64>,
+}
+
+impl ConnectionArgs {
+    /// Validate the MongoDB URI, time limit and credentials before any connection is attempted
+    pub fn validate(&self) -> Result<()> {
+        detect_uri_scheme(&self.mongodb_uri)?;
+        if self.mongo_max_time_ms == Some(0) {
+            bail!("--mongo-max-time-ms must be greater than 0");
+        }
+        self.credential()?;
+        Ok(())
+    }
+
+    /// Build the credential selected by --auth-mechanism and its flags
+    ///
+    /// # Returns
+    /// `None` when no --auth-mechanism is given, so the URI's credentials apply
+    pub fn credential(&self) -> Result<Option<mongodb::options::Credential>> {
+        let Some(ref mechanism) = self.auth_mechanism else {
+            return Ok(None);
+        };
+        let settings = AuthSettings {
+            username: self.auth_username.clone(),
+            password: self.auth_password.clone(),
+            source: self.auth_source.clone(),
+            aws_session_token: self.aws_session_token.clone(),
+            client_certificate: self.tls_cert_key_file.is_some()
+                || self.mongodb_uri.to_ascii_lowercase().contains("tlscertificatekeyfile="),
+        };
+        Ok(Some(build_credential(mechanism.parse::<AuthMechanism>()?, &settings)?))
+    }
+}
+
+/// Arguments for the `inspect` subcommand
+#[derive(clap::Args, Debug)]
+pub struct InspectArgs {
+    /// MongoDB database name to inspect
+    #[arg(short, long, required = true)]
+    pub database: String,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+
+    /// Inspect a specific collection instead of all of them
+    #[arg(short, long)]
+    pub table: Option<String>,
+
+    /// Number of documents to sample for schema inference
+    #[arg(long, default_value = "100")]
+    pub sample_size: usize,
+
+    /// Infer numeric types for string fields holding numbers
+    #[arg(long)]
+    pub coerce_numeric_strings: bool,
+
+    /// Annotate each column of the printed CREATE TABLE with a SQL comment
+    ///
+    /// Comments look like `-- column: age (inferred INTEGER, 95% of sample)`
+    #[arg(long)]
+    pub column_comments: bool,
+}
+
+/// Arguments for the `export` subcommand
+#[derive(clap::Args, Debug)]
+pub struct ExportArgs {
+    /// MongoDB database name to export
+    #[arg(short, long, required = true)]
+    pub database: String,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+
+    /// Export a specific collection
+    #[arg(short, long, conflicts_with = "all_tables")]
+    pub table: Option<String>,
+
+    /// Export all collections in the database
+    #[arg(long, conflicts_with = "table")]
+    pub all_tables: bool,
+
+    /// Export format: jsonl (relaxed Extended JSON per line), json, csv, sql-dump or bson-stream
+    ///
+    /// json writes each collection as one JSON array of relaxed Extended
+    /// JSON documents, streamed without loading the collection into memory.
+    /// csv and sql-dump write the columns of the inferred schema, converted
+    /// like a migration would store them.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = "jsonl",
+        value_parser = ["jsonl", "json", "csv", "sql-dump", "bson-stream"]
+    )]
+    pub format: String,
+
+    /// Number of documents to sample for the csv and sql-dump schema
+    #[arg(long, default_value = "100")]
+    pub sample_size: usize,
+
+    /// Annotate each column of a sql-dump CREATE TABLE with a SQL comment
+    ///
+    /// Comments look like `-- column: age (inferred INTEGER, 95% of sample)`
+    #[arg(long)]
+    pub column_comments: bool,
+
+    /// Compress the output: none or gzip
+    ///
+    /// An --output file name ending in .gz is always gzip-compressed; with
+    /// --output-dir, compressed files get a .gz suffix
+    #[arg(long, value_name = "CODEC", default_value = "none")]
+    pub compress: String,
+
+    /// File to write to instead of stdout
+    #[arg(short, long, conflicts_with = "output_dir")]
+    pub output: Option<String>,
+
+    /// Directory to write one file per collection to, e.g. users.jsonl
+    ///
+    /// Created if it doesn't exist
+    #[arg(long, value_name = "DIR")]
+    pub output_dir: Option<String>,
+
+    /// Export documents in a stable order sorted by this field
+    ///
+    /// Prefix the field with '-' to sort descending
+    #[arg(long, value_name = "FIELD")]
+    pub sort_by: Option<String>,
+}
+
+impl ExportArgs {
+    /// Validate that the arguments are consistent and complete
+    pub fn validate(&self) -> Result<()> {
+        if self.table.is_none() && !self.all_tables {
+            bail!("Either --table <TABLE> or --all-tables must be specified");
+        }
+
+        self.connection.validate()?;
+
+        // Arrays of several collections back to back are not valid JSON, and
+        // a CSV file has a single header
+        if (self.format == "json" || self.format == "csv") && self.all_tables && self.output_dir.is_none() {
+            bail!("--format {} with --all-tables requires --output-dir", self.format);
+        }
+
+        if self.sample_size == 0 {
+            bail!("--sample-size must be greater than 0");
+        }
+
+        if self.column_comments && self.format != "sql-dump" {
+            bail!("--column-comments requires --format sql-dump");
+        }
+
+        self.compress.parse::<ExportCompression>()?;
+
+        if let Some(ref sort_by) = self.sort_by {
+            build_sort_document(sort_by)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Arguments for the `check` subcommand
+#[derive(clap::Args, Debug)]
+pub struct CheckArgs {
+    /// MongoDB database that must exist
+    #[arg(short, long)]
+    pub database: Option<String>,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+
+    /// SQLite database file to open
+    ///
+    /// Ignored if TURSO_DATABASE_URL and TURSO_AUTH_TOKEN are set
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+/// Arguments for the `migrate` subcommand
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// MongoDB database name to migrate
+    #[arg(short, long, required = true)]
+    pub database: String,
+
+    /// Read options from a TOML file
+    ///
+    /// Keys are long option names, e.g. database = "app", all-tables = true,
+    /// transform = ["email=lower"]. Options given on the command line take
+    /// precedence over the file, which takes precedence over environment
+    /// variables and defaults.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<String>,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+
     /// Migrate a specific table/collection
     /// 
     /// Mutually exclusive with --all-tables
@@ -32,6 +520,51 @@ pub struct Args {
     #[arg(long, conflicts_with = "table")]
     pub all_tables: bool,
 
+    /// Migrate the collections listed in a file, one name per line
+    ///
+    /// Blank lines and lines starting with # are ignored. Mutually
+    /// exclusive with --table and --all-tables.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["table", "all_tables"])]
+    pub tables_from_file: Option<String>,
+
+    /// Only migrate the N collections with the most documents
+    ///
+    /// Requires --all-tables. Each collection is counted up front and the
+    /// selected collections are printed before the migration starts.
+    #[arg(long, value_name = "N", requires = "all_tables")]
+    pub top_collections: Option<usize>,
+
+    /// Leave collections without documents out entirely, creating no table
+    #[arg(long, conflicts_with = "error_on_empty")]
+    pub skip_empty: bool,
+
+    /// Fail if any requested collection has no documents
+    #[arg(long)]
+    pub error_on_empty: bool,
+
+    /// Union collections matching a pattern into one table (repeatable)
+    ///
+    /// Format: <pattern>=<target>, where * matches any characters and ?
+    /// one character. The target table gets the union of the collections'
+    /// fields, widening conflicting types. Requires --all-tables.
+    /// Example: --merge "events_*=events"
+    #[arg(long = "merge", value_name = "PATTERN=TARGET", requires = "all_tables", conflicts_with = "watch")]
+    pub merges: Vec<String>,
+
+    /// Migrate the files of a GridFS bucket instead of collections
+    ///
+    /// Each file is reassembled from <BUCKET>.chunks and stored in a
+    /// <BUCKET>_files table with columns (id, filename, length, content_type,
+    /// data, path). Mutually exclusive with --table and --all-tables.
+    #[arg(long, value_name = "BUCKET", conflicts_with_all = ["table", "all_tables", "tables_from_file", "schema_only", "data_only"])]
+    pub gridfs: Option<String>,
+
+    /// Write GridFS file contents to this directory instead of the data column
+    ///
+    /// Files are named after their id and referenced by the path column
+    #[arg(long, value_name = "DIR", requires = "gridfs")]
+    pub externalize_binary: Option<String>,
+
     /// Only migrate schema (CREATE TABLE statements), skip data migration
     /// 
     /// Useful for previewing the schema before migrating data
@@ -50,6 +583,36 @@ pub struct Args {
     #[arg(long, requires = "data_only")]
     pub truncate: bool,
 
+    /// Update existing rows on a primary key conflict instead of failing
+    ///
+    /// Only valid with --data-only. Generates INSERT ... ON CONFLICT(pk)
+    /// DO UPDATE SET, so columns not in the inferred schema keep their values.
+    #[arg(long, requires = "data_only", conflicts_with = "truncate")]
+    pub upsert: bool,
+
+    /// Skip rows that violate a unique constraint, e.g. a duplicate _id
+    ///
+    /// A batch that hits a conflict is retried row by row without a
+    /// transaction, so the rest of the batch is still inserted. The key of
+    /// every skipped row is logged.
+    #[arg(long)]
+    pub skip_conflicts: bool,
+
+    /// Insert rows without wrapping each batch in BEGIN/COMMIT
+    ///
+    /// Every row is autocommitted on its own, for targets where transactions
+    /// are managed externally or large transactions are discouraged. A failed
+    /// batch leaves the rows inserted before the error in the table.
+    #[arg(long)]
+    pub no_transaction: bool,
+
+    /// Store _id in an ordinary nullable column instead of the primary key
+    ///
+    /// For append-only or log-like data: re-runs and duplicate documents add
+    /// rows instead of failing on the unique constraint.
+    #[arg(long, conflicts_with_all = ["upsert", "watch"])]
+    pub no_primary_key: bool,
+
     /// Drop existing tables before creating new schema
     /// 
     /// Use with caution! This will delete all existing data and schema.
@@ -57,104 +620,1225 @@ pub struct Args {
     #[arg(long, conflicts_with = "data_only")]
     pub drop_tables: bool,
 
+    /// Delete the output file before connecting, starting from an empty database
+    ///
+    /// Unlike --drop-tables, which only drops the tables being migrated,
+    /// every table of an existing file is lost. Local files only; not
+    /// supported for Turso.
+    #[arg(long, conflicts_with_all = ["data_only", "map_to_existing", "resume_schema"])]
+    pub overwrite: bool,
+
+    /// Don't ask for confirmation before --drop-tables, --truncate or --overwrite
+    ///
+    /// Without it, those flags prompt on a terminal and fail otherwise.
+    #[arg(short = 'y', long)]
+    pub yes: bool,
+
     /// Output SQLite database file path
     /// 
     /// If TURSO_DATABASE_URL and TURSO_AUTH_TOKEN are set, this is ignored
     /// and data is written to the Turso cloud database instead.
-    #[arg(short, long, default_value = "output.db")]
+    #[arg(short, long, env = "OUTPUT_DB", default_value = "output.db")]
     pub output: Option<String>,
 
     /// Number of documents to insert per batch
     /// 
     /// Larger batches are faster but use more memory
-    #[arg(long, default_value = "1000")]
+    #[arg(long, env = "BATCH_SIZE", default_value = "1000")]
     pub batch_size: usize,
 
+    /// Also insert a batch once its documents reach this many BSON bytes
+    ///
+    /// Bounds memory for large documents: a batch is inserted after
+    /// --batch-size documents or --batch-bytes bytes, whichever comes first
+    #[arg(long, value_name = "BYTES")]
+    pub batch_bytes: Option<usize>,
+
+    /// Soft limit in MiB on the converted rows of a batch held in memory
+    ///
+    /// Estimates the size of converted values and inserts a batch early
+    /// once another row would exceed the limit, shrinking the effective
+    /// batch size for collections with large documents
+    #[arg(long, value_name = "MB")]
+    pub max_memory_mb: Option<usize>,
+
+    /// Maximum bound parameters per SQLite statement
+    ///
+    /// Tables whose rows need more parameters than this are rejected before
+    /// inserting. Use 999 for SQLite builds older than 3.32.0.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_SQLITE_VARIABLE_LIMIT)]
+    pub sqlite_variable_limit: usize,
+
     /// Number of documents to sample for schema inference
     /// 
     /// More samples produce more accurate schemas but take longer
-    #[arg(long, default_value = "100")]
+    #[arg(long, env = "SAMPLE_SIZE", default_value = "100")]
     pub sample_size: usize,
-}
 
-impl Args {
-    /// Validate that the arguments are consistent and complete
+    /// Only sample documents matching this filter for schema inference
     ///
-    /// This function validates that:
-    /// - Either --table or --all-tables is specified
-    /// - batch_size and sample_size are greater than 0
-    pub fn validate(&self) -> Result<()> {
-        // Ensure either --table or --all-tables is specified
-        if self.table.is_none() && !self.all_tables {
-            bail!("Either --table <TABLE> or --all-tables must be specified");
-        }
+    /// A JSON object (Extended JSON allowed), e.g.
+    /// '{"createdAt": {"$gte": {"$date": "2024-01-01T00:00:00Z"}}}', for
+    /// collections whose older documents have a different shape. All
+    /// documents are still migrated.
+    #[arg(long, value_name = "JSON", conflicts_with = "pipeline")]
+    pub sample_query: Option<String>,
 
-        // Validate batch size
-        if self.batch_size == 0 {
-            bail!("--batch-size must be greater than 0");
-        }
+    /// Update the progress bar every N converted documents
+    ///
+    /// By default the bar only moves after each batch is inserted; a smaller
+    /// interval gives smoother feedback with large --batch-size values
+    #[arg(long, value_name = "DOCS", env = "PROGRESS_INTERVAL")]
+    pub progress_interval: Option<usize>,
 
-        // Validate sample size
-        if self.sample_size == 0 {
-            bail!("--sample-size must be greater than 0");
-        }
+    /// Log progress lines instead of drawing a progress bar
+    ///
+    /// Also the default when stderr is not a terminal, e.g. in CI. Lines
+    /// look like "users: 50000/200000 (25%)", see --progress-log-interval.
+    #[arg(long)]
+    pub no_progress: bool,
 
-        Ok(())
-    }
-}
+    /// Inserted rows between progress log lines when no progress bar is drawn
+    #[arg(long, value_name = "DOCS", default_value = "10000")]
+    pub progress_log_interval: usize,
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Seconds between keep-alive queries on a remote target (0 disables)
+    ///
+    /// While a collection streams, `SELECT 1` is sent to Turso at this
+    /// interval so long MongoDB reads don't let the connection go idle
+    #[arg(long, value_name = "SECS", env = "HEARTBEAT_SECS", default_value = "30")]
+    pub heartbeat_secs: u64,
 
-    #[test]
-    fn test_validate_missing_table_flags() {
-        let args = Args {
-            database: "test".to_string(),
-            mongodb_uri: "mongodb://localhost:27017".to_string(),
-            table: None,
-            all_tables: false,
-            schema_only: false,
-            data_only: false,
-            output: Some("output.db".to_string()),
-            batch_size: 1000,
-            sample_size: 100,
-        };
+    /// Transform a field's values during migration (repeatable)
+    ///
+    /// Format: <field>=<op>, where op is one of: lower, upper, sha256, redact.
+    /// Example: --transform email=lower --transform ssn=redact
+    #[arg(long = "transform", value_name = "FIELD=OP")]
+    pub transforms: Vec<String>,
 
-        assert!(args.validate().is_err());
-    }
+    /// Store this value instead of NULL when a field is missing or null (repeatable)
+    ///
+    /// Format: <field>=<value>. The value becomes a number in INTEGER and
+    /// REAL columns when it parses as one, and text otherwise.
+    /// Example: --default score=0 --default nickname=
+    #[arg(long = "default", value_name = "FIELD=VALUE")]
+    pub defaults: Vec<String>,
 
-    #[test]
-    fn test_validate_zero_batch_size() {
-        let args = Args {
-            database: "test".to_string(),
-            mongodb_uri: "mongodb://localhost:27017".to_string(),
-            table: Some("users".to_string()),
-            all_tables: false,
-            schema_only: false,
-            data_only: false,
-            output: Some("output.db".to_string()),
-            batch_size: 0,
-            sample_size: 100,
-        };
+    /// Insert documents in a stable order sorted by this field
+    ///
+    /// Prefix the field with '-' or append ':desc' to sort descending (e.g.
+    /// --sort-by=-created_at or --read-sort created_at:desc). Defaults to _id
+    /// ascending when given without a value. Sorting by an indexed field
+    /// reads in index order; large collections without a matching index get
+    /// a warning, since MongoDB has to sort them in memory.
+    #[arg(long, alias = "read-sort", value_name = "FIELD", num_args = 0..=1, default_missing_value = "_id")]
+    pub sort_by: Option<String>,
 
-        assert!(args.validate().is_err());
-    }
+    /// Read each collection with N concurrent _id range readers
+    ///
+    /// The ObjectId _id space is split into N ranges streamed in parallel
+    /// into a single writer, which helps when MongoDB read latency dominates.
+    /// Rows are inserted in arrival order, so this cannot be used with --sort-by.
+    #[arg(long, value_name = "N", env = "READ_PARALLELISM", default_value = "1", conflicts_with = "sort_by")]
+    pub read_parallelism: usize,
 
-    #[test]
-    fn test_validate_valid_args() {
-        let args = Args {
+    /// Read collections in _id pages of N documents instead of one long cursor
+    ///
+    /// Used when a sample of _id values is all ObjectIds or all integers and
+    /// no --sort-by is given; each page continues after the last _id read.
+    /// Other collections are read with a single cursor.
+    #[arg(long, value_name = "N")]
+    pub keyset_page_size: Option<usize>,
+
+    /// Migrate at most N documents from each collection
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Print a continuation token after migrating, for chunked runs
+    ///
+    /// Collections are read in _id order and the last _id migrated from each
+    /// is printed to stdout as a JSON object. Pass it to --continue-from to
+    /// migrate the next --limit documents.
+    #[arg(long, conflicts_with_all = ["sort_by", "watch", "gridfs", "pipeline"])]
+    pub continue_token: bool,
+
+    /// Continue after the position printed by --continue-token
+    ///
+    /// Reads only documents whose _id is greater than the last _id recorded
+    /// for their collection, in _id order.
+    #[arg(long, value_name = "TOKEN", conflicts_with_all = ["sort_by", "watch", "gridfs", "pipeline"])]
+    pub continue_from: Option<String>,
+
+    /// Wrap typed values in MongoDB Extended JSON envelopes
+    ///
+    /// ObjectIds, dates and JavaScript code are stored as {"$oid": ...},
+    /// {"$date": ...} and {"$code": ...} so they can be told apart from
+    /// plain strings on re-import.
+    #[arg(long)]
+    pub extended_json_types: bool,
+
+    /// Print why each column got its inferred type
+    ///
+    /// Shows the observed type distribution per field alongside the chosen
+    /// type and the reason (majority, tie-break or null-fallback)
+    #[arg(long, conflicts_with = "data_only")]
+    pub explain_schema: bool,
+
+    /// Fail if sampled documents have no _id field
+    ///
+    /// With --require-id false, collections whose documents lack _id get a
+    /// synthesized rowid-based primary key column (_rowid) instead
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub require_id: bool,
+
+    /// Load collection schemas from a JSON file instead of sampling MongoDB
+    ///
+    /// The file holds a JSON array of schemas, each with a collection_name
+    /// and its fields. Every migrated collection must have a schema.
+    #[arg(long, value_name = "PATH")]
+    pub schema_file: Option<String>,
+
+    /// Text format for dates: rfc3339 or sqlite-text
+    ///
+    /// rfc3339 writes `2024-01-15T10:30:00+00:00`. sqlite-text writes UTC
+    /// `2024-01-15 10:30:00.000`, the format SQLite's date and time functions
+    /// produce. Dates inside --extended-json-types envelopes stay RFC 3339.
+    #[arg(long, value_name = "FORMAT", default_value = "rfc3339")]
+    pub datetime_as: String,
+
+    /// Byte order of legacy (subtype 3) UUIDs: standard, csharp or java
+    ///
+    /// Subtype 3 UUIDs were written with driver-specific byte orders. Pick the
+    /// driver that wrote them so they decode to the right UUID string.
+    #[arg(long, value_name = "ENCODING", default_value = "standard")]
+    pub legacy_uuid: String,
+
+    /// Skip documents whose value of this top-level field was already migrated
+    ///
+    /// The number of skipped documents is reported per collection. See
+    /// --dedup-strategy for how duplicates are detected.
+    #[arg(long, value_name = "FIELD", conflicts_with = "upsert")]
+    pub dedup_by: Option<String>,
+
+    /// How --dedup-by finds duplicates: memory or last-wins-sql
+    ///
+    /// memory keeps the first document and holds every distinct key value in
+    /// memory (about the key's size plus ~50 bytes each), which can be a lot
+    /// for large collections. last-wins-sql adds a unique index on the key
+    /// column and uses INSERT OR REPLACE, so the last document wins instead.
+    #[arg(long, value_name = "STRATEGY", default_value = "memory")]
+    pub dedup_strategy: String,
+
+    /// Maximum serialized BSON size of a document to migrate, in bytes
+    ///
+    /// Larger documents are skipped or abort the migration, see --on-oversize
+    #[arg(long, value_name = "N", env = "MAX_DOC_BYTES")]
+    pub max_doc_bytes: Option<usize>,
+
+    /// What to do with documents larger than --max-doc-bytes: skip or abort
+    #[arg(long, value_name = "POLICY", default_value = "skip")]
+    pub on_oversize: String,
+
+    /// Check every document before converting it
+    ///
+    /// Documents without an _id, or whose _id is null or an array, are
+    /// skipped and listed with the reason, or abort the migration, see
+    /// --on-invalid
+    #[arg(long)]
+    pub validate_bson: bool,
+
+    /// With --validate-bson, also reject documents holding undefined or
+    /// DBPointer values, which can only be stored as NULL
+    #[arg(long, requires = "validate_bson")]
+    pub strict: bool,
+
+    /// What to do with documents failing --validate-bson: skip or abort
+    #[arg(long, value_name = "POLICY", default_value = "skip")]
+    pub on_invalid: String,
+
+    /// Only insert rows of a collection that match a condition (repeatable)
+    ///
+    /// Format: <collection>=<column> <op> <literal>, evaluated on converted
+    /// values before insert. op is one of =, !=, <>, <, <=, >, >= and literal
+    /// is a number, a 'quoted string' or NULL.
+    /// Example: --post-filter "users=age >= 18"
+    #[arg(long = "post-filter", value_name = "COLLECTION=CONDITION")]
+    pub post_filters: Vec<String>,
+
+    /// Where to read documents from: mongodb or bson-stream
+    ///
+    /// bson-stream reads length-prefixed BSON documents from stdin (e.g. the
+    /// output of another run with --output-format bson-stream) into --table
+    #[arg(long, value_name = "SOURCE", default_value = "mongodb", value_parser = ["mongodb", "bson-stream"])]
+    pub input: String,
+
+    /// Output format: sqlite, bson-stream or parquet
+    ///
+    /// bson-stream writes length-prefixed BSON documents to stdout instead of
+    /// migrating into SQLite, so runs can be chained with pipes. parquet
+    /// writes one <collection>.parquet file per collection into the
+    /// directory given by --output, typed from the inferred schema.
+    #[arg(long, value_name = "FORMAT", default_value = "sqlite", value_parser = ["sqlite", "bson-stream", "parquet"])]
+    pub output_format: String,
+
+    /// Compression codec for --output-format parquet: none, snappy, gzip or zstd
+    #[arg(long, value_name = "CODEC", default_value = "snappy")]
+    pub parquet_compression: String,
+
+    /// Skip schema creation for tables a previous run already created
+    ///
+    /// Completed tables are recorded in the _migration_meta table. With this
+    /// flag they are neither dropped (--drop-tables) nor recreated, making
+    /// recovery from a partially failed schema phase safe.
+    #[arg(long, conflicts_with = "data_only")]
+    pub resume_schema: bool,
+
+    /// Identifier quoting style for generated SQL: ansi or mysql
+    ///
+    /// ansi uses "double quotes" (the SQLite default); mysql uses `backticks`,
+    /// which SQLite also accepts, for MySQL-compatible tooling
+    #[arg(long, value_name = "STYLE", default_value = "ansi")]
+    pub quote_style: String,
+
+    /// Give string fields holding numbers (e.g. "42", "3.14") a numeric type
+    ///
+    /// A field is coerced when at least 90% of its sampled strings parse as
+    /// numbers; values that don't parse are stored as NULL with a warning
+    #[arg(long)]
+    pub coerce_numeric_strings: bool,
+
+    /// Keep migrating the remaining collections when one fails
+    ///
+    /// Failed collections are listed at the end and the process exits with a
+    /// non-zero status. By default the run stops at the first failure.
+    #[arg(long)]
+    pub continue_on_error: bool,
+
+    /// Add columns for new fields to existing tables during the schema phase
+    ///
+    /// After CREATE TABLE IF NOT EXISTS finds an existing table, its columns
+    /// are compared with the inferred schema and new fields are added with
+    /// ALTER TABLE ... ADD COLUMN as nullable columns, so a re-run against an
+    /// evolved collection keeps the new fields.
+    #[arg(long, conflicts_with = "data_only")]
+    pub evolve_schema: bool,
+
+    /// Add inferred columns missing from an existing target table
+    ///
+    /// Runs ALTER TABLE ... ADD COLUMN before inserting. Without it, column
+    /// mismatches with an existing table are only reported as warnings.
+    #[arg(long, conflicts_with = "schema_only")]
+    pub alter_add_columns: bool,
+
+    /// Load into existing tables, e.g. created by Prisma or Diesel
+    ///
+    /// No tables are created: each table's columns are read with PRAGMA
+    /// table_info and fields are matched to them by name, ignoring case.
+    /// Fields without a column are skipped and columns without a field keep
+    /// their defaults. Implies --data-only.
+    #[arg(long, conflicts_with_all = ["schema_only", "drop_tables", "evolve_schema", "alter_add_columns", "watch"])]
+    pub map_to_existing: bool,
+
+    /// Round Double values in REAL columns to this many decimal places
+    ///
+    /// Normalizes floating point noise such as 0.30000000000000004. Rounding
+    /// is done in binary floating point, so results are the nearest REAL to
+    /// the rounded decimal and not always exact (1.005 rounds to 1.0).
+    #[arg(long, value_name = "PLACES")]
+    pub round_real: Option<u32>,
+
+    /// Store NaN and ±Infinity doubles as null or text
+    ///
+    /// text writes the strings NaN, Infinity and -Infinity. Either way the
+    /// values stay out of REAL columns, where they can't be queried or
+    /// exported as JSON numbers.
+    #[arg(long, value_name = "MODE", default_value = "null")]
+    pub non_finite: String,
+
+    /// Store ObjectIds as hex, timestamp or extjson
+    ///
+    /// timestamp stores the ObjectId's creation time as INTEGER Unix seconds;
+    /// extjson stores {"$oid": "..."}. _id keeps its hex form with timestamp,
+    /// since creation times are not unique.
+    #[arg(long, value_name = "FORMAT", default_value = "hex")]
+    pub objectid_as: String,
+
+    /// Store Decimal128 values as text, real or blob
+    ///
+    /// text keeps the exact decimal string. real stores the nearest double
+    /// in a REAL column, losing digits beyond ~15 significant ones; blob
+    /// stores the 16 raw bytes.
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    pub decimal_as: String,
+
+    /// Store nested documents and arrays as indented JSON
+    ///
+    /// Easier to read in the SQLite file, at the cost of larger columns.
+    #[arg(long)]
+    pub pretty_json: bool,
+
+    /// Decode base64 strings in this field into a BLOB column (repeatable)
+    ///
+    /// For fields that store binary data (images, keys) as base64 text.
+    /// Values that are not valid base64 are stored unchanged as TEXT, with a
+    /// warning.
+    #[arg(long = "base64-to-blob", value_name = "FIELD")]
+    pub base64_blob_fields: Vec<String>,
+
+    /// Store this field's values in a lookup table (repeatable)
+    ///
+    /// For enum-like fields such as status. Each distinct value gets a row in
+    /// <table>_<field>_lookup(id INTEGER PRIMARY KEY, value TEXT UNIQUE) and
+    /// the column stores its id as an INTEGER foreign key. Not supported with
+    /// --watch.
+    #[arg(long = "lookup-table", value_name = "FIELD", conflicts_with = "watch")]
+    pub lookup_fields: Vec<String>,
+
+    /// Add a column computed from each document's fields (repeatable)
+    ///
+    /// Format: <name>=<expression>, where the expression joins field names
+    /// and literals (numbers, 'quoted strings') with + and -. + concatenates
+    /// when either side is text and adds numbers otherwise. A NULL or missing
+    /// field makes the result NULL. Not supported with --watch.
+    /// Example: --computed "full_name=first + ' ' + last"
+    #[arg(long = "computed", value_name = "NAME=EXPR", conflicts_with = "watch")]
+    pub computed_columns: Vec<String>,
+
+    /// Create a <table>_json view extracting the keys of embedded documents
+    ///
+    /// Embedded documents are stored as JSON TEXT; the view adds a
+    /// json_extract(<column>, '$.<key>') AS <column>_<key> column for every
+    /// top-level key seen in the sample.
+    #[arg(long, conflicts_with = "data_only")]
+    pub create_json_views: bool,
+
+    /// Add a generated column per key of embedded documents to each table
+    ///
+    /// Declares <column>_<key> GENERATED ALWAYS AS (json_extract(<column>,
+    /// '$.<key>')) VIRTUAL for every top-level key seen in the sample, so the
+    /// keys can be indexed and queried without repeating json_extract.
+    #[arg(long, conflicts_with_all = ["data_only", "create_json_views"])]
+    pub generate_json_columns: bool,
+
+    /// Add a _raw TEXT column holding each whole document as JSON
+    ///
+    /// The document is stored as canonical MongoDB Extended JSON, so nothing
+    /// is lost even for fields the inferred columns don't cover. With
+    /// --schema-file, add a _raw TEXT field to the schema to get the column.
+    #[arg(long)]
+    pub keep_raw_json: bool,
+
+    /// Migrate the output of an aggregation pipeline run on --table
+    ///
+    /// A JSON array of stages (Extended JSON allowed), e.g.
+    /// '[{"$lookup": {...}}, {"$addFields": {...}}]'. The schema is inferred
+    /// from the pipeline run on a $sample of --sample-size input documents.
+    #[arg(long, value_name = "JSON", requires = "table")]
+    pub pipeline: Option<String>,
+
+    /// Record the original BSON types of each column in a _bson_types table
+    ///
+    /// Stores the sampled BSON type names (e.g. ObjectId, Decimal128) per
+    /// collection and column, for high-fidelity reverse migration
+    #[arg(long, conflicts_with = "data_only")]
+    pub bson_type_metadata: bool,
+
+    /// Name of the primary key column that stores each document's _id
+    ///
+    /// Values are still read from _id; only the SQLite column is renamed
+    #[arg(long, value_name = "NAME", default_value = "_id")]
+    pub id_column: String,
+
+    /// Collation for TEXT columns: binary, nocase or rtrim
+    ///
+    /// Appends e.g. COLLATE NOCASE to TEXT column definitions so lookups and
+    /// ORDER BY compare case-insensitively. The primary key is left alone
+    /// unless --pk-collation is given.
+    #[arg(long, value_name = "COLLATION", conflicts_with = "data_only")]
+    pub text_collation: Option<String>,
+
+    /// Collation for a TEXT primary key column: binary, nocase or rtrim
+    #[arg(long, value_name = "COLLATION", conflicts_with = "data_only")]
+    pub pk_collation: Option<String>,
+
+    /// Run PRAGMA integrity_check on the output file after migrating
+    ///
+    /// Fails if SQLite reports any problem, printing them verbatim. Skipped
+    /// for Turso databases.
+    #[arg(long)]
+    pub integrity_check: bool,
+
+    /// Run the SQL script at this path after migrating, e.g. to add indexes or views
+    ///
+    /// Statements are split on semicolons outside quotes and comments and run
+    /// in one transaction, which is rolled back if any fails. Statements that
+    /// can't run in a transaction, such as VACUUM, are not supported.
+    #[arg(long, value_name = "PATH", conflicts_with = "gridfs")]
+    pub post_sql: Option<String>,
+
+    /// Compare the inferred schema with the existing tables, then exit
+    ///
+    /// Nothing is migrated. Differences are reported as added columns (safe
+    /// when nullable), type changes and removed columns; the exit status is
+    /// 6 if any change is breaking, for use as a CI gate.
+    #[arg(long, conflicts_with_all = ["watch", "drop_tables", "truncate", "schema_only", "data_only"])]
+    pub check_schema: bool,
+
+    /// Speed up loading into a local file by relaxing durability
+    ///
+    /// Sets PRAGMA synchronous=OFF, journal_mode=MEMORY and a larger page
+    /// cache during the migration and restores the defaults afterwards. A
+    /// crash or power loss during the load can corrupt the output file, so
+    /// only use it for files you can recreate. Ignored for Turso.
+    #[arg(long)]
+    pub fast_load: bool,
+
+    /// SQLite driver for the output database: libsql or rusqlite
+    ///
+    /// libsql writes local files and Turso; rusqlite only writes local files
+    /// and cannot be combined with --gridfs
+    #[arg(long, value_name = "DRIVER", default_value = "libsql")]
+    pub backend: String,
+
+    /// Write each collection into its own SQLite file
+    ///
+    /// Files are named after --output with the collection appended, e.g.
+    /// output_users.db and output_orders.db for --output output.db. Each
+    /// file gets its own connection, which is closed once the collection is
+    /// done. Local files only.
+    #[arg(long, conflicts_with_all = ["watch", "merges", "check_schema", "continue_token", "continue_from", "gridfs"])]
+    pub split_files: bool,
+
+    /// Report which columns take the most space after migrating
+    ///
+    /// Sums LENGTH() of every column of the migrated tables and prints them
+    /// largest first. Only local database files are analyzed.
+    #[arg(long, conflicts_with = "schema_only")]
+    pub analyze_storage: bool,
+
+    /// Run ANALYZE after migrating so the query planner has statistics
+    ///
+    /// Fills sqlite_stat1, which helps SQLite choose indexes for queries on
+    /// the migrated data. Not to be confused with --analyze-storage.
+    #[arg(long)]
+    pub analyze: bool,
+
+    /// Write per-column statistics to a _field_stats table after migrating
+    ///
+    /// Records the non-NULL count, distinct count and, for INTEGER and REAL
+    /// columns, the minimum and maximum of every migrated column. Runs one
+    /// extra query per column.
+    #[arg(long, conflicts_with = "schema_only")]
+    pub profile: bool,
+
+    /// Table and column naming: preserve, snake or lower
+    ///
+    /// snake converts camelCase, PascalCase and kebab-case names to
+    /// snake_case; lower only lowercases. Names that collide after conversion
+    /// get a numeric suffix (e.g. user_id_2). --post-filter conditions refer
+    /// to the converted column names.
+    #[arg(long, value_name = "STYLE", default_value = "preserve")]
+    pub naming: String,
+
+    /// Replace dots in collection names with underscores in table names
+    ///
+    /// On by default: foo.bar is migrated into table foo_bar, or foo_bar_2 if
+    /// another collection is named foo_bar. Pass false to keep the dots.
+    #[arg(long, value_name = "BOOL", default_value_t = true, action = clap::ArgAction::Set)]
+    pub sanitize_table_names: bool,
+
+    /// Truncate table and column names longer than N characters
+    ///
+    /// For downstream tools that cap identifier length, e.g. at 64. A
+    /// truncated name ends in a short hash of the full name, so long names
+    /// sharing a prefix stay distinct.
+    #[arg(long, value_name = "N")]
+    pub max_identifier_length: Option<usize>,
+
+    /// Keep applying changes from MongoDB change streams after migrating
+    ///
+    /// Inserts and updates are upserted on _id and deletes remove the row,
+    /// until interrupted with Ctrl+C. The last applied event is recorded in
+    /// _migration_meta, so the next --watch run resumes where this one
+    /// stopped. Requires a replica set or sharded cluster.
+    #[arg(long, conflicts_with_all = ["schema_only", "pipeline", "gridfs"])]
+    pub watch: bool,
+}
+
+impl Args {
+    /// Validate that the arguments are consistent and complete
+    ///
+    /// This function validates that:
+    /// - Either --table, --all-tables, --tables-from-file or --gridfs is specified
+    /// - --tables-from-file names a readable file listing at least one collection
+    /// - --gridfs reads from MongoDB and writes to SQLite
+    /// - --mongodb-uri is a mongodb:// or single-host mongodb+srv:// URI when reading from MongoDB
+    /// - --input bson-stream has a --table and is not piped straight back out
+    /// - --output-format parquet reads from MongoDB and --parquet-compression names a known codec
+    /// - batch_size, sample_size, read_parallelism, progress_interval, progress_log_interval and top_collections are greater than 0
+    /// - --sqlite-variable-limit, --keyset-page-size, --batch-bytes, --max-memory-mb and --limit are greater than 0
+    /// - --limit writes to SQLite; --continue-token and --continue-from read from MongoDB into SQLite
+    /// - --continue-from is a token printed by --continue-token
+    /// - --check-schema compares against SQLite tables
+    /// - every --transform is a valid <field>=<op> specification
+    /// - every --default is a <field>=<value> specification
+    /// - --keep-raw-json is not combined with --transform
+    /// - --round-real is at most MAX_ROUND_PLACES
+    /// - --max-identifier-length is at least MIN_IDENTIFIER_LENGTH
+    /// - --pipeline is a JSON array of stages and reads from MongoDB into SQLite
+    /// - --sample-query is a JSON object and reads from MongoDB
+    /// - --watch reads from MongoDB and writes to SQLite
+    /// - --dedup-strategy is memory or last-wins-sql
+    /// - --max-doc-bytes is greater than 0 and --on-oversize is skip or abort
+    /// - --on-invalid is skip or abort
+    /// - every --post-filter is a valid <collection>=<condition>
+    /// - every --computed is a valid <name>=<expression>
+    /// - every --merge is a valid <pattern>=<target>
+    /// - --backend is libsql or rusqlite, and rusqlite writes SQLite without --gridfs
+    /// - --split-files writes SQLite
+    /// - --post-sql writes SQLite and reads a script with at least one statement
+    /// - --quote-style is ansi or mysql
+    /// - --naming is preserve, snake or lower
+    /// - --id-column is not empty
+    /// - --text-collation and --pk-collation are binary, nocase or rtrim
+    /// - --datetime-as is rfc3339 or sqlite-text
+    /// - --non-finite is null or text
+    /// - --objectid-as is hex, timestamp or extjson
+    /// - --decimal-as is text, real or blob
+    /// - --legacy-uuid names a known byte order
+    /// - --sort-by names a valid field
+    pub fn validate(&self) -> Result<()> {
+        // GridFS buckets are migrated on their own
+        if self.gridfs.is_some() {
+            if self.input != "mongodb" || self.output_format != "sqlite" {
+                bail!("--gridfs requires --input mongodb and --output-format sqlite");
+            }
+        } else if let Some(ref path) = self.tables_from_file {
+            read_collection_list(path)?;
+        } else if self.table.is_none() && !self.all_tables {
+            // Ensure either --table, --all-tables or --tables-from-file is specified
+            bail!(
+                "Either --table <TABLE>, --all-tables, --tables-from-file <PATH> or --gridfs <BUCKET> must be specified"
+            );
+        }
+
+        if self.input == "mongodb" {
+            self.connection.validate()?;
+        }
+
+        // BSON stream input carries a single unnamed collection
+        if self.input == "bson-stream" {
+            if self.table.is_none() {
+                bail!("--input bson-stream requires --table <TABLE>");
+            }
+            if self.output_format == "bson-stream" {
+                bail!("--input bson-stream cannot be combined with --output-format bson-stream");
+            }
+        }
+
+        if self.output_format == "parquet" && self.input != "mongodb" {
+            bail!("--output-format parquet requires --input mongodb");
+        }
+        self.parquet_compression.parse::<ParquetCompression>()?;
+
+        // Validate batch size
+        if self.batch_size == 0 {
+            bail!("--batch-size must be greater than 0");
+        }
+
+        // Validate sample size
+        if self.sample_size == 0 {
+            bail!("--sample-size must be greater than 0");
+        }
+
+        // Change streams are read from MongoDB and applied to SQLite
+        if self.watch && (self.input != "mongodb" || self.output_format != "sqlite") {
+            bail!("--watch requires --input mongodb and --output-format sqlite");
+        }
+
+        // Validate aggregation pipeline
+        if let Some(ref pipeline) = self.pipeline {
+            parse_pipeline(pipeline)?;
+            if self.input != "mongodb" || self.output_format != "sqlite" {
+                bail!("--pipeline requires --input mongodb and --output-format sqlite");
+            }
+        }
+
+        if let Some(ref sample_query) = self.sample_query {
+            parse_sample_query(sample_query)?;
+            if self.input != "mongodb" {
+                bail!("--sample-query requires --input mongodb");
+            }
+        }
+
+        if self.read_parallelism == 0 {
+            bail!("--read-parallelism must be greater than 0");
+        }
+
+        if self.sqlite_variable_limit == 0 {
+            bail!("--sqlite-variable-limit must be greater than 0");
+        }
+
+        if self.keyset_page_size == Some(0) {
+            bail!("--keyset-page-size must be greater than 0");
+        }
+
+        // Chunked runs go through the migrator, which reads MongoDB by _id
+        if self.limit == Some(0) {
+            bail!("--limit must be greater than 0");
+        }
+        if self.limit.is_some() && self.output_format != "sqlite" {
+            bail!("--limit requires --output-format sqlite");
+        }
+        let continued = self.continue_token || self.continue_from.is_some();
+        if continued && (self.input != "mongodb" || self.output_format != "sqlite") {
+            bail!("--continue-token and --continue-from require --input mongodb and --output-format sqlite");
+        }
+        if let Some(ref token) = self.continue_from {
+            ContinuationToken::parse(token)?;
+        }
+
+        if self.check_schema && self.output_format != "sqlite" {
+            bail!("--check-schema requires --output-format sqlite");
+        }
+
+        if self.top_collections == Some(0) {
+            bail!("--top-collections must be greater than 0");
+        }
+
+        // Validate progress interval
+        if self.progress_interval == Some(0) {
+            bail!("--progress-interval must be greater than 0");
+        }
+        if self.progress_log_interval == 0 {
+            bail!("--progress-log-interval must be greater than 0");
+        }
+
+        // Validate transform specifications
+        ConverterOptions::from_transform_specs(&self.transforms)?;
+        parse_default_specs(&self.defaults)?;
+
+        if self.round_real.is_some_and(|places| places > MAX_ROUND_PLACES) {
+            bail!("--round-real must be at most {} decimal places", MAX_ROUND_PLACES);
+        }
+        if self.max_identifier_length.is_some_and(|length| length < MIN_IDENTIFIER_LENGTH) {
+            bail!("--max-identifier-length must be at least {}", MIN_IDENTIFIER_LENGTH);
+        }
+
+        // The raw document would expose the values --transform hides
+        if self.keep_raw_json && !self.transforms.is_empty() {
+            bail!("--keep-raw-json cannot be combined with --transform");
+        }
+
+        // Validate deduplication strategy
+        self.dedup_strategy.parse::<DedupStrategy>()?;
+
+        // Validate document size limit
+        if self.max_doc_bytes == Some(0) {
+            bail!("--max-doc-bytes must be greater than 0");
+        }
+        if self.batch_bytes == Some(0) {
+            bail!("--batch-bytes must be greater than 0");
+        }
+        if self.max_memory_mb == Some(0) {
+            bail!("--max-memory-mb must be greater than 0");
+        }
+        self.on_oversize.parse::<OversizePolicy>()?;
+        self.on_invalid.parse::<InvalidDocumentPolicy>()?;
+
+        // Validate post-filter conditions
+        parse_post_filter_specs(&self.post_filters)?;
+
+        // Validate computed column expressions
+        parse_computed_specs(&self.computed_columns)?;
+
+        // Validate merge rules
+        for merge in &self.merges {
+            merge.parse::<MergeSpec>()?;
+        }
+
+        // Validate the SQLite driver
+        if self.backend.parse::<Backend>()? == Backend::Rusqlite
+            && (self.output_format != "sqlite" || self.gridfs.is_some())
+        {
+            bail!("--backend rusqlite requires --output-format sqlite and cannot be combined with --gridfs");
+        }
+
+        if self.split_files && self.output_format != "sqlite" {
+            bail!("--split-files requires --output-format sqlite");
+        }
+
+        if let Some(path) = &self.post_sql {
+            if self.output_format != "sqlite" {
+                bail!("--post-sql requires --output-format sqlite");
+            }
+            read_post_sql(path)?;
+        }
+
+        // Validate identifier quoting style
+        self.quote_style.parse::<QuoteStyle>()?;
+
+        // Validate table/column naming style
+        self.naming.parse::<NamingStyle>()?;
+
+        // Validate the primary key column name
+        if self.id_column.trim().is_empty() {
+            bail!("--id-column must not be empty");
+        }
+
+        // Validate collations
+        for collation in [&self.text_collation, &self.pk_collation].into_iter().flatten() {
+            collation.parse::<Collation>()?;
+        }
+
+        // Validate datetime format
+        self.datetime_as.parse::<DatetimeFormat>()?;
+        self.non_finite.parse::<NonFiniteMode>()?;
+        self.objectid_as.parse::<ObjectIdFormat>()?;
+        self.decimal_as.parse::<DecimalFormat>()?;
+
+        // Validate legacy UUID encoding
+        self.legacy_uuid.parse::<LegacyUuidEncoding>()?;
+
+        // Validate sort field
+        if let Some(ref sort_by) = self.sort_by {
+            build_sort_document(sort_by)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base_args() -> Args {
+        Args {
             database: "test".to_string(),
-            mongodb_uri: "mongodb://localhost:27017".to_string(),
+            config: None,
+            connection: ConnectionArgs {
+                mongodb_uri: "mongodb://localhost:27017".to_string(),
+                tls_ca_file: None,
+                tls_allow_invalid_certs: false,
+                tls_cert_key_file: None,
+                auth_mechanism: None,
+                auth_username: None,
+                auth_password: None,
+                auth_source: None,
+                aws_session_token: None,
+                mongo_max_time_ms: None,
+            },
             table: Some("users".to_string()),
             all_tables: false,
+            tables_from_file: None,
+            gridfs: None,
+            externalize_binary: None,
             schema_only: false,
             data_only: false,
+            upsert: false,
+            skip_conflicts: false,
+            no_transaction: false,
+            no_primary_key: false,
+            truncate: false,
+            drop_tables: false,
+            overwrite: false,
+            yes: false,
             output: Some("output.db".to_string()),
             batch_size: 1000,
+            batch_bytes: None,
+            max_memory_mb: None,
+            sqlite_variable_limit: DEFAULT_SQLITE_VARIABLE_LIMIT,
             sample_size: 100,
+            sample_query: None,
+            progress_interval: None,
+            no_progress: false,
+            progress_log_interval: 10000,
+            top_collections: None,
+            heartbeat_secs: 30,
+            transforms: Vec::new(),
+            defaults: Vec::new(),
+            merges: Vec::new(),
+            skip_empty: false,
+            error_on_empty: false,
+            sort_by: None,
+            read_parallelism: 1,
+            keyset_page_size: None,
+            limit: None,
+            continue_token: false,
+            continue_from: None,
+            extended_json_types: false,
+            explain_schema: false,
+            require_id: true,
+            schema_file: None,
+            datetime_as: "rfc3339".to_string(),
+            legacy_uuid: "standard".to_string(),
+            dedup_by: None,
+            dedup_strategy: "memory".to_string(),
+            max_doc_bytes: None,
+            on_oversize: "skip".to_string(),
+            validate_bson: false,
+            strict: false,
+            on_invalid: "skip".to_string(),
+            post_filters: Vec::new(),
+            input: "mongodb".to_string(),
+            output_format: "sqlite".to_string(),
+            parquet_compression: "snappy".to_string(),
+            resume_schema: false,
+            quote_style: "ansi".to_string(),
+            coerce_numeric_strings: false,
+            continue_on_error: false,
+            alter_add_columns: false,
+            map_to_existing: false,
+            evolve_schema: false,
+            keep_raw_json: false,
+            round_real: None,
+            non_finite: "null".to_string(),
+            objectid_as: "hex".to_string(),
+            decimal_as: "text".to_string(),
+            pretty_json: false,
+            base64_blob_fields: Vec::new(),
+            lookup_fields: Vec::new(),
+            computed_columns: Vec::new(),
+            create_json_views: false,
+            generate_json_columns: false,
+            pipeline: None,
+            bson_type_metadata: false,
+            analyze_storage: false,
+            analyze: false,
+            profile: false,
+            integrity_check: false,
+            post_sql: None,
+            check_schema: false,
+            fast_load: false,
+            backend: "libsql".to_string(),
+            split_files: false,
+            id_column: "_id".to_string(),
+            text_collation: None,
+            pk_collation: None,
+            naming: "preserve".to_string(),
+            sanitize_table_names: true,
+            max_identifier_length: None,
+            watch: false,
+        }
+    }
+
+    #[test]
+    fn test_validate_missing_table_flags() {
+        let args = Args {
+            table: None,
+            ..base_args()
+        };
+
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_zero_batch_size() {
+        let args = Args {
+            batch_size: 0,
+            ..base_args()
+        };
+
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_valid_args() {
+        let args = base_args();
+
+        assert!(args.validate().is_ok());
+    }
+
+    fn parse(args: &[&str]) -> Command {
+        let args = args.iter().map(OsString::from).collect();
+        Cli::parse_from(with_default_subcommand(args)).command
+    }
+
+    fn parse_migrate(args: &[&str]) -> Args {
+        match parse(args) {
+            Command::Migrate(args) => *args,
+            other => panic!("Expected migrate, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_bare_sort_by() {
+        let args = parse_migrate(&["mongo-to-sqlite", "-d", "test", "--all-tables", "--sort-by"]);
+        assert_eq!(args.sort_by.as_deref(), Some("_id"));
+
+        let args = parse_migrate(&["mongo-to-sqlite", "-d", "test", "--all-tables", "--sort-by=-age"]);
+        assert_eq!(args.sort_by.as_deref(), Some("-age"));
+    }
+
+    /// Parse `migrate` arguments with --batch-size and --max-doc-bytes read
+    /// from the given variables
+    ///
+    /// Setting the real BATCH_SIZE / MAX_DOC_BYTES would leak into other
+    /// tests parsing in parallel, so each caller uses its own names.
+    fn parse_migrate_with_env(env: [&'static str; 2], args: &[&str]) -> Args {
+        use clap::FromArgMatches;
+
+        let command = Cli::command().mut_subcommand("migrate", |migrate| {
+            migrate
+                .mut_arg("batch_size", |arg| arg.env(env[0]))
+                .mut_arg("max_doc_bytes", |arg| arg.env(env[1]))
+        });
+        let matches = command.try_get_matches_from(args).unwrap();
+        match Cli::from_arg_matches(&matches).unwrap().command {
+            Command::Migrate(args) => *args,
+            other => panic!("Expected migrate, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_numeric_options_from_env() {
+        std::env::set_var("CLI_TEST_ENV_BATCH_SIZE", "250");
+        std::env::set_var("CLI_TEST_ENV_MAX_DOC_BYTES", "4096");
+
+        let env = ["CLI_TEST_ENV_BATCH_SIZE", "CLI_TEST_ENV_MAX_DOC_BYTES"];
+        let args = parse_migrate_with_env(env, &["mongo-to-sqlite", "migrate", "-d", "test", "--all-tables"]);
+        assert_eq!(args.batch_size, 250);
+        assert_eq!(args.max_doc_bytes, Some(4096));
+
+        // An explicit flag wins over the environment
+        let args = parse_migrate_with_env(
+            env,
+            &["mongo-to-sqlite", "migrate", "-d", "test", "--all-tables", "--batch-size", "10"],
+        );
+        assert_eq!(args.batch_size, 10);
+
+        // Environment values are validated like flags
+        std::env::set_var("CLI_TEST_ENV_INVALID_BATCH_SIZE", "0");
+        let env = ["CLI_TEST_ENV_INVALID_BATCH_SIZE", "CLI_TEST_ENV_INVALID_MAX_DOC_BYTES"];
+        let args = parse_migrate_with_env(env, &["mongo-to-sqlite", "migrate", "-d", "test", "--all-tables"]);
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_default_subcommand() {
+        let args = parse_migrate(&["mongo-to-sqlite", "-d", "test", "--all-tables"]);
+        assert_eq!(args.database, "test");
+        assert!(args.all_tables);
+    }
+
+    #[test]
+    fn test_parse_subcommands() {
+        let args = parse_migrate(&["mongo-to-sqlite", "migrate", "-d", "test", "-t", "users"]);
+        assert_eq!(args.table.as_deref(), Some("users"));
+
+        match parse(&["mongo-to-sqlite", "inspect", "-d", "test"]) {
+            Command::Inspect(args) => {
+                assert_eq!(args.database, "test");
+                assert_eq!(args.sample_size, 100);
+            }
+            other => panic!("Expected inspect, got {:?}", other),
+        }
+
+        match parse(&["mongo-to-sqlite", "export", "-d", "test", "--all-tables"]) {
+            Command::Export(args) => {
+                assert_eq!(args.format, "jsonl");
+                assert!(args.validate().is_ok());
+            }
+            other => panic!("Expected export, got {:?}", other),
+        }
+
+        match parse(&["mongo-to-sqlite", "export", "-d", "test", "--all-tables", "--format", "csv"]) {
+            // One CSV file cannot hold several collections
+            Command::Export(args) => assert!(args.validate().is_err()),
+            other => panic!("Expected export, got {:?}", other),
+        }
+
+        match parse(&["mongo-to-sqlite", "export", "-d", "test", "-t", "users", "--column-comments"]) {
+            // Only a SQL dump has column definitions to comment
+            Command::Export(args) => assert!(args.validate().is_err()),
+            other => panic!("Expected export, got {:?}", other),
+        }
+
+        match parse(&["mongo-to-sqlite", "check"]) {
+            Command::Check(args) => assert!(args.database.is_none()),
+            other => panic!("Expected check, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_bson_stream_input() {
+        let args = Args {
+            input: "bson-stream".to_string(),
+            ..base_args()
+        };
+        assert!(args.validate().is_ok());
+
+        let args = Args {
+            input: "bson-stream".to_string(),
+            table: None,
+            all_tables: true,
+            ..base_args()
+        };
+        assert!(args.validate().is_err());
+
+        let args = Args {
+            input: "bson-stream".to_string(),
+            output_format: "bson-stream".to_string(),
+            ..base_args()
+        };
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_backend() {
+        let args = Args {
+            backend: "rusqlite".to_string(),
+            ..base_args()
+        };
+        assert!(args.validate().is_ok());
+
+        let args = Args {
+            backend: "sqlx".to_string(),
+            ..base_args()
+        };
+        assert!(args.validate().is_err());
+
+        let args = Args {
+            backend: "rusqlite".to_string(),
+            table: None,
+            gridfs: Some("fs".to_string()),
+            ..base_args()
+        };
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_collection_list() {
+        let text = "# nightly export\nusers\n\n  orders  \n# audit_log\nevents.2024\n";
+        assert_eq!(parse_collection_list(text), vec!["users", "orders", "events.2024"]);
+        assert!(parse_collection_list("# nothing\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_validate_tables_from_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let args = Args {
+            table: None,
+            tables_from_file: Some(file.path().to_string_lossy().into_owned()),
+            ..base_args()
+        };
+        assert!(args.validate().is_err());
+
+        std::io::Write::write_all(&mut file, b"users\n").unwrap();
+        assert!(args.validate().is_ok());
+
+        let args = Args {
+            table: None,
+            tables_from_file: Some("/nonexistent/collections.txt".to_string()),
+            ..base_args()
+        };
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_gridfs() {
+        let args = Args {
+            table: None,
+            gridfs: Some("fs".to_string()),
+            ..base_args()
+        };
+        assert!(args.validate().is_ok());
+
+        let args = Args {
+            table: None,
+            gridfs: Some("fs".to_string()),
+            output_format: "bson-stream".to_string(),
+            ..base_args()
+        };
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_pipeline() {
+        let args = Args {
+            pipeline: Some(r#"[{"$match": {"active": true}}]"#.to_string()),
+            ..base_args()
+        };
+        assert!(args.validate().is_ok());
+
+        let args = Args {
+            pipeline: Some(r#"{"$match": {"active": true}}"#.to_string()),
+            ..base_args()
+        };
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let options = parse_config(
+            r#"
+            database = "app"
+            all_tables = true
+            dry-run = false
+            batch-size = 50
+            transform = ["email=lower", "ssn=redact"]
+            "#,
+        )
+        .unwrap();
+
+        assert!(options.contains(&("database".to_string(), vec!["app".to_string()])));
+        assert!(options.contains(&("all-tables".to_string(), vec![])));
+        assert!(options.contains(&("batch-size".to_string(), vec!["50".to_string()])));
+        assert!(options.contains(&(
+            "transform".to_string(),
+            vec!["email=lower".to_string(), "ssn=redact".to_string()]
+        )));
+        // false flags are left off
+        assert_eq!(options.len(), 4);
+
+        assert!(parse_config("[nested]\nkey = 1").is_err());
+        assert!(parse_config("database = ").is_err());
+    }
+
+    #[test]
+    fn test_config_file_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("migrate.toml");
+        std::fs::write(
+            &path,
+            "database = \"file_db\"\nbatch-size = 50\nall-tables = true\n",
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = ["mongo-to-sqlite", "-d", "cli_db", "--config", path]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        let merged = merge_config_file(with_default_subcommand(args)).unwrap();
+        let args = match Cli::parse_from(merged).command {
+            Command::Migrate(args) => *args,
+            other => panic!("Expected migrate, got {:?}", other),
         };
 
+        // The flag wins over the file; the rest comes from the file
+        assert_eq!(args.database, "cli_db");
+        assert_eq!(args.batch_size, 50);
+        assert!(args.all_tables);
         assert!(args.validate().is_ok());
+
+        std::fs::write(dir.path().join("bad.toml"), "no-such-option = 1\n").unwrap();
+        let bad = dir.path().join("bad.toml");
+        let args = ["mongo-to-sqlite", "--config", bad.to_str().unwrap()]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        assert!(merge_config_file(with_default_subcommand(args)).is_err());
+    }
+
+    #[test]
+    fn test_validate_invalid_transform() {
+        let args = Args {
+            transforms: vec!["email=reverse".to_string()],
+            ..base_args()
+        };
+
+        assert!(args.validate().is_err());
     }
 }
@@ -0,0 +1,363 @@
+use anyhow::{bail, Result};
+use libsql::Value as SqlValue;
+use std::str::FromStr;
+
+use crate::filter::Literal;
+use crate::schema::Field;
+
+/// Operator joining two operands of a computed column expression
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArithOp {
+    /// Concatenate when either side is text, add numbers otherwise
+    Add,
+    /// Subtract numbers
+    Sub,
+}
+
+/// Operand of a computed column expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    /// Value of a document field, by MongoDB field name or column name
+    Field(String),
+    /// A constant
+    Literal(Literal),
+}
+
+impl FromStr for Operand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            bail!("Missing operand");
+        }
+
+        if s.starts_with('\'') || s.eq_ignore_ascii_case("null") || s.parse::<f64>().is_ok() {
+            return Ok(Operand::Literal(s.parse()?));
+        }
+
+        if s.contains(char::is_whitespace) {
+            bail!("Invalid operand '{}': expected a field name or a literal", s);
+        }
+
+        Ok(Operand::Field(s.to_string()))
+    }
+}
+
+impl Operand {
+    /// Value of this operand in a converted row
+    fn value(&self, row: &[SqlValue], fields: &[Field]) -> SqlValue {
+        match self {
+            Operand::Field(name) => field_index(fields, name)
+                .and_then(|index| row.get(index).cloned())
+                .unwrap_or(SqlValue::Null),
+            Operand::Literal(Literal::Null) => SqlValue::Null,
+            Operand::Literal(Literal::Integer(i)) => SqlValue::Integer(*i),
+            Operand::Literal(Literal::Real(r)) => SqlValue::Real(*r),
+            Operand::Literal(Literal::Text(s)) => SqlValue::Text(s.clone()),
+        }
+    }
+
+    /// SQLite type of this operand's values, or `None` for NULL
+    fn sql_type<'a>(&self, fields: &'a [Field]) -> Option<&'a str> {
+        match self {
+            Operand::Field(name) => Some(
+                field_index(fields, name)
+                    .map(|index| fields[index].sql_type.as_str())
+                    .unwrap_or("TEXT"),
+            ),
+            Operand::Literal(Literal::Null) => None,
+            Operand::Literal(Literal::Integer(_)) => Some("INTEGER"),
+            Operand::Literal(Literal::Real(_)) => Some("REAL"),
+            Operand::Literal(Literal::Text(_)) => Some("TEXT"),
+        }
+    }
+}
+
+/// Find the column of a field referenced in an expression
+fn field_index(fields: &[Field], name: &str) -> Option<usize> {
+    fields
+        .iter()
+        .position(|field| field.source_key() == name)
+        .or_else(|| fields.iter().position(|field| field.name == name))
+}
+
+/// An extra column computed from each document's field values
+///
+/// Grammar: `<operand> [(+|-) <operand>]...`, evaluated left to right,
+/// where an operand is a field name or a literal: an integer, a real number,
+/// a single-quoted string (`''` escapes a quote) or `NULL`.
+///
+/// `+` concatenates when either side is text and adds numbers otherwise;
+/// `-` subtracts numbers. As in SQL, a NULL operand makes the result NULL,
+/// and so does subtracting text or using a BLOB.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputedColumn {
+    pub name: String,
+    pub first: Operand,
+    pub rest: Vec<(ArithOp, Operand)>,
+}
+
+impl FromStr for ComputedColumn {
+    type Err = anyhow::Error;
+
+    /// Parse a `<name>=<expression>` specification
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((name, expression)) = s.split_once('=') else {
+            bail!("Invalid computed column '{}': expected <name>=<expression>", s);
+        };
+
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("Invalid computed column '{}': column name is empty", s);
+        }
+
+        let (first, rest) = parse_expression(expression)
+            .map_err(|e| anyhow::anyhow!("Invalid computed column '{}': {}", s, e))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            first,
+            rest,
+        })
+    }
+}
+
+/// Split an expression into operands at `+` and `-` outside quotes
+///
+/// A `-` with no operand before it is a sign, e.g. in `price - -1`.
+fn parse_expression(expression: &str) -> Result<(Operand, Vec<(ArithOp, Operand)>)> {
+    let mut operands = Vec::new();
+    let mut ops = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in expression.chars() {
+        match c {
+            // '' inside a string toggles twice, leaving the escape to Literal
+            '\'' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            '+' | '-' if !in_quotes && !current.trim().is_empty() => {
+                operands.push(std::mem::take(&mut current).parse::<Operand>()?);
+                ops.push(if c == '+' { ArithOp::Add } else { ArithOp::Sub });
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if in_quotes {
+        bail!("unterminated string");
+    }
+    operands.push(current.parse::<Operand>()?);
+
+    let mut operands = operands.into_iter();
+    let first = operands.next().expect("at least one operand");
+    Ok((first, ops.into_iter().zip(operands).collect()))
+}
+
+/// Numeric value of a SQL value, if it is a number
+fn as_number(value: &SqlValue) -> Option<f64> {
+    match value {
+        SqlValue::Integer(i) => Some(*i as f64),
+        SqlValue::Real(r) => Some(*r),
+        _ => None,
+    }
+}
+
+/// Text of a SQL value for concatenation, if it has one
+fn as_text(value: &SqlValue) -> Option<String> {
+    match value {
+        SqlValue::Integer(i) => Some(i.to_string()),
+        SqlValue::Real(r) => Some(r.to_string()),
+        SqlValue::Text(s) => Some(s.clone()),
+        SqlValue::Null | SqlValue::Blob(_) => None,
+    }
+}
+
+/// Apply one operator to two values
+fn apply(op: ArithOp, left: SqlValue, right: SqlValue) -> SqlValue {
+    match (op, &left, &right) {
+        (_, SqlValue::Integer(a), SqlValue::Integer(b)) => {
+            let result = match op {
+                ArithOp::Add => a.checked_add(*b),
+                ArithOp::Sub => a.checked_sub(*b),
+            };
+            // Overflowing integers fall back to REAL, like SQLite
+            result.map(SqlValue::Integer).unwrap_or_else(|| {
+                let (a, b) = (*a as f64, *b as f64);
+                SqlValue::Real(if op == ArithOp::Add { a + b } else { a - b })
+            })
+        }
+        (ArithOp::Add, SqlValue::Text(_), _) | (ArithOp::Add, _, SqlValue::Text(_)) => {
+            match (as_text(&left), as_text(&right)) {
+                (Some(a), Some(b)) => SqlValue::Text(a + &b),
+                _ => SqlValue::Null,
+            }
+        }
+        _ => match (as_number(&left), as_number(&right)) {
+            (Some(a), Some(b)) => SqlValue::Real(if op == ArithOp::Add { a + b } else { a - b }),
+            _ => SqlValue::Null,
+        },
+    }
+}
+
+impl ComputedColumn {
+    /// Evaluate the expression against a converted row
+    ///
+    /// # Arguments
+    /// * `row` - Converted values of the document, in field order
+    /// * `fields` - Ordered target fields of the table
+    ///
+    /// # Returns
+    /// The computed value
+    pub fn evaluate(&self, row: &[SqlValue], fields: &[Field]) -> SqlValue {
+        self.rest
+            .iter()
+            .fold(self.first.value(row, fields), |acc, (op, operand)| {
+                apply(*op, acc, operand.value(row, fields))
+            })
+    }
+
+    /// Infer the column type of the expression
+    ///
+    /// Only integers give INTEGER and numbers give REAL; anything else
+    /// concatenated with `+` gives TEXT, and subtractions stay numeric.
+    ///
+    /// # Arguments
+    /// * `fields` - Ordered target fields of the table
+    ///
+    /// # Returns
+    /// SQLite type of the computed column
+    pub fn sql_type(&self, fields: &[Field]) -> &'static str {
+        let types: Vec<&str> = std::iter::once(&self.first)
+            .chain(self.rest.iter().map(|(_, operand)| operand))
+            .filter_map(|operand| operand.sql_type(fields))
+            .collect();
+        let subtracts = self.rest.iter().any(|(op, _)| *op == ArithOp::Sub);
+
+        if types.iter().all(|t| *t == "INTEGER") && !types.is_empty() {
+            "INTEGER"
+        } else if subtracts || (types.iter().all(|t| matches!(*t, "INTEGER" | "REAL")) && !types.is_empty()) {
+            "REAL"
+        } else {
+            "TEXT"
+        }
+    }
+}
+
+/// Parse `--computed <name>=<expression>` specifications
+///
+/// # Arguments
+/// * `specs` - Computed column specifications as given on the command line
+///
+/// # Returns
+/// The computed columns, in the order given
+pub fn parse_computed_specs(specs: &[String]) -> Result<Vec<ComputedColumn>> {
+    let mut columns: Vec<ComputedColumn> = Vec::new();
+
+    for spec in specs {
+        let column: ComputedColumn = spec.parse()?;
+        if columns.iter().any(|other| other.name == column.name) {
+            bail!("Computed column '{}' is defined more than once", column.name);
+        }
+        columns.push(column);
+    }
+
+    Ok(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converter::{document_to_sql_values, ConverterOptions};
+    use crate::schema::{CollectionSchema, InferenceOptions, SchemaInferrer};
+    use bson::doc;
+    use std::collections::HashSet;
+
+    /// Infer a schema from the documents, add the column and evaluate it per document
+    fn compute(spec: &str, docs: &[bson::Document]) -> (CollectionSchema, Vec<SqlValue>) {
+        let column: ComputedColumn = spec.parse().unwrap();
+        let schema = SchemaInferrer::infer_schema("people", docs, &InferenceOptions::default())
+            .unwrap()
+            .with_computed_columns(std::slice::from_ref(&column))
+            .unwrap();
+
+        let values = docs
+            .iter()
+            .map(|doc| {
+                let row = document_to_sql_values(
+                    doc,
+                    &schema.fields,
+                    &ConverterOptions::default(),
+                    &mut HashSet::new(),
+                );
+                column.evaluate(&row, &schema.fields)
+            })
+            .collect();
+        (schema, values)
+    }
+
+    #[test]
+    fn test_concat_expression() {
+        let docs = [
+            doc! { "_id": 1, "first": "Ada", "last": "Lovelace" },
+            doc! { "_id": 2, "first": "Alan", "last": "Turing" },
+            doc! { "_id": 3, "first": "Grace" },
+        ];
+        let (schema, values) = compute("full_name=first + ' ' + last", &docs);
+
+        let column = schema.fields.last().unwrap();
+        assert_eq!(column.name, "full_name");
+        assert_eq!(column.sql_type, "TEXT");
+        assert_eq!(
+            values,
+            vec![
+                SqlValue::Text("Ada Lovelace".to_string()),
+                SqlValue::Text("Alan Turing".to_string()),
+                // A missing field is NULL, which makes the result NULL
+                SqlValue::Null,
+            ]
+        );
+
+        let (_, values) = compute("label='#' + _id + ': it''s ' + first", &docs[..1]);
+        assert_eq!(values, vec![SqlValue::Text("#1: it's Ada".to_string())]);
+    }
+
+    #[test]
+    fn test_numeric_expression() {
+        let docs = [
+            doc! { "_id": 1, "price": 10, "discount": 3, "fee": 0.5 },
+            doc! { "_id": 2, "price": 7, "discount": 0, "fee": 1.25 },
+        ];
+
+        let (schema, values) = compute("net=price - discount + 1", &docs);
+        assert_eq!(schema.fields.last().unwrap().sql_type, "INTEGER");
+        assert_eq!(values, vec![SqlValue::Integer(8), SqlValue::Integer(8)]);
+
+        let (schema, values) = compute("total = price + fee - -1", &docs);
+        assert_eq!(schema.fields.last().unwrap().sql_type, "REAL");
+        assert_eq!(values, vec![SqlValue::Real(11.5), SqlValue::Real(9.25)]);
+
+        // Text can't be subtracted
+        assert_eq!(
+            apply(ArithOp::Sub, SqlValue::Text("a".to_string()), SqlValue::Integer(1)),
+            SqlValue::Null
+        );
+    }
+
+    #[test]
+    fn test_parse_computed_specs() {
+        let columns = parse_computed_specs(&["a=x + 1".to_string()]).unwrap();
+        assert_eq!(columns[0].first, Operand::Field("x".to_string()));
+        assert_eq!(columns[0].rest, vec![(ArithOp::Add, Operand::Literal(Literal::Integer(1)))]);
+
+        assert!(parse_computed_specs(&["a=x".to_string(), "a=y".to_string()]).is_err());
+        assert!("no_expression".parse::<ComputedColumn>().is_err());
+        assert!("=x + 1".parse::<ComputedColumn>().is_err());
+        assert!("a=x +".parse::<ComputedColumn>().is_err());
+        assert!("a='open".parse::<ComputedColumn>().is_err());
+        assert!("a=first name".parse::<ComputedColumn>().is_err());
+    }
+}
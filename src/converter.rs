@@ -1,9 +1,606 @@
-use bson::{Bson, Document};
+use anyhow::{bail, Result};
+use bson::{oid::ObjectId, spec::BinarySubtype, Binary, Bson, Document};
 use chrono::{DateTime, Utc};
 use libsql::Value as SqlValue;
-use serde_json;
+use sha2::{Digest, Sha256};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use tracing::warn;
 
+use crate::schema::{bson_type_name, Field, RAW_JSON_FIELD};
+
+/// Largest integer magnitude that a REAL (IEEE 754 double) can represent exactly (2^53)
+const MAX_SAFE_INTEGER: i64 = 1 << 53;
+
+/// A built-in value transformation applied to a field during migration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransformOp {
+    /// Convert text to lowercase
+    Lower,
+    /// Convert text to uppercase
+    Upper,
+    /// Replace the value with its SHA-256 hex digest
+    Sha256,
+    /// Replace the value with a fixed placeholder
+    Redact,
+}
+
+impl TransformOp {
+    /// Apply this transformation to a converted SQL value
+    ///
+    /// NULL values are left untouched by every operation.
+    ///
+    /// # Arguments
+    /// * `value` - The SQL value to transform
+    ///
+    /// # Returns
+    /// The transformed SQL value
+    pub fn apply(&self, value: SqlValue) -> SqlValue {
+        match (self, value) {
+            (_, SqlValue::Null) => SqlValue::Null,
+            (TransformOp::Lower, SqlValue::Text(s)) => SqlValue::Text(s.to_lowercase()),
+            (TransformOp::Upper, SqlValue::Text(s)) => SqlValue::Text(s.to_uppercase()),
+            (TransformOp::Lower | TransformOp::Upper, other) => other,
+            (TransformOp::Sha256, SqlValue::Text(s)) => sha256_hex(s.as_bytes()),
+            (TransformOp::Sha256, SqlValue::Blob(b)) => sha256_hex(&b),
+            (TransformOp::Sha256, SqlValue::Integer(i)) => sha256_hex(i.to_string().as_bytes()),
+            (TransformOp::Sha256, SqlValue::Real(r)) => sha256_hex(r.to_string().as_bytes()),
+            (TransformOp::Redact, _) => SqlValue::Text("[REDACTED]".to_string()),
+        }
+    }
+}
+
+/// Hash bytes with SHA-256 and return the hex digest as a text value
+fn sha256_hex(bytes: &[u8]) -> SqlValue {
+    SqlValue::Text(hex::encode(Sha256::digest(bytes)))
+}
+
+impl FromStr for TransformOp {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "lower" => Ok(TransformOp::Lower),
+            "upper" => Ok(TransformOp::Upper),
+            "sha256" => Ok(TransformOp::Sha256),
+            "redact" => Ok(TransformOp::Redact),
+            other => bail!(
+                "Unknown transform '{}' (expected one of: lower, upper, sha256, redact)",
+                other
+            ),
+        }
+    }
+}
+
+/// Byte order used by legacy (subtype 3) UUID binaries
+///
+/// Older drivers each wrote subtype 3 UUIDs with their own byte order, so the
+/// bytes must be reordered before formatting depending on which driver wrote them.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LegacyUuidEncoding {
+    /// Bytes are already in standard order (Python driver, and subtype 4)
+    #[default]
+    Standard,
+    /// C# driver layout: first three groups are little-endian
+    CSharp,
+    /// Java driver layout: each 8-byte half is reversed
+    Java,
+}
+
+impl LegacyUuidEncoding {
+    /// Reorder legacy UUID bytes into standard order
+    fn reorder(&self, bytes: &mut [u8; 16]) {
+        match self {
+            LegacyUuidEncoding::Standard => {}
+            LegacyUuidEncoding::CSharp => {
+                bytes[0..4].reverse();
+                bytes[4..6].reverse();
+                bytes[6..8].reverse();
+            }
+            LegacyUuidEncoding::Java => {
+                bytes[0..8].reverse();
+                bytes[8..16].reverse();
+            }
+        }
+    }
+}
+
+impl FromStr for LegacyUuidEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "standard" | "python" => Ok(LegacyUuidEncoding::Standard),
+            "csharp" => Ok(LegacyUuidEncoding::CSharp),
+            "java" => Ok(LegacyUuidEncoding::Java),
+            other => bail!(
+                "Unknown legacy UUID encoding '{}' (expected one of: standard, csharp, java)",
+                other
+            ),
+        }
+    }
+}
+
+/// Text format for BSON DateTime values
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DatetimeFormat {
+    /// RFC 3339 with an explicit offset, e.g. `2024-01-15T10:30:00+00:00`
+    #[default]
+    Rfc3339,
+    /// SQLite's own `YYYY-MM-DD HH:MM:SS.SSS` UTC format, e.g. `2024-01-15 10:30:00.000`
+    SqliteText,
+}
+
+impl DatetimeFormat {
+    /// Format a UTC datetime in this format
+    ///
+    /// # Arguments
+    /// * `datetime` - The datetime to format
+    ///
+    /// # Returns
+    /// The formatted text
+    pub fn format(&self, datetime: &DateTime<Utc>) -> String {
+        match self {
+            DatetimeFormat::Rfc3339 => datetime.to_rfc3339(),
+            DatetimeFormat::SqliteText => datetime.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+        }
+    }
+}
+
+impl FromStr for DatetimeFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "rfc3339" => Ok(DatetimeFormat::Rfc3339),
+            "sqlite-text" => Ok(DatetimeFormat::SqliteText),
+            other => bail!(
+                "Unknown datetime format '{}' (expected one of: rfc3339, sqlite-text)",
+                other
+            ),
+        }
+    }
+}
+
+/// Representation of ObjectId values
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ObjectIdFormat {
+    /// 24-character hex string, e.g. `507f1f77bcf86cd799439011`
+    #[default]
+    Hex,
+    /// The embedded creation time, as INTEGER Unix seconds
+    Timestamp,
+    /// Extended JSON envelope, e.g. `{"$oid":"507f1f77bcf86cd799439011"}`
+    ExtJson,
+}
+
+impl ObjectIdFormat {
+    /// SQLite column type for ObjectId values in this format
+    pub fn sql_type(&self) -> &'static str {
+        match self {
+            ObjectIdFormat::Hex | ObjectIdFormat::ExtJson => "TEXT",
+            ObjectIdFormat::Timestamp => "INTEGER",
+        }
+    }
+
+    /// Convert an ObjectId in this format
+    ///
+    /// # Arguments
+    /// * `oid` - The ObjectId to convert
+    /// * `extended_json_types` - Whether hex ObjectIds get an Extended JSON envelope too
+    ///
+    /// # Returns
+    /// The SQLite value
+    pub fn sql_value(&self, oid: &ObjectId, extended_json_types: bool) -> SqlValue {
+        match self {
+            ObjectIdFormat::Hex if !extended_json_types => SqlValue::Text(oid.to_hex()),
+            ObjectIdFormat::Hex | ObjectIdFormat::ExtJson => {
+                SqlValue::Text(serde_json::json!({ "$oid": oid.to_hex() }).to_string())
+            }
+            ObjectIdFormat::Timestamp => SqlValue::Integer(oid.timestamp().timestamp_millis() / 1000),
+        }
+    }
+}
+
+impl FromStr for ObjectIdFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "hex" => Ok(ObjectIdFormat::Hex),
+            "timestamp" => Ok(ObjectIdFormat::Timestamp),
+            "extjson" => Ok(ObjectIdFormat::ExtJson),
+            other => bail!(
+                "Unknown ObjectId format '{}' (expected one of: hex, timestamp, extjson)",
+                other
+            ),
+        }
+    }
+}
+
+/// Storage for Decimal128 values
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DecimalFormat {
+    /// The exact decimal string, e.g. `1234.5678`
+    #[default]
+    Text,
+    /// The nearest f64, which may lose precision beyond ~15 significant digits
+    Real,
+    /// The 16 raw IEEE 754-2008 BID bytes, as stored in BSON
+    Blob,
+}
+
+impl DecimalFormat {
+    /// SQLite column type for Decimal128 values in this format
+    pub fn sql_type(&self) -> &'static str {
+        match self {
+            DecimalFormat::Text => "TEXT",
+            DecimalFormat::Real => "REAL",
+            DecimalFormat::Blob => "BLOB",
+        }
+    }
+}
+
+impl FromStr for DecimalFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(DecimalFormat::Text),
+            "real" => Ok(DecimalFormat::Real),
+            "blob" => Ok(DecimalFormat::Blob),
+            other => bail!(
+                "Unknown decimal format '{}' (expected one of: text, real, blob)",
+                other
+            ),
+        }
+    }
+}
+
+/// Storage for NaN and ±Infinity Double values
+///
+/// SQLite can't usefully store or query them as REAL, and JSON has no
+/// number for them.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NonFiniteMode {
+    /// Store them as NULL
+    #[default]
+    Null,
+    /// Store them as the text `NaN`, `Infinity` or `-Infinity`
+    Text,
+}
+
+impl NonFiniteMode {
+    /// Convert a NaN or infinite double in this mode
+    ///
+    /// # Arguments
+    /// * `value` - A double that is not finite
+    ///
+    /// # Returns
+    /// NULL or the text sentinel for `value`
+    pub fn sql_value(&self, value: f64) -> SqlValue {
+        match self {
+            NonFiniteMode::Null => SqlValue::Null,
+            NonFiniteMode::Text if value.is_nan() => SqlValue::Text("NaN".to_string()),
+            NonFiniteMode::Text if value > 0.0 => SqlValue::Text("Infinity".to_string()),
+            NonFiniteMode::Text => SqlValue::Text("-Infinity".to_string()),
+        }
+    }
+}
+
+impl FromStr for NonFiniteMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "null" => Ok(NonFiniteMode::Null),
+            "text" => Ok(NonFiniteMode::Text),
+            other => bail!(
+                "Unknown non-finite mode '{}' (expected one of: null, text)",
+                other
+            ),
+        }
+    }
+}
+
+/// Check whether a binary value holds a UUID (subtype 3 or 4, 16 bytes)
+fn is_uuid_binary(binary: &Binary) -> bool {
+    matches!(binary.subtype, BinarySubtype::Uuid | BinarySubtype::UuidOld)
+        && binary.bytes.len() == 16
+}
+
+/// Format a UUID binary as its canonical `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` string
+///
+/// # Arguments
+/// * `binary` - A 16-byte binary of subtype 3 or 4
+/// * `legacy_uuid` - Byte order to assume for subtype 3 values
+///
+/// # Returns
+/// The lowercase hyphenated UUID string
+fn format_uuid(binary: &Binary, legacy_uuid: LegacyUuidEncoding) -> String {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&binary.bytes);
+
+    if binary.subtype == BinarySubtype::UuidOld {
+        legacy_uuid.reorder(&mut bytes);
+    }
+
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// A user-supplied conversion consulted before the built-in BSON mapping
+///
+/// Returning `Some` replaces the built-in conversion of that value; `None`
+/// falls through to the next converter and finally the built-in mapping.
+#[derive(Clone)]
+pub struct CustomConverter(Arc<ConvertFn>);
+
+/// Signature of a custom conversion
+type ConvertFn = dyn Fn(&Bson) -> Option<SqlValue> + Send + Sync;
+
+impl CustomConverter {
+    /// Wrap a conversion closure
+    ///
+    /// # Arguments
+    /// * `convert` - Closure returning the SQL value for the BSON values it handles
+    ///
+    /// # Returns
+    /// A new CustomConverter
+    pub fn new<F>(convert: F) -> Self
+    where
+        F: Fn(&Bson) -> Option<SqlValue> + Send + Sync + 'static,
+    {
+        Self(Arc::new(convert))
+    }
+
+    /// Run the conversion on a value
+    fn convert(&self, bson: &Bson) -> Option<SqlValue> {
+        (self.0)(bson)
+    }
+}
+
+impl fmt::Debug for CustomConverter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CustomConverter(..)")
+    }
+}
+
+/// Conversion outcomes of the values of one BSON type
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TypeCounts {
+    /// Values converted, including those that failed or were coerced
+    pub converted: u64,
+    /// Values that could not be serialized and were stored as NULL
+    pub failed: u64,
+    /// Values stored differently than their type maps to, e.g. numeric
+    /// strings parsed into numbers or large Int64 values kept as TEXT
+    pub coerced: u64,
+}
+
+/// Conversion outcomes per BSON type, accumulated across a run
+///
+/// Shared through [`ConverterOptions::stats`], so every clone of the options
+/// records into the same counters. Only top-level field values are counted;
+/// values nested in documents and arrays are serialized with their parent.
+#[derive(Debug, Default)]
+pub struct ConversionStats {
+    counts: Mutex<BTreeMap<&'static str, TypeCounts>>,
+}
+
+impl ConversionStats {
+    /// Update the counts of a value's BSON type
+    fn record(&self, bson: &Bson, update: impl FnOnce(&mut TypeCounts)) {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        update(counts.entry(bson_type_name(bson)).or_default());
+    }
+
+    /// Snapshot of the counts, by BSON type name in alphabetical order
+    pub fn by_type(&self) -> Vec<(&'static str, TypeCounts)> {
+        let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        counts.iter().map(|(name, counts)| (*name, *counts)).collect()
+    }
+
+    /// Sum of the counts over all types
+    pub fn total(&self) -> TypeCounts {
+        self.by_type()
+            .into_iter()
+            .fold(TypeCounts::default(), |total, (_, counts)| TypeCounts {
+                converted: total.converted + counts.converted,
+                failed: total.failed + counts.failed,
+                coerced: total.coerced + counts.coerced,
+            })
+    }
+}
+
+/// Options controlling how documents are converted to SQL values
+#[derive(Debug, Clone, Default)]
+pub struct ConverterOptions {
+    /// Transformations to apply, keyed by field name
+    pub transforms: HashMap<String, TransformOp>,
+    /// Wrap ObjectId, DateTime and JavaScript code values in Extended JSON envelopes
+    pub extended_json_types: bool,
+    /// Serialize nested documents, arrays and other JSON values with indentation
+    pub pretty_json: bool,
+    /// Byte order of legacy (subtype 3) UUID binaries
+    pub legacy_uuid: LegacyUuidEncoding,
+    /// Parse string values into numbers for INTEGER/REAL columns
+    pub coerce_numeric_strings: bool,
+    /// Fill the raw document column with the whole document as canonical Extended JSON
+    pub keep_raw_json: bool,
+    /// Round Double values in REAL columns to this many decimal places
+    pub round_real: Option<u32>,
+    /// Text format for DateTime values outside Extended JSON envelopes
+    pub datetime_format: DatetimeFormat,
+    /// Storage for NaN and ±Infinity Double values
+    pub non_finite: NonFiniteMode,
+    /// Representation of ObjectId values other than `_id`
+    pub objectid_format: ObjectIdFormat,
+    /// Representation of Decimal128 values
+    pub decimal_format: DecimalFormat,
+    /// Custom conversions for top-level field values, consulted in order
+    pub custom_converters: Vec<CustomConverter>,
+    /// Text stored instead of NULL when a field is missing or null, keyed by MongoDB field name
+    pub defaults: HashMap<String, String>,
+    /// Fields whose base64 string values are decoded into BLOBs, keyed by MongoDB field name
+    pub base64_blob_fields: HashSet<String>,
+    /// Counters of conversion outcomes, if the run keeps statistics
+    pub stats: Option<Arc<ConversionStats>>,
+}
+
+impl ConverterOptions {
+    /// Register a custom conversion that overrides the built-in mapping
+    ///
+    /// Converters are consulted in registration order before the built-in
+    /// mapping, for each field value of a document. Values nested inside
+    /// documents and arrays are serialized to JSON as usual.
+    ///
+    /// ```
+    /// use bson::{spec::BinarySubtype, Binary, Bson};
+    /// use libsql::Value as SqlValue;
+    /// use mongo_to_sqlite::converter::{bson_to_sql_value_with_options, ConverterOptions};
+    ///
+    /// // Store a project-specific binary subtype as a tagged hex string
+    /// let options = ConverterOptions::default().with_custom_converter(|bson| match bson {
+    ///     Bson::Binary(binary) if binary.subtype == BinarySubtype::UserDefined(0x80) => {
+    ///         let hex: String = binary.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    ///         Some(SqlValue::Text(format!("geohash:{}", hex)))
+    ///     }
+    ///     _ => None,
+    /// });
+    ///
+    /// let custom = Bson::Binary(Binary {
+    ///     subtype: BinarySubtype::UserDefined(0x80),
+    ///     bytes: vec![0xca, 0xfe],
+    /// });
+    /// assert!(matches!(
+    ///     bson_to_sql_value_with_options(&custom, &options),
+    ///     SqlValue::Text(text) if text == "geohash:cafe"
+    /// ));
+    ///
+    /// // Other values keep the built-in mapping
+    /// assert!(matches!(
+    ///     bson_to_sql_value_with_options(&Bson::Int32(7), &options),
+    ///     SqlValue::Integer(7)
+    /// ));
+    /// ```
+    ///
+    /// # Arguments
+    /// * `convert` - Closure returning `Some` for the values it converts
+    ///
+    /// # Returns
+    /// The options with the converter added
+    pub fn with_custom_converter<F>(mut self, convert: F) -> Self
+    where
+        F: Fn(&Bson) -> Option<SqlValue> + Send + Sync + 'static,
+    {
+        self.custom_converters.push(CustomConverter::new(convert));
+        self
+    }
+
+    /// Update the conversion statistics, if any, for a value
+    fn record_stat(&self, bson: &Bson, update: impl FnOnce(&mut TypeCounts)) {
+        if let Some(stats) = &self.stats {
+            stats.record(bson, update);
+        }
+    }
+
+    /// Run the custom converters on a value, returning the first result
+    fn custom_value(&self, bson: &Bson) -> Option<SqlValue> {
+        self.custom_converters
+            .iter()
+            .find_map(|converter| converter.convert(bson))
+    }
+
+    /// Build converter options from `--transform <field>=<op>` specifications
+    ///
+    /// # Arguments
+    /// * `specs` - Transform specifications as given on the command line
+    ///
+    /// # Returns
+    /// Converter options with the parsed transforms
+    pub fn from_transform_specs(specs: &[String]) -> Result<Self> {
+        let mut transforms = HashMap::new();
+
+        for spec in specs {
+            let Some((field, op)) = spec.split_once('=') else {
+                bail!("Invalid transform '{}': expected <field>=<op>", spec);
+            };
+
+            let field = field.trim();
+            if field.is_empty() {
+                bail!("Invalid transform '{}': field name is empty", spec);
+            }
+
+            transforms.insert(field.to_string(), op.trim().parse()?);
+        }
+
+        Ok(Self {
+            transforms,
+            ..Self::default()
+        })
+    }
+}
+
+/// Parse `--default <field>=<value>` specifications
+///
+/// The value is kept as text and typed by its column when stored, see
+/// [`default_sql_value`]. An empty value is an empty string default.
+///
+/// # Arguments
+/// * `specs` - Default specifications as given on the command line
+///
+/// # Returns
+/// The default value text, keyed by MongoDB field name
+pub fn parse_default_specs(specs: &[String]) -> Result<HashMap<String, String>> {
+    let mut defaults = HashMap::new();
+
+    for spec in specs {
+        let Some((field, value)) = spec.split_once('=') else {
+            bail!("Invalid default '{}': expected <field>=<value>", spec);
+        };
+
+        let field = field.trim();
+        if field.is_empty() {
+            bail!("Invalid default '{}': field name is empty", spec);
+        }
+
+        defaults.insert(field.to_string(), value.to_string());
+    }
+
+    Ok(defaults)
+}
+
+/// Type a default value's text for its column
+///
+/// INTEGER and REAL columns get a number when the text parses as one; any
+/// other text is stored as TEXT.
+///
+/// # Arguments
+/// * `text` - The default value text
+/// * `sql_type` - SQLite column type
+///
+/// # Returns
+/// The SQLite value
+fn default_sql_value(text: &str, sql_type: &str) -> SqlValue {
+    match sql_type {
+        "INTEGER" | "REAL" => {
+            parse_numeric_string(text, sql_type).unwrap_or_else(|| SqlValue::Text(text.to_string()))
+        }
+        _ => SqlValue::Text(text.to_string()),
+    }
+}
+
 /// Convert a BSON value to a SQLite value
 ///
 /// This function handles the conversion of MongoDB BSON types to SQLite types.
@@ -15,46 +612,58 @@ use tracing::warn;
 /// # Returns
 /// A SQLite Value that can be used in queries
 pub fn bson_to_sql_value(bson: &Bson) -> SqlValue {
+    bson_to_sql_value_with_options(bson, &ConverterOptions::default())
+}
+
+/// Convert a BSON value to a SQLite value using the given conversion options
+///
+/// Custom converters registered in `options` are consulted first. With
+/// `extended_json_types` enabled, ObjectId, DateTime and JavaScript code
+/// values are wrapped in MongoDB Extended JSON envelopes (`{"$oid": ...}`,
+/// `{"$date": ...}`, `{"$code": ...}`) instead of being stored as bare strings.
+///
+/// # Arguments
+/// * `bson` - The BSON value to convert
+/// * `options` - Conversion options
+///
+/// # Returns
+/// A SQLite Value that can be used in queries
+pub fn bson_to_sql_value_with_options(bson: &Bson, options: &ConverterOptions) -> SqlValue {
+    match options.custom_value(bson) {
+        Some(value) => value,
+        None => builtin_sql_value(bson, options),
+    }
+}
+
+/// Convert a BSON value using only the built-in mapping
+fn builtin_sql_value(bson: &Bson, options: &ConverterOptions) -> SqlValue {
+    let extended = options.extended_json_types;
+
     match bson {
+        Bson::Double(v) if !v.is_finite() => options.non_finite.sql_value(*v),
         Bson::Double(v) => SqlValue::Real(*v),
         Bson::String(v) => SqlValue::Text(v.clone()),
-        Bson::Document(doc) => {
-            // Serialize nested documents as JSON
-            match serde_json::to_string(doc) {
-                Ok(json) => SqlValue::Text(json),
-                Err(e) => {
-                    warn!("Failed to serialize document to JSON: {}", e);
-                    SqlValue::Null
-                }
-            }
-        }
-        Bson::Array(arr) => {
-            // Serialize arrays as JSON
-            match serde_json::to_string(arr) {
-                Ok(json) => SqlValue::Text(json),
-                Err(e) => {
-                    warn!("Failed to serialize array to JSON: {}", e);
-                    SqlValue::Null
-                }
-            }
+        // Serialize nested documents and arrays as JSON
+        Bson::Document(doc) => json_text(doc, bson, options),
+        Bson::Array(arr) => json_text(arr, bson, options),
+        Bson::Binary(binary) if is_uuid_binary(binary) => {
+            SqlValue::Text(format_uuid(binary, options.legacy_uuid))
         }
         Bson::Binary(_) => {
             // Convert binary to base64 text for now
             // TODO: Could store as BLOB if needed
-            match serde_json::to_string(bson) {
-                Ok(json) => SqlValue::Text(json),
-                Err(e) => {
-                    warn!("Failed to serialize binary to JSON: {}", e);
-                    SqlValue::Null
-                }
-            }
+            json_text(bson, bson, options)
         }
-        Bson::ObjectId(oid) => SqlValue::Text(oid.to_hex()),
+        Bson::ObjectId(oid) => options.objectid_format.sql_value(oid, extended),
         Bson::Boolean(v) => SqlValue::Integer(if *v { 1 } else { 0 }),
         Bson::DateTime(dt) => {
-            // Convert to ISO 8601 string
             let datetime: DateTime<Utc> = (*dt).into();
-            SqlValue::Text(datetime.to_rfc3339())
+            if extended {
+                // Extended JSON envelopes always carry ISO 8601 dates
+                SqlValue::Text(serde_json::json!({ "$date": datetime.to_rfc3339() }).to_string())
+            } else {
+                SqlValue::Text(options.datetime_format.format(&datetime))
+            }
         }
         Bson::Null => SqlValue::Null,
         Bson::RegularExpression(regex) => {
@@ -65,7 +674,17 @@ pub fn bson_to_sql_value(bson: &Bson) -> SqlValue {
             });
             SqlValue::Text(json.to_string())
         }
+        Bson::JavaScriptCode(code) if extended => {
+            SqlValue::Text(serde_json::json!({ "$code": code }).to_string())
+        }
         Bson::JavaScriptCode(code) => SqlValue::Text(code.clone()),
+        Bson::JavaScriptCodeWithScope(code_with_scope) if extended => {
+            let json = serde_json::json!({
+                "$code": code_with_scope.code,
+                "$scope": code_with_scope.scope
+            });
+            SqlValue::Text(json.to_string())
+        }
         Bson::JavaScriptCodeWithScope(code_with_scope) => {
             let json = serde_json::json!({
                 "code": code_with_scope.code,
@@ -76,21 +695,56 @@ pub fn bson_to_sql_value(bson: &Bson) -> SqlValue {
         Bson::Int32(v) => SqlValue::Integer(*v as i64),
         Bson::Int64(v) => SqlValue::Integer(*v),
         Bson::Timestamp(ts) => SqlValue::Integer(ts.time as i64),
-        Bson::Decimal128(dec) => {
-            // Convert Decimal128 to string for precision
-            SqlValue::Text(dec.to_string())
-        }
+        Bson::Decimal128(dec) => match options.decimal_format {
+            // Text keeps the full precision
+            DecimalFormat::Text => SqlValue::Text(dec.to_string()),
+            DecimalFormat::Blob => SqlValue::Blob(dec.bytes().to_vec()),
+            DecimalFormat::Real => match dec.to_string().parse::<f64>() {
+                Ok(v) if !v.is_finite() => options.non_finite.sql_value(v),
+                Ok(v) => SqlValue::Real(v),
+                Err(_) => {
+                    options.record_stat(bson, |counts| counts.coerced += 1);
+                    SqlValue::Text(dec.to_string())
+                }
+            },
+        },
         Bson::Undefined => SqlValue::Null,
         Bson::MaxKey => SqlValue::Text("$maxKey".to_string()),
         Bson::MinKey => SqlValue::Text("$minKey".to_string()),
         Bson::DbPointer(_) => {
             warn!("DbPointer type is deprecated, storing as null");
+            options.record_stat(bson, |counts| counts.failed += 1);
             SqlValue::Null
         }
         Bson::Symbol(s) => SqlValue::Text(s.clone()),
     }
 }
 
+/// Serialize a value as JSON text, storing NULL if serialization fails
+///
+/// # Arguments
+/// * `value` - The value to serialize
+/// * `bson` - The BSON value it comes from, whose type a failure is counted under
+/// * `options` - Conversion options holding the statistics and the pretty flag
+///
+/// # Returns
+/// The JSON text, or NULL
+fn json_text<T: Serialize + ?Sized>(value: &T, bson: &Bson, options: &ConverterOptions) -> SqlValue {
+    let json = if options.pretty_json {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    match json {
+        Ok(json) => SqlValue::Text(json),
+        Err(e) => {
+            warn!("Failed to serialize {} to JSON: {}", bson_type_name(bson), e);
+            options.record_stat(bson, |counts| counts.failed += 1);
+            SqlValue::Null
+        }
+    }
+}
+
 /// Infer SQLite type from BSON value
 ///
 /// # Arguments
@@ -99,13 +753,26 @@ pub fn bson_to_sql_value(bson: &Bson) -> SqlValue {
 /// # Returns
 /// SQLite type as a string (TEXT, INTEGER, REAL, BLOB, NULL)
 pub fn infer_sqlite_type(bson: &Bson) -> &'static str {
+    infer_sqlite_type_with(bson, ObjectIdFormat::Hex)
+}
+
+/// Infer SQLite type from BSON value, for a given ObjectId representation
+///
+/// # Arguments
+/// * `bson` - The BSON value to analyze
+/// * `objectid_format` - How ObjectId values are stored
+///
+/// # Returns
+/// SQLite type as a string (TEXT, INTEGER, REAL, BLOB, NULL)
+pub fn infer_sqlite_type_with(bson: &Bson, objectid_format: ObjectIdFormat) -> &'static str {
     match bson {
         Bson::Double(_) => "REAL",
         Bson::String(_) => "TEXT",
         Bson::Document(_) => "TEXT", // JSON
         Bson::Array(_) => "TEXT",     // JSON
+        Bson::Binary(binary) if is_uuid_binary(binary) => "TEXT",
         Bson::Binary(_) => "BLOB",
-        Bson::ObjectId(_) => "TEXT",
+        Bson::ObjectId(_) => objectid_format.sql_type(),
         Bson::Boolean(_) => "INTEGER",
         Bson::DateTime(_) => "TEXT",
         Bson::Null | Bson::Undefined => "NULL",
@@ -125,32 +792,320 @@ pub fn infer_sqlite_type(bson: &Bson) -> &'static str {
 ///
 /// # Arguments
 /// * `doc` - The MongoDB document to convert
-/// * `field_names` - Ordered list of field names to extract
+/// * `fields` - Ordered list of target fields to extract
+/// * `options` - Conversion options (e.g. per-field transforms)
+/// * `warned_fields` - Fields that have already triggered a precision warning
 ///
 /// # Returns
-/// Vector of SQL values in the same order as field_names
-pub fn document_to_sql_values(doc: &Document, field_names: &[String]) -> Vec<SqlValue> {
-    field_names
+/// Vector of SQL values in the same order as fields
+pub fn document_to_sql_values(
+    doc: &Document,
+    fields: &[Field],
+    options: &ConverterOptions,
+    warned_fields: &mut HashSet<String>,
+) -> Vec<SqlValue> {
+    fields
         .iter()
-        .map(|field_name| {
-            doc.get(field_name)
-                .map(bson_to_sql_value)
-                .unwrap_or(SqlValue::Null)
+        .map(|field| {
+            if options.keep_raw_json && field.name == RAW_JSON_FIELD {
+                return document_to_raw_json(doc);
+            }
+
+            let bson = doc.get(field.source_key());
+            let default = options
+                .defaults
+                .get(field.source_key())
+                .filter(|_| matches!(bson, None | Some(Bson::Null | Bson::Undefined)));
+
+            let value = match (bson, default) {
+                (_, Some(default)) => default_sql_value(default, &field.sql_type),
+                (Some(bson), None) => {
+                    options.record_stat(bson, |counts| counts.converted += 1);
+                    field_value_to_sql(bson, field, options, warned_fields)
+                }
+                (None, None) => SqlValue::Null,
+            };
+
+            match options.transforms.get(field.source_key()) {
+                Some(op) => op.apply(value),
+                None => value,
+            }
         })
         .collect()
 }
 
+/// Serialize a whole document as canonical Extended JSON text
+///
+/// Canonical mode keeps every BSON type distinguishable (e.g. `{"$numberLong": "1"}`),
+/// so the original document can be restored without loss.
+fn document_to_raw_json(doc: &Document) -> SqlValue {
+    SqlValue::Text(Bson::Document(doc.clone()).into_canonical_extjson().to_string())
+}
+
+/// Convert a BSON value to a SQLite value for a specific target column
+///
+/// Int64 values beyond 2^53 cannot be stored exactly in a REAL column, so they
+/// are stored as TEXT instead, warning once per field. With
+/// `coerce_numeric_strings`, strings in INTEGER/REAL columns are parsed into
+/// numbers; unparseable ones become NULL, again warning once per field.
+fn field_value_to_sql(
+    bson: &Bson,
+    field: &Field,
+    options: &ConverterOptions,
+    warned_fields: &mut HashSet<String>,
+) -> SqlValue {
+    if let Some(value) = options.custom_value(bson) {
+        return value;
+    }
+
+    if field.source_key() == "_id" {
+        if field.mixed_key {
+            return key_sql_value(bson, field, options);
+        }
+        // A TEXT key holds every _id as text, e.g. one the sample didn't cover
+        if field.is_primary_key && field.sql_type == "TEXT" {
+            return match id_sql_value(bson, options) {
+                SqlValue::Integer(i) => SqlValue::Text(i.to_string()),
+                SqlValue::Real(r) => SqlValue::Text(r.to_string()),
+                other => other,
+            };
+        }
+        if matches!(bson, Bson::ObjectId(_)) {
+            return id_sql_value(bson, options);
+        }
+    }
+
+    if let Bson::Int64(v) = bson {
+        if field.sql_type == "REAL" && v.unsigned_abs() > MAX_SAFE_INTEGER as u64 {
+            if warned_fields.insert(field.name.clone()) {
+                warn!(
+                    "Field '{}' has Int64 values beyond 2^53 in a REAL column; storing them as TEXT to preserve precision",
+                    field.name
+                );
+            }
+            options.record_stat(bson, |counts| counts.coerced += 1);
+            return SqlValue::Text(v.to_string());
+        }
+    }
+
+    if let Bson::String(s) = bson {
+        if options.coerce_numeric_strings && matches!(field.sql_type.as_str(), "INTEGER" | "REAL") {
+            options.record_stat(bson, |counts| counts.coerced += 1);
+            return match parse_numeric_string(s, &field.sql_type) {
+                Some(value) => value,
+                None => {
+                    if warned_fields.insert(format!("{} (numeric string)", field.name)) {
+                        warn!(
+                            "Field '{}' has non-numeric strings in a {} column; storing them as NULL",
+                            field.name, field.sql_type
+                        );
+                    }
+                    SqlValue::Null
+                }
+            };
+        }
+    }
+
+    if let Bson::String(s) = bson {
+        if options.base64_blob_fields.contains(field.source_key()) {
+            return match decode_base64(s) {
+                Some(bytes) => {
+                    options.record_stat(bson, |counts| counts.coerced += 1);
+                    SqlValue::Blob(bytes)
+                }
+                None => {
+                    if warned_fields.insert(format!("{} (base64)", field.name)) {
+                        warn!(
+                            "Field '{}' has values that are not valid base64; storing them as TEXT",
+                            field.name
+                        );
+                    }
+                    SqlValue::Text(s.clone())
+                }
+            };
+        }
+    }
+
+    if let Bson::Double(v) = bson {
+        if !v.is_finite() {
+            if warned_fields.insert(format!("{} (non-finite)", field.name)) {
+                warn!(
+                    "Field '{}' has NaN or infinite values; storing them as {}",
+                    field.name,
+                    match options.non_finite {
+                        NonFiniteMode::Null => "NULL",
+                        NonFiniteMode::Text => "TEXT",
+                    }
+                );
+            }
+            options.record_stat(bson, |counts| counts.coerced += 1);
+            return options.non_finite.sql_value(*v);
+        }
+    }
+
+    if let Bson::Decimal128(_) = bson {
+        if options.decimal_format == DecimalFormat::Real
+            && warned_fields.insert(format!("{} (decimal)", field.name))
+        {
+            warn!(
+                "Field '{}' has Decimal128 values stored as REAL; digits beyond f64 precision are lost",
+                field.name
+            );
+        }
+    }
+
+    if let (Bson::Double(v), Some(places)) = (bson, options.round_real) {
+        if field.sql_type == "REAL" {
+            return SqlValue::Real(round_to_places(*v, places));
+        }
+    }
+
+    builtin_sql_value(bson, options)
+}
+
+/// Convert a document's `_id` to the SQLite value of its primary key
+///
+/// ObjectId timestamps are not unique, so `_id` ObjectIds stay hex (or an
+/// Extended JSON envelope) with `ObjectIdFormat::Timestamp`.
+///
+/// # Arguments
+/// * `id` - The `_id` value
+/// * `options` - Conversion options
+///
+/// # Returns
+/// The SQLite value
+pub fn id_sql_value(id: &Bson, options: &ConverterOptions) -> SqlValue {
+    match id {
+        Bson::ObjectId(oid) if options.objectid_format == ObjectIdFormat::Timestamp => {
+            ObjectIdFormat::Hex.sql_value(oid, options.extended_json_types)
+        }
+        other => bson_to_sql_value_with_options(other, options),
+    }
+}
+
+/// Convert an `_id` to the value stored in its primary key column
+///
+/// Keys of mixed types are stored as relaxed Extended JSON text, which keeps
+/// the type apart: `3`, `3.0` and `"3"` become `3`, `3.0` and `"3"`.
+///
+/// # Arguments
+/// * `id` - The `_id` value
+/// * `field` - The primary key field
+/// * `options` - Conversion options
+///
+/// # Returns
+/// The SQLite value
+pub fn key_sql_value(id: &Bson, field: &Field, options: &ConverterOptions) -> SqlValue {
+    if field.mixed_key {
+        SqlValue::Text(id.clone().into_relaxed_extjson().to_string())
+    } else {
+        id_sql_value(id, options)
+    }
+}
+
+/// Decode a base64 string, as stored by applications keeping binary data in text
+///
+/// Whitespace (e.g. MIME line breaks) is ignored and padding is optional.
+///
+/// # Arguments
+/// * `s` - The base64 text, standard alphabet
+///
+/// # Returns
+/// The decoded bytes, or `None` if `s` is not valid base64
+pub fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+    use base64::Engine;
+
+    let compact: String = s.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    STANDARD
+        .decode(&compact)
+        .or_else(|_| STANDARD_NO_PAD.decode(&compact))
+        .ok()
+}
+
+/// Most decimal places `--round-real` accepts; an f64 carries about 15-17
+/// significant digits, so rounding any finer has no effect
+pub const MAX_ROUND_PLACES: u32 = 15;
+
+/// Round a floating point value to a number of decimal places
+///
+/// Halves round away from zero. Binary floating point cannot represent most
+/// decimal fractions exactly, so the result is the nearest f64 to the rounded
+/// decimal, and values like 1.005 (stored as 1.00499999...) round down.
+/// Values too large to scale are returned unchanged.
+///
+/// # Arguments
+/// * `value` - The value to round
+/// * `places` - Number of decimal places to keep
+///
+/// # Returns
+/// The rounded value
+pub fn round_to_places(value: f64, places: u32) -> f64 {
+    let factor = 10_f64.powi(places.min(MAX_ROUND_PLACES) as i32);
+    let scaled = value * factor;
+
+    if !scaled.is_finite() {
+        return value;
+    }
+
+    scaled.round() / factor
+}
+
+/// Parse a numeric string for an INTEGER or REAL column
+///
+/// Integers stay integers in a REAL column only if they are exact; strings
+/// with a fractional part are stored as REAL even in an INTEGER column.
+fn parse_numeric_string(s: &str, sql_type: &str) -> Option<SqlValue> {
+    let s = s.trim();
+
+    if sql_type == "INTEGER" {
+        if let Ok(i) = s.parse::<i64>() {
+            return Some(SqlValue::Integer(i));
+        }
+    }
+
+    s.parse::<f64>()
+        .ok()
+        .filter(|r| r.is_finite())
+        .map(SqlValue::Real)
+}
+
+/// Quoting style for SQL identifiers
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum QuoteStyle {
+    /// ANSI double quotes (`"name"`), the SQLite standard
+    #[default]
+    Ansi,
+    /// MySQL backticks (`` `name` ``), also accepted by SQLite
+    Mysql,
+}
+
+impl FromStr for QuoteStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "ansi" => Ok(QuoteStyle::Ansi),
+            "mysql" => Ok(QuoteStyle::Mysql),
+            other => bail!("Unknown quote style '{}' (expected ansi or mysql)", other),
+        }
+    }
+}
+
 /// Escape SQL identifier (table or column name)
 ///
 /// # Arguments
 /// * `identifier` - The identifier to escape
+/// * `style` - Quoting style to use
 ///
 /// # Returns
 /// Escaped identifier safe for use in SQL
-pub fn escape_identifier(identifier: &str) -> String {
-    // SQLite uses double quotes for identifiers
-    // Escape any existing double quotes by doubling them
-    format!("\"{}\"", identifier.replace('"', "\"\""))
+pub fn escape_identifier(identifier: &str, style: QuoteStyle) -> String {
+    // Escape any existing quote characters by doubling them
+    match style {
+        QuoteStyle::Ansi => format!("\"{}\"", identifier.replace('"', "\"\"")),
+        QuoteStyle::Mysql => format!("`{}`", identifier.replace('`', "``")),
+    }
 }
 
 #[cfg(test)]
@@ -158,6 +1113,19 @@ mod tests {
     use super::*;
     use bson::oid::ObjectId;
 
+    fn text_field(name: &str) -> Field {
+        Field {
+            name: name.to_string(),
+            sql_type: "TEXT".to_string(),
+            nullable: true,
+            is_primary_key: false,
+            source: None,
+            element_type: None,
+            references: None,
+            mixed_key: false,
+        }
+    }
+
     #[test]
     fn test_bson_string_to_sql() {
         let bson = Bson::String("hello".to_string());
@@ -208,16 +1176,26 @@ mod tests {
     fn test_infer_types() {
         assert_eq!(infer_sqlite_type(&Bson::String("test".into())), "TEXT");
         assert_eq!(infer_sqlite_type(&Bson::Int32(42)), "INTEGER");
-        assert_eq!(infer_sqlite_type(&Bson::Double(3.14)), "REAL");
+        assert_eq!(infer_sqlite_type(&Bson::Double(2.5)), "REAL");
         assert_eq!(infer_sqlite_type(&Bson::Boolean(true)), "INTEGER");
         assert_eq!(infer_sqlite_type(&Bson::Null), "NULL");
     }
 
     #[test]
     fn test_escape_identifier() {
-        assert_eq!(escape_identifier("users"), "\"users\"");
-        assert_eq!(escape_identifier("user_name"), "\"user_name\"");
-        assert_eq!(escape_identifier("user\"name"), "\"user\"\"name\"");
+        assert_eq!(escape_identifier("users", QuoteStyle::Ansi), "\"users\"");
+        assert_eq!(escape_identifier("user_name", QuoteStyle::Ansi), "\"user_name\"");
+        assert_eq!(escape_identifier("user\"name", QuoteStyle::Ansi), "\"user\"\"name\"");
+        assert_eq!(escape_identifier("user`name", QuoteStyle::Ansi), "\"user`name\"");
+    }
+
+    #[test]
+    fn test_escape_identifier_mysql() {
+        assert_eq!(escape_identifier("users", QuoteStyle::Mysql), "`users`");
+        assert_eq!(escape_identifier("user`name", QuoteStyle::Mysql), "`user``name`");
+        assert_eq!(escape_identifier("user\"name", QuoteStyle::Mysql), "`user\"name`");
+        assert_eq!("MySQL".parse::<QuoteStyle>().unwrap(), QuoteStyle::Mysql);
+        assert!("brackets".parse::<QuoteStyle>().is_err());
     }
 
     #[test]
@@ -227,14 +1205,626 @@ mod tests {
         doc.insert("age", 30);
         doc.insert("active", true);
 
-        let field_names = vec![
-            "name".to_string(),
-            "age".to_string(),
-            "active".to_string(),
-        ];
+        let fields = vec![text_field("name"), text_field("age"), text_field("active")];
 
-        let values = document_to_sql_values(&doc, &field_names);
+        let values = document_to_sql_values(
+            &doc,
+            &fields,
+            &ConverterOptions::default(),
+            &mut HashSet::new(),
+        );
         assert_eq!(values.len(), 3);
     }
+
+    #[test]
+    fn test_keep_raw_json() {
+        use crate::schema::{InferenceOptions, SchemaInferrer};
+
+        let doc = bson::doc! { "_id": 1, "name": "Alice", "tags": ["a", "b"], "n": Bson::Int64(7) };
+        let inference_options = InferenceOptions {
+            keep_raw_json: true,
+            ..InferenceOptions::default()
+        };
+        let schema =
+            SchemaInferrer::infer_schema("users", std::slice::from_ref(&doc), &inference_options)
+                .unwrap();
+        let raw_index = schema
+            .fields
+            .iter()
+            .position(|f| f.name == RAW_JSON_FIELD)
+            .unwrap();
+        assert_eq!(schema.fields[raw_index].sql_type, "TEXT");
+
+        let options = ConverterOptions {
+            keep_raw_json: true,
+            ..ConverterOptions::default()
+        };
+        let values = document_to_sql_values(&doc, &schema.fields, &options, &mut HashSet::new());
+
+        let SqlValue::Text(raw) = &values[raw_index] else {
+            panic!("expected raw JSON text, got {:?}", values[raw_index]);
+        };
+        let json: serde_json::Value = serde_json::from_str(raw).unwrap();
+        assert_eq!(json["name"], "Alice");
+        assert_eq!(json["tags"], serde_json::json!(["a", "b"]));
+        assert_eq!(json["n"], serde_json::json!({ "$numberLong": "7" }));
+    }
+
+    #[test]
+    fn test_large_int64_in_real_column() {
+        let mut doc = Document::new();
+        doc.insert("amount", Bson::Int64(1 << 60));
+        doc.insert("small", Bson::Int64(42));
+
+        let fields = vec![
+            Field {
+                sql_type: "REAL".to_string(),
+                ..text_field("amount")
+            },
+            Field {
+                sql_type: "REAL".to_string(),
+                ..text_field("small")
+            },
+        ];
+
+        let mut warned_fields = HashSet::new();
+        let values =
+            document_to_sql_values(&doc, &fields, &ConverterOptions::default(), &mut warned_fields);
+
+        match &values[0] {
+            SqlValue::Text(s) => assert_eq!(s, "1152921504606846976"),
+            _ => panic!("Expected Text value"),
+        }
+        match &values[1] {
+            SqlValue::Integer(i) => assert_eq!(*i, 42),
+            _ => panic!("Expected Integer value"),
+        }
+        assert!(warned_fields.contains("amount"));
+        assert!(!warned_fields.contains("small"));
+    }
+
+    fn extended_json(bson: &Bson) -> serde_json::Value {
+        let options = ConverterOptions {
+            extended_json_types: true,
+            ..ConverterOptions::default()
+        };
+        match bson_to_sql_value_with_options(bson, &options) {
+            SqlValue::Text(s) => serde_json::from_str(&s).unwrap(),
+            _ => panic!("Expected Text value"),
+        }
+    }
+
+    #[test]
+    fn test_extended_json_objectid() {
+        let oid = ObjectId::new();
+        assert_eq!(
+            extended_json(&Bson::ObjectId(oid)),
+            serde_json::json!({ "$oid": oid.to_hex() })
+        );
+    }
+
+    #[test]
+    fn test_objectid_formats() {
+        let oid = ObjectId::parse_str("507f1f77bcf86cd799439011").unwrap();
+        let convert = |objectid_format: ObjectIdFormat| {
+            let options = ConverterOptions {
+                objectid_format,
+                ..ConverterOptions::default()
+            };
+            bson_to_sql_value_with_options(&Bson::ObjectId(oid), &options)
+        };
+
+        assert_eq!(
+            convert(ObjectIdFormat::Hex),
+            SqlValue::Text("507f1f77bcf86cd799439011".to_string())
+        );
+        // 0x507f1f77 seconds: 2012-10-17T21:13:27Z
+        assert_eq!(convert(ObjectIdFormat::Timestamp), SqlValue::Integer(1_350_508_407));
+        assert_eq!(
+            convert(ObjectIdFormat::ExtJson),
+            SqlValue::Text(r#"{"$oid":"507f1f77bcf86cd799439011"}"#.to_string())
+        );
+
+        assert_eq!(infer_sqlite_type_with(&Bson::ObjectId(oid), ObjectIdFormat::Hex), "TEXT");
+        assert_eq!(infer_sqlite_type_with(&Bson::ObjectId(oid), ObjectIdFormat::Timestamp), "INTEGER");
+        assert_eq!(infer_sqlite_type_with(&Bson::ObjectId(oid), ObjectIdFormat::ExtJson), "TEXT");
+
+        // The primary key keeps its unique hex form
+        let options = ConverterOptions {
+            objectid_format: ObjectIdFormat::Timestamp,
+            ..ConverterOptions::default()
+        };
+        let doc = bson::doc! { "_id": oid, "owner": oid };
+        let values = document_to_sql_values(
+            &doc,
+            &[text_field("_id"), text_field("owner")],
+            &options,
+            &mut HashSet::new(),
+        );
+        assert_eq!(values[0], SqlValue::Text("507f1f77bcf86cd799439011".to_string()));
+        assert_eq!(values[1], SqlValue::Integer(1_350_508_407));
+
+        assert_eq!("EXTJSON".parse::<ObjectIdFormat>().unwrap(), ObjectIdFormat::ExtJson);
+        assert!("base64".parse::<ObjectIdFormat>().is_err());
+    }
+
+    #[test]
+    fn test_pretty_json() {
+        let nested = Bson::Document(bson::doc! { "address": { "city": "Paris" }, "tags": ["a"] });
+        let convert = |pretty_json: bool| {
+            let options = ConverterOptions {
+                pretty_json,
+                ..ConverterOptions::default()
+            };
+            bson_to_sql_value_with_options(&nested, &options)
+        };
+
+        assert_eq!(
+            convert(false),
+            SqlValue::Text(r#"{"address":{"city":"Paris"},"tags":["a"]}"#.to_string())
+        );
+        assert_eq!(
+            convert(true),
+            SqlValue::Text(
+                "{\n  \"address\": {\n    \"city\": \"Paris\"\n  },\n  \"tags\": [\n    \"a\"\n  ]\n}"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_decimal_formats() {
+        use crate::schema::{InferenceOptions, SchemaInferrer};
+
+        let dec: bson::Decimal128 = "1234.5678".parse().unwrap();
+        let doc = bson::doc! { "_id": 1, "price": Bson::Decimal128(dec) };
+        let convert = |decimal_format: DecimalFormat| {
+            let options = ConverterOptions {
+                decimal_format,
+                ..ConverterOptions::default()
+            };
+            let field = Field {
+                sql_type: decimal_format.sql_type().to_string(),
+                ..text_field("price")
+            };
+            let mut warned_fields = HashSet::new();
+            let values = document_to_sql_values(&doc, &[field], &options, &mut warned_fields);
+            (values[0].clone(), warned_fields.contains("price (decimal)"))
+        };
+        let infer = |decimal_format: DecimalFormat| {
+            let options = InferenceOptions {
+                decimal_format,
+                ..InferenceOptions::default()
+            };
+            let schema =
+                SchemaInferrer::infer_schema("items", std::slice::from_ref(&doc), &options).unwrap();
+            schema.fields.iter().find(|f| f.name == "price").unwrap().sql_type.clone()
+        };
+
+        // Text is the default and keeps every digit
+        assert_eq!(convert(DecimalFormat::default()), (SqlValue::Text("1234.5678".to_string()), false));
+        assert_eq!(infer(DecimalFormat::Text), "TEXT");
+
+        // Real is opt-in and warns about the precision loss
+        assert_eq!(convert(DecimalFormat::Real), (SqlValue::Real(1234.5678), true));
+        assert_eq!(infer(DecimalFormat::Real), "REAL");
+
+        assert_eq!(convert(DecimalFormat::Blob), (SqlValue::Blob(dec.bytes().to_vec()), false));
+        assert_eq!(infer(DecimalFormat::Blob), "BLOB");
+
+        assert_eq!("REAL".parse::<DecimalFormat>().unwrap(), DecimalFormat::Real);
+        assert!("float".parse::<DecimalFormat>().is_err());
+    }
+
+    #[test]
+    fn test_extended_json_datetime() {
+        let dt = bson::DateTime::from_millis(0);
+        assert_eq!(
+            extended_json(&Bson::DateTime(dt)),
+            serde_json::json!({ "$date": "1970-01-01T00:00:00+00:00" })
+        );
+    }
+
+    #[test]
+    fn test_datetime_formats() {
+        let dt = Bson::DateTime(bson::DateTime::from_millis(1_705_314_600_123));
+        let convert = |datetime_format: DatetimeFormat, extended_json_types: bool| {
+            let options = ConverterOptions {
+                datetime_format,
+                extended_json_types,
+                ..ConverterOptions::default()
+            };
+            match bson_to_sql_value_with_options(&dt, &options) {
+                SqlValue::Text(s) => s,
+                _ => panic!("Expected Text value"),
+            }
+        };
+
+        assert_eq!(convert(DatetimeFormat::Rfc3339, false), "2024-01-15T10:30:00.123+00:00");
+        assert_eq!(convert(DatetimeFormat::SqliteText, false), "2024-01-15 10:30:00.123");
+        assert_eq!(
+            convert(DatetimeFormat::SqliteText, true),
+            r#"{"$date":"2024-01-15T10:30:00.123+00:00"}"#
+        );
+        assert_eq!(
+            DatetimeFormat::SqliteText.format(&DateTime::<Utc>::from_timestamp(0, 0).unwrap()),
+            "1970-01-01 00:00:00.000"
+        );
+        assert_eq!("SQLITE-TEXT".parse::<DatetimeFormat>().unwrap(), DatetimeFormat::SqliteText);
+        assert!("epoch".parse::<DatetimeFormat>().is_err());
+    }
+
+    #[test]
+    fn test_extended_json_code() {
+        assert_eq!(
+            extended_json(&Bson::JavaScriptCode("return 1;".to_string())),
+            serde_json::json!({ "$code": "return 1;" })
+        );
+
+        let code_with_scope = bson::JavaScriptCodeWithScope {
+            code: "return x;".to_string(),
+            scope: bson::doc! { "x": 1 },
+        };
+        assert_eq!(
+            extended_json(&Bson::JavaScriptCodeWithScope(code_with_scope)),
+            serde_json::json!({ "$code": "return x;", "$scope": { "x": 1 } })
+        );
+    }
+
+    #[test]
+    fn test_plain_code_without_extended_json() {
+        match bson_to_sql_value(&Bson::JavaScriptCode("return 1;".to_string())) {
+            SqlValue::Text(s) => assert_eq!(s, "return 1;"),
+            _ => panic!("Expected Text value"),
+        }
+    }
+
+    const UUID_BYTES: [u8; 16] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
+        0xee, 0xff,
+    ];
+
+    fn uuid_binary(subtype: BinarySubtype) -> Bson {
+        Bson::Binary(Binary {
+            subtype,
+            bytes: UUID_BYTES.to_vec(),
+        })
+    }
+
+    #[test]
+    fn test_uuid_subtype_4_to_text() {
+        let bson = uuid_binary(BinarySubtype::Uuid);
+        assert_eq!(infer_sqlite_type(&bson), "TEXT");
+        match bson_to_sql_value(&bson) {
+            SqlValue::Text(s) => assert_eq!(s, "00112233-4455-6677-8899-aabbccddeeff"),
+            _ => panic!("Expected Text value"),
+        }
+    }
+
+    #[test]
+    fn test_legacy_uuid_byte_order() {
+        let bson = uuid_binary(BinarySubtype::UuidOld);
+        let convert = |legacy_uuid: &str| {
+            let options = ConverterOptions {
+                legacy_uuid: legacy_uuid.parse().unwrap(),
+                ..ConverterOptions::default()
+            };
+            match bson_to_sql_value_with_options(&bson, &options) {
+                SqlValue::Text(s) => s,
+                _ => panic!("Expected Text value"),
+            }
+        };
+
+        assert_eq!(convert("standard"), "00112233-4455-6677-8899-aabbccddeeff");
+        assert_eq!(convert("csharp"), "33221100-5544-7766-8899-aabbccddeeff");
+        assert_eq!(convert("java"), "77665544-3322-1100-ffee-ddccbbaa9988");
+    }
+
+    #[test]
+    fn test_uuid_subtype_4_ignores_legacy_order() {
+        let options = ConverterOptions {
+            legacy_uuid: LegacyUuidEncoding::Java,
+            ..ConverterOptions::default()
+        };
+        match bson_to_sql_value_with_options(&uuid_binary(BinarySubtype::Uuid), &options) {
+            SqlValue::Text(s) => assert_eq!(s, "00112233-4455-6677-8899-aabbccddeeff"),
+            _ => panic!("Expected Text value"),
+        }
+    }
+
+    fn coerce(value: &str, sql_type: &str) -> SqlValue {
+        let mut doc = Document::new();
+        doc.insert("value", value);
+        let field = Field {
+            sql_type: sql_type.to_string(),
+            ..text_field("value")
+        };
+        let options = ConverterOptions {
+            coerce_numeric_strings: true,
+            ..ConverterOptions::default()
+        };
+
+        let mut warned_fields = HashSet::new();
+        let values = document_to_sql_values(&doc, &[field], &options, &mut warned_fields);
+        values.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn test_coerce_numeric_strings() {
+        match coerce("42", "INTEGER") {
+            SqlValue::Integer(i) => assert_eq!(i, 42),
+            _ => panic!("Expected Integer value"),
+        }
+        match coerce(" 2.75 ", "REAL") {
+            SqlValue::Real(r) => assert_eq!(r, 2.75),
+            _ => panic!("Expected Real value"),
+        }
+        match coerce("2.5", "INTEGER") {
+            SqlValue::Real(r) => assert_eq!(r, 2.5),
+            _ => panic!("Expected Real value"),
+        }
+        match coerce("n/a", "INTEGER") {
+            SqlValue::Null => (),
+            _ => panic!("Expected Null value"),
+        }
+        match coerce("42", "TEXT") {
+            SqlValue::Text(s) => assert_eq!(s, "42"),
+            _ => panic!("Expected Text value"),
+        }
+    }
+
+    #[test]
+    fn test_round_to_places() {
+        assert_eq!(round_to_places(12.34567, 2), 12.35);
+        assert_eq!(round_to_places(-12.34567, 2), -12.35);
+        assert_eq!(round_to_places(-0.1251, 2), -0.13);
+        assert_eq!(round_to_places(0.1 + 0.2, 2), 0.3);
+        assert_eq!(round_to_places(12.34567, 4), 12.3457);
+        assert_eq!(round_to_places(-8.765432, 4), -8.7654);
+        assert_eq!(round_to_places(1e308, 4), 1e308);
+        // 1.005 is stored as 1.00499999999999989..., so it rounds down
+        assert_eq!(round_to_places(1.005, 2), 1.0);
+    }
+
+    #[test]
+    fn test_round_real_only_applies_to_real_columns() {
+        let doc = bson::doc! { "price": 19.98765, "label": 19.98765 };
+        let fields = [
+            Field {
+                sql_type: "REAL".to_string(),
+                ..text_field("price")
+            },
+            text_field("label"),
+        ];
+        let options = ConverterOptions {
+            round_real: Some(2),
+            ..ConverterOptions::default()
+        };
+
+        let values = document_to_sql_values(&doc, &fields, &options, &mut HashSet::new());
+        match &values[0] {
+            SqlValue::Real(r) => assert_eq!(*r, 19.99),
+            _ => panic!("Expected Real value"),
+        }
+        match &values[1] {
+            SqlValue::Real(r) => assert_eq!(*r, 19.98765),
+            _ => panic!("Expected Real value"),
+        }
+    }
+
+    #[test]
+    fn test_non_finite_doubles() {
+        let text = |s: &str| SqlValue::Text(s.to_string());
+        let convert = |value: f64, non_finite: NonFiniteMode| {
+            let options = ConverterOptions {
+                non_finite,
+                ..ConverterOptions::default()
+            };
+            bson_to_sql_value_with_options(&Bson::Double(value), &options)
+        };
+
+        assert_eq!(convert(f64::NAN, NonFiniteMode::Null), SqlValue::Null);
+        assert_eq!(convert(f64::INFINITY, NonFiniteMode::Null), SqlValue::Null);
+        assert_eq!(convert(f64::NEG_INFINITY, NonFiniteMode::Null), SqlValue::Null);
+        assert_eq!(convert(f64::NAN, NonFiniteMode::Text), text("NaN"));
+        assert_eq!(convert(f64::INFINITY, NonFiniteMode::Text), text("Infinity"));
+        assert_eq!(convert(f64::NEG_INFINITY, NonFiniteMode::Text), text("-Infinity"));
+        assert_eq!(convert(1.5, NonFiniteMode::Text), SqlValue::Real(1.5));
+
+        // Rounding doesn't apply, and each field warns once
+        let doc = bson::doc! { "a": f64::NAN, "b": f64::INFINITY, "c": f64::NEG_INFINITY };
+        let fields = ["a", "b", "c"].map(|name| Field {
+            sql_type: "REAL".to_string(),
+            ..text_field(name)
+        });
+        let options = ConverterOptions {
+            round_real: Some(2),
+            non_finite: NonFiniteMode::Text,
+            ..ConverterOptions::default()
+        };
+        let mut warned_fields = HashSet::new();
+        let values = document_to_sql_values(&doc, &fields, &options, &mut warned_fields);
+        assert_eq!(values, vec![text("NaN"), text("Infinity"), text("-Infinity")]);
+        assert!(warned_fields.contains("a (non-finite)"));
+
+        let values =
+            document_to_sql_values(&doc, &fields, &ConverterOptions::default(), &mut warned_fields);
+        assert_eq!(values, vec![SqlValue::Null, SqlValue::Null, SqlValue::Null]);
+
+        assert_eq!("TEXT".parse::<NonFiniteMode>().unwrap(), NonFiniteMode::Text);
+        assert!("zero".parse::<NonFiniteMode>().is_err());
+    }
+
+    #[test]
+    fn test_conversion_stats() {
+        let stats = Arc::new(ConversionStats::default());
+        let options = ConverterOptions {
+            coerce_numeric_strings: true,
+            stats: Some(stats.clone()),
+            ..Default::default()
+        };
+        let mut age = text_field("age");
+        age.sql_type = "INTEGER".to_string();
+        let fields = vec![text_field("name"), text_field("tags"), text_field("big"), age];
+        let doc = bson::doc! { "name": "Ada", "tags": ["a", "b"], "age": "42", "big": 1_i64 << 60 };
+        document_to_sql_values(&doc, &fields, &options, &mut HashSet::new());
+
+        /// A value whose serialization always fails
+        struct Unserializable;
+        impl Serialize for Unserializable {
+            fn serialize<S: serde::Serializer>(&self, _: S) -> std::result::Result<S::Ok, S::Error> {
+                Err(serde::ser::Error::custom("unsupported"))
+            }
+        }
+        let array = Bson::Array(vec![Bson::Int32(1)]);
+        assert!(matches!(json_text(&Unserializable, &array, &options), SqlValue::Null));
+
+        let counts: HashMap<&str, TypeCounts> = stats.by_type().into_iter().collect();
+        assert_eq!(
+            counts["Array"],
+            TypeCounts { converted: 1, failed: 1, coerced: 0 }
+        );
+        assert_eq!(
+            counts["String"],
+            TypeCounts { converted: 2, failed: 0, coerced: 1 }
+        );
+        assert_eq!(counts["Int64"].converted, 1);
+        assert_eq!(
+            stats.total(),
+            TypeCounts { converted: 4, failed: 1, coerced: 1 }
+        );
+    }
+
+    #[test]
+    fn test_base64_to_blob() {
+        let doc = bson::doc! {
+            "thumbnail": "aGVsbG8=",
+            "unpadded": "aGVsbG8",
+            "broken": "not base64!",
+        };
+        let fields = [
+            text_field("thumbnail"),
+            text_field("unpadded"),
+            text_field("broken"),
+        ];
+        let options = ConverterOptions {
+            base64_blob_fields: ["thumbnail", "unpadded", "broken"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..ConverterOptions::default()
+        };
+
+        let mut warned_fields = HashSet::new();
+        let values = document_to_sql_values(&doc, &fields, &options, &mut warned_fields);
+        assert_eq!(values[0], SqlValue::Blob(b"hello".to_vec()));
+        assert_eq!(values[1], SqlValue::Blob(b"hello".to_vec()));
+        // Invalid base64 is kept as text, with a single warning
+        assert_eq!(values[2], SqlValue::Text("not base64!".to_string()));
+        assert!(warned_fields.contains("broken (base64)"));
+
+        assert_eq!(decode_base64("aGVs\nbG8=").as_deref(), Some(&b"hello"[..]));
+    }
+
+    fn transform_email(op: &str) -> SqlValue {
+        let mut doc = Document::new();
+        doc.insert("email", "Alice@Example.com");
+
+        let options =
+            ConverterOptions::from_transform_specs(&[format!("email={}", op)]).unwrap();
+        let values =
+            document_to_sql_values(&doc, &[text_field("email")], &options, &mut HashSet::new());
+        values.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn test_transform_lower() {
+        match transform_email("lower") {
+            SqlValue::Text(s) => assert_eq!(s, "alice@example.com"),
+            _ => panic!("Expected Text value"),
+        }
+    }
+
+    #[test]
+    fn test_transform_upper() {
+        match transform_email("upper") {
+            SqlValue::Text(s) => assert_eq!(s, "ALICE@EXAMPLE.COM"),
+            _ => panic!("Expected Text value"),
+        }
+    }
+
+    #[test]
+    fn test_transform_sha256() {
+        match transform_email("sha256") {
+            SqlValue::Text(s) => assert_eq!(
+                s,
+                hex::encode(Sha256::digest("Alice@Example.com".as_bytes()))
+            ),
+            _ => panic!("Expected Text value"),
+        }
+    }
+
+    #[test]
+    fn test_transform_redact() {
+        match transform_email("redact") {
+            SqlValue::Text(s) => assert_eq!(s, "[REDACTED]"),
+            _ => panic!("Expected Text value"),
+        }
+    }
+
+    #[test]
+    fn test_transform_absent_field() {
+        let doc = Document::new();
+        let options =
+            ConverterOptions::from_transform_specs(&["email=sha256".to_string()]).unwrap();
+
+        let values =
+            document_to_sql_values(&doc, &[text_field("email")], &options, &mut HashSet::new());
+        match &values[0] {
+            SqlValue::Null => (),
+            _ => panic!("Expected Null value"),
+        }
+    }
+
+    #[test]
+    fn test_transform_invalid_specs() {
+        assert!(ConverterOptions::from_transform_specs(&["email".to_string()]).is_err());
+        assert!(ConverterOptions::from_transform_specs(&["=lower".to_string()]).is_err());
+        assert!(ConverterOptions::from_transform_specs(&["email=reverse".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_default_values() {
+        let options = ConverterOptions {
+            defaults: parse_default_specs(&["nickname=".to_string(), "score=0".to_string()]).unwrap(),
+            ..ConverterOptions::default()
+        };
+        let fields = [
+            text_field("nickname"),
+            Field {
+                sql_type: "INTEGER".to_string(),
+                ..text_field("score")
+            },
+            text_field("email"),
+        ];
+        let convert = |doc: Document| document_to_sql_values(&doc, &fields, &options, &mut HashSet::new());
+
+        // Missing and null fields get their default, fields without one stay NULL
+        let expected = vec![SqlValue::Text(String::new()), SqlValue::Integer(0), SqlValue::Null];
+        assert_eq!(convert(Document::new()), expected);
+        assert_eq!(
+            convert(bson::doc! { "nickname": Bson::Null, "score": Bson::Null, "email": Bson::Null }),
+            expected
+        );
+
+        // Present values are kept
+        assert_eq!(
+            convert(bson::doc! { "nickname": "Al", "score": 7 }),
+            vec![SqlValue::Text("Al".to_string()), SqlValue::Integer(7), SqlValue::Null]
+        );
+
+        assert!(parse_default_specs(&["score".to_string()]).is_err());
+        assert!(parse_default_specs(&["=0".to_string()]).is_err());
+    }
 }
 
@@ -1,5 +1,25 @@
 use thiserror::Error;
 
+/// Process exit codes, so scripts can tell failure categories apart
+pub mod exit_code {
+    /// Migration finished without errors
+    pub const SUCCESS: i32 = 0;
+    /// Any failure not covered by a more specific code
+    pub const GENERAL: i32 = 1;
+    /// Invalid arguments or configuration
+    pub const CONFIG: i32 = 2;
+    /// MongoDB could not be reached or returned an error
+    pub const MONGODB: i32 = 3;
+    /// The SQLite/LibSQL target returned an error
+    pub const TARGET: i32 = 4;
+    /// Some collections failed under `--continue-on-error`
+    pub const PARTIAL_FAILURE: i32 = 5;
+    /// `--check-schema` found breaking changes
+    pub const BREAKING_SCHEMA: i32 = 6;
+    /// The run was interrupted (128 + SIGINT)
+    pub const INTERRUPTED: i32 = 130;
+}
+
 /// Errors that can occur during migration
 #[derive(Error, Debug)]
 #[allow(dead_code)]
@@ -12,6 +32,14 @@ pub enum MigrationError {
     #[error("LibSQL error: {0}")]
     LibSqlError(#[from] libsql::Error),
 
+    /// The target database ran out of disk space
+    #[error("The target database is out of disk space ({0}). Free up space or choose a different --output location")]
+    DiskFull(String),
+
+    /// `PRAGMA integrity_check` reported problems with the target database
+    #[error("Integrity check failed:\n{}", .0.join("\n"))]
+    IntegrityCheckFailed(Vec<String>),
+
     /// Schema inference error
     #[error("Schema inference error: {0}")]
     SchemaInferenceError(String),
@@ -79,3 +107,251 @@ impl MigrationError {
         MigrationError::Other(msg.into())
     }
 }
+
+/// Whether a SQLite error message reports a full disk (`SQLITE_FULL`)
+fn is_disk_full_message(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    message.contains("database or disk is full") || message.contains("sqlite_full")
+}
+
+/// Whether an error reports a UNIQUE or PRIMARY KEY constraint violation
+///
+/// Both drivers pass SQLite's message through, e.g. "UNIQUE constraint
+/// failed: users._id", so the message chain is what gets inspected.
+///
+/// # Arguments
+/// * `err` - Error returned by an insert
+///
+/// # Returns
+/// True for a unique constraint violation
+pub fn is_unique_violation(err: &anyhow::Error) -> bool {
+    let message = format!("{:#}", err).to_ascii_lowercase();
+    message.contains("unique constraint failed")
+        || message.contains("sqlite_constraint_unique")
+        || message.contains("sqlite_constraint_primarykey")
+}
+
+/// Turn a LibSQL error into a `MigrationError`, recognizing a full disk
+///
+/// Local databases report `SQLITE_FULL` with the message "database or disk
+/// is full"; remote ones pass the same message through, so the message is
+/// what gets inspected.
+///
+/// # Arguments
+/// * `err` - Error returned by LibSQL
+///
+/// # Returns
+/// `MigrationError::DiskFull` for a full disk, otherwise `MigrationError::LibSqlError`
+pub fn classify_libsql_error(err: libsql::Error) -> MigrationError {
+    let message = err.to_string();
+    if is_disk_full_message(&message) {
+        MigrationError::DiskFull(message)
+    } else {
+        MigrationError::LibSqlError(err)
+    }
+}
+
+/// Explain a common Turso connection failure in terms of its configuration
+///
+/// # Arguments
+/// * `message` - Error message returned while connecting to a remote database
+///
+/// # Returns
+/// What to check, or `None` if the message is not a recognized failure
+fn turso_error_hint(message: &str) -> Option<&'static str> {
+    let message = message.to_ascii_lowercase();
+    let mentions = |needles: &[&str]| needles.iter().any(|needle| message.contains(needle));
+
+    if mentions(&["401", "403", "unauthorized", "forbidden", "jwt", "auth token", "authentication"]) {
+        Some("Turso auth token rejected — check TURSO_AUTH_TOKEN")
+    } else if mentions(&["404", "not found", "does not exist"]) {
+        Some("Turso database not found — check TURSO_DATABASE_URL")
+    } else if mentions(&["dns error", "failed to lookup address", "connection refused", "invalid uri"]) {
+        Some("Turso database unreachable — check TURSO_DATABASE_URL and your network")
+    } else {
+        None
+    }
+}
+
+/// Turn an error from connecting to Turso into a `MigrationError`
+///
+/// A rejected token, a missing database or an unreachable host become a
+/// `MigrationError::ConfigError` naming the environment variable to check.
+///
+/// # Arguments
+/// * `err` - Error returned by LibSQL while connecting
+///
+/// # Returns
+/// `MigrationError::ConfigError` for recognized failures, otherwise what
+/// [`classify_libsql_error`] returns
+pub fn classify_turso_error(err: libsql::Error) -> MigrationError {
+    let message = err.to_string();
+    match turso_error_hint(&message) {
+        Some(hint) => MigrationError::ConfigError(format!("{} ({})", hint, message)),
+        None => classify_libsql_error(err),
+    }
+}
+
+/// Map a migration error to its process exit code
+///
+/// # Arguments
+/// * `err` - The error that ended the run
+///
+/// # Returns
+/// One of the codes in [`exit_code`]
+pub fn exit_code_for(err: &MigrationError) -> i32 {
+    match err {
+        MigrationError::MongoConnectionError(_) => exit_code::MONGODB,
+        MigrationError::LibSqlError(_)
+        | MigrationError::DiskFull(_)
+        | MigrationError::IntegrityCheckFailed(_) => exit_code::TARGET,
+        MigrationError::ConfigError(_)
+        | MigrationError::CollectionNotFound(..)
+        | MigrationError::DatabaseNotFound(_) => exit_code::CONFIG,
+        MigrationError::Interrupted => exit_code::INTERRUPTED,
+        MigrationError::SchemaInferenceError(_)
+        | MigrationError::TypeConversionError(_)
+        | MigrationError::IoError(_)
+        | MigrationError::BsonError(_)
+        | MigrationError::BsonDocumentError(_)
+        | MigrationError::Other(_) => exit_code::GENERAL,
+    }
+}
+
+/// Map any error to its process exit code
+///
+/// Walks the error chain for a `MigrationError`, or a MongoDB or LibSQL
+/// error propagated directly, and falls back to [`exit_code::GENERAL`].
+///
+/// # Arguments
+/// * `err` - The error that ended the run
+///
+/// # Returns
+/// One of the codes in [`exit_code`]
+pub fn exit_code_for_error(err: &anyhow::Error) -> i32 {
+    for cause in err.chain() {
+        if let Some(err) = cause.downcast_ref::<MigrationError>() {
+            return exit_code_for(err);
+        }
+        if cause.downcast_ref::<mongodb::error::Error>().is_some() {
+            return exit_code::MONGODB;
+        }
+        if cause.downcast_ref::<libsql::Error>().is_some() {
+            return exit_code::TARGET;
+        }
+    }
+
+    exit_code::GENERAL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code_for() {
+        let mongo_error = mongodb::error::Error::custom("unreachable");
+        let cases = vec![
+            (MigrationError::from(mongo_error), exit_code::MONGODB),
+            (
+                MigrationError::LibSqlError(libsql::Error::Misuse("closed".to_string())),
+                exit_code::TARGET,
+            ),
+            (MigrationError::DiskFull("database or disk is full".to_string()), exit_code::TARGET),
+            (
+                MigrationError::IntegrityCheckFailed(vec!["row 3 missing from index".to_string()]),
+                exit_code::TARGET,
+            ),
+            (MigrationError::schema_inference("empty"), exit_code::GENERAL),
+            (MigrationError::type_conversion("bad"), exit_code::GENERAL),
+            (
+                MigrationError::CollectionNotFound("users".to_string(), "db".to_string()),
+                exit_code::CONFIG,
+            ),
+            (MigrationError::DatabaseNotFound("db".to_string()), exit_code::CONFIG),
+            (MigrationError::config("bad flag"), exit_code::CONFIG),
+            (
+                MigrationError::from(std::io::Error::other("disk full")),
+                exit_code::GENERAL,
+            ),
+            (
+                MigrationError::from(bson::ser::Error::InvalidDocumentKey(bson::Bson::Null)),
+                exit_code::GENERAL,
+            ),
+            (
+                MigrationError::from(bson::document::ValueAccessError::NotPresent),
+                exit_code::GENERAL,
+            ),
+            (MigrationError::Interrupted, exit_code::INTERRUPTED),
+            (MigrationError::other("boom"), exit_code::GENERAL),
+        ];
+
+        for (err, code) in cases {
+            assert_eq!(exit_code_for(&err), code, "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_classify_libsql_error() {
+        assert!(is_disk_full_message("database or disk is full"));
+        assert!(is_disk_full_message("SQLite failure: `SQLITE_FULL: Database or disk is full`"));
+        assert!(!is_disk_full_message("UNIQUE constraint failed: users._id"));
+
+        let err = classify_libsql_error(libsql::Error::Misuse("database or disk is full".to_string()));
+        assert!(matches!(err, MigrationError::DiskFull(_)));
+        assert!(err.to_string().contains("Free up space"));
+
+        let err = classify_libsql_error(libsql::Error::Misuse("no such table: users".to_string()));
+        assert!(matches!(err, MigrationError::LibSqlError(_)));
+    }
+
+    #[test]
+    fn test_is_unique_violation() {
+        assert!(is_unique_violation(&anyhow::anyhow!("UNIQUE constraint failed: users._id")));
+        assert!(is_unique_violation(
+            &anyhow::anyhow!("SQLITE_CONSTRAINT_PRIMARYKEY").context("Batch insert failed")
+        ));
+        assert!(!is_unique_violation(&anyhow::anyhow!("NOT NULL constraint failed: users.name")));
+        assert!(!is_unique_violation(&anyhow::anyhow!("database or disk is full")));
+    }
+
+    #[test]
+    fn test_classify_turso_error() {
+        let expired = "Hrana: `api error: `status=401 Unauthorized, body={\"error\":\"Unauthorized: `The JWT is invalid`\"}``";
+        assert_eq!(
+            turso_error_hint(expired),
+            Some("Turso auth token rejected — check TURSO_AUTH_TOKEN")
+        );
+        let missing = "Hrana: `api error: `status=404 Not Found, body={\"error\":\"Namespace `app-org` does not exist\"}``";
+        assert_eq!(
+            turso_error_hint(missing),
+            Some("Turso database not found — check TURSO_DATABASE_URL")
+        );
+        let unreachable = "Hrana: `http error: `error trying to connect: dns error: failed to lookup address information``";
+        assert_eq!(
+            turso_error_hint(unreachable),
+            Some("Turso database unreachable — check TURSO_DATABASE_URL and your network")
+        );
+        assert_eq!(turso_error_hint("UNIQUE constraint failed: users._id"), None);
+
+        let err = classify_turso_error(libsql::Error::Misuse(expired.to_string()));
+        assert!(matches!(err, MigrationError::ConfigError(_)));
+        assert!(err.to_string().contains("TURSO_AUTH_TOKEN"));
+        assert_eq!(exit_code_for(&err), exit_code::CONFIG);
+
+        let err = classify_turso_error(libsql::Error::Misuse("database or disk is full".to_string()));
+        assert!(matches!(err, MigrationError::DiskFull(_)));
+    }
+
+    #[test]
+    fn test_exit_code_for_error_chain() {
+        let err = anyhow::Error::from(MigrationError::DatabaseNotFound("db".to_string()))
+            .context("check failed");
+        assert_eq!(exit_code_for_error(&err), exit_code::CONFIG);
+
+        let err = anyhow::Error::from(mongodb::error::Error::custom("unreachable"));
+        assert_eq!(exit_code_for_error(&err), exit_code::MONGODB);
+
+        assert_eq!(exit_code_for_error(&anyhow::anyhow!("boom")), exit_code::GENERAL);
+    }
+}
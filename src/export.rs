@@ -0,0 +1,496 @@
+use anyhow::{bail, Result};
+use bson::{Bson, Document};
+use flate2::write::GzEncoder;
+use libsql::Value as SqlValue;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::converter::{escape_identifier, QuoteStyle};
+use crate::schema::{CollectionSchema, FieldInfo};
+
+/// Compression applied to export output
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ExportCompression {
+    /// Plain output
+    #[default]
+    None,
+    /// Gzip, e.g. `users.jsonl.gz`
+    Gzip,
+}
+
+impl FromStr for ExportCompression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(ExportCompression::None),
+            "gzip" => Ok(ExportCompression::Gzip),
+            _ => bail!("Unknown export compression '{}': expected none or gzip", s),
+        }
+    }
+}
+
+impl ExportCompression {
+    /// Compression for an output file, enabling gzip for a `.gz` name
+    ///
+    /// # Arguments
+    /// * `path` - File given to `--output`
+    ///
+    /// # Returns
+    /// Gzip if requested or if the file name ends in `.gz`
+    pub fn for_path(self, path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("gz") => ExportCompression::Gzip,
+            _ => self,
+        }
+    }
+
+    /// Suffix appended to per-collection file names, e.g. `.gz`
+    pub fn file_suffix(&self) -> &'static str {
+        match self {
+            ExportCompression::None => "",
+            ExportCompression::Gzip => ".gz",
+        }
+    }
+}
+
+/// A sink for export output that compresses on the way through
+///
+/// Writers are boxed so every format writes the same way regardless of
+/// compression. `finish` must be called to write the gzip trailer.
+pub enum ExportWriter {
+    /// Bytes go straight to the destination
+    Plain(Box<dyn Write>),
+    /// Bytes are gzip-compressed into the destination
+    Gzip(GzEncoder<Box<dyn Write>>),
+}
+
+impl ExportWriter {
+    /// Wrap a writer in the requested compression
+    ///
+    /// # Arguments
+    /// * `writer` - Destination file or stdout
+    /// * `compression` - Compression to apply
+    ///
+    /// # Returns
+    /// A writer compressing into `writer`
+    pub fn new(writer: Box<dyn Write>, compression: ExportCompression) -> Self {
+        match compression {
+            ExportCompression::None => ExportWriter::Plain(writer),
+            ExportCompression::Gzip => {
+                ExportWriter::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+            }
+        }
+    }
+
+    /// Complete the compressed stream and flush the destination
+    pub fn finish(self) -> std::io::Result<()> {
+        let mut writer = match self {
+            ExportWriter::Plain(writer) => writer,
+            ExportWriter::Gzip(encoder) => encoder.finish()?,
+        };
+        writer.flush()
+    }
+}
+
+impl Write for ExportWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            ExportWriter::Plain(writer) => writer.write(buf),
+            ExportWriter::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            ExportWriter::Plain(writer) => writer.flush(),
+            ExportWriter::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Create an output directory for per-collection export files
+///
+/// Missing parent directories are created as well. An existing path must be
+/// a directory.
+///
+/// # Arguments
+/// * `dir` - Directory given to `--output-dir`
+///
+/// # Returns
+/// Ok if the directory exists (or was created)
+pub fn prepare_output_dir(dir: &Path) -> Result<()> {
+    if dir.exists() {
+        if !dir.is_dir() {
+            bail!("--output-dir {} exists and is not a directory", dir.display());
+        }
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(dir)?;
+    Ok(())
+}
+
+/// File extension used for an export format
+///
+/// # Arguments
+/// * `format` - Export format, e.g. `jsonl` or `bson-stream`
+///
+/// # Returns
+/// The extension, without a leading dot
+pub fn format_extension(format: &str) -> &str {
+    match format {
+        "bson-stream" => "bson",
+        "sql-dump" => "sql",
+        other => other,
+    }
+}
+
+/// Path of the export file for one collection
+///
+/// Path separators in the collection name are replaced so every file ends
+/// up directly inside `dir`.
+///
+/// # Arguments
+/// * `dir` - Output directory
+/// * `collection_name` - Name of the collection
+/// * `format` - Export format, which picks the file extension
+/// * `compression` - Compression, which may add a suffix such as `.gz`
+///
+/// # Returns
+/// `<dir>/<collection>.<ext>`, e.g. `out/users.jsonl.gz`
+pub fn collection_file_path(
+    dir: &Path,
+    collection_name: &str,
+    format: &str,
+    compression: ExportCompression,
+) -> PathBuf {
+    let file_stem: String = collection_name
+        .chars()
+        .map(|c| if c == '/' || c == '\\' { '_' } else { c })
+        .collect();
+    dir.join(format!(
+        "{}.{}{}",
+        file_stem,
+        format_extension(format),
+        compression.file_suffix()
+    ))
+}
+
+/// Serialize a document as relaxed Extended JSON
+///
+/// # Arguments
+/// * `doc` - Document to serialize
+///
+/// # Returns
+/// The document as a single line of JSON
+pub fn document_json(doc: Document) -> String {
+    Bson::Document(doc).into_relaxed_extjson().to_string()
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+fn csv_escape(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Format the header line of a CSV export
+///
+/// # Arguments
+/// * `schema` - Schema whose column names head the file
+///
+/// # Returns
+/// The column names as one CSV line, without a line break
+pub fn csv_header(schema: &CollectionSchema) -> String {
+    schema
+        .fields
+        .iter()
+        .map(|field| csv_escape(&field.name))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Format converted values as a CSV line
+///
+/// NULL becomes an empty field and BLOBs are written as hex.
+///
+/// # Arguments
+/// * `values` - Row values, in schema order
+///
+/// # Returns
+/// The row as one CSV line, without a line break
+pub fn csv_row(values: &[SqlValue]) -> String {
+    values
+        .iter()
+        .map(|value| match value {
+            SqlValue::Null => String::new(),
+            SqlValue::Integer(i) => i.to_string(),
+            SqlValue::Real(r) => r.to_string(),
+            SqlValue::Text(t) => csv_escape(t),
+            SqlValue::Blob(bytes) => hex::encode(bytes),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Format a value as a SQLite literal
+fn sql_literal(value: &SqlValue) -> String {
+    match value {
+        SqlValue::Null => "NULL".to_string(),
+        SqlValue::Integer(i) => i.to_string(),
+        // SQLite has no literal for NaN; 1e999 overflows to infinity
+        SqlValue::Real(r) if r.is_nan() => "NULL".to_string(),
+        SqlValue::Real(r) if r.is_infinite() => {
+            if *r > 0.0 { "1e999" } else { "-1e999" }.to_string()
+        }
+        SqlValue::Real(r) => format!("{:?}", r),
+        SqlValue::Text(t) => format!("'{}'", t.replace('\'', "''")),
+        SqlValue::Blob(bytes) => format!("X'{}'", hex::encode(bytes)),
+    }
+}
+
+/// Format the CREATE TABLE statement opening a SQL dump of a collection
+///
+/// # Arguments
+/// * `schema` - Schema of the dumped table
+/// * `field_infos` - Inference stats to comment each column with, for
+///   `--column-comments`
+///
+/// # Returns
+/// The statement, terminated by a semicolon
+pub fn sql_dump_create_table(schema: &CollectionSchema, field_infos: Option<&[(String, FieldInfo)]>) -> String {
+    match field_infos {
+        Some(field_infos) => format!("{};", schema.to_commented_create_table_sql(QuoteStyle::Ansi, field_infos)),
+        None => format!("{};", schema.to_create_table_sql(QuoteStyle::Ansi)),
+    }
+}
+
+/// Format a row as an INSERT statement of a SQL dump
+///
+/// # Arguments
+/// * `schema` - Schema of the dumped table
+/// * `values` - Row values, in schema order
+///
+/// # Returns
+/// The statement, terminated by a semicolon
+pub fn sql_dump_insert(schema: &CollectionSchema, values: &[SqlValue]) -> String {
+    let columns: Vec<String> = schema
+        .fields
+        .iter()
+        .map(|field| escape_identifier(&field.name, QuoteStyle::Ansi))
+        .collect();
+    let literals: Vec<String> = values.iter().map(sql_literal).collect();
+
+    format!(
+        "INSERT INTO {} ({}) VALUES ({});",
+        escape_identifier(&schema.collection_name, QuoteStyle::Ansi),
+        columns.join(", "),
+        literals.join(", ")
+    )
+}
+
+/// Streams JSON values into a single well-formed JSON array
+///
+/// `[` is written up front, elements are separated by commas as they
+/// arrive and `]` is written by `finish`, so a collection never has to be
+/// held in memory. An array without elements is written as `[]`.
+pub struct JsonArrayWriter<W: Write> {
+    writer: W,
+    elements: usize,
+}
+
+impl<W: Write> JsonArrayWriter<W> {
+    /// Start an array by writing its opening bracket
+    ///
+    /// # Arguments
+    /// * `writer` - Destination of the array
+    pub fn begin(mut writer: W) -> std::io::Result<Self> {
+        writer.write_all(b"[")?;
+        Ok(Self { writer, elements: 0 })
+    }
+
+    /// Append one element, which must already be serialized JSON
+    ///
+    /// # Arguments
+    /// * `json` - Serialized JSON value
+    pub fn write_element(&mut self, json: &str) -> std::io::Result<()> {
+        let separator: &[u8] = if self.elements == 0 { b"\n" } else { b",\n" };
+        self.writer.write_all(separator)?;
+        self.writer.write_all(json.as_bytes())?;
+        self.elements += 1;
+        Ok(())
+    }
+
+    /// Close the array
+    ///
+    /// # Returns
+    /// The number of elements written
+    pub fn finish(mut self) -> std::io::Result<usize> {
+        let closing: &[u8] = if self.elements == 0 { b"]\n" } else { b"\n]\n" };
+        self.writer.write_all(closing)?;
+        Ok(self.elements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bson::doc;
+
+    #[test]
+    fn test_prepare_output_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let nested = temp_dir.path().join("exports").join("2024");
+        prepare_output_dir(&nested).unwrap();
+        assert!(nested.is_dir());
+
+        // An existing directory is reused
+        prepare_output_dir(&nested).unwrap();
+
+        let file = temp_dir.path().join("not-a-dir");
+        std::fs::write(&file, b"").unwrap();
+        assert!(prepare_output_dir(&file).is_err());
+    }
+
+    #[test]
+    fn test_collection_file_path() {
+        let dir = Path::new("out");
+        let none = ExportCompression::None;
+        assert_eq!(collection_file_path(dir, "users", "jsonl", none), dir.join("users.jsonl"));
+        assert_eq!(collection_file_path(dir, "a/b", "bson-stream", none), dir.join("a_b.bson"));
+        assert_eq!(collection_file_path(dir, "users", "json", none), dir.join("users.json"));
+        assert_eq!(collection_file_path(dir, "users", "sql-dump", none), dir.join("users.sql"));
+        assert_eq!(
+            collection_file_path(dir, "users", "csv", ExportCompression::Gzip),
+            dir.join("users.csv.gz")
+        );
+    }
+
+    #[test]
+    fn test_export_compression_for_path() {
+        let none = ExportCompression::None;
+        assert_eq!(none.for_path(Path::new("users.jsonl.gz")), ExportCompression::Gzip);
+        assert_eq!(none.for_path(Path::new("users.jsonl")), ExportCompression::None);
+        assert_eq!(
+            ExportCompression::Gzip.for_path(Path::new("users.jsonl")),
+            ExportCompression::Gzip
+        );
+        assert!("zip".parse::<ExportCompression>().is_err());
+    }
+
+    #[test]
+    fn test_gzip_export_round_trip() {
+        use std::io::Read;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("users.jsonl.gz");
+        let compression = ExportCompression::None.for_path(&path);
+
+        let file = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+        let mut writer = ExportWriter::new(Box::new(file), compression);
+        let lines: Vec<String> = (0..3)
+            .map(|i| document_json(doc! { "_id": i, "name": format!("user {}", i) }))
+            .collect();
+        for line in &lines {
+            writeln!(writer, "{}", line).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let compressed = std::fs::read(&path).unwrap();
+        assert_eq!(&compressed[..2], &[0x1f, 0x8b]);
+
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, format!("{}\n", lines.join("\n")));
+    }
+
+    fn sample_schema() -> CollectionSchema {
+        let docs = [doc! { "_id": 1, "name": "a,b", "score": 1.5, "data": bson::Binary {
+            subtype: bson::spec::BinarySubtype::Generic,
+            bytes: vec![0xab],
+        } }];
+        crate::schema::SchemaInferrer::infer_schema("users", &docs, &Default::default()).unwrap()
+    }
+
+    #[test]
+    fn test_csv_rows() {
+        let schema = sample_schema();
+        assert_eq!(csv_header(&schema), "_id,data,name,score");
+
+        let row = csv_row(&[
+            SqlValue::Integer(1),
+            SqlValue::Text("say \"hi\", \nbye".to_string()),
+            SqlValue::Null,
+            SqlValue::Blob(vec![0xab, 0x01]),
+        ]);
+        assert_eq!(row, "1,\"say \"\"hi\"\", \nbye\",,ab01");
+    }
+
+    #[tokio::test]
+    async fn test_sql_dump_loads_into_sqlite() {
+        let schema = sample_schema();
+        let values = [
+            SqlValue::Integer(1),
+            SqlValue::Blob(vec![0xab]),
+            SqlValue::Text("it's".to_string()),
+            SqlValue::Real(2.0),
+        ];
+        let client = crate::libsql_client::LibSqlClient::new(Some(":memory:")).await.unwrap();
+        client.execute(&sql_dump_create_table(&schema, None)).await.unwrap();
+        client.execute(&sql_dump_insert(&schema, &values)).await.unwrap();
+
+        let mut rows = client
+            .query("SELECT _id, name, score, data FROM users")
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<i64>(0).unwrap(), 1);
+        assert_eq!(row.get::<String>(1).unwrap(), "it's");
+        assert_eq!(row.get::<f64>(2).unwrap(), 2.0);
+        assert_eq!(row.get::<Vec<u8>>(3).unwrap(), vec![0xab]);
+    }
+
+    #[tokio::test]
+    async fn test_sql_dump_column_comments() {
+        let docs = [doc! { "_id": 1, "age": 30 }];
+        let (schema, field_infos) =
+            crate::schema::SchemaInferrer::infer_schema_explained("users", &docs, &Default::default()).unwrap();
+        let sql = sql_dump_create_table(&schema, Some(&field_infos));
+        assert!(sql.contains("-- column: age (inferred INTEGER"), "{}", sql);
+
+        // The comments don't stop the dump from loading
+        let client = crate::libsql_client::LibSqlClient::new(Some(":memory:")).await.unwrap();
+        client.execute(&sql).await.unwrap();
+    }
+
+    #[test]
+    fn test_json_array_writer() {
+        for count in [0, 1, 3] {
+            let mut output = Vec::new();
+            let mut array = JsonArrayWriter::begin(&mut output).unwrap();
+            for i in 0..count {
+                array.write_element(&document_json(doc! { "_id": i, "name": "a,b" })).unwrap();
+            }
+            assert_eq!(array.finish().unwrap(), count as usize);
+
+            let parsed: serde_json::Value = serde_json::from_slice(&output).unwrap();
+            let elements = parsed.as_array().unwrap();
+            assert_eq!(elements.len(), count as usize);
+            for (i, element) in elements.iter().enumerate() {
+                assert_eq!(element["_id"], i as i64);
+                assert_eq!(element["name"], "a,b");
+            }
+        }
+
+        let mut output = Vec::new();
+        JsonArrayWriter::begin(&mut output).unwrap().finish().unwrap();
+        assert_eq!(output, b"[]\n");
+    }
+}
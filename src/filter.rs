@@ -0,0 +1,261 @@
+use anyhow::{bail, Result};
+use libsql::Value as SqlValue;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::schema::Field;
+
+/// Comparison operator of a post-filter predicate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    /// Operators in the order they are matched, longest first
+    const TOKENS: [(&'static str, CompareOp); 7] = [
+        ("<=", CompareOp::Le),
+        (">=", CompareOp::Ge),
+        ("!=", CompareOp::Ne),
+        ("<>", CompareOp::Ne),
+        ("=", CompareOp::Eq),
+        ("<", CompareOp::Lt),
+        (">", CompareOp::Gt),
+    ];
+
+    /// Check whether an ordering satisfies this operator
+    fn holds(&self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Eq => ordering == Ordering::Equal,
+            CompareOp::Ne => ordering != Ordering::Equal,
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// Literal value on the right-hand side of a post-filter predicate
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl FromStr for Literal {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("null") {
+            return Ok(Literal::Null);
+        }
+
+        if let Some(text) = s.strip_prefix('\'').and_then(|rest| rest.strip_suffix('\'')) {
+            return Ok(Literal::Text(text.replace("''", "'")));
+        }
+
+        if let Ok(i) = s.parse::<i64>() {
+            return Ok(Literal::Integer(i));
+        }
+
+        if let Ok(r) = s.parse::<f64>() {
+            return Ok(Literal::Real(r));
+        }
+
+        bail!(
+            "Invalid literal '{}': expected a number, a 'quoted string' or NULL",
+            s
+        )
+    }
+}
+
+/// A single-column predicate evaluated on converted rows before insert
+///
+/// Grammar: `<column> <op> <literal>`, where `<op>` is one of `=`, `!=`,
+/// `<>`, `<`, `<=`, `>`, `>=` and `<literal>` is an integer, a real number,
+/// a single-quoted string (`''` escapes a quote) or `NULL`.
+///
+/// Comparisons follow SQL semantics: a NULL column value never matches,
+/// except for `= NULL` / `!= NULL`, which test for NULL / NOT NULL. Numbers
+/// compare numerically and text compares byte-wise; comparing a number with
+/// text never matches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostFilter {
+    pub column: String,
+    pub op: CompareOp,
+    pub value: Literal,
+}
+
+impl FromStr for PostFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some(op_start) = s.find(['<', '>', '=', '!']) else {
+            bail!("Invalid condition '{}': expected <column> <op> <literal>", s);
+        };
+
+        let (column, rest) = s.split_at(op_start);
+        let column = column.trim();
+        if column.is_empty() {
+            bail!("Invalid condition '{}': column name is empty", s);
+        }
+
+        let Some((token, op)) = CompareOp::TOKENS
+            .iter()
+            .find(|(token, _)| rest.starts_with(token))
+        else {
+            bail!("Invalid condition '{}': unknown operator", s);
+        };
+
+        let value: Literal = rest[token.len()..].trim().parse()?;
+        if value == Literal::Null && !matches!(op, CompareOp::Eq | CompareOp::Ne) {
+            bail!("Invalid condition '{}': NULL can only be compared with = or !=", s);
+        }
+
+        Ok(Self {
+            column: column.to_string(),
+            op: *op,
+            value,
+        })
+    }
+}
+
+impl PostFilter {
+    /// Find the position of this filter's column in a row
+    ///
+    /// # Arguments
+    /// * `fields` - Ordered target fields of the table
+    ///
+    /// # Returns
+    /// Index of the column, or `None` if the table has no such column
+    pub fn column_index(&self, fields: &[Field]) -> Option<usize> {
+        fields.iter().position(|field| field.name == self.column)
+    }
+
+    /// Evaluate the predicate against a converted column value
+    ///
+    /// # Arguments
+    /// * `value` - The SQL value of this filter's column
+    ///
+    /// # Returns
+    /// True if the row should be kept
+    pub fn matches(&self, value: &SqlValue) -> bool {
+        let ordering = match (value, &self.value) {
+            (SqlValue::Null, Literal::Null) => Some(Ordering::Equal),
+            (_, Literal::Null) => Some(Ordering::Greater),
+            (SqlValue::Null, _) => return false,
+            (SqlValue::Integer(a), Literal::Integer(b)) => Some(a.cmp(b)),
+            (SqlValue::Integer(a), Literal::Real(b)) => (*a as f64).partial_cmp(b),
+            (SqlValue::Real(a), Literal::Integer(b)) => a.partial_cmp(&(*b as f64)),
+            (SqlValue::Real(a), Literal::Real(b)) => a.partial_cmp(b),
+            (SqlValue::Text(a), Literal::Text(b)) => Some(a.as_str().cmp(b.as_str())),
+            _ => None,
+        };
+
+        ordering.is_some_and(|ordering| self.op.holds(ordering))
+    }
+}
+
+/// Parse `--post-filter <collection>=<condition>` specifications
+///
+/// # Arguments
+/// * `specs` - Post-filter specifications as given on the command line
+///
+/// # Returns
+/// Post-filters keyed by collection name
+pub fn parse_post_filter_specs(specs: &[String]) -> Result<HashMap<String, PostFilter>> {
+    let mut filters = HashMap::new();
+
+    for spec in specs {
+        let Some((collection, condition)) = spec.split_once('=') else {
+            bail!("Invalid post-filter '{}': expected <collection>=<condition>", spec);
+        };
+
+        let collection = collection.trim();
+        if collection.is_empty() {
+            bail!("Invalid post-filter '{}': collection name is empty", spec);
+        }
+
+        if filters
+            .insert(collection.to_string(), condition.parse()?)
+            .is_some()
+        {
+            bail!("Only one --post-filter is supported per collection ('{}')", collection);
+        }
+    }
+
+    Ok(filters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(condition: &str) -> PostFilter {
+        condition.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_post_filter() {
+        assert_eq!(
+            filter("age >= 18"),
+            PostFilter {
+                column: "age".to_string(),
+                op: CompareOp::Ge,
+                value: Literal::Integer(18),
+            }
+        );
+        assert_eq!(filter("score<2.5").value, Literal::Real(2.5));
+        assert_eq!(filter("status <> 'it''s'").value, Literal::Text("it's".to_string()));
+        assert_eq!(filter("email != NULL").op, CompareOp::Ne);
+    }
+
+    #[test]
+    fn test_parse_post_filter_invalid() {
+        assert!("age".parse::<PostFilter>().is_err());
+        assert!(">= 18".parse::<PostFilter>().is_err());
+        assert!("age >= adult".parse::<PostFilter>().is_err());
+        assert!("age > NULL".parse::<PostFilter>().is_err());
+    }
+
+    #[test]
+    fn test_post_filter_matches() {
+        let adult = filter("age >= 18");
+        assert!(adult.matches(&SqlValue::Integer(18)));
+        assert!(adult.matches(&SqlValue::Real(18.5)));
+        assert!(!adult.matches(&SqlValue::Integer(17)));
+        assert!(!adult.matches(&SqlValue::Null));
+        assert!(!adult.matches(&SqlValue::Text("20".to_string())));
+
+        let active = filter("status = 'active'");
+        assert!(active.matches(&SqlValue::Text("active".to_string())));
+        assert!(!active.matches(&SqlValue::Text("inactive".to_string())));
+
+        let has_email = filter("email != NULL");
+        assert!(has_email.matches(&SqlValue::Text("a@example.com".to_string())));
+        assert!(!has_email.matches(&SqlValue::Null));
+        assert!(filter("email = NULL").matches(&SqlValue::Null));
+    }
+
+    #[test]
+    fn test_parse_post_filter_specs() {
+        let filters = parse_post_filter_specs(&["users=age >= 18".to_string()]).unwrap();
+        assert_eq!(filters["users"], filter("age >= 18"));
+
+        assert!(parse_post_filter_specs(&["age >= 18".to_string()]).is_err());
+        assert!(parse_post_filter_specs(&[
+            "users=age >= 18".to_string(),
+            "users=age < 65".to_string(),
+        ])
+        .is_err());
+    }
+}
@@ -0,0 +1,274 @@
+use anyhow::{bail, Result};
+use bson::{Bson, Document};
+use futures::stream::{Stream, TryStreamExt};
+use libsql::Value as SqlValue;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::Path;
+use tracing::{debug, info};
+
+use crate::converter::{bson_to_sql_value, escape_identifier, QuoteStyle};
+use crate::mongodb_client::MongoClient;
+use crate::sqlite_backend::SqliteBackend;
+
+/// Name of the SQLite table holding the files of a GridFS bucket
+///
+/// # Arguments
+/// * `bucket` - GridFS bucket name (e.g. `fs`)
+///
+/// # Returns
+/// The table name, `<bucket>_files`
+pub fn gridfs_table_name(bucket: &str) -> String {
+    format!("{}_files", bucket)
+}
+
+/// Write a GridFS file's contents from its chunk documents
+///
+/// Chunks are written as they arrive, so a file is never held in memory
+/// twice. They must arrive sorted by their `n` index, form the contiguous
+/// sequence 0, 1, 2, ... and have a combined size equal to the file's
+/// `length`.
+///
+/// # Arguments
+/// * `file_id` - Display form of the file's `_id`, used in error messages
+/// * `chunks` - The file's documents from `<bucket>.chunks`, in `n` order
+/// * `length` - The file's length in bytes, from `<bucket>.files`
+/// * `writer` - Destination of the file contents
+pub async fn write_chunks<S, W>(file_id: &str, mut chunks: S, length: u64, writer: &mut W) -> Result<()>
+where
+    S: Stream<Item = Result<Document>> + Unpin,
+    W: Write,
+{
+    let mut expected = 0i64;
+    let mut written = 0u64;
+
+    while let Some(chunk) = chunks.try_next().await? {
+        let n = match chunk.get("n") {
+            Some(Bson::Int32(n)) => *n as i64,
+            Some(Bson::Int64(n)) => *n,
+            _ => bail!("GridFS file {}: chunk without a numeric 'n' index", file_id),
+        };
+        if n != expected {
+            bail!(
+                "GridFS file {}: expected chunk {} but found chunk {}",
+                file_id,
+                expected,
+                n
+            );
+        }
+        let data = match chunk.get("data") {
+            Some(Bson::Binary(binary)) => &binary.bytes,
+            _ => bail!("GridFS file {}: chunk {} has no binary 'data'", file_id, n),
+        };
+
+        writer.write_all(data)?;
+        written += data.len() as u64;
+        expected += 1;
+    }
+
+    if written != length {
+        bail!(
+            "GridFS file {}: chunks hold {} bytes but the file length is {}",
+            file_id,
+            written,
+            length
+        );
+    }
+
+    Ok(())
+}
+
+/// Turn a file `_id` into a safe file name for externalized contents
+///
+/// Ids made only of `[A-Za-z0-9_-]` (e.g. ObjectIds) are used as is. Other
+/// ids have their unsafe characters replaced and a `.<hash>` suffix of the
+/// raw id appended, so ids such as `a.b` and `a/b` get distinct names; the
+/// `.` never appears in an unchanged id.
+fn external_file_name(file_id: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if file_id.chars().all(is_safe) {
+        return file_id.to_string();
+    }
+
+    let sanitized: String = file_id.chars().map(|c| if is_safe(c) { c } else { '_' }).collect();
+    let hash = hex::encode(Sha256::digest(file_id.as_bytes()));
+    format!("{}.{}", sanitized, &hash[..8])
+}
+
+/// Read a numeric BSON value as u64, whichever numeric type the driver used
+fn bson_to_u64(value: &Bson) -> Option<u64> {
+    match value {
+        Bson::Int32(v) => u64::try_from(*v).ok(),
+        Bson::Int64(v) => u64::try_from(*v).ok(),
+        Bson::Double(v) if *v >= 0.0 => Some(*v as u64),
+        _ => None,
+    }
+}
+
+/// Migrate every file of a GridFS bucket into a `<bucket>_files` table
+///
+/// The table has the columns `(id, filename, length, content_type, data, path)`.
+/// File contents go into the `data` BLOB, or, with `externalize_dir`, are
+/// written to `<externalize_dir>/<id>` and referenced by `path` instead.
+///
+/// # Arguments
+/// * `mongo_client` - Connected MongoDB client
+/// * `backend` - Target SQLite/LibSQL database
+/// * `database_name` - Name of the MongoDB database
+/// * `bucket` - GridFS bucket name (e.g. `fs`)
+/// * `externalize_dir` - Optional directory to write file contents to
+/// * `drop_table` - Whether to drop an existing files table first
+/// * `quote_style` - How to quote identifiers in generated SQL
+///
+/// # Returns
+/// Number of files migrated
+pub async fn migrate_bucket(
+    mongo_client: &MongoClient,
+    backend: &dyn SqliteBackend,
+    database_name: &str,
+    bucket: &str,
+    externalize_dir: Option<&Path>,
+    drop_table: bool,
+    quote_style: QuoteStyle,
+) -> Result<usize> {
+    let table = escape_identifier(&gridfs_table_name(bucket), quote_style);
+    info!("Migrating GridFS bucket {} into {}", bucket, table);
+
+    if drop_table {
+        backend
+            .execute(&format!("DROP TABLE IF EXISTS {}", table))
+            .await?;
+    }
+
+    backend
+        .execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, filename TEXT, length INTEGER, content_type TEXT, data BLOB, path TEXT)",
+            table
+        ))
+        .await?;
+
+    if let Some(dir) = externalize_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let insert_sql = format!(
+        "INSERT INTO {} (id, filename, length, content_type, data, path) VALUES (?, ?, ?, ?, ?, ?)",
+        table
+    );
+
+    let mut files = mongo_client.gridfs_files(database_name, bucket).await?;
+    let mut migrated = 0;
+
+    while let Some(file) = files.try_next().await? {
+        let Some(file_id) = file.get("_id") else {
+            bail!("GridFS bucket {}: file document without _id", bucket);
+        };
+        let id = bson_to_sql_value(file_id);
+        let id_text = match &id {
+            SqlValue::Text(text) => text.clone(),
+            SqlValue::Integer(i) => i.to_string(),
+            other => format!("{:?}", other),
+        };
+
+        let length = file.get("length").and_then(bson_to_u64).unwrap_or(0);
+        let chunks = mongo_client
+            .gridfs_chunks(database_name, bucket, file_id)
+            .await?
+            .map_err(anyhow::Error::from);
+
+        let (data, path) = match externalize_dir {
+            Some(dir) => {
+                let path = dir.join(external_file_name(&id_text));
+                let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+                write_chunks(&id_text, chunks, length, &mut file).await?;
+                file.flush()?;
+                (SqlValue::Null, SqlValue::Text(path.display().to_string()))
+            }
+            None => {
+                let mut bytes = Vec::with_capacity(length as usize);
+                write_chunks(&id_text, chunks, length, &mut bytes).await?;
+                (SqlValue::Blob(bytes), SqlValue::Null)
+            }
+        };
+
+        let text_or_null = |key: &str| match file.get_str(key) {
+            Ok(value) => SqlValue::Text(value.to_string()),
+            Err(_) => SqlValue::Null,
+        };
+
+        backend
+            .execute_with_params(
+                &insert_sql,
+                vec![
+                    id,
+                    text_or_null("filename"),
+                    SqlValue::Integer(length as i64),
+                    text_or_null("contentType"),
+                    data,
+                    path,
+                ],
+            )
+            .await?;
+
+        debug!("Migrated GridFS file {} ({} bytes)", id_text, length);
+        migrated += 1;
+    }
+
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bson::{doc, spec::BinarySubtype, Binary};
+    use futures::stream;
+
+    fn chunk(n: i32, bytes: &[u8]) -> Document {
+        doc! {
+            "n": n,
+            "data": Binary { subtype: BinarySubtype::Generic, bytes: bytes.to_vec() },
+        }
+    }
+
+    /// Write chunks into a buffer, returning its contents
+    async fn write_to_vec(chunks: Vec<Document>, length: u64) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        write_chunks("f1", stream::iter(chunks.into_iter().map(Ok)), length, &mut bytes).await?;
+        Ok(bytes)
+    }
+
+    #[tokio::test]
+    async fn test_write_chunks() {
+        let chunks = vec![chunk(0, b"abc"), chunk(1, b"def"), chunk(2, b"ghi")];
+        assert_eq!(write_to_vec(chunks, 9).await.unwrap(), b"abcdefghi");
+    }
+
+    #[tokio::test]
+    async fn test_write_chunks_missing_or_unsorted_chunk() {
+        let chunks = vec![chunk(0, b"abc"), chunk(2, b"ghi")];
+        assert!(write_to_vec(chunks, 6).await.is_err());
+
+        let chunks = vec![chunk(1, b"def"), chunk(0, b"abc")];
+        assert!(write_to_vec(chunks, 6).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_chunks_length_mismatch() {
+        let chunks = vec![chunk(0, b"abc"), chunk(1, b"de")];
+        assert!(write_to_vec(chunks, 6).await.is_err());
+        assert_eq!(write_to_vec(Vec::new(), 0).await.unwrap(), b"");
+    }
+
+    #[test]
+    fn test_external_file_name() {
+        assert_eq!(external_file_name("65a1b2c3d4e5f60718293a4b"), "65a1b2c3d4e5f60718293a4b");
+
+        let escaped = external_file_name("../etc/passwd");
+        assert!(escaped.starts_with("___etc_passwd."));
+        assert!(!escaped.contains('/'));
+
+        // Ids differing only in unsafe characters must not collide
+        assert_ne!(external_file_name("a.b"), external_file_name("a/b"));
+        assert_ne!(external_file_name("a.b"), external_file_name("a_b"));
+    }
+}
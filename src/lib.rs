@@ -0,0 +1,22 @@
+//! Library side of the `mongo-to-sqlite` CLI
+//!
+//! The binary in `main.rs` wires these modules together; they are exposed as
+//! a library so benchmarks under `benches/` can exercise the conversion and
+//! schema inference code directly.
+
+pub mod cli;
+pub mod computed;
+pub mod converter;
+pub mod error;
+pub mod export;
+pub mod filter;
+pub mod gridfs;
+pub mod libsql_client;
+pub mod migration;
+pub mod mongodb_client;
+pub mod naming;
+pub mod parquet_sink;
+pub mod redact;
+pub mod schema;
+pub mod source;
+pub mod sqlite_backend;
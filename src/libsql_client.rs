@@ -1,7 +1,95 @@
 use anyhow::Result;
 use libsql::{Builder, Connection, Database};
 use std::env;
-use tracing::{debug, info};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, MutexGuard};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::error::{classify_libsql_error, classify_turso_error};
+use crate::redact::redact_uri;
+
+/// PRAGMAs trading durability for bulk load speed, see `enable_fast_load`
+///
+/// `(name, value)` pairs. A 256 MiB page cache (negative sizes are KiB)
+/// keeps index pages of large tables in memory.
+pub const FAST_LOAD_PRAGMAS: [(&str, &str); 3] = [
+    ("synchronous", "OFF"),
+    ("journal_mode", "MEMORY"),
+    ("cache_size", "-262144"),
+];
+
+/// PRAGMA values read by `enable_fast_load`, restored by `finish_fast_load`
+///
+/// Empty when nothing was changed, e.g. for a remote database.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SavedPragmas(Vec<(String, String)>);
+
+impl SavedPragmas {
+    /// Record the value a PRAGMA had before the fast load
+    pub fn push(&mut self, name: &str, value: String) {
+        self.0.push((name.to_string(), value));
+    }
+
+    /// Statements setting every saved PRAGMA back to its value
+    pub fn restore_statements(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|(name, value)| format!("PRAGMA {} = {}", name, value))
+            .collect()
+    }
+}
+
+/// Format the value a `PRAGMA name` query returned so it can be set again
+///
+/// # Arguments
+/// * `name` - Name of the PRAGMA, for the error message
+/// * `value` - First column of the query's row
+///
+/// # Returns
+/// The value as it is written after `PRAGMA name =`
+pub fn pragma_setting(name: &str, value: &libsql::Value) -> Result<String> {
+    match value {
+        libsql::Value::Integer(i) => Ok(i.to_string()),
+        libsql::Value::Text(s) => Ok(s.clone()),
+        other => anyhow::bail!("Unexpected value for PRAGMA {}: {:?}", name, other),
+    }
+}
+
+/// Check whether TURSO_DATABASE_URL and TURSO_AUTH_TOKEN select a remote database
+pub fn remote_configured() -> bool {
+    env::var("TURSO_DATABASE_URL").is_ok() && env::var("TURSO_AUTH_TOKEN").is_ok()
+}
+
+/// Delete a local database file and its journal files, for `--overwrite`
+///
+/// The `-wal`, `-shm` and `-journal` files next to the database are removed
+/// too, so SQLite can't replay them into the new file.
+///
+/// # Arguments
+/// * `path` - Path of the SQLite file
+///
+/// # Returns
+/// Whether the database file existed
+pub fn remove_database_file(path: &std::path::Path) -> Result<bool> {
+    let existed = path.exists();
+    if existed {
+        std::fs::remove_file(path)?;
+    }
+
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let mut companion = path.as_os_str().to_owned();
+        companion.push(suffix);
+        let companion = std::path::PathBuf::from(companion);
+        if companion.exists() {
+            std::fs::remove_file(&companion)?;
+        }
+    }
+
+    Ok(existed)
+}
 
 /// LibSQL client wrapper supporting both local and remote (Turso) databases
 pub struct LibSqlClient {
@@ -26,6 +114,9 @@ impl LibSqlClient {
     /// If TURSO_DATABASE_URL and TURSO_AUTH_TOKEN environment variables are set,
     /// connects to Turso cloud. Otherwise, creates/connects to a local SQLite file.
     ///
+    /// Remote connections are checked with a trivial query, so a rejected
+    /// token or a missing database fails here with a configuration error.
+    ///
     /// # Arguments
     /// * `output_path` - Optional path for local SQLite file (ignored if using Turso)
     ///
@@ -37,10 +128,11 @@ impl LibSqlClient {
 
         let (database, mode) = match (turso_url, turso_token) {
             (Some(url), Some(token)) => {
-                info!("Connecting to Turso cloud database: {}", url);
+                info!("Connecting to Turso cloud database: {}", redact_uri(&url));
                 let db = Builder::new_remote(url.clone(), token)
                     .build()
-                    .await?;
+                    .await
+                    .map_err(classify_turso_error)?;
                 (db, ConnectionMode::Remote { url })
             }
             _ => {
@@ -62,7 +154,15 @@ impl LibSqlClient {
         };
 
         let connection = database.connect()?;
-        
+
+        // Remote databases are only contacted on the first statement
+        if let ConnectionMode::Remote { .. } = mode {
+            connection
+                .query("SELECT 1", ())
+                .await
+                .map_err(classify_turso_error)?;
+        }
+
         debug!("Successfully connected to LibSQL database");
 
         Ok(Self {
@@ -81,7 +181,11 @@ impl LibSqlClient {
     /// Number of rows affected
     pub async fn execute(&self, sql: &str) -> Result<u64> {
         debug!("Executing SQL: {}", sql);
-        let result = self.connection.execute(sql, ()).await?;
+        let result = self
+            .connection
+            .execute(sql, ())
+            .await
+            .map_err(classify_libsql_error)?;
         Ok(result)
     }
 
@@ -97,10 +201,58 @@ impl LibSqlClient {
     where
         P: libsql::params::IntoParams,
     {
-        let result = self.connection.execute(sql, params).await?;
+        let result = self
+            .connection
+            .execute(sql, params)
+            .await
+            .map_err(classify_libsql_error)?;
         Ok(result)
     }
 
+    /// Prepare a statement for repeated execution
+    ///
+    /// # Arguments
+    /// * `sql` - SQL statement to prepare (with ? placeholders)
+    ///
+    /// # Returns
+    /// The prepared statement
+    pub async fn prepare(&self, sql: &str) -> Result<libsql::Statement> {
+        debug!("Preparing SQL: {}", sql);
+        let statement = self
+            .connection
+            .prepare(sql)
+            .await
+            .map_err(classify_libsql_error)?;
+        Ok(statement)
+    }
+
+    /// Execute one statement once per parameter set, preparing it only once
+    ///
+    /// The statement is parsed a single time and reset between rows, which
+    /// saves re-preparing the SQL for every row of a batch.
+    ///
+    /// # Arguments
+    /// * `sql` - SQL statement to execute (with ? placeholders)
+    /// * `rows` - Parameters for each execution
+    ///
+    /// # Returns
+    /// Total number of rows affected
+    pub async fn execute_prepared(&self, sql: &str, rows: &[Vec<libsql::Value>]) -> Result<u64> {
+        let statement = self.prepare(sql).await?;
+
+        let mut total_affected = 0u64;
+        for values in rows {
+            let affected = statement
+                .execute(libsql::params_from_iter(values.iter().cloned()))
+                .await
+                .map_err(classify_libsql_error)?;
+            statement.reset();
+            total_affected += affected as u64;
+        }
+
+        Ok(total_affected)
+    }
+
     /// Execute a batch of SQL statements in a transaction
     ///
     /// # Arguments
@@ -108,7 +260,6 @@ impl LibSqlClient {
     ///
     /// # Returns
     /// Total number of rows affected
-    #[allow(dead_code)]
     pub async fn execute_batch(&self, statements: Vec<String>) -> Result<u64> {
         debug!("Executing batch of {} statements", statements.len());
         
@@ -121,7 +272,7 @@ impl LibSqlClient {
                 Ok(affected)
             }
             Err(e) => {
-                self.connection.execute("ROLLBACK", ()).await?;
+                self.rollback().await;
                 Err(e)
             }
         }
@@ -132,7 +283,7 @@ impl LibSqlClient {
         let mut total_affected = 0u64;
         
         for stmt in statements {
-            let affected = self.connection.execute(stmt.as_str(), ()).await?;
+            let affected = self.execute(stmt).await?;
             total_affected += affected;
         }
         
@@ -171,7 +322,7 @@ impl LibSqlClient {
                 Ok(count)
             }
             Err(e) => {
-                self.connection.execute("ROLLBACK", ()).await?;
+                self.rollback().await;
                 Err(e)
             }
         }
@@ -189,13 +340,24 @@ impl LibSqlClient {
         let mut count = 0u64;
         
         for params in param_sets {
-            self.connection.execute(sql, params).await?;
+            self.execute_with_params(sql, params).await?;
             count += 1;
         }
         
         Ok(count)
     }
 
+    /// Roll back the open transaction after a failed statement
+    ///
+    /// SQLite may already have rolled back on its own (e.g. after
+    /// `SQLITE_FULL`), so a failing ROLLBACK is only logged and the original
+    /// error is kept.
+    pub async fn rollback(&self) {
+        if let Err(e) = self.connection.execute("ROLLBACK", ()).await {
+            warn!("ROLLBACK failed, the transaction may already be rolled back: {}", e);
+        }
+    }
+
     /// Query for data (returns rows)
     ///
     /// # Arguments
@@ -210,6 +372,139 @@ impl LibSqlClient {
         Ok(rows)
     }
 
+    /// Query for data with parameters
+    ///
+    /// # Arguments
+    /// * `sql` - SQL query to execute (with ? placeholders)
+    /// * `params` - Parameters to bind to the query
+    ///
+    /// # Returns
+    /// Rows result set
+    pub async fn query_with_params<P>(&self, sql: &str, params: P) -> Result<libsql::Rows>
+    where
+        P: libsql::params::IntoParams,
+    {
+        debug!("Querying: {}", sql);
+        let rows = self
+            .connection
+            .query(sql, params)
+            .await
+            .map_err(classify_libsql_error)?;
+        Ok(rows)
+    }
+
+    /// Start a background task that keeps the connection warm
+    ///
+    /// The task runs `SELECT 1` every `interval` until the returned
+    /// [`Heartbeat`] is stopped or dropped.
+    ///
+    /// # Arguments
+    /// * `interval` - Time between heartbeat queries
+    ///
+    /// # Returns
+    /// A handle that stops the heartbeat
+    pub fn start_heartbeat(&self, interval: Duration) -> Heartbeat {
+        Heartbeat::start(self.connection.clone(), interval)
+    }
+
+    /// List the column names of a table
+    ///
+    /// # Arguments
+    /// * `table` - Name of the table (unquoted)
+    ///
+    /// # Returns
+    /// Column names in table order, or an empty list if the table doesn't exist
+    pub async fn table_columns(&self, table: &str) -> Result<Vec<String>> {
+        let columns = self.table_column_types(table).await?;
+        Ok(columns.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// List the column names of a table with their declared types
+    ///
+    /// # Arguments
+    /// * `table` - Name of the table (unquoted)
+    ///
+    /// # Returns
+    /// `(name, declared type)` pairs in table order, or an empty list if the
+    /// table doesn't exist
+    pub async fn table_column_types(&self, table: &str) -> Result<Vec<(String, String)>> {
+        let sql = format!("PRAGMA table_info(\"{}\")", table.replace('"', "\"\""));
+        let mut rows = self.query(&sql).await?;
+
+        let mut columns = Vec::new();
+        while let Some(row) = rows.next().await? {
+            columns.push((row.get::<String>(1)?, row.get::<String>(2)?));
+        }
+
+        Ok(columns)
+    }
+
+    /// Run `PRAGMA integrity_check` on the database
+    ///
+    /// # Returns
+    /// The problems reported, empty if the database is intact
+    pub async fn integrity_check(&self) -> Result<Vec<String>> {
+        let mut rows = self.query("PRAGMA integrity_check").await?;
+
+        let mut results = Vec::new();
+        while let Some(row) = rows.next().await? {
+            results.push(row.get::<String>(0)?);
+        }
+
+        Ok(integrity_problems(results))
+    }
+
+    /// Speed up a bulk load into a local file at the cost of durability
+    ///
+    /// Applies [`FAST_LOAD_PRAGMAS`]: no fsync and an in-memory rollback
+    /// journal, so a crash or power loss during the load can corrupt the
+    /// file. Call `finish_fast_load` once the load is done. Remote databases
+    /// manage their own durability and are left alone.
+    ///
+    /// # Returns
+    /// The values the PRAGMAs had before, for `finish_fast_load`
+    pub async fn enable_fast_load(&self) -> Result<SavedPragmas> {
+        let mut saved = SavedPragmas::default();
+        if !self.is_local() {
+            warn!("Ignoring --fast-load: only local database files can be tuned");
+            return Ok(saved);
+        }
+
+        warn!("Fast load enabled: a crash during the load can corrupt the output file");
+        for (name, value) in FAST_LOAD_PRAGMAS {
+            // The rows are dropped first; an open statement blocks leaving WAL mode
+            let current = match self.query(&format!("PRAGMA {}", name)).await?.next().await? {
+                Some(row) => Some(row.get_value(0)?),
+                None => None,
+            };
+            if let Some(current) = current {
+                saved.push(name, pragma_setting(name, &current)?);
+            }
+            self.run_pragma(&format!("PRAGMA {} = {}", name, value)).await?;
+        }
+        Ok(saved)
+    }
+
+    /// Restore the settings `enable_fast_load` changed
+    ///
+    /// Switching the journal back flushes the loaded data to disk.
+    ///
+    /// # Arguments
+    /// * `saved` - The values returned by `enable_fast_load`
+    pub async fn finish_fast_load(&self, saved: &SavedPragmas) -> Result<()> {
+        for statement in saved.restore_statements() {
+            self.run_pragma(&statement).await?;
+        }
+        Ok(())
+    }
+
+    /// Run a PRAGMA statement, which may report its new value as a row
+    async fn run_pragma(&self, pragma: &str) -> Result<()> {
+        let mut rows = self.query(pragma).await?;
+        while rows.next().await?.is_some() {}
+        Ok(())
+    }
+
     /// Get the connection mode (local or remote)
     ///
     /// # Returns
@@ -218,7 +513,7 @@ impl LibSqlClient {
     pub fn connection_info(&self) -> String {
         match &self.mode {
             ConnectionMode::Local(path) => format!("Local file: {}", path),
-            ConnectionMode::Remote { url } => format!("Turso cloud: {}", url),
+            ConnectionMode::Remote { url } => format!("Turso cloud: {}", redact_uri(url)),
         }
     }
 
@@ -226,7 +521,6 @@ impl LibSqlClient {
     ///
     /// # Returns
     /// True if using local SQLite file, false if using Turso
-    #[allow(dead_code)]
     pub fn is_local(&self) -> bool {
         matches!(self.mode, ConnectionMode::Local(_))
     }
@@ -244,11 +538,240 @@ impl LibSqlClient {
     }
 }
 
+/// Background `SELECT 1` loop keeping an idle connection from being dropped
+///
+/// Remote (Turso) connections can be closed while a long MongoDB read keeps
+/// the target idle. The heartbeat is aborted when stopped or dropped.
+pub struct Heartbeat {
+    handle: JoinHandle<()>,
+    beats: Arc<AtomicU64>,
+    /// Held by the heartbeat while it queries and by writers via `pause`
+    connection_lock: Arc<Mutex<()>>,
+}
+
+impl Heartbeat {
+    /// Spawn the heartbeat task
+    ///
+    /// # Arguments
+    /// * `connection` - Connection to keep alive
+    /// * `interval` - Time between heartbeat queries
+    ///
+    /// # Returns
+    /// A handle to the running heartbeat
+    fn start(connection: Connection, interval: Duration) -> Self {
+        let beats = Arc::new(AtomicU64::new(0));
+        let counter = Arc::clone(&beats);
+        let connection_lock = Arc::new(Mutex::new(()));
+        let lock = Arc::clone(&connection_lock);
+
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately; the connection is warm already
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                let _paused = lock.lock().await;
+                match connection.query("SELECT 1", ()).await {
+                    Ok(_) => {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                        debug!("Heartbeat sent to LibSQL connection");
+                    }
+                    Err(e) => warn!("LibSQL heartbeat failed: {}", e),
+                }
+            }
+        });
+
+        Self {
+            handle,
+            beats,
+            connection_lock,
+        }
+    }
+
+    /// Hold off heartbeat queries until the returned guard is dropped
+    ///
+    /// The heartbeat shares the writer's connection, so writers pause it
+    /// while a transaction is open; a `SELECT 1` must not run inside it.
+    ///
+    /// # Returns
+    /// A guard that lets the heartbeat resume when dropped
+    pub async fn pause(&self) -> MutexGuard<'_, ()> {
+        self.connection_lock.lock().await
+    }
+
+    /// Check whether the heartbeat task is still running
+    pub fn is_running(&self) -> bool {
+        !self.handle.is_finished()
+    }
+
+    /// Number of successful heartbeat queries so far
+    pub fn beats(&self) -> u64 {
+        self.beats.load(Ordering::Relaxed)
+    }
+
+    /// Stop the heartbeat and wait for the task to end
+    ///
+    /// # Returns
+    /// Number of successful heartbeat queries sent
+    pub async fn stop(mut self) -> u64 {
+        self.handle.abort();
+        // Aborting makes the task end with a cancellation error, which is expected
+        let _ = (&mut self.handle).await;
+        self.beats()
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Interpret the rows returned by `PRAGMA integrity_check`
+///
+/// An intact database yields a single `ok` row; otherwise every row
+/// describes one problem.
+///
+/// # Arguments
+/// * `rows` - The text of each result row
+///
+/// # Returns
+/// The problems, empty if the result was `ok`
+pub fn integrity_problems(rows: Vec<String>) -> Vec<String> {
+    match rows.as_slice() {
+        [ok] if ok == "ok" => Vec::new(),
+        [] => vec!["PRAGMA integrity_check returned no result".to_string()],
+        _ => rows,
+    }
+}
+
+/// Split a SQL script into statements on `;`
+///
+/// Semicolons inside quoted strings and identifiers (`'...'`, `"..."`,
+/// `` `...` ``, `[...]`) and comments (`-- ...`, `/* ... */`) don't end a
+/// statement. Statements holding only whitespace and comments are dropped.
+/// Trigger bodies (`BEGIN ...; END`) are not recognized and would be split.
+///
+/// # Arguments
+/// * `script` - The SQL script
+///
+/// # Returns
+/// The statements without their trailing `;`, in script order
+pub fn split_sql_statements(script: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut has_sql = false;
+    let mut chars = script.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' | '[' => {
+                // A doubled quote closes and reopens the string, which is equivalent
+                let close = if c == '[' { ']' } else { c };
+                current.push(c);
+                has_sql = true;
+                for quoted in chars.by_ref() {
+                    current.push(quoted);
+                    if quoted == close {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                current.push(c);
+                for commented in chars.by_ref() {
+                    current.push(commented);
+                    if commented == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                current.push(c);
+                current.extend(chars.next());
+                let mut previous = ' ';
+                for commented in chars.by_ref() {
+                    current.push(commented);
+                    if previous == '*' && commented == '/' {
+                        break;
+                    }
+                    previous = commented;
+                }
+            }
+            ';' => {
+                if has_sql {
+                    statements.push(current.trim().to_string());
+                }
+                current.clear();
+                has_sql = false;
+            }
+            c => {
+                has_sql |= !c.is_whitespace();
+                current.push(c);
+            }
+        }
+    }
+
+    if has_sql {
+        statements.push(current.trim().to_string());
+    }
+    statements
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::NamedTempFile;
 
+    #[test]
+    fn test_split_sql_statements() {
+        let script = "-- indexes\n\
+            CREATE INDEX idx_users_name ON users(name);\n\
+            INSERT INTO notes VALUES ('a;b', 'it''s; fine');\n\
+            /* ; */ UPDATE t SET \"x;y\" = 1;\n\
+            ;\n\
+            -- no statement after this;\n";
+
+        assert_eq!(
+            split_sql_statements(script),
+            vec![
+                "-- indexes\nCREATE INDEX idx_users_name ON users(name)".to_string(),
+                "INSERT INTO notes VALUES ('a;b', 'it''s; fine')".to_string(),
+                "/* ; */ UPDATE t SET \"x;y\" = 1".to_string(),
+            ]
+        );
+        assert_eq!(split_sql_statements("ANALYZE"), vec!["ANALYZE".to_string()]);
+        assert!(split_sql_statements("  -- nothing\n").is_empty());
+    }
+
+    #[test]
+    fn test_integrity_problems() {
+        assert!(integrity_problems(vec!["ok".to_string()]).is_empty());
+
+        let problems = vec![
+            "*** in database main ***".to_string(),
+            "row 3 missing from index users_email".to_string(),
+        ];
+        assert_eq!(integrity_problems(problems.clone()), problems);
+        assert_eq!(integrity_problems(Vec::new()).len(), 1);
+    }
+
+    #[test]
+    fn test_remove_database_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output.db");
+        std::fs::write(&path, b"old").unwrap();
+        std::fs::write(dir.path().join("output.db-wal"), b"old").unwrap();
+
+        assert!(remove_database_file(&path).unwrap());
+        assert!(!path.exists());
+        assert!(!dir.path().join("output.db-wal").exists());
+
+        // Nothing to remove the second time
+        assert!(!remove_database_file(&path).unwrap());
+    }
+
     #[tokio::test]
     async fn test_create_local_database() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -296,5 +819,101 @@ mod tests {
         // Note: Can't easily test row contents without more complex assertions
         assert!(rows.column_count() > 0);
     }
-}
 
+    #[tokio::test]
+    async fn test_execute_prepared_inserts_rows() {
+        let client = LibSqlClient::new(Some(":memory:")).await.unwrap();
+        client
+            .execute("CREATE TABLE test (id INTEGER PRIMARY KEY, name TEXT)")
+            .await
+            .unwrap();
+
+        let rows: Vec<Vec<libsql::Value>> = (1..=3)
+            .map(|i| vec![libsql::Value::Integer(i), libsql::Value::Text(format!("user-{}", i))])
+            .collect();
+        let affected = client
+            .execute_prepared("INSERT INTO test (id, name) VALUES (?, ?)", &rows)
+            .await
+            .unwrap();
+        assert_eq!(affected, 3);
+
+        let mut result = client.query("SELECT id, name FROM test ORDER BY id").await.unwrap();
+        let mut names = Vec::new();
+        while let Some(row) = result.next().await.unwrap() {
+            names.push((row.get::<i64>(0).unwrap(), row.get::<String>(1).unwrap()));
+        }
+        assert_eq!(
+            names,
+            vec![
+                (1, "user-1".to_string()),
+                (2, "user-2".to_string()),
+                (3, "user-3".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fast_load_pragmas() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let client = LibSqlClient::new(temp_file.path().to_str()).await.unwrap();
+
+        async fn pragma(client: &LibSqlClient, name: &str) -> String {
+            let mut rows = client.query(&format!("PRAGMA {}", name)).await.unwrap();
+            let row = rows.next().await.unwrap().unwrap();
+            match row.get_value(0).unwrap() {
+                libsql::Value::Integer(i) => i.to_string(),
+                libsql::Value::Text(s) => s,
+                other => panic!("Unexpected PRAGMA value {:?}", other),
+            }
+        }
+
+        // Settings other than SQLite's defaults must come back unchanged
+        client.run_pragma("PRAGMA journal_mode = WAL").await.unwrap();
+        client.run_pragma("PRAGMA synchronous = NORMAL").await.unwrap();
+        client.run_pragma("PRAGMA cache_size = -4000").await.unwrap();
+
+        let saved = client.enable_fast_load().await.unwrap();
+        assert_eq!(pragma(&client, "synchronous").await, "0");
+        assert_eq!(pragma(&client, "journal_mode").await, "memory");
+        assert_eq!(pragma(&client, "cache_size").await, "-262144");
+
+        client.finish_fast_load(&saved).await.unwrap();
+        assert_eq!(pragma(&client, "synchronous").await, "1");
+        assert_eq!(pragma(&client, "journal_mode").await, "wal");
+        assert_eq!(pragma(&client, "cache_size").await, "-4000");
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_starts_and_stops() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let client = LibSqlClient::new(temp_file.path().to_str()).await.unwrap();
+
+        let heartbeat = client.start_heartbeat(Duration::from_millis(10));
+        assert!(heartbeat.is_running());
+
+        // Simulate a slow MongoDB stream while the heartbeat runs
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(heartbeat.is_running());
+
+        let beats = heartbeat.stop().await;
+        assert!(beats >= 1, "expected at least one heartbeat, got {}", beats);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_pause() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let client = LibSqlClient::new(temp_file.path().to_str()).await.unwrap();
+        let heartbeat = client.start_heartbeat(Duration::from_millis(10));
+
+        // No heartbeat runs while a writer holds the connection
+        let paused = heartbeat.pause().await;
+        let beats = heartbeat.beats();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(heartbeat.beats(), beats);
+
+        drop(paused);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(heartbeat.beats() > beats);
+        heartbeat.stop().await;
+    }
+}
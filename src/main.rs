@@ -1,15 +1,17 @@
-mod cli;
-mod converter;
-mod error;
-mod libsql_client;
-mod migration;
-mod mongodb_client;
-mod schema;
-
 use anyhow::Result;
-use cli::Args;
-use clap::Parser;
+use mongo_to_sqlite::{
+    computed, converter, export, filter, gridfs, libsql_client, migration, mongodb_client, parquet_sink,
+    schema, source, sqlite_backend,
+};
+use mongo_to_sqlite::cli::{
+    read_collection_list, read_post_sql, Args, CheckArgs, Cli, Command, ConnectionArgs, ExportArgs, InspectArgs,
+};
+use mongo_to_sqlite::error::{exit_code, exit_code_for_error, MigrationError};
 use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use futures::stream::TryStreamExt;
+use std::collections::{HashMap, HashSet};
+use std::io::{IsTerminal, Write};
 use tracing_subscriber::{fmt, EnvFilter};
 
 #[tokio::main]
@@ -17,49 +19,152 @@ async fn main() -> Result<()> {
     // Load .env file if it exists (ignore errors if not found)
     let _ = dotenvy::dotenv();
 
-    // Initialize logging
-    init_logging();
-
     // Parse command-line arguments
-    let args = Args::parse();
+    let cli = Cli::parse_with_default();
+
+    match cli.command {
+        Command::Migrate(args) => migrate_command(*args).await,
+        Command::Inspect(args) => {
+            init_logging(false);
+            exit_on_error("Inspect", run_inspect(args).await)
+        }
+        Command::Export(args) => {
+            // Logs must stay off stdout when it carries the export
+            init_logging(args.output.is_none());
+            exit_on_invalid(args.validate());
+            exit_on_error("Export", run_export(args).await)
+        }
+        Command::Check(args) => {
+            init_logging(false);
+            exit_on_error("Check", run_check(args).await)
+        }
+    }
+}
+
+/// Print a failure message and exit if a subcommand failed
+///
+/// The exit status tells the failure category apart, see `error::exit_code`.
+fn exit_on_error(command: &str, result: Result<()>) -> Result<()> {
+    if let Err(e) = result {
+        eprintln!("\n{}", format!("❌ {} failed!", command).red().bold());
+        eprintln!("   Error: {}", e.to_string().red());
+        std::process::exit(exit_code_for_error(&e));
+    }
+    Ok(())
+}
+
+/// Print a validation error and exit with the configuration error status
+fn exit_on_invalid(result: Result<()>) {
+    if let Err(e) = result {
+        eprintln!("{}", "❌ Invalid arguments!".red().bold());
+        eprintln!("   Error: {}", e.to_string().red());
+        std::process::exit(exit_code::CONFIG);
+    }
+}
+
+/// Run the `migrate` subcommand
+async fn migrate_command(args: Args) -> Result<()> {
+    // Initialize logging (on stderr when stdout carries a BSON stream)
+    let bson_stream_output = args.output_format == "bson-stream";
+    init_logging(bson_stream_output);
 
     // Validate arguments
-    args.validate()?;
+    exit_on_invalid(args.validate());
+
+    if bson_stream_output {
+        return exit_on_error("Export", run_bson_export(args).await);
+    }
+
+    if args.output_format == "parquet" {
+        print_banner();
+        return exit_on_error("Parquet export", run_parquet_export(args).await);
+    }
 
     // Print banner
     print_banner();
 
-    // Run migration
-    match run_migration(args).await {
+    // Run migration; Ctrl+C ends it with the interrupted exit code. --watch
+    // handles Ctrl+C itself to stop watching cleanly.
+    let result = if args.watch {
+        run_migration(args).await
+    } else {
+        tokio::select! {
+            result = run_migration(args) => result,
+            _ = tokio::signal::ctrl_c() => Err(MigrationError::Interrupted.into()),
+        }
+    };
+    match result {
         Ok(stats) => {
-            println!("\n{}", "✅ Migration completed successfully!".green().bold());
+            if stats.failures.is_empty() {
+                println!("\n{}", "✅ Migration completed successfully!".green().bold());
+            } else {
+                println!("\n{}", "⚠️  Migration completed with errors".yellow().bold());
+            }
             println!("   Total documents migrated: {}", stats.total_documents.to_string().cyan());
             println!("   Tables migrated: {}", stats.tables_migrated.to_string().cyan());
             println!("   Time elapsed: {:.2}s", stats.elapsed_seconds.to_string().cyan());
             if let Some(output) = stats.output_path {
                 println!("   Output: {}", output.cyan());
             }
+            for output in &stats.output_files {
+                println!("   Output: {}", output.cyan());
+            }
+            print_timings(&stats.timings);
+            print_invalid_documents(&stats.invalid_documents);
+            if let Some(ref conversion) = stats.conversion {
+                print_conversion_summary(conversion);
+            }
+
+            // Printed last and on its own line, so schedulers can take the
+            // final line of stdout
+            if let Some(ref continuation) = stats.continuation {
+                println!("{}", continuation.to_json());
+            }
+
+            if !stats.failures.is_empty() {
+                eprintln!(
+                    "\n{}",
+                    format!("❌ {} collection(s) failed:", stats.failures.len()).red().bold()
+                );
+                for failure in &stats.failures {
+                    eprintln!(
+                        "   {} ({}): {}",
+                        failure.collection.cyan(),
+                        failure.phase,
+                        failure.error.red()
+                    );
+                }
+                std::process::exit(exit_code::PARTIAL_FAILURE);
+            }
             Ok(())
         }
         Err(e) => {
             eprintln!("\n{}", "❌ Migration failed!".red().bold());
             eprintln!("   Error: {}", e.to_string().red());
-            std::process::exit(1);
+            std::process::exit(exit_code_for_error(&e));
         }
     }
 }
 
 /// Initialize logging based on RUST_LOG environment variable
-fn init_logging() {
+///
+/// # Arguments
+/// * `to_stderr` - Write logs to stderr instead of stdout
+fn init_logging(to_stderr: bool) {
     let filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new("info"));
 
-    fmt()
+    let subscriber = fmt()
         .with_env_filter(filter)
         .with_target(false)
         .with_thread_ids(false)
-        .with_thread_names(false)
-        .init();
+        .with_thread_names(false);
+
+    if to_stderr {
+        subscriber.with_writer(std::io::stderr).init();
+    } else {
+        subscriber.init();
+    }
 }
 
 /// Print application banner
@@ -71,29 +176,668 @@ fn print_banner() {
     println!();
 }
 
+/// Connect to MongoDB using the connection and TLS arguments
+async fn connect_mongodb(connection: &ConnectionArgs) -> Result<mongodb_client::MongoClient> {
+    let tls_options = mongodb_client::build_tls_options(
+        connection.tls_ca_file.as_deref(),
+        connection.tls_allow_invalid_certs,
+        connection.tls_cert_key_file.as_deref(),
+    )?;
+    let mongo_client = mongodb_client::MongoClient::new(
+        &connection.mongodb_uri,
+        tls_options,
+        connection.credential()?,
+    )
+    .await?;
+    Ok(mongo_client.with_max_time(connection.mongo_max_time_ms.map(std::time::Duration::from_millis)))
+}
+
+/// Resolve the collections to process from --table, --all-tables or --tables-from-file
+async fn resolve_collections(
+    mongo_client: &mongodb_client::MongoClient,
+    database: &str,
+    table: Option<&str>,
+    all_tables: bool,
+    tables_from_file: Option<&str>,
+) -> Result<Vec<String>> {
+    let collections = if all_tables {
+        mongo_client.list_collections(database).await?
+    } else if let Some(table) = table {
+        vec![table.to_string()]
+    } else if let Some(path) = tables_from_file {
+        read_collection_list(path)?
+    } else {
+        anyhow::bail!("Either --all-tables, --table or --tables-from-file must be specified");
+    };
+
+    if collections.is_empty() {
+        anyhow::bail!("No collections found in database '{}'", database);
+    }
+
+    Ok(collections)
+}
+
+/// Narrow the collections down to the --top-collections largest ones
+async fn select_largest_collections(
+    mongo_client: &mongodb_client::MongoClient,
+    database: &str,
+    collections: Vec<String>,
+    top: usize,
+) -> Result<Vec<String>> {
+    println!("{}", format!("📏 Counting documents in {} collections...", collections.len()).yellow());
+
+    let mut counts = HashMap::new();
+    for collection_name in collections {
+        let count = mongo_client.count_documents(database, &collection_name).await?;
+        counts.insert(collection_name, count);
+    }
+
+    let selected = migration::select_top_collections(&counts, top);
+    println!("{}", format!("   ✓ Selected the {} largest collections:", selected.len()).green());
+    for collection_name in &selected {
+        println!("     {} ({} documents)", collection_name.cyan(), counts[collection_name]);
+    }
+
+    Ok(selected)
+}
+
+/// Build the optional sort document from --sort-by
+fn sort_document(sort_by: Option<&str>) -> Result<Option<bson::Document>> {
+    sort_by.map(mongodb_client::build_sort_document).transpose()
+}
+
+/// How `export_documents` writes documents
+struct ExportFormat<'a> {
+    /// `jsonl` (relaxed Extended JSON, one document per line), `json`,
+    /// `csv`, `sql-dump` or `bson-stream` (length-prefixed BSON)
+    name: &'a str,
+    /// Documents sampled for the csv and sql-dump schema
+    sample_size: usize,
+    /// Comment each sql-dump column with its inference stats
+    column_comments: bool,
+}
+
+impl<'a> ExportFormat<'a> {
+    /// Format for a plain document dump, which needs no schema
+    fn documents(name: &'a str) -> Self {
+        Self {
+            name,
+            sample_size: 0,
+            column_comments: false,
+        }
+    }
+}
+
+/// Write every document of the given collections in an export format
+async fn export_documents(
+    mongo_client: &mongodb_client::MongoClient,
+    database: &str,
+    collections: &[String],
+    sort: Option<bson::Document>,
+    format: &ExportFormat<'_>,
+    mut writer: &mut dyn Write,
+) -> Result<()> {
+    for collection_name in collections {
+        if format.name == "csv" || format.name == "sql-dump" {
+            let sample = mongo_client
+                .sample_documents(database, collection_name, format.sample_size)
+                .await?;
+            let (schema, field_infos) = schema::SchemaInferrer::infer_schema_explained(
+                collection_name,
+                &sample,
+                &schema::InferenceOptions::default(),
+            )?;
+
+            if format.name == "csv" {
+                writeln!(writer, "{}", export::csv_header(&schema))?;
+            } else {
+                let field_infos = format.column_comments.then_some(field_infos.as_slice());
+                writeln!(writer, "{}", export::sql_dump_create_table(&schema, field_infos))?;
+            }
+
+            let options = converter::ConverterOptions::default();
+            let mut warned_fields = HashSet::new();
+            let mut cursor = mongo_client
+                .stream_documents(database, collection_name, sort.clone())
+                .await?;
+            while let Some(doc) = cursor.try_next().await? {
+                let values =
+                    converter::document_to_sql_values(&doc, &schema.fields, &options, &mut warned_fields);
+                if format.name == "csv" {
+                    writeln!(writer, "{}", export::csv_row(&values))?;
+                } else {
+                    writeln!(writer, "{}", export::sql_dump_insert(&schema, &values))?;
+                }
+            }
+            continue;
+        }
+
+        let mut cursor = mongo_client
+            .stream_documents(database, collection_name, sort.clone())
+            .await?;
+
+        if format.name == "json" {
+            let mut array = export::JsonArrayWriter::begin(&mut writer)?;
+            while let Some(doc) = cursor.try_next().await? {
+                array.write_element(&export::document_json(doc))?;
+            }
+            array.finish()?;
+            continue;
+        }
+
+        while let Some(doc) = cursor.try_next().await? {
+            if format.name == "bson-stream" {
+                source::write_bson_document(&mut writer, &doc)?;
+            } else {
+                writeln!(writer, "{}", export::document_json(doc))?;
+            }
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Export collections to stdout as a length-prefixed BSON stream
+///
+/// Nothing but BSON is written to stdout, so the output can be piped into
+/// another run using `--input bson-stream`.
+async fn run_bson_export(args: Args) -> Result<()> {
+    let mongo_client = connect_mongodb(&args.connection).await?;
+    let collections = resolve_collections(
+        &mongo_client,
+        &args.database,
+        args.table.as_deref(),
+        args.all_tables,
+        args.tables_from_file.as_deref(),
+    )
+    .await?;
+    let sort = sort_document(args.sort_by.as_deref())?;
+
+    let mut writer = std::io::BufWriter::new(std::io::stdout().lock());
+    export_documents(
+        &mongo_client,
+        &args.database,
+        &collections,
+        sort,
+        &ExportFormat::documents("bson-stream"),
+        &mut writer,
+    )
+    .await
+}
+
+/// Write each collection to `<output>/<collection>.parquet`
+///
+/// Column types come from the same schema inference and value conversion
+/// as a SQLite migration.
+async fn run_parquet_export(args: Args) -> Result<()> {
+    let compression: parquet_sink::ParquetCompression = args.parquet_compression.parse()?;
+    let converter_options = converter_options(&args)?;
+    let inference_options = inference_options(&args);
+
+    println!("{}", "🔍 Connecting to MongoDB...".yellow());
+    let sample_query = args
+        .sample_query
+        .as_deref()
+        .map(mongodb_client::parse_sample_query)
+        .transpose()?;
+    let mongo_client = connect_mongodb(&args.connection)
+        .await?
+        .with_sample_query(sample_query);
+    println!("{}", "   ✓ Connected to MongoDB".green());
+
+    let collections = resolve_collections(
+        &mongo_client,
+        &args.database,
+        args.table.as_deref(),
+        args.all_tables,
+        args.tables_from_file.as_deref(),
+    )
+    .await?;
+    let sort = sort_document(args.sort_by.as_deref())?;
+    let dir = std::path::Path::new(args.output.as_deref().unwrap_or("output"));
+    export::prepare_output_dir(dir)?;
+
+    for collection_name in &collections {
+        let documents = mongo_client
+            .sample_documents(&args.database, collection_name, args.sample_size)
+            .await?;
+        if documents.is_empty() {
+            println!("  {} {}: No documents to export", "✓".green(), collection_name.cyan());
+            continue;
+        }
+        let schema =
+            schema::SchemaInferrer::infer_schema(collection_name, &documents, &inference_options)?;
+
+        let path = export::collection_file_path(dir, collection_name, "parquet", export::ExportCompression::None);
+        let mut sink = parquet_sink::ParquetSink::create(&path, &schema, compression)?;
+        let mut cursor = mongo_client
+            .stream_documents(&args.database, collection_name, sort.clone())
+            .await?;
+        let mut rows = Vec::with_capacity(args.batch_size);
+        let mut warned_fields = HashSet::new();
+
+        while let Some(doc) = cursor.try_next().await? {
+            rows.push(converter::document_to_sql_values(
+                &doc,
+                &schema.fields,
+                &converter_options,
+                &mut warned_fields,
+            ));
+            if rows.len() >= args.batch_size {
+                sink.write(&rows)?;
+                rows.clear();
+            }
+        }
+        sink.write(&rows)?;
+        let written = sink.close()?;
+
+        println!(
+            "  {} {} → {} ({} rows)",
+            "✓".green(),
+            collection_name.cyan(),
+            path.display().to_string().cyan(),
+            written.to_string().cyan()
+        );
+    }
+
+    println!(
+        "\n{} Exported {} collection(s) to {}",
+        "✅".green(),
+        collections.len().to_string().cyan(),
+        dir.display().to_string().cyan()
+    );
+    Ok(())
+}
+
+/// Build the document conversion options from the migrate arguments
+fn converter_options(args: &Args) -> Result<converter::ConverterOptions> {
+    Ok(converter::ConverterOptions {
+        extended_json_types: args.extended_json_types,
+        pretty_json: args.pretty_json,
+        legacy_uuid: args.legacy_uuid.parse()?,
+        coerce_numeric_strings: args.coerce_numeric_strings,
+        keep_raw_json: args.keep_raw_json,
+        round_real: args.round_real,
+        base64_blob_fields: args.base64_blob_fields.iter().cloned().collect(),
+        defaults: converter::parse_default_specs(&args.defaults)?,
+        datetime_format: args.datetime_as.parse()?,
+        non_finite: args.non_finite.parse()?,
+        objectid_format: args.objectid_as.parse()?,
+        decimal_format: args.decimal_as.parse()?,
+        ..converter::ConverterOptions::from_transform_specs(&args.transforms)?
+    })
+}
+
+/// Build the schema inference options from the migrate arguments
+fn inference_options(args: &Args) -> schema::InferenceOptions {
+    schema::InferenceOptions {
+        require_id: args.require_id,
+        coerce_numeric_strings: args.coerce_numeric_strings,
+        keep_raw_json: args.keep_raw_json,
+        objectid_format: args.objectid_as.parse().unwrap_or_default(),
+        decimal_format: args.decimal_as.parse().unwrap_or_default(),
+    }
+}
+
+/// Run the `export` subcommand
+async fn run_export(args: ExportArgs) -> Result<()> {
+    let mongo_client = connect_mongodb(&args.connection).await?;
+    let collections = resolve_collections(
+        &mongo_client,
+        &args.database,
+        args.table.as_deref(),
+        args.all_tables,
+        None,
+    )
+    .await?;
+    let sort = sort_document(args.sort_by.as_deref())?;
+    let compression: export::ExportCompression = args.compress.parse()?;
+    let format = ExportFormat {
+        name: &args.format,
+        sample_size: args.sample_size,
+        column_comments: args.column_comments,
+    };
+
+    if let Some(dir) = args.output_dir.as_deref() {
+        let dir = std::path::Path::new(dir);
+        export::prepare_output_dir(dir)?;
+
+        for collection_name in &collections {
+            let path = export::collection_file_path(dir, collection_name, &args.format, compression);
+            let file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+            let mut writer = export::ExportWriter::new(Box::new(file), compression);
+            export_documents(
+                &mongo_client,
+                &args.database,
+                std::slice::from_ref(collection_name),
+                sort.clone(),
+                &format,
+                &mut writer,
+            )
+            .await?;
+            writer.finish()?;
+        }
+
+        eprintln!(
+            "{} Exported {} collection(s) to {}",
+            "✓".green(),
+            collections.len().to_string().cyan(),
+            dir.display().to_string().cyan()
+        );
+        return Ok(());
+    }
+
+    let (destination, compression): (Box<dyn Write>, _) = match args.output.as_deref() {
+        Some(path) => (
+            Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+            compression.for_path(std::path::Path::new(path)),
+        ),
+        None => (Box::new(std::io::BufWriter::new(std::io::stdout().lock())), compression),
+    };
+    let mut writer = export::ExportWriter::new(destination, compression);
+
+    export_documents(
+        &mongo_client,
+        &args.database,
+        &collections,
+        sort,
+        &format,
+        &mut writer,
+    )
+    .await?;
+    writer.finish()?;
+
+    if let Some(path) = args.output {
+        eprintln!(
+            "{} Exported {} collection(s) to {}",
+            "✓".green(),
+            collections.len().to_string().cyan(),
+            path.cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Run the `inspect` subcommand
+async fn run_inspect(args: InspectArgs) -> Result<()> {
+    let mongo_client = connect_mongodb(&args.connection).await?;
+    let collections = resolve_collections(
+        &mongo_client,
+        &args.database,
+        args.table.as_deref(),
+        args.table.is_none(),
+        None,
+    )
+    .await?;
+
+    let inference_options = schema::InferenceOptions {
+        require_id: false,
+        coerce_numeric_strings: args.coerce_numeric_strings,
+        keep_raw_json: false,
+        objectid_format: converter::ObjectIdFormat::Hex,
+        decimal_format: converter::DecimalFormat::Text,
+    };
+
+    for collection_name in &collections {
+        let count = mongo_client
+            .count_documents(&args.database, collection_name)
+            .await?;
+        let documents = mongo_client
+            .sample_documents(&args.database, collection_name, args.sample_size)
+            .await?;
+        let (schema, field_infos) = schema::SchemaInferrer::infer_schema_explained(
+            collection_name,
+            &documents,
+            &inference_options,
+        )?;
+
+        println!(
+            "\n{} {} ({} documents)",
+            "📊".yellow(),
+            collection_name.cyan().bold(),
+            count.to_string().cyan()
+        );
+        let create_table_sql = if args.column_comments {
+            schema.to_commented_create_table_sql(converter::QuoteStyle::Ansi, &field_infos)
+        } else {
+            schema.to_create_table_sql(converter::QuoteStyle::Ansi)
+        };
+        println!("{}", create_table_sql);
+        migration::print_schema_explanation(&field_infos);
+    }
+
+    Ok(())
+}
+
+/// Run the `check` subcommand
+async fn run_check(args: CheckArgs) -> Result<()> {
+    println!("{}", "🔍 Connecting to MongoDB...".yellow());
+    let mongo_client = connect_mongodb(&args.connection).await?;
+    println!("{}", "   ✓ Connected to MongoDB".green());
+
+    if let Some(ref database) = args.database {
+        if !mongo_client.database_exists(database).await? {
+            return Err(MigrationError::DatabaseNotFound(database.clone()).into());
+        }
+        println!("   ✓ Database {} exists", database.cyan());
+    }
+
+    println!("\n{}", "🔗 Connecting to SQLite/LibSQL...".yellow());
+    let libsql_client = libsql_client::LibSqlClient::new(args.output.as_deref()).await?;
+    libsql_client.query("SELECT 1").await?;
+    println!(
+        "{}",
+        format!("   ✓ Connected to SQLite/LibSQL ({})", libsql_client.connection_info()).green()
+    );
+
+    println!("\n{}", "✅ All checks passed".green().bold());
+    Ok(())
+}
+
 /// Run the migration process
 async fn run_migration(args: Args) -> Result<MigrationStats> {
     use std::time::Instant;
     let start = Instant::now();
 
+    if args.input == "bson-stream" {
+        // Stream documents from stdin into the single --table collection
+        println!("{}", "📥 Reading BSON stream from stdin...".yellow());
+        let source = source::BsonStreamSource::from_reader(std::io::stdin());
+
+        let Some(table) = args.table.clone() else {
+            anyhow::bail!("--input bson-stream requires --table");
+        };
+        return migrate_from_source(source, vec![table], args, start).await;
+    }
+
     // Connect to MongoDB
     println!("{}", "🔍 Connecting to MongoDB...".yellow());
-    let mongo_client = mongodb_client::MongoClient::new(&args.mongodb_uri).await?;
+    let sample_query = args
+        .sample_query
+        .as_deref()
+        .map(mongodb_client::parse_sample_query)
+        .transpose()?;
+    let mongo_client = connect_mongodb(&args.connection)
+        .await?
+        .with_sample_query(sample_query)
+        .with_keyset_page_size(args.keyset_page_size);
     println!("{}", "   ✓ Connected to MongoDB".green());
 
+    if let Some(bucket) = args.gridfs.clone() {
+        return migrate_gridfs(&mongo_client, &bucket, args, start).await;
+    }
+
+    if let Some(ref pipeline) = args.pipeline {
+        // The pipeline output is migrated into the single --table
+        let pipeline = mongodb_client::parse_pipeline(pipeline)?;
+        let Some(table) = args.table.clone() else {
+            anyhow::bail!("--pipeline requires --table");
+        };
+        let source = source::AggregationSource::new(mongo_client, pipeline);
+        return migrate_from_source(source, vec![table], args, start).await;
+    }
+
     // Get list of collections to migrate
-    let collections = if args.all_tables {
-        mongo_client.list_collections(&args.database).await?
-    } else if let Some(ref table) = args.table {
-        vec![table.clone()]
-    } else {
-        anyhow::bail!("Either --all-tables or --table must be specified");
+    let collections = resolve_collections(
+        &mongo_client,
+        &args.database,
+        args.table.as_deref(),
+        args.all_tables,
+        args.tables_from_file.as_deref(),
+    )
+    .await?;
+    let collections = match args.top_collections {
+        Some(top) => select_largest_collections(&mongo_client, &args.database, collections, top).await?,
+        None => collections,
     };
 
-    if collections.is_empty() {
-        anyhow::bail!("No collections found in database '{}'", args.database);
+    if let Some(sort) = sort_document(args.sort_by.as_deref())? {
+        for collection_name in &collections {
+            // Views have no indexes to list; the check is only advisory
+            if let Err(e) = mongo_client
+                .check_sort_index(&args.database, collection_name, &sort)
+                .await
+            {
+                tracing::debug!("Could not check the indexes of {}: {}", collection_name, e);
+            }
+        }
+    }
+
+    migrate_from_source(mongo_client, collections, args, start).await
+}
+
+/// Migrate the files of a GridFS bucket into SQLite/LibSQL
+async fn migrate_gridfs(
+    mongo_client: &mongodb_client::MongoClient,
+    bucket: &str,
+    args: Args,
+    start: std::time::Instant,
+) -> Result<MigrationStats> {
+    if args.drop_tables {
+        let tables = [gridfs::gridfs_table_name(bucket)];
+        confirm_destructive("--drop-tables", &table_action("drop", &tables), args.yes)?;
     }
 
+    if args.overwrite {
+        overwrite_outputs(&[args.output.clone().unwrap_or_else(|| "output.db".to_string())], args.yes)?;
+    }
+
+    println!("\n{}", "🔗 Connecting to SQLite/LibSQL...".yellow());
+    let backend = sqlite_backend::connect_backend(args.backend.parse()?, args.output.as_deref()).await?;
+    println!("{}", "   ✓ Connected to SQLite/LibSQL".green());
+
+    println!(
+        "\n{} Migrating GridFS bucket {}...",
+        "📦".yellow(),
+        bucket.cyan().bold()
+    );
+    let total_documents = gridfs::migrate_bucket(
+        mongo_client,
+        backend.as_ref(),
+        &args.database,
+        bucket,
+        args.externalize_binary.as_deref().map(std::path::Path::new),
+        args.drop_tables,
+        args.quote_style.parse()?,
+    )
+    .await?;
+
+    Ok(MigrationStats {
+        total_documents,
+        tables_migrated: 1,
+        failures: Vec::new(),
+        timings: migration::PhaseTimings::default(),
+        invalid_documents: Vec::new(),
+        elapsed_seconds: start.elapsed().as_secs_f64(),
+        output_path: args.output,
+        output_files: Vec::new(),
+        continuation: None,
+        conversion: None,
+    })
+}
+
+/// Delete existing output files for --overwrite, after confirmation
+///
+/// # Arguments
+/// * `paths` - Local database files that will be written
+/// * `assume_yes` - Whether --yes was given
+fn overwrite_outputs(paths: &[String], assume_yes: bool) -> Result<()> {
+    if libsql_client::remote_configured() {
+        anyhow::bail!("--overwrite is not supported for Turso databases; use --drop-tables instead");
+    }
+
+    let existing: Vec<String> = paths
+        .iter()
+        .filter(|path| path.as_str() != ":memory:" && std::path::Path::new(path).exists())
+        .cloned()
+        .collect();
+    if existing.is_empty() {
+        return Ok(());
+    }
+
+    confirm_destructive("--overwrite", &format!("delete {}", existing.join(", ")), assume_yes)?;
+
+    for path in &existing {
+        libsql_client::remove_database_file(std::path::Path::new(path))?;
+        println!("  {} Deleted {}", "✓".green(), path.cyan());
+    }
+    Ok(())
+}
+
+/// Ask before a destructive operation, unless --yes was given
+///
+/// Without a terminal to answer on, fails instead of waiting for input.
+///
+/// # Arguments
+/// * `flag` - The flag asking for the operation, e.g. `--drop-tables`
+/// * `action` - What will happen, e.g. `drop 2 table(s): users, orders`
+/// * `assume_yes` - Whether --yes was given
+fn confirm_destructive(flag: &str, action: &str, assume_yes: bool) -> Result<()> {
+    let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+
+    match migration::Confirmation::decide(interactive, assume_yes) {
+        migration::Confirmation::Skip => Ok(()),
+        migration::Confirmation::Refuse => anyhow::bail!(
+            "{} needs confirmation but there is no terminal to ask on; pass --yes to {}",
+            flag,
+            action
+        ),
+        migration::Confirmation::Prompt => {
+            println!("\n{} {} will {}", "⚠️ ".yellow(), flag, action.cyan());
+            print!("   Continue? [y/N] ");
+            std::io::stdout().flush()?;
+
+            let mut answer = String::new();
+            std::io::stdin().read_line(&mut answer)?;
+            match answer.trim().to_lowercase().as_str() {
+                "y" | "yes" => Ok(()),
+                _ => anyhow::bail!("Aborted: nothing was changed"),
+            }
+        }
+    }
+}
+
+/// Describe an operation on tables for `confirm_destructive`
+///
+/// # Arguments
+/// * `verb` - What happens to the tables, e.g. `drop`
+/// * `tables` - Affected table names
+///
+/// # Returns
+/// The action, e.g. `drop 2 table(s): users, orders`
+fn table_action(verb: &str, tables: &[String]) -> String {
+    format!("{} {} table(s): {}", verb, tables.len(), tables.join(", "))
+}
+
+/// Migrate the given collections from a document source into SQLite/LibSQL
+async fn migrate_from_source<S: source::DocumentSource>(
+    source: S,
+    collections: Vec<String>,
+    args: Args,
+    start: std::time::Instant,
+) -> Result<MigrationStats> {
     let collections_count = collections.len();
     let collections_display = collections.join(", ");
     
@@ -102,38 +846,558 @@ async fn run_migration(args: Args) -> Result<MigrationStats> {
         collections_count.to_string().cyan().bold(),
         collections_display.cyan()
     );
+    print_collection_stats(&source, &args.database, &collections).await;
+
+    // With --split-files every collection gets its own file and connection
+    let targets: Vec<(Option<String>, Vec<String>)> = if args.split_files {
+        let base = args.output.as_deref().unwrap_or("output.db");
+        collections
+            .iter()
+            .map(|name| (Some(migration::split_file_path(base, name)), vec![name.clone()]))
+            .collect()
+    } else {
+        vec![(args.output.clone(), collections.clone())]
+    };
+
+    if args.overwrite {
+        let paths: Vec<String> = targets
+            .iter()
+            .map(|(output, _)| output.clone().unwrap_or_else(|| "output.db".to_string()))
+            .collect();
+        overwrite_outputs(&paths, args.yes)?;
+    }
 
-    // Connect to LibSQL (local or remote)
+    // Connect to LibSQL (local or remote) or a local file via rusqlite
     println!("\n{}", "🔗 Connecting to SQLite/LibSQL...".yellow());
-    let libsql_client = libsql_client::LibSqlClient::new(args.output.as_deref()).await?;
+    let backend_kind: sqlite_backend::Backend = args.backend.parse()?;
+    let backend = sqlite_backend::connect_backend(backend_kind, targets[0].0.as_deref()).await?;
+    if args.split_files && !backend.is_local() {
+        anyhow::bail!("--split-files only writes local files; unset TURSO_DATABASE_URL to use it");
+    }
     println!("{}", "   ✓ Connected to SQLite/LibSQL".green());
+    let mut saved_pragmas = if args.fast_load {
+        Some(backend.enable_fast_load().await?)
+    } else {
+        None
+    };
 
     // Run migration
+    let conversion_stats = std::sync::Arc::new(converter::ConversionStats::default());
+    let converter_options = converter::ConverterOptions {
+        stats: Some(conversion_stats.clone()),
+        ..converter_options(&args)?
+    };
+    let sort = sort_document(args.sort_by.as_deref())?;
     let migrator = migration::Migrator::new(
-        mongo_client,
-        libsql_client,
+        source,
+        backend,
         args.database.clone(),
         args.batch_size,
         args.sample_size,
+        converter_options,
+    )
+    .with_explain_schema(args.explain_schema)
+    .with_inference_options(inference_options(&args));
+    let migrator = match sort {
+        Some(sort) => migrator.with_sort(sort),
+        None => migrator,
+    };
+    let migrator = match args.schema_file.as_deref() {
+        Some(path) => migrator.with_schemas(schema::load_schema_file(std::path::Path::new(path))?),
+        None => migrator,
+    };
+    let merges = args
+        .merges
+        .iter()
+        .map(|merge| merge.parse())
+        .collect::<Result<Vec<migration::MergeSpec>>>()?;
+    let migrator = migrator.with_merges(&merges, &collections).await?;
+    let post_filters = filter::parse_post_filter_specs(&args.post_filters)?;
+    let migrator = migrator
+        .with_post_filters(post_filters)
+        .with_resume_schema(args.resume_schema)
+        .with_quote_style(args.quote_style.parse()?)
+        .with_continue_on_error(args.continue_on_error)
+        .with_alter_add_columns(args.alter_add_columns)
+        .with_map_to_existing(args.map_to_existing)
+        .with_evolve_schema(args.evolve_schema)
+        .with_upsert(args.upsert)
+        .with_skip_conflicts(args.skip_conflicts)
+        .with_transactions(!args.no_transaction)
+        .with_read_parallelism(args.read_parallelism)
+        .with_bson_type_metadata(args.bson_type_metadata)
+        .with_naming(args.naming.parse()?)
+        .with_sanitize_table_names(args.sanitize_table_names)
+        .with_max_identifier_length(args.max_identifier_length)
+        .with_id_column(args.id_column.clone())
+        .with_lookup_fields(args.lookup_fields.iter().cloned().collect())
+        .with_computed_columns(computed::parse_computed_specs(&args.computed_columns)?)
+        .with_variable_limit(args.sqlite_variable_limit)
+        .with_json_views(args.create_json_views)
+        .with_analyze(args.analyze)
+        .with_generate_json_columns(args.generate_json_columns)
+        .with_primary_key(!args.no_primary_key)
+        .with_empty_policy(migration::EmptyCollectionPolicy::from_args(
+            args.skip_empty,
+            args.error_on_empty,
+        ))
+        .with_text_collation(schema::TextCollation {
+            columns: args.text_collation.as_deref().map(str::parse).transpose()?,
+            primary_key: args.pk_collation.as_deref().map(str::parse).transpose()?,
+        });
+    let migrator = match args.heartbeat_secs {
+        0 => migrator,
+        secs => migrator.with_heartbeat(std::time::Duration::from_secs(secs)),
+    };
+    let migrator = match args.progress_interval {
+        Some(progress_interval) => migrator.with_progress_interval(progress_interval),
+        None => migrator,
+    };
+    // The progress bar draws on stderr and only renders on a terminal
+    let migrator = if args.no_progress || !std::io::stderr().is_terminal() {
+        migrator.with_progress_log(args.progress_log_interval)
+    } else {
+        migrator.on_progress(progress_bar_callback())
+    };
+    let migrator = match args.dedup_by.clone() {
+        Some(field) => migrator.with_dedup(field, args.dedup_strategy.parse()?),
+        None => migrator,
+    };
+    let migrator = match args.batch_bytes {
+        Some(batch_bytes) => migrator.with_batch_bytes(batch_bytes),
+        None => migrator,
+    };
+    let migrator = match args.max_memory_mb {
+        Some(max_memory_mb) => migrator.with_max_memory(max_memory_mb * 1024 * 1024),
+        None => migrator,
+    };
+    let migrator = match args.limit {
+        Some(limit) => migrator.with_limit(limit),
+        None => migrator,
+    };
+    let migrator = match (&args.continue_from, args.continue_token) {
+        (Some(token), _) => migrator.with_continuation(migration::ContinuationToken::parse(token)?),
+        (None, true) => migrator.with_continuation(migration::ContinuationToken::default()),
+        (None, false) => migrator,
+    };
+    let migrator = if args.validate_bson {
+        migrator.with_validate_bson(args.strict, args.on_invalid.parse()?)
+    } else {
+        migrator
+    };
+    let migrator = match args.max_doc_bytes {
+        Some(max_doc_bytes) => migrator.with_max_doc_bytes(max_doc_bytes, args.on_oversize.parse()?),
+        None => migrator,
+    };
+
+    if args.check_schema {
+        let checks = migrator.check_schemas(&collections).await?;
+        let breaking = print_schema_checks(&checks);
+        std::process::exit(if breaking { exit_code::BREAKING_SCHEMA } else { exit_code::SUCCESS });
+    }
+
+    let mode = migration::MigrationMode::from_args(
+        args.schema_only,
+        args.data_only || args.map_to_existing,
     );
+    if args.drop_tables && mode != migration::MigrationMode::DataOnly {
+        confirm_destructive("--drop-tables", &table_action("drop", &migrator.table_names(&collections)), args.yes)?;
+    }
+    if args.truncate && mode == migration::MigrationMode::DataOnly {
+        confirm_destructive("--truncate", &table_action("truncate", &migrator.table_names(&collections)), args.yes)?;
+    }
+    let watch_collections = if args.watch { collections.clone() } else { Vec::new() };
+    // Changes made while the tables are copied are replayed from this point
+    let watch_start = if args.watch {
+        let start_at = migrator.operation_time().await?;
+        if start_at.is_none() {
+            tracing::warn!("The source reports no operation time; changes made during the copy may be missed");
+        }
+        start_at
+    } else {
+        None
+    };
+    let post_sql = args.post_sql.as_deref().map(read_post_sql).transpose()?;
+    let mut migrator = migrator;
+    let mut report = migration::MigrationReport::default();
+    let mut output_files = Vec::new();
+    for (index, (output, target_collections)) in targets.into_iter().enumerate() {
+        if index > 0 {
+            let backend = sqlite_backend::connect_backend(backend_kind, output.as_deref()).await?;
+            if args.fast_load {
+                saved_pragmas = Some(backend.enable_fast_load().await?);
+            }
+            migrator = migrator.with_backend(backend);
+        }
 
-    let mode = migration::MigrationMode::from_args(args.schema_only, args.data_only);
-    let total_documents = migrator.migrate(collections, mode, args.truncate, args.drop_tables).await?;
+        let target_report = migrator
+            .migrate(target_collections.clone(), mode, args.truncate, args.drop_tables)
+            .await?;
+
+        if let Some(saved) = saved_pragmas.take() {
+            migrator.finish_fast_load(&saved).await?;
+        }
+
+        if args.integrity_check {
+            migrator.check_integrity().await?;
+        }
+
+        let succeeded: Vec<String> = target_collections
+            .into_iter()
+            .filter(|name| !target_report.failures.iter().any(|f| &f.collection == name))
+            .collect();
+
+        if args.analyze_storage {
+            print_storage_report(&migrator.analyze_storage(&succeeded).await?);
+        }
+
+        if args.profile {
+            let stats = migrator.profile(&succeeded).await?;
+            println!(
+                "  {} Wrote statistics of {} column(s) to _field_stats",
+                "✓".green(),
+                stats.len().to_string().cyan()
+            );
+        }
+
+        if let Some(statements) = &post_sql {
+            let count = migrator.run_post_sql(statements).await?;
+            println!(
+                "  {} Ran {} post-migration SQL statement(s)",
+                "✓".green(),
+                count.to_string().cyan()
+            );
+        }
+
+        report.extend(target_report);
+        if args.split_files {
+            output_files.extend(output);
+        }
+    }
+
+    if args.watch {
+        // Tables that failed to migrate cannot receive changes either
+        let watch_collections: Vec<String> = watch_collections
+            .into_iter()
+            .filter(|name| !report.failures.iter().any(|f| &f.collection == name))
+            .collect();
+        let applied = migrator.watch(&watch_collections, watch_start).await?;
+        println!("  {} Applied {} change event(s)", "✓".green(), applied.to_string().cyan());
+    }
 
     let elapsed = start.elapsed();
     
     Ok(MigrationStats {
-        total_documents,
-        tables_migrated: collections_count,
+        total_documents: report.total_documents,
+        tables_migrated: collections_count - report.failures.len(),
+        failures: report.failures,
+        timings: report.timings,
+        invalid_documents: report.invalid_documents,
         elapsed_seconds: elapsed.as_secs_f64(),
-        output_path: args.output,
+        output_path: if args.split_files { None } else { args.output },
+        output_files,
+        continuation: report.continuation.filter(|_| args.continue_token),
+        conversion: Some(conversion_stats),
     })
 }
 
+/// Print the document count and data size of each collection, where known
+///
+/// Statistics are only a guide to the migration's duration, so a collection
+/// without them (e.g. a view, or missing permissions) is left out.
+async fn print_collection_stats<S: source::DocumentSource>(
+    source: &S,
+    database: &str,
+    collections: &[String],
+) {
+    for collection_name in collections {
+        match source.collection_stats(database, collection_name).await {
+            Ok(Some(stats)) => println!(
+                "   {}: {} documents, {} ({} per document on average)",
+                collection_name.cyan(),
+                stats.count,
+                format_bytes(stats.size),
+                format_bytes(stats.avg_obj_size)
+            ),
+            Ok(None) => {}
+            Err(e) => tracing::debug!("No statistics for {}: {}", collection_name, e),
+        }
+    }
+}
+
+/// Format a byte count with a binary unit, e.g. `1.5 MiB`
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Print the documents --validate-bson skipped, at most 20 of them
+fn print_invalid_documents(invalid: &[migration::InvalidDocument]) {
+    const MAX_SHOWN: usize = 20;
+
+    if invalid.is_empty() {
+        return;
+    }
+
+    println!(
+        "\n{}",
+        format!("⚠️  {} invalid document(s) skipped:", invalid.len()).yellow()
+    );
+    for document in invalid.iter().take(MAX_SHOWN) {
+        println!(
+            "   {} {}: {}",
+            document.collection.cyan(),
+            document.id.as_deref().unwrap_or("(no _id)"),
+            document.reason
+        );
+    }
+    if invalid.len() > MAX_SHOWN {
+        println!("   ... and {} more", invalid.len() - MAX_SHOWN);
+    }
+}
+
+/// Print where the time went per collection, as an aligned table
+fn print_timings(timings: &migration::PhaseTimings) {
+    if timings.is_empty() {
+        return;
+    }
+
+    let width = timings
+        .collections()
+        .iter()
+        .map(|timing| timing.collection.len())
+        .max()
+        .unwrap_or(0)
+        .max("Collection".len());
+
+    println!("\n{}", "⏱️  Time per collection:".yellow());
+    println!(
+        "   {:<width$}  {:>10}  {:>10}  {:>10}  {:>10}",
+        "Collection",
+        "Sampling",
+        "Schema",
+        "Data",
+        "Total",
+        width = width
+    );
+    for timing in timings.collections() {
+        println!(
+            "   {:<width$}  {:>9.2}s  {:>9.2}s  {:>9.2}s  {:>9.2}s",
+            timing.collection,
+            timing.sampling.as_secs_f64(),
+            timing.schema.as_secs_f64(),
+            timing.data.as_secs_f64(),
+            timing.total().as_secs_f64(),
+            width = width
+        );
+    }
+}
+
+/// Print how many values of each BSON type were converted, failed or coerced
+fn print_conversion_summary(stats: &converter::ConversionStats) {
+    let by_type = stats.by_type();
+    if by_type.is_empty() {
+        return;
+    }
+
+    let width = by_type
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("BSON type".len());
+
+    println!("\n{}", "🔢 Conversion summary:".yellow());
+    println!(
+        "   {:<width$}  {:>12}  {:>8}  {:>8}",
+        "BSON type",
+        "Converted",
+        "Failed",
+        "Coerced",
+        width = width
+    );
+    for (name, counts) in &by_type {
+        println!(
+            "   {:<width$}  {:>12}  {:>8}  {:>8}",
+            name,
+            counts.converted,
+            counts.failed,
+            counts.coerced,
+            width = width
+        );
+    }
+
+    let total = stats.total();
+    if total.failed > 0 {
+        println!(
+            "   {}",
+            format!("⚠ {} value(s) could not be serialized and were stored as NULL", total.failed).yellow()
+        );
+    }
+    if total.coerced > 0 {
+        println!(
+            "   {} value(s) were coerced or widened (see warnings above for the fields)",
+            total.coerced.to_string().cyan()
+        );
+    }
+}
+
+/// Print per-column storage sizes as a table, largest first
+fn print_storage_report(sizes: &[migration::ColumnSize]) {
+    let total: u64 = sizes.iter().map(|size| size.bytes).sum();
+    if total == 0 {
+        return;
+    }
+
+    println!("\n{}", "📐 Storage by column (approximate):".yellow());
+    let width = sizes
+        .iter()
+        .map(|size| size.table.len() + size.column.len() + 1)
+        .max()
+        .unwrap_or(0);
+    for size in sizes.iter().filter(|size| size.bytes > 0) {
+        println!(
+            "   {:<width$}  {:>14} bytes  {:>5.1}%",
+            format!("{}.{}", size.table, size.column),
+            size.bytes,
+            size.bytes as f64 * 100.0 / total as f64,
+            width = width
+        );
+    }
+}
+
+/// Print the `--check-schema` report, grouped by kind of change
+///
+/// # Returns
+/// Whether any table has a breaking change
+fn print_schema_checks(checks: &[migration::SchemaCheck]) -> bool {
+    println!("\n{}", "🔎 Checking schema against the target...".yellow());
+
+    for check in checks {
+        if check.new_table {
+            println!("  {} {}: new table", "✓".green(), check.table.cyan());
+            continue;
+        }
+        if check.changes.is_empty() {
+            println!("  {} {}: unchanged", "✓".green(), check.table.cyan());
+            continue;
+        }
+
+        let marker = if check.is_breaking() { "✗".red() } else { "✓".green() };
+        println!("  {} {}:", marker, check.table.cyan());
+        for change in &check.changes {
+            let line = match change {
+                migration::SchemaChange::Added { column, sql_type, nullable: true } => {
+                    format!("additive: new column {} {}", column, sql_type)
+                }
+                migration::SchemaChange::Added { column, sql_type, nullable: false } => {
+                    format!("breaking: new required column {} {}", column, sql_type)
+                }
+                migration::SchemaChange::TypeChanged { column, table_type, inferred_type } => {
+                    format!("breaking: {} changes from {} to {}", column, table_type, inferred_type)
+                }
+                migration::SchemaChange::Removed { column, table_type } => {
+                    format!("breaking: {} {} is no longer in the source", column, table_type)
+                }
+            };
+            if change.is_breaking() {
+                println!("      {}", line.red());
+            } else {
+                println!("      {}", line);
+            }
+        }
+    }
+
+    let breaking = checks.iter().filter(|check| check.is_breaking()).count();
+    if breaking > 0 {
+        println!(
+            "\n{}",
+            format!("❌ {} table(s) with breaking schema changes", breaking).red().bold()
+        );
+    } else {
+        println!("\n{}", "✅ No breaking schema changes".green().bold());
+    }
+    breaking > 0
+}
+
 /// Statistics about the migration
 struct MigrationStats {
     total_documents: usize,
     tables_migrated: usize,
+    failures: Vec<migration::CollectionFailure>,
+    timings: migration::PhaseTimings,
+    /// Documents skipped by --validate-bson
+    invalid_documents: Vec<migration::InvalidDocument>,
     elapsed_seconds: f64,
     output_path: Option<String>,
+    /// One file per collection, under --split-files
+    output_files: Vec<String>,
+    /// Token for the next chunk, when --continue-token was given
+    continuation: Option<migration::ContinuationToken>,
+    /// Conversion outcomes per BSON type
+    conversion: Option<std::sync::Arc<converter::ConversionStats>>,
+}
+
+/// Build a progress callback that draws a progress bar per collection
+///
+/// # Returns
+/// A callback for `Migrator::on_progress`
+fn progress_bar_callback() -> impl Fn(migration::ProgressEvent) + Send + Sync + 'static {
+    // The bar of the collection being migrated, with its inserted and
+    // skipped document counts
+    let current = std::sync::Mutex::new(None::<(ProgressBar, u64, u64)>);
+
+    move |event| {
+        let mut current = current.lock().expect("progress bar lock poisoned");
+        match event {
+            migration::ProgressEvent::CollectionStarted { collection, total } => {
+                // Empty collections print their own status line; streams of
+                // unknown length get no bar
+                *current = (total > 0).then(|| {
+                    let pb = ProgressBar::new(total);
+                    pb.set_style(
+                        ProgressStyle::default_bar()
+                            .template("  {msg} [{bar:40.cyan/blue}] {pos}/{len} ({percent}%)")
+                            .expect("Invalid progress bar template")
+                            .progress_chars("#>-"),
+                    );
+                    pb.set_message(format!("{}", collection.cyan()));
+                    (pb, 0, 0)
+                });
+            }
+            migration::ProgressEvent::DocumentsConverted { converted, .. } => {
+                if let Some((pb, _, skipped)) = current.as_ref() {
+                    pb.set_position(converted as u64 + skipped);
+                }
+            }
+            migration::ProgressEvent::BatchInserted { count, .. } => {
+                // Change stream events arrive after the bar is finished
+                if let Some((pb, inserted, skipped)) = current.as_mut() {
+                    *inserted += count as u64;
+                    pb.set_position(*inserted + *skipped);
+                }
+            }
+            migration::ProgressEvent::DocumentsSkipped { count, .. } => {
+                if let Some((pb, inserted, skipped)) = current.as_mut() {
+                    *skipped += count as u64;
+                    pb.set_position(*inserted + *skipped);
+                }
+            }
+            migration::ProgressEvent::CollectionFinished { collection, .. } => {
+                if let Some((pb, ..)) = current.take() {
+                    pb.finish_with_message(format!("{} ✓", collection.cyan()));
+                }
+            }
+        }
+    }
 }
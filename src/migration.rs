@@ -1,14 +1,31 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use bson::{doc, Bson, Document, Timestamp};
 use colored::Colorize;
-use futures::stream::TryStreamExt;
-use indicatif::{ProgressBar, ProgressStyle};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use mongodb::change_stream::event::{ChangeStreamEvent, OperationType, ResumeToken};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
 use crate::{
-    converter::document_to_sql_values,
-    libsql_client::LibSqlClient,
-    mongodb_client::MongoClient,
-    schema::SchemaInferrer,
+    computed::ComputedColumn,
+    converter::{
+        document_to_sql_values, escape_identifier, key_sql_value,
+        ConverterOptions, QuoteStyle,
+    },
+    error::{is_unique_violation, MigrationError},
+    filter::PostFilter,
+    naming::{assign_table_names, truncate_names, NamingStyle},
+    libsql_client::SavedPragmas,
+    mongodb_client::{keyset_filter, CappedInfo},
+    schema::{
+        bson_type_rows, format_field_explanation, json_generated_columns, json_view_name, json_view_sql,
+        lookup_table_name, lookup_table_sql, merge_schemas, CollectionSchema, Field, FieldInfo, InferenceOptions, SchemaInferrer,
+        TextCollation,
+    },
+    source::DocumentSource,
+    sqlite_backend::SqliteBackend,
 };
 
 /// Migration mode determines what gets migrated
@@ -40,319 +57,3900 @@ impl MigrationMode {
     }
 }
 
-/// Orchestrates the migration process
-pub struct Migrator {
-    mongo_client: MongoClient,
-    libsql_client: LibSqlClient,
-    database_name: String,
-    batch_size: usize,
-    sample_size: usize,
+/// How a destructive operation such as `--drop-tables` gets confirmed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Confirmation {
+    /// Go ahead without asking (`--yes`)
+    Skip,
+    /// Ask on the terminal
+    Prompt,
+    /// Nobody can answer a prompt, so refuse instead of waiting forever
+    Refuse,
 }
 
-impl Migrator {
-    /// Create a new Migrator
+impl Confirmation {
+    /// Decide how to confirm a destructive operation
     ///
     /// # Arguments
-    /// * `mongo_client` - MongoDB client
-    /// * `libsql_client` - LibSQL client
-    /// * `database_name` - Name of MongoDB database to migrate
-    /// * `batch_size` - Number of documents to insert per batch
-    /// * `sample_size` - Number of documents to sample for schema inference
+    /// * `interactive` - Whether stdin and stdout are both terminals
+    /// * `assume_yes` - Whether --yes was given
     ///
     /// # Returns
-    /// A new Migrator instance
-    pub fn new(
-        mongo_client: MongoClient,
-        libsql_client: LibSqlClient,
-        database_name: String,
-        batch_size: usize,
-        sample_size: usize,
-    ) -> Self {
-        Self {
-            mongo_client,
-            libsql_client,
-            database_name,
-            batch_size,
-            sample_size,
+    /// The confirmation to perform
+    pub fn decide(interactive: bool, assume_yes: bool) -> Self {
+        match (interactive, assume_yes) {
+            (_, true) => Confirmation::Skip,
+            (true, false) => Confirmation::Prompt,
+            (false, false) => Confirmation::Refuse,
         }
     }
+}
 
-    /// Migrate collections from MongoDB to SQLite
+/// What to do with a requested collection that has no documents
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EmptyCollectionPolicy {
+    /// Create an empty table for it
+    #[default]
+    Create,
+    /// Leave it out of the migration, creating no table
+    Skip,
+    /// Fail the migration
+    Error,
+}
+
+impl EmptyCollectionPolicy {
+    /// Create the policy from command-line flags
     ///
     /// # Arguments
-    /// * `collections` - List of collection names to migrate
-    /// * `mode` - Migration mode (full, schema only, or data only)
-    /// * `truncate` - If true, delete existing data before inserting (only for data-only mode)
-    /// * `drop_tables` - If true, drop tables before creating schema
+    /// * `skip_empty` - Whether --skip-empty was set
+    /// * `error_on_empty` - Whether --error-on-empty was set
     ///
     /// # Returns
-    /// Total number of documents migrated
-    pub async fn migrate(
-        &self,
-        collections: Vec<String>,
-        mode: MigrationMode,
-        truncate: bool,
-        drop_tables: bool,
-    ) -> Result<usize> {
-        info!("Starting migration of {} collection(s)", collections.len());
-        
-        let mut total_documents = 0;
-
-        // Drop tables if requested (before schema migration)
-        if drop_tables && (mode == MigrationMode::Full || mode == MigrationMode::SchemaOnly) {
-            println!("\n{}", "🗑️  Dropping existing tables...".yellow());
-            self.drop_tables(&collections).await?;
-        }
-
-        // Migrate schema if needed
-        if mode == MigrationMode::Full || mode == MigrationMode::SchemaOnly {
-            println!("\n{}", "📋 Migrating schema...".yellow());
-            self.migrate_schemas(&collections).await?;
+    /// The matching EmptyCollectionPolicy
+    pub fn from_args(skip_empty: bool, error_on_empty: bool) -> Self {
+        match (skip_empty, error_on_empty) {
+            (true, false) => EmptyCollectionPolicy::Skip,
+            (false, true) => EmptyCollectionPolicy::Error,
+            _ => EmptyCollectionPolicy::Create,
         }
+    }
 
-        // Truncate tables if requested (only for data-only mode)
-        if truncate && mode == MigrationMode::DataOnly {
-            println!("\n{}", "🗑️  Truncating existing tables...".yellow());
-            self.truncate_tables(&collections).await?;
+    /// Decide whether a collection is migrated
+    ///
+    /// # Arguments
+    /// * `collection_name` - Name of the collection
+    /// * `count` - Number of documents in the collection
+    ///
+    /// # Returns
+    /// True if the collection should be migrated, or an error under `Error`
+    /// when it is empty
+    pub fn keep(&self, collection_name: &str, count: u64) -> Result<bool> {
+        match self {
+            _ if count > 0 => Ok(true),
+            EmptyCollectionPolicy::Create => Ok(true),
+            EmptyCollectionPolicy::Skip => Ok(false),
+            EmptyCollectionPolicy::Error => {
+                bail!("Collection {} is empty (--error-on-empty)", collection_name)
+            }
         }
+    }
+}
 
-        // Migrate data if needed
-        if mode == MigrationMode::Full || mode == MigrationMode::DataOnly {
-            println!("\n{}", "📦 Migrating data...".yellow());
-            total_documents = self.migrate_data(&collections).await?;
-        }
+/// What to do with a document larger than `--max-doc-bytes`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OversizePolicy {
+    /// Skip the document and keep migrating
+    #[default]
+    Skip,
+    /// Abort the migration
+    Abort,
+}
 
-        Ok(total_documents)
-    }
+impl std::str::FromStr for OversizePolicy {
+    type Err = anyhow::Error;
 
-    /// Drop tables completely (removes schema and data)
-    async fn drop_tables(&self, collections: &[String]) -> Result<()> {
-        for collection_name in collections {
-            let sql = format!("DROP TABLE IF EXISTS \"{}\"", collection_name.replace('"', "\"\""));
-            debug!("Dropping table: {}", collection_name);
-            
-            match self.libsql_client.execute(&sql).await {
-                Ok(_) => {
-                    println!("  {} Dropped table: {}", 
-                        "✓".green(), 
-                        collection_name.cyan()
-                    );
-                }
-                Err(e) => {
-                    warn!("Failed to drop table {}: {}", collection_name, e);
-                    // Continue with other tables even if one fails
-                }
-            }
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(OversizePolicy::Skip),
+            "abort" => Ok(OversizePolicy::Abort),
+            other => bail!("Unknown oversize policy '{}' (expected skip or abort)", other),
         }
-        Ok(())
     }
+}
 
-    /// Truncate (delete all data from) tables
-    async fn truncate_tables(&self, collections: &[String]) -> Result<()> {
-        for collection_name in collections {
-            let sql = format!("DELETE FROM \"{}\"", collection_name.replace('"', "\"\""));
-            debug!("Truncating table: {}", collection_name);
-            
-            match self.libsql_client.execute(&sql).await {
-                Ok(affected) => {
-                    println!("  {} Truncated table: {} ({} rows deleted)", 
-                        "✓".green(), 
-                        collection_name.cyan(),
-                        affected
-                    );
-                }
-                Err(e) => {
-                    warn!("Failed to truncate table {}: {}", collection_name, e);
-                    // Continue with other tables even if one fails
-                }
-            }
+/// What to do with a document failing `--validate-bson`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum InvalidDocumentPolicy {
+    /// Skip the document, record it and keep migrating
+    #[default]
+    Skip,
+    /// Abort the migration
+    Abort,
+}
+
+impl std::str::FromStr for InvalidDocumentPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(InvalidDocumentPolicy::Skip),
+            "abort" => Ok(InvalidDocumentPolicy::Abort),
+            other => bail!("Unknown invalid document policy '{}' (expected skip or abort)", other),
         }
-        Ok(())
     }
+}
 
-    /// Migrate schemas for all collections
-    async fn migrate_schemas(&self, collections: &[String]) -> Result<()> {
-        for collection_name in collections {
-            self.migrate_schema(collection_name).await?;
+/// A document skipped by `--validate-bson`
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidDocument {
+    /// Collection the document was read from
+    pub collection: String,
+    /// The document's `_id` as relaxed Extended JSON, if it has one
+    pub id: Option<String>,
+    /// The invariant the document violates
+    pub reason: String,
+}
+
+/// How `--dedup-by` removes documents that share a key value
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DedupStrategy {
+    /// Remember every key value seen and skip later duplicates (first wins)
+    #[default]
+    Memory,
+    /// Put a unique index on the key column and `INSERT OR REPLACE` (last wins)
+    LastWinsSql,
+}
+
+impl std::str::FromStr for DedupStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "memory" => Ok(DedupStrategy::Memory),
+            "last-wins-sql" => Ok(DedupStrategy::LastWinsSql),
+            other => bail!(
+                "Unknown dedup strategy '{}' (expected memory or last-wins-sql)",
+                other
+            ),
         }
-        Ok(())
     }
+}
 
-    /// Migrate schema for a single collection
-    async fn migrate_schema(&self, collection_name: &str) -> Result<()> {
-        debug!("Migrating schema for collection: {}", collection_name);
+/// A `--merge <pattern>=<target>` rule unioning collections into one table
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeSpec {
+    /// Collection name pattern; `*` matches any run of characters, `?` one character
+    pub pattern: String,
+    /// Table the matching collections are merged into
+    pub target: String,
+}
 
-        // Sample documents for schema inference
-        let documents = self
-            .mongo_client
-            .sample_documents(&self.database_name, collection_name, self.sample_size)
-            .await?;
+impl std::str::FromStr for MergeSpec {
+    type Err = anyhow::Error;
 
-        // Infer schema
-        let schema = SchemaInferrer::infer_schema(collection_name, &documents);
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((pattern, target)) = s.split_once('=') else {
+            bail!("Invalid merge '{}': expected <pattern>=<target>", s);
+        };
+        let (pattern, target) = (pattern.trim(), target.trim());
+        if pattern.is_empty() || target.is_empty() {
+            bail!("Invalid merge '{}': pattern and target must not be empty", s);
+        }
 
-        // Generate and execute CREATE TABLE statement
-        let create_table_sql = schema.to_create_table_sql();
-        debug!("CREATE TABLE SQL: {}", create_table_sql);
+        Ok(MergeSpec {
+            pattern: pattern.to_string(),
+            target: target.to_string(),
+        })
+    }
+}
 
-        self.libsql_client.execute(&create_table_sql).await?;
+impl MergeSpec {
+    /// Check whether a collection name matches the pattern
+    ///
+    /// # Arguments
+    /// * `collection_name` - Name of the collection
+    ///
+    /// # Returns
+    /// True if the whole name matches
+    pub fn matches(&self, collection_name: &str) -> bool {
+        let pattern: Vec<char> = self.pattern.chars().collect();
+        let name: Vec<char> = collection_name.chars().collect();
 
-        println!(
-            "  {} Created table: {} ({} columns)",
-            "✓".green(),
-            collection_name.cyan(),
-            schema.fields.len().to_string().cyan()
-        );
+        // Iterative wildcard matching, backtracking to the last `*`
+        let (mut p, mut n) = (0, 0);
+        let mut star: Option<(usize, usize)> = None;
+        while n < name.len() {
+            if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+                p += 1;
+                n += 1;
+            } else if p < pattern.len() && pattern[p] == '*' {
+                star = Some((p, n));
+                p += 1;
+            } else if let Some((star_p, star_n)) = star {
+                p = star_p + 1;
+                n = star_n + 1;
+                star = Some((star_p, star_n + 1));
+            } else {
+                return false;
+            }
+        }
 
-        Ok(())
+        pattern[p..].iter().all(|&c| c == '*')
     }
+}
 
-    /// Migrate data for all collections
-    async fn migrate_data(&self, collections: &[String]) -> Result<usize> {
-        let mut total_documents = 0;
+/// Skips documents whose key value was already seen in this collection
+///
+/// Key values are kept as canonical Extended JSON strings, so memory grows
+/// with the number of distinct keys (roughly the key's text size plus ~50
+/// bytes each). Documents without the key are never treated as duplicates.
+pub struct DuplicateFilter {
+    field: String,
+    seen: HashSet<String>,
+}
 
-        for collection_name in collections {
-            let count = self.migrate_collection_data(collection_name).await?;
-            total_documents += count;
+impl DuplicateFilter {
+    /// Create a filter for a top-level document field
+    ///
+    /// # Arguments
+    /// * `field` - Field whose values must be unique
+    ///
+    /// # Returns
+    /// A new DuplicateFilter with no values seen
+    pub fn new(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            seen: HashSet::new(),
         }
+    }
 
-        Ok(total_documents)
+    /// Record a document's key value
+    ///
+    /// # Arguments
+    /// * `doc` - The document about to be migrated
+    ///
+    /// # Returns
+    /// `true` if the document should be migrated, `false` if it is a duplicate
+    pub fn keep(&mut self, doc: &Document) -> bool {
+        match doc.get(&self.field) {
+            Some(value) => self
+                .seen
+                .insert(value.clone().into_canonical_extjson().to_string()),
+            None => true,
+        }
     }
+}
 
-    /// Migrate data for a single collection
-    async fn migrate_collection_data(&self, collection_name: &str) -> Result<usize> {
-        debug!("Migrating data for collection: {}", collection_name);
+/// Assigns stable integer ids to the values of a `--lookup-table` column
+///
+/// Ids start at 1 in order of first appearance. A cache seeded from an
+/// existing lookup table continues after its largest id, so values keep
+/// their ids across runs.
+#[derive(Debug, Default)]
+pub struct LookupCache {
+    ids: HashMap<String, i64>,
+    last_id: i64,
+}
 
-        // Get total document count
-        let total_count = self
-            .mongo_client
-            .count_documents(&self.database_name, collection_name)
-            .await?;
+impl LookupCache {
+    /// Create a cache from the rows already in a lookup table
+    ///
+    /// # Arguments
+    /// * `rows` - `(id, value)` pairs read from the lookup table
+    ///
+    /// # Returns
+    /// A LookupCache that knows those values
+    pub fn from_rows(rows: impl IntoIterator<Item = (i64, String)>) -> Self {
+        let mut cache = Self::default();
+        for (id, value) in rows {
+            cache.last_id = cache.last_id.max(id);
+            cache.ids.insert(value, id);
+        }
+        cache
+    }
 
-        if total_count == 0 {
-            println!(
-                "  {} {}: No documents to migrate",
-                "✓".green(),
-                collection_name.cyan()
-            );
-            return Ok(0);
+    /// Look up the id of a value, assigning the next id to new values
+    ///
+    /// # Arguments
+    /// * `value` - Text of the value
+    ///
+    /// # Returns
+    /// The id, and whether it was just assigned (so the value still has to
+    /// be added to the lookup table)
+    pub fn id_for(&mut self, value: &str) -> (i64, bool) {
+        if let Some(&id) = self.ids.get(value) {
+            return (id, false);
         }
+        self.last_id += 1;
+        self.ids.insert(value.to_string(), self.last_id);
+        (self.last_id, true)
+    }
+}
 
-        // Sample documents to infer schema (needed for field ordering)
-        let sample_docs = self
-            .mongo_client
-            .sample_documents(&self.database_name, collection_name, self.sample_size)
-            .await?;
+/// Text a converted value is stored under in a lookup table
+///
+/// # Arguments
+/// * `value` - The converted column value
+///
+/// # Returns
+/// The value as text, or `None` for NULL (which stays NULL)
+pub fn lookup_value(value: &libsql::Value) -> Option<String> {
+    match value {
+        libsql::Value::Null => None,
+        libsql::Value::Integer(i) => Some(i.to_string()),
+        libsql::Value::Real(r) => Some(r.to_string()),
+        libsql::Value::Text(s) => Some(s.clone()),
+        libsql::Value::Blob(b) => Some(hex::encode(b)),
+    }
+}
 
-        let schema = SchemaInferrer::infer_schema(collection_name, &sample_docs);
-        let insert_sql = schema.to_insert_sql();
-        let field_names = schema.field_names();
-
-        // Create progress bar
-        let pb = ProgressBar::new(total_count);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("  {msg} [{bar:40.cyan/blue}] {pos}/{len} ({percent}%)")
-                .expect("Invalid progress bar template")
-                .progress_chars("#>-"),
-        );
-        pb.set_message(format!("{}", collection_name.cyan()));
+/// A lookup column of the table being migrated, with its value cache
+struct ColumnLookup {
+    index: usize,
+    insert_sql: String,
+    cache: LookupCache,
+}
 
-        // Stream documents and insert in batches
-        let mut cursor = self
-            .mongo_client
-            .stream_documents(&self.database_name, collection_name)
-            .await?;
+/// Decide whether a document of the given size should be migrated
+///
+/// # Arguments
+/// * `doc_bytes` - Serialized BSON size of the document
+/// * `max_doc_bytes` - Size limit, if any
+/// * `policy` - What to do when the limit is exceeded
+///
+/// # Returns
+/// `Ok(true)` to migrate the document, `Ok(false)` to skip it, or an error to abort
+pub fn check_document_size(
+    doc_bytes: usize,
+    max_doc_bytes: Option<usize>,
+    policy: OversizePolicy,
+) -> Result<bool> {
+    match max_doc_bytes {
+        Some(max) if doc_bytes > max => match policy {
+            OversizePolicy::Skip => Ok(false),
+            OversizePolicy::Abort => bail!(
+                "Document of {} bytes exceeds --max-doc-bytes {}",
+                doc_bytes,
+                max
+            ),
+        },
+        _ => Ok(true),
+    }
+}
 
-        let mut batch = Vec::new();
-        let mut total_migrated = 0;
+/// Check the invariants `--validate-bson` asserts for every document
+///
+/// A document needs an `_id` that is neither null, undefined nor an array.
+/// With `strict`, the deprecated `undefined` and DBPointer types, which the
+/// converter can only store as NULL, are rejected anywhere in the document.
+///
+/// # Arguments
+/// * `doc` - The document to check
+/// * `strict` - Whether to also reject values that can't be converted
+///
+/// # Returns
+/// `None` for a valid document, otherwise the violated invariant
+pub fn validate_document(doc: &Document, strict: bool) -> Option<String> {
+    match doc.get("_id") {
+        None => return Some("missing _id".to_string()),
+        Some(Bson::Null) | Some(Bson::Undefined) => return Some("_id is null".to_string()),
+        Some(Bson::Array(_)) => return Some("_id is an array".to_string()),
+        Some(_) => {}
+    }
 
-        while let Some(doc) = cursor.try_next().await? {
-            // Convert document to SQL values
-            let values = document_to_sql_values(&doc, &field_names);
-            batch.push(values);
+    if !strict {
+        return None;
+    }
 
-            // Insert batch when it reaches the batch size
-            if batch.len() >= self.batch_size {
-                self.insert_batch(&insert_sql, &batch).await?;
-                total_migrated += batch.len();
-                pb.set_position(total_migrated as u64);
-                batch.clear();
-            }
+    fn unsupported(path: &str, value: &Bson) -> Option<String> {
+        match value {
+            Bson::Undefined => Some(format!("{} is undefined", path)),
+            Bson::DbPointer(_) => Some(format!("{} is a DBPointer", path)),
+            Bson::Document(doc) => doc
+                .iter()
+                .find_map(|(key, value)| unsupported(&format!("{}.{}", path, key), value)),
+            Bson::Array(items) => items
+                .iter()
+                .enumerate()
+                .find_map(|(index, value)| unsupported(&format!("{}.{}", path, index), value)),
+            _ => None,
         }
+    }
 
-        // Insert remaining documents
-        if !batch.is_empty() {
-            self.insert_batch(&insert_sql, &batch).await?;
-            total_migrated += batch.len();
-            pb.set_position(total_migrated as u64);
-        }
+    doc.iter().find_map(|(key, value)| unsupported(key, value))
+}
 
-        pb.finish_with_message(format!("{} ✓", collection_name.cyan()));
+/// Decide whether a batch is full and should be inserted
+///
+/// # Arguments
+/// * `rows` - Documents in the batch
+/// * `bytes` - Combined serialized BSON size of those documents
+/// * `batch_size` - Document count that fills a batch
+/// * `batch_bytes` - Byte size that fills a batch, if limited
+///
+/// # Returns
+/// Whether either threshold has been reached
+pub fn should_flush_batch(
+    rows: usize,
+    bytes: usize,
+    batch_size: usize,
+    batch_bytes: Option<usize>,
+) -> bool {
+    rows >= batch_size || batch_bytes.is_some_and(|limit| bytes >= limit)
+}
 
-        if total_migrated != total_count as usize {
-            warn!(
-                "Expected {} documents but migrated {} for collection {}",
-                total_count, total_migrated, collection_name
-            );
-        }
+/// Estimate the memory held by a converted row
+///
+/// Counts the payload of TEXT and BLOB values plus a fixed 16 bytes per value
+/// for the value itself; allocator overhead is ignored.
+///
+/// # Arguments
+/// * `values` - The row's SQL values
+///
+/// # Returns
+/// The estimated size in bytes
+pub fn estimated_row_bytes(values: &[libsql::Value]) -> usize {
+    values
+        .iter()
+        .map(|value| {
+            16 + match value {
+                libsql::Value::Text(s) => s.len(),
+                libsql::Value::Blob(b) => b.len(),
+                _ => 0,
+            }
+        })
+        .sum()
+}
 
-        Ok(total_migrated)
+/// Decide whether a batch should be inserted early to stay under `--max-memory-mb`
+///
+/// The batch is flushed once another row of the batch's average size would
+/// exceed the budget, so the effective batch size shrinks as rows grow.
+///
+/// # Arguments
+/// * `rows` - Rows in the batch
+/// * `memory` - Estimated memory of those rows, see [`estimated_row_bytes`]
+/// * `max_memory` - Memory budget for a batch in bytes, if limited
+///
+/// # Returns
+/// Whether the batch should be inserted now
+pub fn should_flush_for_memory(rows: usize, memory: usize, max_memory: Option<usize>) -> bool {
+    match max_memory {
+        Some(budget) if rows > 0 => memory + memory / rows > budget,
+        _ => false,
     }
+}
 
-    /// Insert a batch of documents
-    async fn insert_batch(
-        &self,
-        insert_sql: &str,
-        batch: &[Vec<libsql::Value>],
-    ) -> Result<()> {
-        if batch.is_empty() {
-            return Ok(());
-        }
+/// `_migration_meta` key marking a collection whose table was created
+const SCHEMA_CHECKPOINT_KEY: &str = "schema_created";
 
-        // Insert each row individually within a transaction
-        // Start transaction
-        self.libsql_client
-            .execute("BEGIN TRANSACTION")
-            .await?;
+/// `_migration_meta` key holding the resume token of the last applied change event
+const RESUME_TOKEN_KEY: &str = "change_stream_resume_token";
 
-        match self.insert_batch_inner(insert_sql, batch).await {
-            Ok(()) => {
-                self.libsql_client.execute("COMMIT").await?;
-                Ok(())
-            }
-            Err(e) => {
-                self.libsql_client.execute("ROLLBACK").await?;
-                Err(e)
-            }
+/// What a change stream event does to the target table
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeAction {
+    /// Insert the document, or update the row with the same `_id`
+    Upsert(Document),
+    /// Delete the row with this `_id`
+    Delete(Bson),
+    /// Nothing to apply (e.g. an update to a document deleted since)
+    Ignore,
+}
+
+/// Work out how a change stream event applies to the target table
+///
+/// Inserts, replaces and updates become upserts of the full document;
+/// deletes remove the row by `_id`. Events that end the stream (drop,
+/// rename, dropDatabase, invalidate) are errors.
+///
+/// # Arguments
+/// * `event` - Event from a change stream opened with `fullDocument: updateLookup`
+///
+/// # Returns
+/// The action to apply
+pub fn change_action(event: &ChangeStreamEvent<Document>) -> Result<ChangeAction> {
+    match &event.operation_type {
+        OperationType::Insert | OperationType::Replace | OperationType::Update => {
+            Ok(match &event.full_document {
+                Some(doc) => ChangeAction::Upsert(doc.clone()),
+                None => ChangeAction::Ignore,
+            })
+        }
+        OperationType::Delete => match event.document_key.as_ref().and_then(|key| key.get("_id")) {
+            Some(id) => Ok(ChangeAction::Delete(id.clone())),
+            None => bail!("Delete event without a document _id"),
+        },
+        OperationType::Drop
+        | OperationType::Rename
+        | OperationType::DropDatabase
+        | OperationType::Invalidate => {
+            bail!("Change stream closed by a {:?} event", event.operation_type)
+        }
+        OperationType::Other(kind) => {
+            debug!("Ignoring change event of type {}", kind);
+            Ok(ChangeAction::Ignore)
+        }
+        // OperationType is non-exhaustive; newer servers may add event types
+        other => {
+            debug!("Ignoring change event of type {:?}", other);
+            Ok(ChangeAction::Ignore)
         }
     }
+}
 
-    /// Inner function to insert batch rows
-    async fn insert_batch_inner(
+/// Prepared statements applying change events to one table
+pub struct ChangeStatements {
+    upsert_sql: String,
+    delete_sql: String,
+    /// The `_id` primary key, to convert deleted keys like inserted ones
+    key: Field,
+}
+
+impl ChangeStatements {
+    /// Prepare the upsert and delete statements for a table
+    ///
+    /// # Arguments
+    /// * `schema` - Schema of the target table, with `_id` as its primary key
+    /// * `quote_style` - Quoting style for identifiers
+    ///
+    /// # Returns
+    /// The statements, or an error if the table has no `_id` primary key
+    pub fn new(schema: &CollectionSchema, quote_style: QuoteStyle) -> Result<Self> {
+        Ok(Self {
+            upsert_sql: schema.to_upsert_sql(quote_style)?,
+            delete_sql: schema.to_delete_sql(quote_style)?,
+            key: schema.id_key()?.clone(),
+        })
+    }
+
+    /// Translate a change action into SQL with its parameters
+    ///
+    /// # Arguments
+    /// * `action` - The action to apply
+    /// * `schema` - Schema of the target table
+    /// * `options` - Conversion options for document values
+    /// * `warned_fields` - Fields that have already triggered a conversion warning
+    ///
+    /// # Returns
+    /// The statement and its parameters, or `None` for `ChangeAction::Ignore`
+    pub fn statement(
         &self,
-        insert_sql: &str,
-        batch: &[Vec<libsql::Value>],
-    ) -> Result<()> {
-        for values in batch {
-            // Clone values to satisfy IntoValue trait bound
-            let params = libsql::params_from_iter(values.iter().cloned());
-            self.libsql_client
-                .execute_with_params(insert_sql, params)
-                .await?;
+        action: &ChangeAction,
+        schema: &CollectionSchema,
+        options: &ConverterOptions,
+        warned_fields: &mut HashSet<String>,
+    ) -> Option<(&str, Vec<libsql::Value>)> {
+        match action {
+            ChangeAction::Upsert(doc) => Some((
+                self.upsert_sql.as_str(),
+                document_to_sql_values(doc, &schema.fields, options, warned_fields),
+            )),
+            ChangeAction::Delete(id) => Some((
+                self.delete_sql.as_str(),
+                vec![key_sql_value(id, &self.key, options)],
+            )),
+            ChangeAction::Ignore => None,
         }
-        Ok(())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Serialize a resume token for the `_migration_meta` table
+fn encode_resume_token(token: &ResumeToken) -> Result<String> {
+    Ok(bson::to_bson(token)?.into_relaxed_extjson().to_string())
+}
 
-    #[test]
-    fn test_migration_mode_from_args() {
-        assert_eq!(
-            MigrationMode::from_args(false, false),
+/// Read back a resume token stored by `encode_resume_token`
+fn decode_resume_token(text: &str) -> Result<ResumeToken> {
+    let json: serde_json::Value = serde_json::from_str(text)?;
+    Ok(bson::from_bson(Bson::try_from(json)?)?)
+}
+
+/// Position of a chunked migration: the last `_id` migrated per collection
+///
+/// Printed as JSON by `--continue-token` and read back by `--continue-from`,
+/// so an external scheduler can migrate a collection `--limit` documents at
+/// a time. Ids are stored as canonical Extended JSON to keep their BSON
+/// type, e.g. `{"users": {"$oid": "507f1f77bcf86cd799439011"}}`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContinuationToken {
+    last_ids: BTreeMap<String, Bson>,
+}
+
+impl ContinuationToken {
+    /// Parse a token printed by `to_json`
+    ///
+    /// # Arguments
+    /// * `text` - JSON object mapping collection names to their last `_id`
+    ///
+    /// # Returns
+    /// The parsed token
+    pub fn parse(text: &str) -> Result<Self> {
+        let json: serde_json::Value = serde_json::from_str(text)
+            .map_err(|e| anyhow::anyhow!("Invalid continuation token JSON: {}", e))?;
+        let Bson::Document(ids) = Bson::try_from(json)? else {
+            bail!("Invalid continuation token: expected a JSON object");
+        };
+        Ok(Self {
+            last_ids: ids.into_iter().collect(),
+        })
+    }
+
+    /// Serialize the token as a single line of canonical Extended JSON
+    pub fn to_json(&self) -> String {
+        let ids: Document = self.last_ids.clone().into_iter().collect();
+        Bson::Document(ids).into_canonical_extjson().to_string()
+    }
+
+    /// Last `_id` migrated from a collection, if any
+    pub fn last_id(&self, collection_name: &str) -> Option<&Bson> {
+        self.last_ids.get(collection_name)
+    }
+
+    /// Record the last `_id` migrated from a collection
+    pub fn record(&mut self, collection_name: &str, id: Bson) {
+        self.last_ids.insert(collection_name.to_string(), id);
+    }
+
+    /// Filter selecting the documents of a collection not migrated yet
+    ///
+    /// # Returns
+    /// `{"_id": {"$gt": <last _id>}}`, or `{}` for a collection not started
+    pub fn filter(&self, collection_name: &str) -> Document {
+        keyset_filter(self.last_id(collection_name))
+    }
+}
+
+/// Select the collections whose schema still needs to be migrated
+///
+/// # Arguments
+/// * `collections` - Collections requested for this run
+/// * `completed` - Collections whose schema a previous run already created
+/// * `resume` - Whether to skip completed collections
+///
+/// # Returns
+/// Collections to drop/create in the schema phase, in their original order
+pub fn pending_schema_collections(
+    collections: &[String],
+    completed: &HashSet<String>,
+    resume: bool,
+) -> Vec<String> {
+    collections
+        .iter()
+        .filter(|name| !resume || !completed.contains(*name))
+        .cloned()
+        .collect()
+}
+
+/// Select the N collections with the most documents
+///
+/// Ties are broken by collection name so the selection is stable.
+///
+/// # Arguments
+/// * `counts` - Document count keyed by collection name
+/// * `top` - Number of collections to keep
+///
+/// # Returns
+/// Up to `top` collection names, largest first
+pub fn select_top_collections(counts: &HashMap<String, u64>, top: usize) -> Vec<String> {
+    let mut by_size: Vec<(&String, u64)> = counts.iter().map(|(name, count)| (name, *count)).collect();
+    by_size.sort_by(|(a_name, a_count), (b_name, b_count)| {
+        b_count.cmp(a_count).then_with(|| a_name.cmp(b_name))
+    });
+
+    by_size
+        .into_iter()
+        .take(top)
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Approximate storage used by one column of a migrated table
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSize {
+    /// Table name
+    pub table: String,
+    /// Column name
+    pub column: String,
+    /// Sum of `LENGTH()` over all rows
+    pub bytes: u64,
+}
+
+/// Build the query that sums the stored length of one column
+///
+/// `LENGTH()` counts bytes for BLOBs and characters for TEXT and numbers, so
+/// the result is an approximation of the on-disk size.
+///
+/// # Arguments
+/// * `table` - Table name (unquoted)
+/// * `column` - Column name (unquoted)
+/// * `quote_style` - How to quote identifiers
+///
+/// # Returns
+/// A `SELECT` returning a single integer, 0 for an empty table
+pub fn column_size_sql(table: &str, column: &str, quote_style: QuoteStyle) -> String {
+    format!(
+        "SELECT COALESCE(SUM(LENGTH({})), 0) FROM {}",
+        escape_identifier(column, quote_style),
+        escape_identifier(table, quote_style)
+    )
+}
+
+/// Data-quality statistics of one column, written to `_field_stats` by `--profile`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnStats {
+    /// Table name
+    pub table: String,
+    /// Column name
+    pub column: String,
+    /// Rows where the column is not NULL
+    pub non_null: u64,
+    /// Distinct non-NULL values
+    pub distinct: u64,
+    /// Smallest numeric value, for INTEGER and REAL columns
+    pub min: Option<f64>,
+    /// Largest numeric value, for INTEGER and REAL columns
+    pub max: Option<f64>,
+}
+
+/// Build the query computing the `--profile` statistics of one column
+///
+/// Every column gets its non-NULL and distinct counts. INTEGER and REAL
+/// columns also get the minimum and maximum of their numeric values; values
+/// SQLite kept as TEXT because they didn't convert are left out.
+///
+/// # Arguments
+/// * `table` - Table name (unquoted)
+/// * `column` - Column name (unquoted)
+/// * `sql_type` - Declared type of the column
+/// * `quote_style` - How to quote identifiers
+///
+/// # Returns
+/// A `SELECT` returning one row of count, distinct count, min and max
+pub fn column_stats_sql(table: &str, column: &str, sql_type: &str, quote_style: QuoteStyle) -> String {
+    let column = escape_identifier(column, quote_style);
+    let numeric = sql_type.eq_ignore_ascii_case("INTEGER") || sql_type.eq_ignore_ascii_case("REAL");
+    let (min, max) = if numeric {
+        let value = format!(
+            "CASE WHEN typeof({0}) IN ('integer', 'real') THEN {0} END",
+            column
+        );
+        (format!("MIN({})", value), format!("MAX({})", value))
+    } else {
+        ("NULL".to_string(), "NULL".to_string())
+    };
+
+    format!(
+        "SELECT COUNT({0}), COUNT(DISTINCT {0}), {1}, {2} FROM {3}",
+        column,
+        min,
+        max,
+        escape_identifier(table, quote_style)
+    )
+}
+
+/// Read a number returned by a statistics query
+///
+/// # Returns
+/// The value as a float, or `None` for NULL and non-numeric values
+pub fn stat_number(value: &libsql::Value) -> Option<f64> {
+    match value {
+        libsql::Value::Integer(i) => Some(*i as f64),
+        libsql::Value::Real(f) => Some(*f),
+        libsql::Value::Text(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Keep only the first collection of each target table
+///
+/// # Arguments
+/// * `collections` - Collection names, in migration order
+/// * `table_names` - Table names keyed by collection name
+///
+/// # Returns
+/// The collections, without those whose table an earlier one already covers
+fn one_per_table(collections: Vec<String>, table_names: &HashMap<String, String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    collections
+        .into_iter()
+        .filter(|name| seen.insert(table_name_for(table_names, name).to_string()))
+        .collect()
+}
+
+/// Look up the table a collection is migrated into
+///
+/// # Arguments
+/// * `table_names` - Table names keyed by collection name
+/// * `collection_name` - Name of the collection
+///
+/// # Returns
+/// The table name, or the collection name if it has no entry
+fn table_name_for<'a>(table_names: &'a HashMap<String, String>, collection_name: &'a str) -> &'a str {
+    table_names
+        .get(collection_name)
+        .map(String::as_str)
+        .unwrap_or(collection_name)
+}
+
+/// SQLite's default limit on bound parameters per statement (3.32.0 and later)
+pub const DEFAULT_SQLITE_VARIABLE_LIMIT: usize = 32766;
+
+/// How many rows fit in one INSERT statement under SQLite's bound parameter limit
+///
+/// Every row binds one parameter per column. Builds older than 3.32.0 allow
+/// only 999 parameters, see `--sqlite-variable-limit`.
+///
+/// # Arguments
+/// * `variable_limit` - Maximum bound parameters per statement
+/// * `num_columns` - Columns bound for each row
+///
+/// # Returns
+/// Rows per statement, or 0 if a single row needs more parameters than the limit
+pub fn max_rows_per_statement(variable_limit: usize, num_columns: usize) -> usize {
+    variable_limit / num_columns.max(1)
+}
+
+/// Check whether progress should be reported after converting a document
+///
+/// # Arguments
+/// * `converted` - Documents converted so far, including the unflushed batch
+/// * `interval` - Update the position every this many documents, if set
+///
+/// # Returns
+/// True if a `DocumentsConverted` event should be emitted now
+pub fn should_update_progress(converted: usize, interval: Option<usize>) -> bool {
+    interval.is_some_and(|interval| interval > 0 && converted.is_multiple_of(interval))
+}
+
+/// Check whether a progress log line is due after inserting rows
+///
+/// Rows are inserted a batch at a time, so a line is due whenever the
+/// position crosses a multiple of the interval, not only when it lands on one.
+///
+/// # Arguments
+/// * `logged` - Position at the last progress line
+/// * `position` - Rows inserted so far
+/// * `interval` - Log every this many rows
+///
+/// # Returns
+/// True if a progress line should be logged now
+pub fn should_log_progress(logged: usize, position: usize, interval: usize) -> bool {
+    interval > 0 && position / interval > logged / interval
+}
+
+/// Columns that differ between an inferred schema and an existing table
+#[derive(Debug, Default, PartialEq)]
+pub struct ColumnDiff {
+    /// Inferred columns the table doesn't have
+    pub missing_in_table: Vec<String>,
+    /// Table columns no inferred field maps to
+    pub missing_in_source: Vec<String>,
+}
+
+impl ColumnDiff {
+    /// Check whether both column sets match
+    pub fn is_empty(&self) -> bool {
+        self.missing_in_table.is_empty() && self.missing_in_source.is_empty()
+    }
+}
+
+/// Compare inferred fields with the columns of an existing table
+///
+/// # Arguments
+/// * `fields` - Inferred (or predefined) schema fields
+/// * `table_columns` - Column names read from the target table
+///
+/// # Returns
+/// The columns missing on either side, each in its original order
+pub fn diff_columns(fields: &[Field], table_columns: &[String]) -> ColumnDiff {
+    ColumnDiff {
+        missing_in_table: fields
+            .iter()
+            .filter(|field| !table_columns.contains(&field.name))
+            .map(|field| field.name.clone())
+            .collect(),
+        missing_in_source: table_columns
+            .iter()
+            .filter(|column| !fields.iter().any(|field| &field.name == *column))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// A difference between the inferred schema and an existing table column
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaChange {
+    /// The source has a column the table lacks
+    Added {
+        column: String,
+        sql_type: String,
+        nullable: bool,
+    },
+    /// The column exists with another declared type
+    TypeChanged {
+        column: String,
+        table_type: String,
+        inferred_type: String,
+    },
+    /// The table has a column no source field maps to any more
+    Removed { column: String, table_type: String },
+}
+
+impl SchemaChange {
+    /// Check whether the change can break the table or its readers
+    ///
+    /// Only a new nullable column is safe: existing rows read it as NULL.
+    /// A new NOT NULL or primary key column can't be added to existing rows,
+    /// a type change alters what readers get back, and a removed column is
+    /// left NULL in new rows.
+    pub fn is_breaking(&self) -> bool {
+        !matches!(self, SchemaChange::Added { nullable: true, .. })
+    }
+}
+
+/// Classify the differences between inferred fields and an existing table
+///
+/// Column names and types are compared case-insensitively, as SQLite does.
+/// Columns declared without a type accept any value and never count as
+/// changed.
+///
+/// # Arguments
+/// * `fields` - Inferred (or predefined) schema fields
+/// * `table_columns` - `(name, declared type)` pairs read with `PRAGMA table_info`
+///
+/// # Returns
+/// Added and changed columns in schema order, then removed columns in table order
+pub fn classify_schema_changes(fields: &[Field], table_columns: &[(String, String)]) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+
+    for field in fields {
+        let column = table_columns
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&field.name));
+        match column {
+            None => changes.push(SchemaChange::Added {
+                column: field.name.clone(),
+                sql_type: field.sql_type.clone(),
+                nullable: field.nullable && !field.is_primary_key,
+            }),
+            Some((_, table_type))
+                if !table_type.is_empty() && !table_type.eq_ignore_ascii_case(&field.sql_type) =>
+            {
+                changes.push(SchemaChange::TypeChanged {
+                    column: field.name.clone(),
+                    table_type: table_type.clone(),
+                    inferred_type: field.sql_type.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (name, table_type) in table_columns {
+        if !fields.iter().any(|field| field.name.eq_ignore_ascii_case(name)) {
+            changes.push(SchemaChange::Removed {
+                column: name.clone(),
+                table_type: table_type.clone(),
+            });
+        }
+    }
+
+    changes
+}
+
+/// Result of `--check-schema` for one target table
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaCheck {
+    /// Target table name
+    pub table: String,
+    /// Whether the table doesn't exist yet, so migrating creates it
+    pub new_table: bool,
+    /// Differences with the existing table
+    pub changes: Vec<SchemaChange>,
+}
+
+impl SchemaCheck {
+    /// Check whether any change can break the table or its readers
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(SchemaChange::is_breaking)
+    }
+}
+
+/// Fields resolved against the columns of a hand-crafted target table
+#[derive(Debug, Default, PartialEq)]
+pub struct ColumnMapping {
+    /// Fields named after the table columns they fill, in table column order
+    pub fields: Vec<Field>,
+    /// Fields with no matching column, which are not migrated
+    pub unmapped_fields: Vec<String>,
+    /// Table columns no field fills, which keep their defaults
+    pub unfilled_columns: Vec<String>,
+}
+
+/// Map schema fields onto the columns of an existing table by name
+///
+/// Used by `--map-to-existing`. Names are compared case-insensitively, as
+/// SQLite does, and each mapped field takes the table's spelling while
+/// still reading its value from the original MongoDB key.
+///
+/// # Arguments
+/// * `fields` - Inferred (or predefined) schema fields
+/// * `table_columns` - Column names read from the target table
+///
+/// # Returns
+/// The mapped fields and what was left out on either side
+pub fn map_fields_to_columns(fields: &[Field], table_columns: &[String]) -> ColumnMapping {
+    let mut mapping = ColumnMapping::default();
+
+    for column in table_columns {
+        match fields.iter().find(|field| field.name.eq_ignore_ascii_case(column)) {
+            Some(field) => {
+                let source = field.source_key();
+                mapping.fields.push(Field {
+                    name: column.clone(),
+                    source: (source != column).then(|| source.to_string()),
+                    ..field.clone()
+                });
+            }
+            None => mapping.unfilled_columns.push(column.clone()),
+        }
+    }
+
+    mapping.unmapped_fields = fields
+        .iter()
+        .filter(|field| !table_columns.iter().any(|column| column.eq_ignore_ascii_case(&field.name)))
+        .map(|field| field.name.clone())
+        .collect();
+
+    mapping
+}
+
+/// Pick the fields that `--evolve-schema` adds to an existing table
+///
+/// Column names are compared case-insensitively, as SQLite does. Primary
+/// key fields are left out since SQLite cannot add them with `ALTER TABLE`.
+///
+/// # Arguments
+/// * `fields` - Inferred (or predefined) schema fields
+/// * `table_columns` - Column names read from the target table
+///
+/// # Returns
+/// The fields to add, in schema order
+pub fn columns_to_add<'a>(fields: &'a [Field], table_columns: &[String]) -> Vec<&'a Field> {
+    fields
+        .iter()
+        .filter(|field| !field.is_primary_key)
+        .filter(|field| {
+            !table_columns
+                .iter()
+                .any(|column| column.eq_ignore_ascii_case(&field.name))
+        })
+        .collect()
+}
+
+/// Print the type-inference decisions for each column as an aligned table
+pub fn print_schema_explanation(field_infos: &[(String, FieldInfo)]) {
+    if field_infos.is_empty() {
+        return;
+    }
+
+    let name_width = field_infos
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("FIELD".len());
+
+    println!(
+        "    {}",
+        format!(
+            "{:<name_width$}  {:<7}  {:<13}  {}",
+            "FIELD",
+            "TYPE",
+            "REASON",
+            "OBSERVED",
+            name_width = name_width
+        )
+        .bold()
+    );
+
+    for (name, info) in field_infos {
+        println!("    {}", format_field_explanation(name, info, name_width).dimmed());
+    }
+}
+
+/// A progress event emitted while migrating data
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent {
+    /// Data migration for a collection has started
+    ///
+    /// `total` is 0 if the source can't count its documents ahead.
+    CollectionStarted { collection: String, total: u64 },
+    /// Documents were converted, including rows still waiting in the batch
+    ///
+    /// Only emitted every `with_progress_interval` documents.
+    DocumentsConverted { collection: String, converted: usize },
+    /// A batch of documents was inserted
+    BatchInserted { collection: String, count: usize },
+    /// Documents were read but not inserted, e.g. excluded by `--post-filter`
+    ///
+    /// Emitted after each batch, so inserted and skipped counts add up to
+    /// the documents read.
+    DocumentsSkipped { collection: String, count: usize },
+    /// Data migration for a collection has finished
+    CollectionFinished { collection: String, total: usize },
+}
+
+/// Phase of the migration in which a collection failed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MigrationPhase {
+    Schema,
+    Data,
+}
+
+impl std::fmt::Display for MigrationPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationPhase::Schema => write!(f, "schema"),
+            MigrationPhase::Data => write!(f, "data"),
+        }
+    }
+}
+
+/// A collection that failed while `--continue-on-error` was set
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionFailure {
+    pub collection: String,
+    pub phase: MigrationPhase,
+    pub error: String,
+}
+
+/// A timed part of migrating one collection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimedPhase {
+    /// Sampling documents for schema inference
+    Sampling,
+    /// Inferring the schema and creating the table
+    Schema,
+    /// Counting, reading, converting and inserting documents
+    Data,
+}
+
+/// Time spent on each phase of one collection
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CollectionTiming {
+    /// Collection name
+    pub collection: String,
+    pub sampling: Duration,
+    pub schema: Duration,
+    pub data: Duration,
+}
+
+impl CollectionTiming {
+    /// Time spent on all phases together
+    pub fn total(&self) -> Duration {
+        self.sampling + self.schema + self.data
+    }
+}
+
+/// Per-collection phase timings of a migration run
+///
+/// Sampling happens in both the schema and the data phase; repeated
+/// measurements of a phase are added up.
+#[derive(Debug, Default)]
+pub struct PhaseTimings {
+    collections: Vec<CollectionTiming>,
+}
+
+impl PhaseTimings {
+    /// Add time spent on a phase of a collection
+    ///
+    /// # Arguments
+    /// * `collection` - Collection name
+    /// * `phase` - Phase the time was spent on
+    /// * `elapsed` - Time spent
+    pub fn add(&mut self, collection: &str, phase: TimedPhase, elapsed: Duration) {
+        let index = match self.collections.iter().position(|t| t.collection == collection) {
+            Some(index) => index,
+            None => {
+                self.collections.push(CollectionTiming {
+                    collection: collection.to_string(),
+                    ..CollectionTiming::default()
+                });
+                self.collections.len() - 1
+            }
+        };
+
+        let timing = &mut self.collections[index];
+        match phase {
+            TimedPhase::Sampling => timing.sampling += elapsed,
+            TimedPhase::Schema => timing.schema += elapsed,
+            TimedPhase::Data => timing.data += elapsed,
+        }
+    }
+
+    /// Timings per collection, in the order collections were first timed
+    pub fn collections(&self) -> &[CollectionTiming] {
+        &self.collections
+    }
+
+    /// Check whether nothing was timed
+    pub fn is_empty(&self) -> bool {
+        self.collections.is_empty()
+    }
+
+    /// Append the timings of another run over different collections
+    pub fn extend(&mut self, other: PhaseTimings) {
+        self.collections.extend(other.collections);
+    }
+}
+
+/// Outcome of a migration run
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    /// Total number of documents migrated
+    pub total_documents: usize,
+    /// Collections that failed and were skipped (only with continue-on-error)
+    pub failures: Vec<CollectionFailure>,
+    /// Time spent per collection on sampling, schema and data
+    pub timings: PhaseTimings,
+    /// Where the next chunk starts, when reading in continuation mode
+    pub continuation: Option<ContinuationToken>,
+    /// Documents skipped by `--validate-bson`
+    pub invalid_documents: Vec<InvalidDocument>,
+}
+
+impl MigrationReport {
+    /// Add the outcome of a run over other collections, e.g. into another file
+    pub fn extend(&mut self, other: MigrationReport) {
+        self.total_documents += other.total_documents;
+        self.failures.extend(other.failures);
+        self.timings.extend(other.timings);
+        self.invalid_documents.extend(other.invalid_documents);
+        if other.continuation.is_some() {
+            self.continuation = other.continuation;
+        }
+    }
+}
+
+/// Path of a collection's own file under `--split-files`
+///
+/// The collection name is inserted before the extension of the base path,
+/// so `out/shop.db` becomes `out/shop_orders.db`. Path separators in the
+/// collection name are replaced with underscores.
+///
+/// # Arguments
+/// * `base` - The `--output` path
+/// * `collection_name` - Collection stored in the file
+///
+/// # Returns
+/// The path of the collection's SQLite file
+pub fn split_file_path(base: &str, collection_name: &str) -> String {
+    let base = std::path::Path::new(base);
+    let stem = base.file_stem().and_then(|stem| stem.to_str()).unwrap_or("output");
+    let extension = base.extension().and_then(|ext| ext.to_str()).unwrap_or("db");
+    let collection = collection_name.replace(['/', '\\'], "_");
+
+    base.with_file_name(format!("{}_{}.{}", stem, collection, extension))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Callback invoked with each progress event
+pub type ProgressCallback = Box<dyn Fn(ProgressEvent) + Send + Sync>;
+
+/// Orchestrates the migration process
+pub struct Migrator<S: DocumentSource> {
+    source: S,
+    backend: Box<dyn SqliteBackend>,
+    database_name: String,
+    batch_size: usize,
+    sample_size: usize,
+    converter_options: ConverterOptions,
+    sort: Option<Document>,
+    explain_schema: bool,
+    inference_options: InferenceOptions,
+    on_progress: Option<ProgressCallback>,
+    schemas: Option<HashMap<String, CollectionSchema>>,
+    max_doc_bytes: Option<usize>,
+    oversize_policy: OversizePolicy,
+    validate_bson: Option<InvalidDocumentPolicy>,
+    strict_bson: bool,
+    post_filters: HashMap<String, PostFilter>,
+    resume_schema: bool,
+    quote_style: QuoteStyle,
+    continue_on_error: bool,
+    alter_add_columns: bool,
+    map_to_existing: bool,
+    progress_interval: Option<usize>,
+    progress_log_interval: Option<usize>,
+    bson_type_metadata: bool,
+    heartbeat_interval: Option<Duration>,
+    naming: NamingStyle,
+    sanitize_table_names: bool,
+    max_identifier_length: Option<usize>,
+    upsert: bool,
+    skip_conflicts: bool,
+    read_parallelism: usize,
+    dedup: Option<(String, DedupStrategy)>,
+    transactions: bool,
+    text_collation: TextCollation,
+    merged_tables: HashMap<String, String>,
+    merged_schemas: HashMap<String, CollectionSchema>,
+    empty_policy: EmptyCollectionPolicy,
+    id_column: String,
+    evolve_schema: bool,
+    lookup_fields: HashSet<String>,
+    variable_limit: usize,
+    json_views: bool,
+    generate_json_columns: bool,
+    primary_key: bool,
+    batch_bytes: Option<usize>,
+    max_memory: Option<usize>,
+    analyze: bool,
+    computed_columns: Vec<ComputedColumn>,
+    limit: Option<usize>,
+    continuation: Option<ContinuationToken>,
+    /// Capped-collection settings looked up so far, shared by the schema and data phases
+    capped: Mutex<HashMap<String, Option<CappedInfo>>>,
+}
+
+impl<S: DocumentSource> Migrator<S> {
+    /// Create a new Migrator
+    ///
+    /// # Arguments
+    /// * `source` - Source of documents (MongoDB or a BSON stream)
+    /// * `backend` - Target SQLite database
+    /// * `database_name` - Name of MongoDB database to migrate
+    /// * `batch_size` - Number of documents to insert per batch
+    /// * `sample_size` - Number of documents to sample for schema inference
+    /// * `converter_options` - Options controlling document-to-row conversion
+    ///
+    /// # Returns
+    /// A new Migrator instance
+    pub fn new(
+        source: S,
+        backend: Box<dyn SqliteBackend>,
+        database_name: String,
+        batch_size: usize,
+        sample_size: usize,
+        converter_options: ConverterOptions,
+    ) -> Self {
+        Self {
+            source,
+            backend,
+            database_name,
+            batch_size,
+            sample_size,
+            converter_options,
+            sort: None,
+            explain_schema: false,
+            inference_options: InferenceOptions::default(),
+            on_progress: None,
+            schemas: None,
+            max_doc_bytes: None,
+            oversize_policy: OversizePolicy::default(),
+            validate_bson: None,
+            strict_bson: false,
+            post_filters: HashMap::new(),
+            resume_schema: false,
+            quote_style: QuoteStyle::default(),
+            continue_on_error: false,
+            alter_add_columns: false,
+            map_to_existing: false,
+            progress_interval: None,
+            progress_log_interval: None,
+            bson_type_metadata: false,
+            heartbeat_interval: None,
+            naming: NamingStyle::default(),
+            sanitize_table_names: true,
+            max_identifier_length: None,
+            upsert: false,
+            skip_conflicts: false,
+            read_parallelism: 1,
+            dedup: None,
+            transactions: true,
+            text_collation: TextCollation::default(),
+            merged_tables: HashMap::new(),
+            merged_schemas: HashMap::new(),
+            empty_policy: EmptyCollectionPolicy::default(),
+            id_column: "_id".to_string(),
+            evolve_schema: false,
+            lookup_fields: HashSet::new(),
+            variable_limit: DEFAULT_SQLITE_VARIABLE_LIMIT,
+            json_views: false,
+            generate_json_columns: false,
+            primary_key: true,
+            batch_bytes: None,
+            max_memory: None,
+            analyze: false,
+            computed_columns: Vec::new(),
+            limit: None,
+            continuation: None,
+            capped: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Write to another target database from now on
+    ///
+    /// Used by `--split-files` to give each collection its own file; the
+    /// previous connection is closed.
+    ///
+    /// # Arguments
+    /// * `backend` - The new target database
+    ///
+    /// # Returns
+    /// The Migrator writing to `backend`
+    pub fn with_backend(mut self, backend: Box<dyn SqliteBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Rename tables and columns according to a naming style
+    ///
+    /// Values are still read from the original MongoDB keys; `_migration_meta`
+    /// keeps recording original collection names.
+    ///
+    /// # Arguments
+    /// * `naming` - Naming style for table and column names
+    ///
+    /// # Returns
+    /// The Migrator with the naming style applied
+    pub fn with_naming(mut self, naming: NamingStyle) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Choose whether dots in collection names become underscores in table names
+    ///
+    /// On by default. Documents are still read from the original collection.
+    ///
+    /// # Arguments
+    /// * `sanitize_table_names` - `false` to keep dotted table names
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_sanitize_table_names(mut self, sanitize_table_names: bool) -> Self {
+        self.sanitize_table_names = sanitize_table_names;
+        self
+    }
+
+    /// Truncate table and column names longer than a maximum length
+    ///
+    /// Truncated names end in a short hash of the full name, so names
+    /// sharing a long prefix stay distinct. Documents are still read from
+    /// the original collections and keys.
+    ///
+    /// # Arguments
+    /// * `max_identifier_length` - Longest name in characters, or `None` for no limit
+    ///
+    /// # Returns
+    /// The Migrator with the limit applied
+    pub fn with_max_identifier_length(mut self, max_identifier_length: Option<usize>) -> Self {
+        self.max_identifier_length = max_identifier_length;
+        self
+    }
+
+    /// Keep remote target connections warm while collections stream
+    ///
+    /// While a collection's data is migrated, a background task runs
+    /// `SELECT 1` on the target every `interval`, so slow MongoDB reads don't
+    /// leave a Turso connection idle long enough to be dropped. Local SQLite
+    /// files need no heartbeat and are skipped.
+    ///
+    /// # Arguments
+    /// * `interval` - Time between heartbeat queries
+    ///
+    /// # Returns
+    /// The Migrator with the heartbeat interval applied
+    pub fn with_heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = Some(interval);
+        self
+    }
+
+    /// Record the original BSON types of each column in a `_bson_types` table
+    ///
+    /// The table keeps the sampled BSON type names (e.g. `ObjectId`,
+    /// `Decimal128`) per collection and column, so a reverse migration can
+    /// reconstruct exact BSON values. Predefined schemas have no samples and
+    /// get no rows.
+    ///
+    /// # Arguments
+    /// * `bson_type_metadata` - Whether to write the `_bson_types` table
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_bson_type_metadata(mut self, bson_type_metadata: bool) -> Self {
+        self.bson_type_metadata = bson_type_metadata;
+        self
+    }
+
+    /// Report progress every N converted documents within a batch
+    ///
+    /// By default progress is only reported when a batch is inserted, so a
+    /// progress bar looks frozen for large batch sizes on slow connections.
+    ///
+    /// # Arguments
+    /// * `progress_interval` - Number of documents between progress events
+    ///
+    /// # Returns
+    /// The Migrator with the interval applied
+    pub fn with_progress_interval(mut self, progress_interval: usize) -> Self {
+        self.progress_interval = Some(progress_interval);
+        self
+    }
+
+    /// Log progress lines instead of drawing a progress bar
+    ///
+    /// For terminals that can't render the bar, e.g. CI logs. Lines look
+    /// like `users: 50000/200000 (25%)`.
+    ///
+    /// # Arguments
+    /// * `interval` - Number of inserted rows between log lines
+    ///
+    /// # Returns
+    /// The Migrator logging progress
+    pub fn with_progress_log(mut self, interval: usize) -> Self {
+        self.progress_log_interval = Some(interval);
+        self
+    }
+
+    /// Update rows whose primary key already exists instead of failing
+    ///
+    /// Inserts use `ON CONFLICT(pk) DO UPDATE SET ...`, so target columns
+    /// outside the schema keep their values. Meant for `--data-only` refreshes.
+    ///
+    /// # Arguments
+    /// * `upsert` - Whether to upsert on the primary key
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_upsert(mut self, upsert: bool) -> Self {
+        self.upsert = upsert;
+        self
+    }
+
+    /// Skip rows that violate a unique constraint instead of failing
+    ///
+    /// A batch that hits a conflict is rolled back and retried row by row
+    /// without a transaction, so every other row of the batch is kept. The
+    /// key of each skipped row is logged.
+    ///
+    /// # Arguments
+    /// * `skip_conflicts` - Whether to skip conflicting rows
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_skip_conflicts(mut self, skip_conflicts: bool) -> Self {
+        self.skip_conflicts = skip_conflicts;
+        self
+    }
+
+    /// Read each collection with several concurrent readers
+    ///
+    /// Readers feed a bounded channel drained by the single writer, so
+    /// inserts stay serialized. Only used when no sort order is requested,
+    /// since rows are written in the order they arrive.
+    ///
+    /// # Arguments
+    /// * `read_parallelism` - Number of concurrent readers per collection
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_read_parallelism(mut self, read_parallelism: usize) -> Self {
+        self.read_parallelism = read_parallelism.max(1);
+        self
+    }
+
+    /// Add inferred columns that an existing target table is missing
+    ///
+    /// Without this, missing columns are only reported before the data phase.
+    ///
+    /// # Arguments
+    /// * `alter_add_columns` - Whether to run `ALTER TABLE ... ADD COLUMN`
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_alter_add_columns(mut self, alter_add_columns: bool) -> Self {
+        self.alter_add_columns = alter_add_columns;
+        self
+    }
+
+    /// Insert into existing tables, filling only the columns they declare
+    ///
+    /// Fields are matched to the table's columns by name; fields without a
+    /// column are dropped and columns without a field keep their defaults.
+    /// Meant for schemas created by another tool, so use it with the
+    /// data-only mode.
+    ///
+    /// # Arguments
+    /// * `map_to_existing` - Whether to map fields onto the existing columns
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_map_to_existing(mut self, map_to_existing: bool) -> Self {
+        self.map_to_existing = map_to_existing;
+        self
+    }
+
+    /// Keep migrating other collections when one collection fails
+    ///
+    /// Failures are logged and returned in the `MigrationReport` instead of
+    /// aborting the run. A collection whose schema failed is skipped in the
+    /// data phase.
+    ///
+    /// # Arguments
+    /// * `continue_on_error` - Whether to continue past failed collections
+    ///
+    /// # Returns
+    /// The Migrator with the error policy applied
+    pub fn with_continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// Set the quoting style for identifiers in generated SQL
+    ///
+    /// # Arguments
+    /// * `quote_style` - Quoting style for table and column names
+    ///
+    /// # Returns
+    /// The Migrator with the quoting style applied
+    pub fn with_quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.quote_style = quote_style;
+        self
+    }
+
+    /// Skip the schema phase for collections completed by a previous run
+    ///
+    /// Completed collections are neither dropped nor recreated, so a re-run
+    /// after a partial schema failure only touches the remaining tables.
+    ///
+    /// # Arguments
+    /// * `resume_schema` - Whether to skip completed collections
+    ///
+    /// # Returns
+    /// The Migrator with the resume setting applied
+    pub fn with_resume_schema(mut self, resume_schema: bool) -> Self {
+        self.resume_schema = resume_schema;
+        self
+    }
+
+    /// Only insert rows matching a per-collection condition
+    ///
+    /// # Arguments
+    /// * `post_filters` - Conditions keyed by collection name
+    ///
+    /// # Returns
+    /// The Migrator with the post-filters installed
+    pub fn with_post_filters(mut self, post_filters: HashMap<String, PostFilter>) -> Self {
+        self.post_filters = post_filters;
+        self
+    }
+
+    /// Limit the serialized size of migrated documents
+    ///
+    /// # Arguments
+    /// * `max_doc_bytes` - Largest BSON document size to migrate
+    /// * `policy` - Whether to skip oversized documents or abort
+    ///
+    /// # Returns
+    /// The Migrator with the size limit installed
+    pub fn with_max_doc_bytes(mut self, max_doc_bytes: usize, policy: OversizePolicy) -> Self {
+        self.max_doc_bytes = Some(max_doc_bytes);
+        self.oversize_policy = policy;
+        self
+    }
+
+    /// Check every document against [`validate_document`] before converting it
+    ///
+    /// # Arguments
+    /// * `strict` - Whether to also reject values that can't be converted
+    /// * `policy` - Whether to skip invalid documents or abort
+    ///
+    /// # Returns
+    /// The Migrator with validation enabled
+    pub fn with_validate_bson(mut self, strict: bool, policy: InvalidDocumentPolicy) -> Self {
+        self.validate_bson = Some(policy);
+        self.strict_bson = strict;
+        self
+    }
+
+    /// Declare collations on the TEXT columns of created tables
+    ///
+    /// # Arguments
+    /// * `text_collation` - Collations for TEXT columns and a TEXT primary key
+    ///
+    /// # Returns
+    /// The Migrator with the collations applied
+    pub fn with_text_collation(mut self, text_collation: TextCollation) -> Self {
+        self.text_collation = text_collation;
+        self
+    }
+
+    /// Choose whether each batch is wrapped in a transaction
+    ///
+    /// Without transactions every row is autocommitted on its own, for
+    /// targets where transactions are managed externally or large ones are
+    /// discouraged. A failing batch then leaves the rows inserted before the
+    /// error in place.
+    ///
+    /// # Arguments
+    /// * `transactions` - Whether to wrap batches in BEGIN/COMMIT
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_transactions(mut self, transactions: bool) -> Self {
+        self.transactions = transactions;
+        self
+    }
+
+    /// Add columns for new fields to existing tables during the schema phase
+    ///
+    /// # Arguments
+    /// * `evolve_schema` - Whether to `ALTER TABLE ... ADD COLUMN` after
+    ///   `CREATE TABLE IF NOT EXISTS` found an existing table
+    ///
+    /// # Returns
+    /// The Migrator with schema evolution configured
+    pub fn with_evolve_schema(mut self, evolve_schema: bool) -> Self {
+        self.evolve_schema = evolve_schema;
+        self
+    }
+
+    /// Store `_id` in a primary key column with a different name
+    ///
+    /// # Arguments
+    /// * `id_column` - Column name for `_id`, e.g. `id`
+    ///
+    /// # Returns
+    /// The Migrator with the key column renamed
+    pub fn with_id_column(mut self, id_column: String) -> Self {
+        self.id_column = id_column;
+        self
+    }
+
+    /// Add columns computed from each document's field values
+    ///
+    /// Values are computed after conversion, so expressions see the values
+    /// as stored, and before `--post-filter`, which can test them.
+    ///
+    /// # Arguments
+    /// * `computed_columns` - Columns from `--computed`
+    ///
+    /// # Returns
+    /// The Migrator with the computed columns applied
+    pub fn with_computed_columns(mut self, computed_columns: Vec<ComputedColumn>) -> Self {
+        self.computed_columns = computed_columns;
+        self
+    }
+
+    /// Migrate at most this many documents per collection
+    ///
+    /// # Arguments
+    /// * `limit` - Maximum number of documents read from each collection
+    ///
+    /// # Returns
+    /// The Migrator with the limit applied
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Read collections in `_id` order, continuing after a previous chunk
+    ///
+    /// Each collection is read from the first `_id` greater than the one
+    /// recorded in `token`. The last `_id` migrated per collection is
+    /// returned in `MigrationReport::continuation` for the next chunk.
+    ///
+    /// # Arguments
+    /// * `token` - Position of the previous chunk, empty for the first one
+    ///
+    /// # Returns
+    /// The Migrator with continuation mode enabled
+    pub fn with_continuation(mut self, token: ContinuationToken) -> Self {
+        self.continuation = Some(token);
+        self
+    }
+
+    /// Normalize fields into lookup tables
+    ///
+    /// Each field's column stores an integer id into a
+    /// `<table>_<field>_lookup (id, value)` table, which is filled as new
+    /// values appear during the data phase.
+    ///
+    /// # Arguments
+    /// * `lookup_fields` - MongoDB field names from `--lookup-table`
+    ///
+    /// # Returns
+    /// The Migrator with the lookup fields applied
+    pub fn with_lookup_fields(mut self, lookup_fields: HashSet<String>) -> Self {
+        self.lookup_fields = lookup_fields;
+        self
+    }
+
+    /// Create a `<table>_json` view per table that extracts the top-level
+    /// keys of JSON columns
+    ///
+    /// Keys are discovered from the sampled documents, so tables with a
+    /// predefined schema get no view.
+    ///
+    /// # Arguments
+    /// * `json_views` - Whether to create the views
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_json_views(mut self, json_views: bool) -> Self {
+        self.json_views = json_views;
+        self
+    }
+
+    /// Run `ANALYZE` at the end of the migration
+    ///
+    /// Fills `sqlite_stat1` with the statistics the query planner uses to
+    /// choose indexes. A remote target that rejects it only gets a warning.
+    ///
+    /// # Arguments
+    /// * `analyze` - Whether to run `ANALYZE`
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_analyze(mut self, analyze: bool) -> Self {
+        self.analyze = analyze;
+        self
+    }
+
+    /// Declare a generated column per top-level key of JSON columns
+    ///
+    /// Like [`Migrator::with_json_views`], but the `<column>_<key>` columns
+    /// are part of the table as `GENERATED ALWAYS AS (json_extract(...))
+    /// VIRTUAL`, so they can be indexed. Keys are discovered from the sampled
+    /// documents.
+    ///
+    /// # Arguments
+    /// * `generate_json_columns` - Whether to declare the columns
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_generate_json_columns(mut self, generate_json_columns: bool) -> Self {
+        self.generate_json_columns = generate_json_columns;
+        self
+    }
+
+    /// Also insert a batch once its documents reach a serialized size
+    ///
+    /// Bounds memory for large documents: a batch is inserted when it holds
+    /// `batch_size` documents or `batch_bytes` bytes of BSON, whichever
+    /// comes first.
+    ///
+    /// # Arguments
+    /// * `batch_bytes` - Combined BSON size that fills a batch
+    ///
+    /// # Returns
+    /// The Migrator with the byte threshold installed
+    pub fn with_batch_bytes(mut self, batch_bytes: usize) -> Self {
+        self.batch_bytes = Some(batch_bytes);
+        self
+    }
+
+    /// Insert batches early when their converted rows approach a memory budget
+    ///
+    /// Unlike `with_batch_bytes`, which counts BSON bytes, this estimates
+    /// the converted values held in memory and adapts the effective batch
+    /// size to the documents seen so far.
+    ///
+    /// # Arguments
+    /// * `max_memory` - Memory budget for a batch in bytes
+    ///
+    /// # Returns
+    /// The Migrator with the memory budget installed
+    pub fn with_max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = Some(max_memory);
+        self
+    }
+
+    /// Choose whether `_id` is the primary key of each table
+    ///
+    /// # Arguments
+    /// * `primary_key` - `false` to store `_id` in an ordinary column that
+    ///   allows duplicates, from `--no-primary-key`
+    ///
+    /// # Returns
+    /// The Migrator with the setting applied
+    pub fn with_primary_key(mut self, primary_key: bool) -> Self {
+        self.primary_key = primary_key;
+        self
+    }
+
+    /// Set the bound parameter limit of the target SQLite build
+    ///
+    /// # Arguments
+    /// * `variable_limit` - Maximum bound parameters per statement, e.g. 999
+    ///   for SQLite builds older than 3.32.0
+    ///
+    /// # Returns
+    /// The Migrator with the limit applied
+    pub fn with_variable_limit(mut self, variable_limit: usize) -> Self {
+        self.variable_limit = variable_limit;
+        self
+    }
+
+    /// Choose what happens to requested collections without documents
+    ///
+    /// # Arguments
+    /// * `empty_policy` - Create an empty table, skip the collection or fail
+    ///
+    /// # Returns
+    /// The Migrator with the policy applied
+    pub fn with_empty_policy(mut self, empty_policy: EmptyCollectionPolicy) -> Self {
+        self.empty_policy = empty_policy;
+        self
+    }
+
+    /// Drop documents that repeat the value of a key field
+    ///
+    /// # Arguments
+    /// * `field` - Top-level field whose values must be unique
+    /// * `strategy` - Whether to skip later duplicates in memory or let the
+    ///   last one replace earlier rows through a unique index
+    ///
+    /// # Returns
+    /// The Migrator with deduplication enabled
+    pub fn with_dedup(mut self, field: String, strategy: DedupStrategy) -> Self {
+        self.dedup = Some((field, strategy));
+        self
+    }
+
+    /// Use predefined schemas instead of sampling MongoDB
+    ///
+    /// # Arguments
+    /// * `schemas` - Schemas keyed by collection name, e.g. from `--schema-file`
+    ///
+    /// # Returns
+    /// The Migrator with the schemas installed
+    pub fn with_schemas(mut self, schemas: HashMap<String, CollectionSchema>) -> Self {
+        self.schemas = Some(schemas);
+        self
+    }
+
+    /// Union the collections matching each merge rule into a single table
+    ///
+    /// Each matching collection is sampled (or taken from `--schema-file`)
+    /// and the schemas are combined with [`merge_schemas`]; the merged
+    /// schema is then used to create the target table and to insert every
+    /// matching collection's documents into it. Apply after `with_schemas`.
+    ///
+    /// # Arguments
+    /// * `merges` - Merge rules, applied in order; a collection joins the first rule it matches
+    /// * `collections` - Collections being migrated
+    ///
+    /// # Returns
+    /// The Migrator with the merges applied
+    pub async fn with_merges(mut self, merges: &[MergeSpec], collections: &[String]) -> Result<Self> {
+        for merge in merges {
+            if self.merged_schemas.contains_key(&merge.target) {
+                bail!("--merge target '{}' is used by more than one rule", merge.target);
+            }
+
+            let sources: Vec<&String> = collections
+                .iter()
+                .filter(|name| !self.merged_tables.contains_key(*name) && merge.matches(name))
+                .collect();
+            if sources.is_empty() {
+                warn!("--merge pattern '{}' matches no collection", merge.pattern);
+                continue;
+            }
+            if let Some(other) = collections
+                .iter()
+                .find(|name| **name == merge.target && !sources.contains(name))
+            {
+                bail!(
+                    "--merge target '{}' is also the name of collection {}",
+                    merge.target,
+                    other
+                );
+            }
+
+            let mut schemas = Vec::with_capacity(sources.len());
+            for collection_name in &sources {
+                let schema = match self.predefined_schema(collection_name) {
+                    Some(schema) => schema.clone(),
+                    None => {
+                        let sample_docs = self
+                            .source
+                            .sample_documents(&self.database_name, collection_name, self.sample_size)
+                            .await?;
+                        SchemaInferrer::infer_schema(collection_name, &sample_docs, &self.inference_options)?
+                    }
+                };
+                schemas.push(schema);
+            }
+
+            info!(
+                "Merging {} collection(s) into {}",
+                sources.len(),
+                merge.target
+            );
+            for collection_name in sources {
+                self.merged_tables.insert(collection_name.clone(), merge.target.clone());
+            }
+            self.merged_schemas
+                .insert(merge.target.clone(), merge_schemas(&merge.target, &schemas));
+        }
+
+        Ok(self)
+    }
+
+    /// Look up the predefined schema for a collection, if schemas were provided
+    ///
+    /// A merged collection gets the merged schema of its target table.
+    fn predefined_schema(&self, collection_name: &str) -> Option<&CollectionSchema> {
+        if let Some(target) = self.merged_tables.get(collection_name) {
+            return self.merged_schemas.get(target);
+        }
+        self.schemas.as_ref()?.get(collection_name)
+    }
+
+    /// Turn a collection's schema into the schema of its target table
+    ///
+    /// Applies the table name, the naming style, column type overrides,
+    /// `--id-column`, `--computed` and `--no-primary-key`.
+    fn target_schema(&self, schema: CollectionSchema, table_name: &str) -> Result<CollectionSchema> {
+        let schema = schema
+            .renamed(table_name, self.naming)
+            .with_max_identifier_length(self.max_identifier_length)
+            .with_blob_fields(&self.converter_options.base64_blob_fields)
+            .with_lookup_fields(&self.lookup_fields)
+            .with_id_column(&self.id_column)?
+            .with_computed_columns(&self.computed_columns)?;
+
+        Ok(if self.primary_key {
+            schema
+        } else {
+            schema.without_primary_key()
+        })
+    }
+
+    /// Resolve the table each collection is migrated into
+    ///
+    /// Names are assigned over all tables at once so collisions get suffixes;
+    /// merged collections share their target's table.
+    fn resolve_table_names(&self, collections: &[String]) -> HashMap<String, String> {
+        let table_key = |name: &String| self.merged_tables.get(name).unwrap_or(name).clone();
+
+        let mut keys: Vec<String> = Vec::new();
+        for key in collections.iter().map(table_key) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        let names: HashMap<String, String> = keys
+            .iter()
+            .cloned()
+            .zip(self.styled_table_names(&keys))
+            .collect();
+
+        collections
+            .iter()
+            .map(|name| (name.clone(), names[&table_key(name)].clone()))
+            .collect()
+    }
+
+    /// Assign table names to collections with the naming, sanitization and length settings
+    fn styled_table_names(&self, collections: &[String]) -> Vec<String> {
+        truncate_names(
+            assign_table_names(collections, self.naming, self.sanitize_table_names),
+            self.max_identifier_length,
+        )
+    }
+
+    /// Names of the tables the given collections are migrated into
+    ///
+    /// Collections merged into one table yield that table once.
+    ///
+    /// # Arguments
+    /// * `collections` - Collection names
+    ///
+    /// # Returns
+    /// Table names, in collection order
+    pub fn table_names(&self, collections: &[String]) -> Vec<String> {
+        let table_names = self.resolve_table_names(collections);
+        one_per_table(collections.to_vec(), &table_names)
+            .iter()
+            .map(|name| table_name_for(&table_names, name).to_string())
+            .collect()
+    }
+
+    /// Install a callback that receives progress events
+    ///
+    /// The migrator draws no progress itself; the CLI installs a callback
+    /// that renders a progress bar per collection.
+    ///
+    /// # Arguments
+    /// * `callback` - Function called with each progress event
+    ///
+    /// # Returns
+    /// The Migrator with the callback installed
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(ProgressEvent) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Send a progress event to the installed callback, if any
+    fn emit(&self, event: ProgressEvent) {
+        if let Some(callback) = &self.on_progress {
+            callback(event);
+        }
+    }
+
+    /// Emit a `DocumentsSkipped` event for documents left out since the last one
+    ///
+    /// # Arguments
+    /// * `collection_name` - Collection being migrated
+    /// * `skipped` - Documents left out so far, for any reason
+    /// * `reported` - Documents already reported, updated when emitting
+    fn report_skipped(&self, collection_name: &str, skipped: usize, reported: &mut usize) {
+        if skipped > *reported {
+            self.emit(ProgressEvent::DocumentsSkipped {
+                collection: collection_name.to_string(),
+                count: skipped - *reported,
+            });
+            *reported = skipped;
+        }
+    }
+
+    /// Stream documents in a fixed order instead of natural order
+    ///
+    /// # Arguments
+    /// * `sort` - Sort document, e.g. from `--sort-by`
+    ///
+    /// # Returns
+    /// The Migrator with the sort order applied
+    pub fn with_sort(mut self, sort: Document) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// Print the reasoning behind each inferred column type
+    ///
+    /// # Arguments
+    /// * `explain_schema` - Whether to explain inference decisions
+    ///
+    /// # Returns
+    /// The Migrator with schema explanation enabled or disabled
+    pub fn with_explain_schema(mut self, explain_schema: bool) -> Self {
+        self.explain_schema = explain_schema;
+        self
+    }
+
+    /// Set the options controlling schema inference
+    ///
+    /// # Arguments
+    /// * `inference_options` - Options such as `--require-id`
+    ///
+    /// # Returns
+    /// The Migrator with the inference options applied
+    pub fn with_inference_options(mut self, inference_options: InferenceOptions) -> Self {
+        self.inference_options = inference_options;
+        self
+    }
+
+    /// Migrate collections from MongoDB to SQLite
+    ///
+    /// # Arguments
+    /// * `collections` - List of collection names to migrate
+    /// * `mode` - Migration mode (full, schema only, or data only)
+    /// * `truncate` - If true, delete existing data before inserting (only for data-only mode)
+    /// * `drop_tables` - If true, drop tables before creating schema
+    ///
+    /// # Returns
+    /// Total number of documents migrated and any collections that failed
+    pub async fn migrate(
+        &self,
+        collections: Vec<String>,
+        mode: MigrationMode,
+        truncate: bool,
+        drop_tables: bool,
+    ) -> Result<MigrationReport> {
+        info!("Starting migration of {} collection(s)", collections.len());
+
+        // Empty collections are counted up front so skipped ones get no table
+        let collections = if self.empty_policy == EmptyCollectionPolicy::Create {
+            collections
+        } else {
+            let mut kept = Vec::with_capacity(collections.len());
+            for collection_name in collections {
+                let count = self
+                    .source
+                    .count_documents(&self.database_name, &collection_name)
+                    .await?;
+                if self.empty_policy.keep(&collection_name, count)? {
+                    kept.push(collection_name);
+                } else {
+                    println!(
+                        "  {} {}: No documents (skipping)",
+                        "✓".green(),
+                        collection_name.cyan()
+                    );
+                }
+            }
+            kept
+        };
+
+        // Predefined schemas must cover every collection being migrated
+        if let Some(schemas) = &self.schemas {
+            let missing: Vec<&str> = collections
+                .iter()
+                .filter(|name| !schemas.contains_key(*name) && !self.merged_tables.contains_key(*name))
+                .map(String::as_str)
+                .collect();
+            if !missing.is_empty() {
+                bail!("Schema file has no schema for collection(s): {}", missing.join(", "));
+            }
+        }
+        
+        let mut report = MigrationReport {
+            continuation: self.continuation.clone(),
+            ..Default::default()
+        };
+
+        // Resolve table names over all collections so collisions get suffixes
+        let table_names = self.resolve_table_names(&collections);
+
+        let schema_phase = mode == MigrationMode::Full || mode == MigrationMode::SchemaOnly;
+
+        // Work out which schemas still need creating when resuming
+        let schema_collections = if schema_phase && self.resume_schema {
+            let completed = self.completed_schemas().await?;
+            let pending = pending_schema_collections(&collections, &completed, true);
+            for collection_name in collections.iter().filter(|name| !pending.contains(name)) {
+                println!(
+                    "  {} Schema already created: {} (skipping)",
+                    "✓".green(),
+                    collection_name.cyan()
+                );
+            }
+            pending
+        } else {
+            collections.clone()
+        };
+        // Merged collections share a table, which is created only once
+        let schema_collections = one_per_table(schema_collections, &table_names);
+
+        // Drop tables if requested (before schema migration)
+        if drop_tables && schema_phase {
+            println!("\n{}", "🗑️  Dropping existing tables...".yellow());
+            self.drop_tables(&schema_collections, &table_names).await?;
+        }
+
+        // Migrate schema if needed
+        if schema_phase {
+            println!("\n{}", "📋 Migrating schema...".yellow());
+            self.migrate_schemas(
+                &schema_collections,
+                &table_names,
+                &mut report.failures,
+                &mut report.timings,
+            )
+            .await?;
+        }
+
+        // Data-only inserts need every table in place before any row is written
+        if mode == MigrationMode::DataOnly {
+            let missing = self.missing_tables(&collections, &table_names).await?;
+            if !missing.is_empty() {
+                bail!(
+                    "Target table(s) do not exist: {}. Create the schema first, e.g. with --schema-only, before running --data-only",
+                    missing.join(", ")
+                );
+            }
+        }
+
+        // Truncate tables if requested (only for data-only mode)
+        if truncate && mode == MigrationMode::DataOnly {
+            println!("\n{}", "🗑️  Truncating existing tables...".yellow());
+            self.truncate_tables(&one_per_table(collections.clone(), &table_names), &table_names)
+                .await?;
+        }
+
+        // Migrate data if needed
+        if mode == MigrationMode::Full || mode == MigrationMode::DataOnly {
+            println!("\n{}", "📦 Migrating data...".yellow());
+            // Collections whose table could not be created have nothing to insert into
+            let data_collections: Vec<String> = collections
+                .into_iter()
+                .filter(|name| {
+                    let table_name = table_name_for(&table_names, name);
+                    !report
+                        .failures
+                        .iter()
+                        .any(|f| table_name_for(&table_names, &f.collection) == table_name)
+                })
+                .collect();
+            report.total_documents = self
+                .migrate_data(
+                    &data_collections,
+                    &table_names,
+                    &mut report.failures,
+                    &mut report.timings,
+                    &mut report.invalid_documents,
+                    report.continuation.as_mut(),
+                )
+                .await?;
+        }
+
+        if self.analyze {
+            self.run_analyze().await?;
+        }
+
+        Ok(report)
+    }
+
+    /// Collect query planner statistics with `ANALYZE`
+    async fn run_analyze(&self) -> Result<()> {
+        match self.backend.execute("ANALYZE").await {
+            Ok(_) => {
+                println!("  {} Collected query planner statistics (ANALYZE)", "✓".green());
+                Ok(())
+            }
+            Err(e) if !self.backend.is_local() => {
+                warn!("Skipping ANALYZE: the remote database rejected it: {}", e);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Find the target tables of the collections that don't exist yet
+    ///
+    /// # Arguments
+    /// * `collections` - Collections to check
+    /// * `table_names` - Target table name per collection
+    ///
+    /// # Returns
+    /// The missing table names, in collection order
+    async fn missing_tables(
+        &self,
+        collections: &[String],
+        table_names: &HashMap<String, String>,
+    ) -> Result<Vec<String>> {
+        let mut missing = Vec::new();
+        for collection_name in one_per_table(collections.to_vec(), table_names) {
+            let table_name = table_name_for(table_names, &collection_name);
+            if !self.backend.table_exists(table_name).await? {
+                missing.push(table_name.to_string());
+            }
+        }
+        Ok(missing)
+    }
+
+    /// Compare the schema a migration would create with the existing tables
+    ///
+    /// Infers each collection's schema as `migrate` would, including renames
+    /// and extra columns, and reads the target table with `PRAGMA
+    /// table_info`. Nothing is written.
+    ///
+    /// # Arguments
+    /// * `collections` - Collections to check
+    ///
+    /// # Returns
+    /// One check per target table, in collection order
+    pub async fn check_schemas(&self, collections: &[String]) -> Result<Vec<SchemaCheck>> {
+        let table_names = self.resolve_table_names(collections);
+        let mut checks = Vec::new();
+
+        for collection_name in one_per_table(collections.to_vec(), &table_names) {
+            let table_name = table_name_for(&table_names, &collection_name);
+            let schema = match self.predefined_schema(&collection_name) {
+                Some(schema) => schema.clone(),
+                None => {
+                    let documents = self
+                        .source
+                        .sample_documents(&self.database_name, &collection_name, self.sample_size)
+                        .await?;
+                    SchemaInferrer::infer_schema(&collection_name, &documents, &self.inference_options)?
+                }
+            };
+            let schema = self.target_schema(schema, table_name)?;
+
+            let table_columns = self.backend.table_column_types(table_name).await?;
+            let new_table = table_columns.is_empty();
+            checks.push(SchemaCheck {
+                table: table_name.to_string(),
+                new_table,
+                changes: if new_table {
+                    Vec::new()
+                } else {
+                    classify_schema_changes(&schema.fields, &table_columns)
+                },
+            });
+        }
+
+        Ok(checks)
+    }
+
+    /// Read the source's current operation time, to watch changes from later
+    ///
+    /// # Returns
+    /// The operation time, or `None` if the source has none
+    pub async fn operation_time(&self) -> Result<Option<Timestamp>> {
+        self.source.operation_time(&self.database_name).await
+    }
+
+    /// Apply change stream events to the target tables until interrupted
+    ///
+    /// Inserts, replaces and updates are upserted on `_id`; deletes remove
+    /// the row. After each event its resume token is stored in
+    /// `_migration_meta`, so a later run continues after the last applied
+    /// event. Stops on Ctrl+C or when a stream is closed.
+    ///
+    /// # Arguments
+    /// * `collections` - Collections to watch; their tables must already exist
+    /// * `start_at` - Operation time from `operation_time`, read before the
+    ///   tables were copied, so changes made during the copy are applied too.
+    ///   Collections with a stored resume token continue from that instead.
+    ///
+    /// # Returns
+    /// Number of change events applied
+    pub async fn watch(&self, collections: &[String], start_at: Option<Timestamp>) -> Result<u64> {
+        let table_names = self.styled_table_names(collections);
+        let mut targets = Vec::with_capacity(collections.len());
+        let mut streams = Vec::with_capacity(collections.len());
+
+        for (index, (collection_name, table_name)) in collections.iter().zip(&table_names).enumerate() {
+            let schema = match self.predefined_schema(collection_name) {
+                Some(schema) => schema.clone(),
+                None => {
+                    let sample_docs = self
+                        .source
+                        .sample_documents(&self.database_name, collection_name, self.sample_size)
+                        .await?;
+                    SchemaInferrer::infer_schema(collection_name, &sample_docs, &self.inference_options)?
+                }
+            };
+            let schema = self.target_schema(schema, table_name)?;
+            let statements = ChangeStatements::new(&schema, self.quote_style)
+                .map_err(|e| e.context(format!("Cannot watch {}", collection_name)))?;
+
+            let resume_after = match self.get_meta(collection_name, RESUME_TOKEN_KEY).await? {
+                Some(text) => {
+                    info!("Resuming change stream for {}", collection_name);
+                    Some(decode_resume_token(&text)?)
+                }
+                None => None,
+            };
+
+            let events = self
+                .source
+                .watch_changes(&self.database_name, collection_name, resume_after, start_at)
+                .await?;
+            streams.push(events.map(move |event| (index, event)).boxed());
+            targets.push((collection_name.clone(), schema, statements));
+        }
+
+        println!(
+            "\n{}",
+            format!("👀 Watching {} collection(s) for changes (Ctrl+C to stop)...", collections.len())
+                .yellow()
+        );
+
+        let mut events = stream::select_all(streams);
+        let interrupted = tokio::signal::ctrl_c();
+        tokio::pin!(interrupted);
+
+        let mut applied = 0u64;
+        let mut warned_fields = HashSet::new();
+
+        loop {
+            let next = tokio::select! {
+                next = events.next() => next,
+                _ = &mut interrupted => {
+                    println!("  {} Stopped watching", "✓".green());
+                    break;
+                }
+            };
+            let Some((index, event)) = next else {
+                break;
+            };
+
+            let (collection_name, schema, statements) = &targets[index];
+            let event = event?;
+            let action = change_action(&event)
+                .map_err(|e| e.context(format!("Watching {}", collection_name)))?;
+
+            if let Some((sql, values)) =
+                statements.statement(&action, schema, &self.converter_options, &mut warned_fields)
+            {
+                self.backend
+                    .execute_with_params(sql, values)
+                    .await?;
+                applied += 1;
+                self.emit(ProgressEvent::BatchInserted {
+                    collection: collection_name.clone(),
+                    count: 1,
+                });
+            }
+
+            self.set_meta(collection_name, RESUME_TOKEN_KEY, Some(encode_resume_token(&event.id)?))
+                .await?;
+        }
+
+        Ok(applied)
+    }
+
+    /// Handle a failed collection according to the error policy
+    ///
+    /// Without continue-on-error the error is returned to abort the run;
+    /// otherwise it is logged and recorded in `failures`.
+    fn record_failure(
+        &self,
+        collection_name: &str,
+        phase: MigrationPhase,
+        error: anyhow::Error,
+        failures: &mut Vec<CollectionFailure>,
+    ) -> Result<()> {
+        if !self.continue_on_error {
+            return Err(error);
+        }
+
+        warn!("{} migration failed for {}: {:#}", phase, collection_name, error);
+        println!(
+            "  {} Failed to migrate {} of {}: {}",
+            "✗".red(),
+            phase,
+            collection_name.cyan(),
+            format!("{:#}", error).red()
+        );
+        failures.push(CollectionFailure {
+            collection: collection_name.to_string(),
+            phase,
+            error: format!("{:#}", error),
+        });
+        Ok(())
+    }
+
+    /// Drop tables completely (removes schema and data)
+    async fn drop_tables(
+        &self,
+        collections: &[String],
+        table_names: &HashMap<String, String>,
+    ) -> Result<()> {
+        for collection_name in collections {
+            let table_name = table_name_for(table_names, collection_name);
+            let sql = format!(
+                "DROP TABLE IF EXISTS {}",
+                escape_identifier(table_name, self.quote_style)
+            );
+            debug!("Dropping table: {}", table_name);
+
+            for field in &self.lookup_fields {
+                let lookup_table = lookup_table_name(table_name, field);
+                self.backend
+                    .execute(&format!(
+                        "DROP TABLE IF EXISTS {}",
+                        escape_identifier(&lookup_table, self.quote_style)
+                    ))
+                    .await?;
+            }
+
+            match self.backend.execute(&sql).await {
+                Ok(_) => {
+                    self.clear_meta(collection_name, SCHEMA_CHECKPOINT_KEY).await?;
+                    println!("  {} Dropped table: {}", 
+                        "✓".green(), 
+                        table_name.cyan()
+                    );
+                }
+                Err(e) => {
+                    warn!("Failed to drop table {}: {}", table_name, e);
+                    // Continue with other tables even if one fails
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Truncate (delete all data from) tables
+    async fn truncate_tables(
+        &self,
+        collections: &[String],
+        table_names: &HashMap<String, String>,
+    ) -> Result<()> {
+        for collection_name in collections {
+            let table_name = table_name_for(table_names, collection_name);
+            let sql = format!(
+                "DELETE FROM {}",
+                escape_identifier(table_name, self.quote_style)
+            );
+            debug!("Truncating table: {}", table_name);
+            
+            match self.backend.execute(&sql).await {
+                Ok(affected) => {
+                    println!("  {} Truncated table: {} ({} rows deleted)", 
+                        "✓".green(), 
+                        table_name.cyan(),
+                        affected
+                    );
+                }
+                Err(e) => {
+                    warn!("Failed to truncate table {}: {}", table_name, e);
+                    // Continue with other tables even if one fails
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Migrate schemas for all collections
+    async fn migrate_schemas(
+        &self,
+        collections: &[String],
+        table_names: &HashMap<String, String>,
+        failures: &mut Vec<CollectionFailure>,
+        timings: &mut PhaseTimings,
+    ) -> Result<()> {
+        for collection_name in collections {
+            let table_name = table_name_for(table_names, collection_name);
+            if let Err(e) = self.migrate_schema(collection_name, table_name, timings).await {
+                self.record_failure(collection_name, MigrationPhase::Schema, e, failures)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Migrate schema for a single collection
+    async fn migrate_schema(
+        &self,
+        collection_name: &str,
+        table_name: &str,
+        timings: &mut PhaseTimings,
+    ) -> Result<()> {
+        debug!("Migrating schema for collection: {}", collection_name);
+        let started = Instant::now();
+        let mut sampling = Duration::ZERO;
+
+        let (schema, field_infos) = match self.predefined_schema(collection_name) {
+            Some(schema) => (schema.clone(), Vec::new()),
+            None => {
+                // Sample documents for schema inference
+                let sampling_started = Instant::now();
+                let documents = self
+                    .source
+                    .sample_documents(&self.database_name, collection_name, self.sample_size)
+                    .await?;
+                sampling = sampling_started.elapsed();
+
+                SchemaInferrer::infer_schema_explained(
+                    collection_name,
+                    &documents,
+                    &self.inference_options,
+                )?
+            }
+        };
+        let schema = self.target_schema(schema, table_name)?;
+        let field_infos: Vec<(String, FieldInfo)> = field_infos
+            .into_iter()
+            .map(|(key, info)| (schema.column_for(&key).unwrap_or(key.as_str()).to_string(), info))
+            .collect();
+
+        // Generate and execute CREATE TABLE statement
+        let generated_columns = if self.generate_json_columns {
+            json_generated_columns(&schema, &field_infos, self.quote_style)
+        } else {
+            Vec::new()
+        };
+        let create_table_sql = schema.to_create_table_sql_with_generated(
+            self.quote_style,
+            self.text_collation,
+            &generated_columns,
+        );
+        debug!("CREATE TABLE SQL: {}", create_table_sql);
+
+        self.create_lookup_tables(&schema).await?;
+        self.backend.execute(&create_table_sql).await?;
+        if self.evolve_schema {
+            self.evolve_table(&schema).await?;
+        }
+        self.set_meta(
+            collection_name,
+            SCHEMA_CHECKPOINT_KEY,
+            Some(chrono::Utc::now().to_rfc3339()),
+        )
+        .await?;
+
+        println!(
+            "  {} Created table: {} ({} columns)",
+            "✓".green(),
+            table_name.cyan(),
+            schema.fields.len().to_string().cyan()
+        );
+
+        if self.json_views {
+            self.create_json_view(&schema, &field_infos).await?;
+        }
+
+        if self.explain_schema {
+            print_schema_explanation(&field_infos);
+        }
+
+        if self.bson_type_metadata {
+            self.record_bson_types(collection_name, &field_infos).await?;
+        }
+
+        if let Some(capped) = self.capped_info(collection_name).await? {
+            warn!(
+                "Collection {} is capped; the cap is recorded in _migration_meta but not enforced in SQLite",
+                collection_name
+            );
+            self.record_capped_meta(collection_name, &capped).await?;
+        }
+
+        timings.add(collection_name, TimedPhase::Sampling, sampling);
+        timings.add(collection_name, TimedPhase::Schema, started.elapsed() - sampling);
+        Ok(())
+    }
+
+    /// Replace the `--create-json-views` view of a table
+    async fn create_json_view(
+        &self,
+        schema: &CollectionSchema,
+        field_infos: &[(String, FieldInfo)],
+    ) -> Result<()> {
+        let view_name = json_view_name(&schema.collection_name);
+        // Recreate the view so keys found by this run's sample are included
+        self.backend
+            .execute(&format!(
+                "DROP VIEW IF EXISTS {}",
+                escape_identifier(&view_name, self.quote_style)
+            ))
+            .await?;
+
+        let Some(create_view_sql) = json_view_sql(schema, field_infos, self.quote_style) else {
+            return Ok(());
+        };
+        debug!("CREATE VIEW SQL: {}", create_view_sql);
+        self.backend.execute(&create_view_sql).await?;
+        println!("  {} Created view: {}", "✓".green(), view_name.cyan());
+
+        Ok(())
+    }
+
+    /// Look up the capped-collection settings of a collection
+    ///
+    /// The source is asked once per collection; the data phase reuses the
+    /// answer the schema phase got.
+    async fn capped_info(&self, collection_name: &str) -> Result<Option<CappedInfo>> {
+        if let Some(capped) = self.lock_capped().get(collection_name) {
+            return Ok(capped.clone());
+        }
+
+        let capped = self
+            .source
+            .capped_info(&self.database_name, collection_name)
+            .await?;
+        self.lock_capped()
+            .insert(collection_name.to_string(), capped.clone());
+        Ok(capped)
+    }
+
+    fn lock_capped(&self) -> std::sync::MutexGuard<'_, HashMap<String, Option<CappedInfo>>> {
+        // The map is only read and extended, so a poisoned lock is still usable
+        self.capped.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record a capped collection's size limits in the `_migration_meta` table
+    async fn record_capped_meta(&self, collection_name: &str, capped: &CappedInfo) -> Result<()> {
+        let entries = [
+            ("capped", Some("true".to_string())),
+            ("capped_size", capped.size.map(|size| size.to_string())),
+            ("capped_max", capped.max.map(|max| max.to_string())),
+        ];
+
+        for (key, value) in entries {
+            self.set_meta(collection_name, key, value).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace a collection's rows in the `_bson_types` metadata table
+    async fn record_bson_types(
+        &self,
+        collection_name: &str,
+        field_infos: &[(String, FieldInfo)],
+    ) -> Result<()> {
+        self.backend
+            .execute(
+                "CREATE TABLE IF NOT EXISTS \"_bson_types\" (\n  \
+                 \"collection\" TEXT NOT NULL,\n  \
+                 \"column\" TEXT NOT NULL,\n  \
+                 \"bson_type\" TEXT NOT NULL,\n  \
+                 \"count\" INTEGER NOT NULL,\n  \
+                 PRIMARY KEY (\"collection\", \"column\", \"bson_type\")\n)",
+            )
+            .await?;
+
+        self.backend
+            .execute_with_params(
+                "DELETE FROM \"_bson_types\" WHERE \"collection\" = ?",
+                vec![libsql::Value::Text(collection_name.to_string())],
+            )
+            .await?;
+
+        for row in bson_type_rows(field_infos) {
+            self.backend
+                .execute_with_params(
+                    "INSERT INTO \"_bson_types\" (\"collection\", \"column\", \"bson_type\", \"count\") VALUES (?, ?, ?, ?)",
+                    vec![
+                        libsql::Value::Text(collection_name.to_string()),
+                        libsql::Value::Text(row.column),
+                        libsql::Value::Text(row.bson_type),
+                        libsql::Value::Integer(row.count as i64),
+                    ],
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Create the `_migration_meta` table if it does not exist yet
+    async fn ensure_meta_table(&self) -> Result<()> {
+        self.backend
+            .execute(
+                "CREATE TABLE IF NOT EXISTS \"_migration_meta\" (\n  \
+                 \"collection\" TEXT NOT NULL,\n  \
+                 \"key\" TEXT NOT NULL,\n  \
+                 \"value\" TEXT,\n  \
+                 PRIMARY KEY (\"collection\", \"key\")\n)",
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Set a per-collection entry in the `_migration_meta` table
+    async fn set_meta(&self, collection_name: &str, key: &str, value: Option<String>) -> Result<()> {
+        self.ensure_meta_table().await?;
+
+        let value = value.map_or(libsql::Value::Null, libsql::Value::Text);
+        self.backend
+            .execute_with_params(
+                "INSERT OR REPLACE INTO \"_migration_meta\" (\"collection\", \"key\", \"value\") VALUES (?, ?, ?)",
+                vec![
+                    libsql::Value::Text(collection_name.to_string()),
+                    libsql::Value::Text(key.to_string()),
+                    value,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Restore the SQLite settings of the target after a `--fast-load` migration
+    ///
+    /// See [`LibSqlClient::finish_fast_load`].
+    ///
+    /// # Arguments
+    /// * `saved` - The values returned by the backend's `enable_fast_load`
+    pub async fn finish_fast_load(&self, saved: &SavedPragmas) -> Result<()> {
+        self.backend.finish_fast_load(saved).await
+    }
+
+    /// Run a post-migration SQL script against the target in one transaction
+    ///
+    /// If a statement fails, the whole script is rolled back.
+    ///
+    /// # Arguments
+    /// * `statements` - Statements, as split by [`split_sql_statements`]
+    ///
+    /// # Returns
+    /// Number of statements run
+    ///
+    /// [`split_sql_statements`]: crate::libsql_client::split_sql_statements
+    pub async fn run_post_sql(&self, statements: &[String]) -> Result<usize> {
+        self.backend.execute_batch(statements.to_vec()).await?;
+        Ok(statements.len())
+    }
+
+    /// Verify the target database file with `PRAGMA integrity_check`
+    ///
+    /// Remote Turso databases are managed by the service and are skipped.
+    ///
+    /// # Returns
+    /// Ok if the database is intact, or `MigrationError::IntegrityCheckFailed`
+    /// listing the reported problems
+    pub async fn check_integrity(&self) -> Result<()> {
+        if !self.backend.is_local() {
+            warn!("Skipping integrity check: only local database files are checked");
+            return Ok(());
+        }
+
+        let problems = self.backend.integrity_check().await?;
+        if !problems.is_empty() {
+            return Err(MigrationError::IntegrityCheckFailed(problems).into());
+        }
+
+        println!("  {} Integrity check passed", "✓".green());
+        Ok(())
+    }
+
+    /// Compute per-column statistics and write them to `_field_stats`
+    ///
+    /// Runs one aggregate query per column of every migrated table, so it is
+    /// an extra pass over the data. Rows of a table are replaced on each run.
+    ///
+    /// # Arguments
+    /// * `collections` - Collections whose tables are profiled
+    ///
+    /// # Returns
+    /// The statistics, in table and column order
+    pub async fn profile(&self, collections: &[String]) -> Result<Vec<ColumnStats>> {
+        self.backend
+            .execute(
+                "CREATE TABLE IF NOT EXISTS \"_field_stats\" (\n  \
+                 \"table\" TEXT NOT NULL,\n  \
+                 \"column\" TEXT NOT NULL,\n  \
+                 \"non_null_count\" INTEGER NOT NULL,\n  \
+                 \"distinct_count\" INTEGER NOT NULL,\n  \
+                 \"min\" REAL,\n  \
+                 \"max\" REAL,\n  \
+                 PRIMARY KEY (\"table\", \"column\")\n)",
+            )
+            .await?;
+
+        let table_names = self.resolve_table_names(collections);
+        let mut all_stats = Vec::new();
+        for collection_name in one_per_table(collections.to_vec(), &table_names) {
+            let table = table_name_for(&table_names, &collection_name).to_string();
+            self.backend
+                .execute_with_params(
+                    "DELETE FROM \"_field_stats\" WHERE \"table\" = ?",
+                    vec![libsql::Value::Text(table.clone())],
+                )
+                .await?;
+
+            for (column, sql_type) in self.backend.table_column_types(&table).await? {
+                let sql = column_stats_sql(&table, &column, &sql_type, self.quote_style);
+                let rows = self.backend.query(&sql).await?;
+                let Some(row) = rows.first() else {
+                    continue;
+                };
+                let count = |index: usize| -> Result<u64> { Ok(row.integer(index)?.max(0) as u64) };
+                let stats = ColumnStats {
+                    table: table.clone(),
+                    column,
+                    non_null: count(0)?,
+                    distinct: count(1)?,
+                    min: stat_number(row.value(2)?),
+                    max: stat_number(row.value(3)?),
+                };
+
+                let to_value = |number: Option<f64>| number.map_or(libsql::Value::Null, libsql::Value::Real);
+                self.backend
+                    .execute_with_params(
+                        "INSERT INTO \"_field_stats\" (\"table\", \"column\", \"non_null_count\", \"distinct_count\", \"min\", \"max\") VALUES (?, ?, ?, ?, ?, ?)",
+                        vec![
+                            libsql::Value::Text(stats.table.clone()),
+                            libsql::Value::Text(stats.column.clone()),
+                            libsql::Value::Integer(stats.non_null as i64),
+                            libsql::Value::Integer(stats.distinct as i64),
+                            to_value(stats.min),
+                            to_value(stats.max),
+                        ],
+                    )
+                    .await?;
+                all_stats.push(stats);
+            }
+        }
+
+        Ok(all_stats)
+    }
+
+    /// Measure the approximate size of every column of the migrated tables
+    ///
+    /// Only local database files are analyzed; on Turso the per-column scans
+    /// would read every row over the network, so the analysis is skipped.
+    ///
+    /// # Arguments
+    /// * `collections` - Collections whose tables to analyze
+    ///
+    /// # Returns
+    /// Column sizes, largest first
+    pub async fn analyze_storage(&self, collections: &[String]) -> Result<Vec<ColumnSize>> {
+        if !self.backend.is_local() {
+            warn!("Skipping storage analysis: only local database files are analyzed");
+            return Ok(Vec::new());
+        }
+
+        let table_names = self.resolve_table_names(collections);
+        let mut sizes = Vec::new();
+        for collection_name in one_per_table(collections.to_vec(), &table_names) {
+            let table = table_name_for(&table_names, &collection_name).to_string();
+            for column in self.backend.table_columns(&table).await? {
+                let sql = column_size_sql(&table, &column, self.quote_style);
+                let rows = self.backend.query(&sql).await?;
+                let bytes = match rows.first() {
+                    Some(row) => row.integer(0)?.max(0) as u64,
+                    None => 0,
+                };
+                sizes.push(ColumnSize { table: table.clone(), column, bytes });
+            }
+        }
+
+        sizes.sort_by(|a, b| {
+            b.bytes
+                .cmp(&a.bytes)
+                .then_with(|| a.table.cmp(&b.table))
+                .then_with(|| a.column.cmp(&b.column))
+        });
+        Ok(sizes)
+    }
+
+    /// Read a per-collection entry from the `_migration_meta` table
+    async fn get_meta(&self, collection_name: &str, key: &str) -> Result<Option<String>> {
+        self.ensure_meta_table().await?;
+
+        let rows = self
+            .backend
+            .query_with_params(
+                "SELECT \"value\" FROM \"_migration_meta\" WHERE \"collection\" = ? AND \"key\" = ?",
+                vec![
+                    libsql::Value::Text(collection_name.to_string()),
+                    libsql::Value::Text(key.to_string()),
+                ],
+            )
+            .await?;
+
+        match rows.first() {
+            Some(row) => row.optional_text(0),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove a per-collection entry from the `_migration_meta` table
+    async fn clear_meta(&self, collection_name: &str, key: &str) -> Result<()> {
+        self.ensure_meta_table().await?;
+
+        self.backend
+            .execute_with_params(
+                "DELETE FROM \"_migration_meta\" WHERE \"collection\" = ? AND \"key\" = ?",
+                vec![
+                    libsql::Value::Text(collection_name.to_string()),
+                    libsql::Value::Text(key.to_string()),
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Read the collections whose schema a previous run already created
+    async fn completed_schemas(&self) -> Result<HashSet<String>> {
+        self.ensure_meta_table().await?;
+
+        let rows = self
+            .backend
+            .query(&format!(
+                "SELECT \"collection\" FROM \"_migration_meta\" WHERE \"key\" = '{}'",
+                SCHEMA_CHECKPOINT_KEY
+            ))
+            .await?;
+
+        let mut completed = HashSet::new();
+        for row in rows {
+            completed.insert(row.text(0)?);
+        }
+
+        Ok(completed)
+    }
+
+    /// Migrate data for all collections
+    async fn migrate_data(
+        &self,
+        collections: &[String],
+        table_names: &HashMap<String, String>,
+        failures: &mut Vec<CollectionFailure>,
+        timings: &mut PhaseTimings,
+        invalid_documents: &mut Vec<InvalidDocument>,
+        mut continuation: Option<&mut ContinuationToken>,
+    ) -> Result<usize> {
+        let mut total_documents = 0;
+
+        for collection_name in collections {
+            let table_name = table_name_for(table_names, collection_name);
+            let result = self
+                .migrate_collection_data(
+                    collection_name,
+                    table_name,
+                    timings,
+                    invalid_documents,
+                    continuation.as_deref_mut(),
+                )
+                .await;
+            match result {
+                Ok(count) => total_documents += count,
+                Err(e) => {
+                    self.record_failure(collection_name, MigrationPhase::Data, e, failures)?
+                }
+            }
+        }
+
+        Ok(total_documents)
+    }
+
+    /// Create the unique index `--dedup-strategy last-wins-sql` replaces rows through
+    ///
+    /// # Arguments
+    /// * `schema` - Schema of the target table
+    /// * `field` - MongoDB field given to `--dedup-by`
+    async fn create_dedup_index(&self, schema: &CollectionSchema, field: &str) -> Result<()> {
+        let Some(column) = schema.column_for(field) else {
+            bail!(
+                "--dedup-by field '{}' is not a column of {}",
+                field,
+                schema.collection_name
+            );
+        };
+
+        let index_name = format!("{}_{}_dedup", schema.collection_name, column);
+        self.backend
+            .execute(&format!(
+                "CREATE UNIQUE INDEX IF NOT EXISTS {} ON {} ({})",
+                escape_identifier(&index_name, self.quote_style),
+                escape_identifier(&schema.collection_name, self.quote_style),
+                escape_identifier(column, self.quote_style)
+            ))
+            .await
+            .map_err(|e| {
+                e.context(format!(
+                    "Failed to create a unique index on {}.{} for --dedup-by; the table may already hold duplicates",
+                    schema.collection_name, column
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    /// Restrict a schema to the columns of its existing target table
+    ///
+    /// Fails if the table doesn't exist, since `--map-to-existing` never
+    /// creates tables.
+    async fn map_to_existing_table(&self, schema: CollectionSchema) -> Result<CollectionSchema> {
+        let table_columns = self
+            .backend
+            .table_columns(&schema.collection_name)
+            .await?;
+        if table_columns.is_empty() {
+            bail!(
+                "--map-to-existing: table {} does not exist in the target",
+                schema.collection_name
+            );
+        }
+
+        let mapping = map_fields_to_columns(&schema.fields, &table_columns);
+        if mapping.fields.is_empty() {
+            bail!(
+                "--map-to-existing: no field matches a column of table {}",
+                schema.collection_name
+            );
+        }
+        if !mapping.unmapped_fields.is_empty() {
+            warn!(
+                "Table {} has no column for field(s) {}; they are not migrated",
+                schema.collection_name,
+                mapping.unmapped_fields.join(", ")
+            );
+        }
+        if !mapping.unfilled_columns.is_empty() {
+            debug!(
+                "Column(s) {} of {} keep their defaults",
+                mapping.unfilled_columns.join(", "),
+                schema.collection_name
+            );
+        }
+
+        Ok(CollectionSchema {
+            fields: mapping.fields,
+            ..schema
+        })
+    }
+
+    /// Compare the schema with the existing target table before inserting
+    ///
+    /// Mismatched columns are reported; inferred columns missing from the
+    /// table are added as nullable columns if `alter_add_columns` is set.
+    async fn check_table_columns(&self, schema: &CollectionSchema) -> Result<()> {
+        let table_columns = self
+            .backend
+            .table_columns(&schema.collection_name)
+            .await?;
+        if table_columns.is_empty() {
+            return Ok(());
+        }
+
+        let diff = diff_columns(&schema.fields, &table_columns);
+
+        for column in &diff.missing_in_source {
+            warn!(
+                "Table {} has column {} that no MongoDB field maps to; it will be left NULL",
+                schema.collection_name, column
+            );
+        }
+
+        for field in schema
+            .fields
+            .iter()
+            .filter(|field| diff.missing_in_table.contains(&field.name))
+        {
+            if !self.alter_add_columns {
+                warn!(
+                    "Table {} has no column {} (inferred {}); inserts will fail unless --alter-add-columns is set",
+                    schema.collection_name, field.name, field.sql_type
+                );
+                continue;
+            }
+
+            self.add_column(schema, field).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Add columns for newly inferred fields to an existing table
+    ///
+    /// Used by `--evolve-schema` right after `CREATE TABLE IF NOT EXISTS`,
+    /// which leaves an existing table untouched.
+    async fn evolve_table(&self, schema: &CollectionSchema) -> Result<()> {
+        let table_columns = self
+            .backend
+            .table_columns(&schema.collection_name)
+            .await?;
+
+        for field in schema.fields.iter().filter(|field| field.is_primary_key) {
+            if !table_columns.iter().any(|column| column.eq_ignore_ascii_case(&field.name)) {
+                warn!(
+                    "Table {} has no primary key column {}; SQLite cannot add a primary key to an existing table",
+                    schema.collection_name, field.name
+                );
+            }
+        }
+
+        for field in columns_to_add(&schema.fields, &table_columns) {
+            self.add_column(schema, field).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Run `ALTER TABLE ... ADD COLUMN` for one field
+    ///
+    /// SQLite only adds columns that are nullable or have a constant
+    /// default, so the column is always added as nullable.
+    async fn add_column(&self, schema: &CollectionSchema, field: &Field) -> Result<()> {
+        self.backend
+            .execute(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}{}{}",
+                escape_identifier(&schema.collection_name, self.quote_style),
+                escape_identifier(&field.name, self.quote_style),
+                field.sql_type,
+                self.text_collation.clause(field),
+                field.references_clause(self.quote_style)
+            ))
+            .await?;
+        println!(
+            "  {} Added column {} {} to {}",
+            "✓".green(),
+            field.name.cyan(),
+            field.sql_type,
+            schema.collection_name.cyan()
+        );
+
+        Ok(())
+    }
+
+    /// Create the lookup tables a schema's `--lookup-table` columns reference
+    async fn create_lookup_tables(&self, schema: &CollectionSchema) -> Result<()> {
+        for lookup_table in schema.fields.iter().filter_map(|field| field.references.as_deref()) {
+            self.backend
+                .execute(&lookup_table_sql(lookup_table, self.quote_style))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Prepare a value cache for each lookup column, seeded from its lookup table
+    async fn load_lookups(&self, schema: &CollectionSchema) -> Result<Vec<ColumnLookup>> {
+        self.create_lookup_tables(schema).await?;
+
+        let mut lookups = Vec::new();
+        for (index, field) in schema.fields.iter().enumerate() {
+            let Some(lookup_table) = field.references.as_deref() else {
+                continue;
+            };
+            let table = escape_identifier(lookup_table, self.quote_style);
+            let id = escape_identifier("id", self.quote_style);
+            let value = escape_identifier("value", self.quote_style);
+
+            let rows = self
+                .backend
+                .query(&format!("SELECT {}, {} FROM {}", id, value, table))
+                .await?;
+            let mut existing = Vec::new();
+            for row in rows {
+                existing.push((row.integer(0)?, row.text(1)?));
+            }
+
+            lookups.push(ColumnLookup {
+                index,
+                insert_sql: format!("INSERT INTO {} ({}, {}) VALUES (?, ?)", table, id, value),
+                cache: LookupCache::from_rows(existing),
+            });
+        }
+
+        Ok(lookups)
+    }
+
+    /// Replace lookup column values with their ids, adding new values to the lookup tables
+    async fn resolve_lookups(
+        &self,
+        values: &mut [libsql::Value],
+        lookups: &mut [ColumnLookup],
+    ) -> Result<()> {
+        for lookup in lookups.iter_mut() {
+            let Some(text) = lookup_value(&values[lookup.index]) else {
+                continue;
+            };
+            let (id, is_new) = lookup.cache.id_for(&text);
+            if is_new {
+                self.backend
+                    .execute_with_params(
+                        &lookup.insert_sql,
+                        vec![libsql::Value::Integer(id), libsql::Value::Text(text)],
+                    )
+                    .await?;
+            }
+            values[lookup.index] = libsql::Value::Integer(id);
+        }
+        Ok(())
+    }
+
+    /// Migrate data for a single collection
+    async fn migrate_collection_data(
+        &self,
+        collection_name: &str,
+        table_name: &str,
+        timings: &mut PhaseTimings,
+        invalid_documents: &mut Vec<InvalidDocument>,
+        continuation: Option<&mut ContinuationToken>,
+    ) -> Result<usize> {
+        debug!("Migrating data for collection: {}", collection_name);
+        let started = Instant::now();
+        let mut sampling = Duration::ZERO;
+
+        // Get total document count; a limited read expects at most the limit
+        let total_count = self
+            .source
+            .count_documents(&self.database_name, collection_name)
+            .await?;
+        let total_count = match self.limit {
+            Some(limit) => total_count.min(limit as u64),
+            None => total_count,
+        };
+
+        // A stream that can't be counted ahead has no known total
+        let exact_count = self.source.count_is_exact();
+        self.emit(ProgressEvent::CollectionStarted {
+            collection: collection_name.to_string(),
+            total: if exact_count { total_count } else { 0 },
+        });
+
+        if total_count == 0 {
+            println!(
+                "  {} {}: No documents to migrate",
+                "✓".green(),
+                collection_name.cyan()
+            );
+            self.emit(ProgressEvent::CollectionFinished {
+                collection: collection_name.to_string(),
+                total: 0,
+            });
+            timings.add(collection_name, TimedPhase::Data, started.elapsed());
+            return Ok(0);
+        }
+
+        let schema = match self.predefined_schema(collection_name) {
+            Some(schema) => schema.clone(),
+            None => {
+                // Sample documents to infer schema (needed for field ordering)
+                let sampling_started = Instant::now();
+                let sample_docs = self
+                    .source
+                    .sample_documents(&self.database_name, collection_name, self.sample_size)
+                    .await?;
+                sampling = sampling_started.elapsed();
+
+                SchemaInferrer::infer_schema(collection_name, &sample_docs, &self.inference_options)?
+            }
+        };
+        let schema = self.target_schema(schema, table_name)?;
+        let schema = if self.map_to_existing {
+            self.map_to_existing_table(schema).await?
+        } else {
+            self.check_table_columns(&schema).await?;
+            schema
+        };
+        let rows_per_statement = max_rows_per_statement(self.variable_limit, schema.fields.len());
+        if rows_per_statement == 0 {
+            bail!(
+                "{} has {} columns, more than the SQLite limit of {} bound parameters per statement (see --sqlite-variable-limit)",
+                table_name,
+                schema.fields.len(),
+                self.variable_limit
+            );
+        }
+        if self.batch_size > rows_per_statement {
+            debug!(
+                "Batch size {} exceeds the {} rows that fit in one statement for {}; rows are inserted one statement each",
+                self.batch_size, rows_per_statement, table_name
+            );
+        }
+        let insert_sql = match &self.dedup {
+            _ if self.upsert => schema.to_upsert_sql(self.quote_style)?,
+            Some((field, DedupStrategy::LastWinsSql)) => {
+                self.create_dedup_index(&schema, field).await?;
+                schema.to_insert_or_replace_sql(self.quote_style)
+            }
+            _ => schema.to_insert_sql(self.quote_style),
+        };
+        let key_index = schema.fields.iter().position(|field| field.is_primary_key);
+        let mut duplicate_filter = match &self.dedup {
+            Some((field, DedupStrategy::Memory)) => Some(DuplicateFilter::new(field)),
+            _ => None,
+        };
+        let mut lookups = self.load_lookups(&schema).await?;
+
+        // Computed columns are appended to the schema under their own names
+        let computed: Vec<(&ComputedColumn, usize)> = self
+            .computed_columns
+            .iter()
+            .filter_map(|column| {
+                let index = schema.fields.iter().position(|field| field.name == column.name)?;
+                Some((column, index))
+            })
+            .collect();
+
+        // Resolve the post-filter column once per collection
+        let post_filter = match self.post_filters.get(collection_name) {
+            Some(filter) => match filter.column_index(&schema.fields) {
+                Some(index) => Some((filter, index)),
+                None => bail!(
+                    "--post-filter for {} references unknown column '{}'",
+                    collection_name,
+                    filter.column
+                ),
+            },
+            None => None,
+        };
+
+        // Capped collections keep insertion order, so stream them in natural
+        // order unless the user asked for an explicit sort
+        let sort = match &self.sort {
+            Some(sort) => Some(sort.clone()),
+            None => self
+                .capped_info(collection_name)
+                .await?
+                .map(|_| doc! { "$natural": 1 }),
+        };
+
+        // Keep a remote target connection warm while documents stream; the
+        // heartbeat is aborted when dropped, including on early returns
+        let heartbeat = match self.heartbeat_interval {
+            Some(interval) if !self.backend.is_local() => {
+                self.backend.start_heartbeat(interval)
+            }
+            _ => None,
+        };
+
+        // Stream documents and insert in batches
+        let cursor = match (&continuation, sort) {
+            (Some(token), _) => {
+                self.source
+                    .stream_documents_after(
+                        &self.database_name,
+                        collection_name,
+                        token.last_id(collection_name).cloned(),
+                        self.limit,
+                    )
+                    .await?
+            }
+            (None, None) if self.read_parallelism > 1 => {
+                self.source
+                    .stream_documents_parallel(&self.database_name, collection_name, self.read_parallelism)
+                    .await?
+            }
+            (None, sort) => {
+                self.source
+                    .stream_documents(&self.database_name, collection_name, sort)
+                    .await?
+            }
+        };
+        let mut cursor = match self.limit {
+            Some(limit) => cursor.take(limit).boxed(),
+            None => cursor,
+        };
+
+        let mut batch = Vec::new();
+        let mut batch_bytes = 0;
+        let mut batch_memory = 0;
+        let mut memory_flushes = 0;
+        let mut total_migrated = 0;
+        let mut warned_fields = HashSet::new();
+        let mut skipped = 0;
+        let mut filtered = 0;
+        let mut duplicates = 0;
+        let mut conflicts = 0;
+        let mut invalid = 0;
+        let mut reported_skipped = 0;
+        let mut logged = 0;
+        let mut last_id = None;
+        // A continued read only covers the rest of the collection
+        let expect_all = continuation.is_none();
+
+        while let Some(doc) = cursor.try_next().await? {
+            if continuation.is_some() {
+                last_id = doc.get("_id").cloned();
+            }
+
+            if let Some(policy) = self.validate_bson {
+                if let Some(reason) = validate_document(&doc, self.strict_bson) {
+                    let id = doc.get("_id").map(|id| id.clone().into_relaxed_extjson().to_string());
+                    if policy == InvalidDocumentPolicy::Abort {
+                        bail!(
+                            "Invalid document {} in {}: {}",
+                            id.as_deref().unwrap_or("without _id"),
+                            collection_name,
+                            reason
+                        );
+                    }
+                    debug!("Skipping invalid document in {}: {}", collection_name, reason);
+                    invalid_documents.push(InvalidDocument {
+                        collection: collection_name.to_string(),
+                        id,
+                        reason,
+                    });
+                    invalid += 1;
+                    continue;
+                }
+            }
+
+            // Documents are only serialized when a size matters
+            let doc_bytes = if self.max_doc_bytes.is_some() || self.batch_bytes.is_some() {
+                bson::to_vec(&doc)?.len()
+            } else {
+                0
+            };
+
+            // Guard against oversized documents
+            if self.max_doc_bytes.is_some() {
+                let keep = check_document_size(doc_bytes, self.max_doc_bytes, self.oversize_policy)
+                    .map_err(|e| e.context(format!("Oversized document in {}", collection_name)))?;
+                if !keep {
+                    debug!("Skipping {}-byte document in {}", doc_bytes, collection_name);
+                    skipped += 1;
+                    continue;
+                }
+            }
+
+            if let Some(ref mut duplicate_filter) = duplicate_filter {
+                if !duplicate_filter.keep(&doc) {
+                    duplicates += 1;
+                    continue;
+                }
+            }
+
+            // Convert document to SQL values
+            let mut values = document_to_sql_values(
+                &doc,
+                &schema.fields,
+                &self.converter_options,
+                &mut warned_fields,
+            );
+            for (column, index) in &computed {
+                values[*index] = column.evaluate(&values, &schema.fields);
+            }
+
+            if let Some((filter, index)) = post_filter {
+                if !filter.matches(&values[index]) {
+                    filtered += 1;
+                    continue;
+                }
+            }
+
+            self.resolve_lookups(&mut values, &mut lookups).await?;
+            if self.max_memory.is_some() {
+                batch_memory += estimated_row_bytes(&values);
+            }
+            batch.push(values);
+            batch_bytes += doc_bytes;
+
+            // Report progress before the batch is flushed, if requested
+            let converted = total_migrated + batch.len();
+            if should_update_progress(converted, self.progress_interval) {
+                self.emit(ProgressEvent::DocumentsConverted {
+                    collection: collection_name.to_string(),
+                    converted,
+                });
+            }
+
+            // Insert batch when it reaches the batch size or byte size, or
+            // early when it approaches the memory budget
+            let full = should_flush_batch(batch.len(), batch_bytes, self.batch_size, self.batch_bytes);
+            if full || should_flush_for_memory(batch.len(), batch_memory, self.max_memory) {
+                if !full {
+                    debug!(
+                        "Flushing {} row(s) of {} early to stay under --max-memory-mb",
+                        batch.len(),
+                        collection_name
+                    );
+                    memory_flushes += 1;
+                }
+                let paused = match &heartbeat {
+                    Some(heartbeat) => Some(heartbeat.pause().await),
+                    None => None,
+                };
+                let conflicting = self.insert_batch(&insert_sql, &batch, key_index).await?;
+                drop(paused);
+                conflicts += conflicting;
+                total_migrated += batch.len() - conflicting;
+                self.log_progress(collection_name, total_migrated, total_count, &mut logged);
+                self.emit(ProgressEvent::BatchInserted {
+                    collection: collection_name.to_string(),
+                    count: batch.len() - conflicting,
+                });
+                self.report_skipped(
+                    collection_name,
+                    skipped + filtered + duplicates + conflicts + invalid,
+                    &mut reported_skipped,
+                );
+                batch.clear();
+                batch_bytes = 0;
+                batch_memory = 0;
+            }
+        }
+
+        // Reading is done, so the connection no longer idles
+        if let Some(heartbeat) = heartbeat {
+            let beats = heartbeat.stop().await;
+            debug!("Sent {} heartbeat(s) while migrating {}", beats, collection_name);
+        }
+
+        // Insert remaining documents
+        if !batch.is_empty() {
+            let conflicting = self.insert_batch(&insert_sql, &batch, key_index).await?;
+            conflicts += conflicting;
+            total_migrated += batch.len() - conflicting;
+            self.emit(ProgressEvent::BatchInserted {
+                collection: collection_name.to_string(),
+                count: batch.len() - conflicting,
+            });
+        }
+        self.report_skipped(
+            collection_name,
+            skipped + filtered + duplicates + conflicts + invalid,
+            &mut reported_skipped,
+        );
+        if self.progress_log_interval.is_some() && total_migrated > logged {
+            info!("{}: {}/{} (done)", collection_name, total_migrated, total_count);
+        }
+
+        if let (Some(token), Some(last_id)) = (continuation, last_id) {
+            token.record(collection_name, last_id);
+        }
+
+        self.emit(ProgressEvent::CollectionFinished {
+            collection: collection_name.to_string(),
+            total: total_migrated,
+        });
+
+        if filtered > 0 {
+            println!(
+                "  {} {}: {} row(s) excluded by --post-filter",
+                "✓".green(),
+                collection_name.cyan(),
+                filtered.to_string().cyan()
+            );
+        }
+
+        if duplicates > 0 {
+            println!(
+                "  {} {}: skipped {} duplicate document(s) by --dedup-by",
+                "✓".green(),
+                collection_name.cyan(),
+                duplicates.to_string().cyan()
+            );
+        }
+
+        if conflicts > 0 {
+            println!(
+                "  {} {}: skipped {} conflicting row(s) by --skip-conflicts",
+                "⚠".yellow(),
+                collection_name.cyan(),
+                conflicts.to_string().yellow()
+            );
+        }
+
+        if memory_flushes > 0 {
+            info!(
+                "{}: {} batch(es) inserted early to stay under --max-memory-mb",
+                collection_name, memory_flushes
+            );
+        }
+
+        if invalid > 0 {
+            println!(
+                "  {} {}: skipped {} invalid document(s) by --validate-bson",
+                "⚠".yellow(),
+                collection_name.cyan(),
+                invalid.to_string().yellow()
+            );
+        }
+
+        if skipped > 0 {
+            warn!(
+                "Skipped {} document(s) in {} larger than --max-doc-bytes",
+                skipped, collection_name
+            );
+            println!(
+                "  {} {}: skipped {} oversized document(s)",
+                "⚠".yellow(),
+                collection_name.cyan(),
+                skipped.to_string().yellow()
+            );
+        }
+
+        if expect_all
+            && exact_count
+            && total_migrated + skipped + filtered + duplicates + conflicts + invalid != total_count as usize
+        {
+            warn!(
+                "Expected {} documents but migrated {} for collection {}",
+                total_count, total_migrated, collection_name
+            );
+        }
+
+        timings.add(collection_name, TimedPhase::Sampling, sampling);
+        timings.add(collection_name, TimedPhase::Data, started.elapsed() - sampling);
+        Ok(total_migrated)
+    }
+
+    /// Log a progress line under `with_progress_log` if one is due
+    ///
+    /// # Arguments
+    /// * `collection_name` - Collection being migrated
+    /// * `position` - Rows inserted so far
+    /// * `total` - Documents expected in the collection
+    /// * `logged` - Position at the last progress line, updated when logging
+    fn log_progress(&self, collection_name: &str, position: usize, total: u64, logged: &mut usize) {
+        let Some(interval) = self.progress_log_interval else {
+            return;
+        };
+        if should_log_progress(*logged, position, interval) {
+            let percent = (position as u64 * 100 / total.max(1)).min(100);
+            info!("{}: {}/{} ({}%)", collection_name, position, total, percent);
+            *logged = position;
+        }
+    }
+
+    /// Insert a batch of documents
+    ///
+    /// # Arguments
+    /// * `insert_sql` - Single-row INSERT statement
+    /// * `batch` - Converted rows
+    /// * `key_index` - Position of the primary key column, to log skipped rows by
+    ///
+    /// # Returns
+    /// Number of rows skipped by `with_skip_conflicts`
+    async fn insert_batch(
+        &self,
+        insert_sql: &str,
+        batch: &[Vec<libsql::Value>],
+        key_index: Option<usize>,
+    ) -> Result<usize> {
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        // Rows are autocommitted one by one, so a failure leaves earlier rows behind
+        if !self.transactions {
+            if self.skip_conflicts {
+                return self.insert_rows_skipping_conflicts(insert_sql, batch, key_index).await;
+            }
+            self.insert_batch_inner(insert_sql, batch).await.inspect_err(|_| {
+                warn!("Batch insert failed without a transaction; rows inserted before the error were kept");
+            })?;
+            return Ok(0);
+        }
+
+        // Insert each row individually within a transaction
+        // Start transaction
+        self.backend
+            .execute("BEGIN TRANSACTION")
+            .await?;
+
+        match self.insert_batch_inner(insert_sql, batch).await {
+            Ok(()) => {
+                self.backend.execute("COMMIT").await?;
+                Ok(0)
+            }
+            Err(e) => {
+                self.backend.rollback().await;
+                if !(self.skip_conflicts && is_unique_violation(&e)) {
+                    return Err(e);
+                }
+                debug!("Batch of {} row(s) hit a conflict; retrying row by row", batch.len());
+                self.insert_rows_skipping_conflicts(insert_sql, batch, key_index).await
+            }
+        }
+    }
+
+    /// Insert rows one at a time without a transaction, skipping conflicts
+    ///
+    /// # Arguments
+    /// * `insert_sql` - Single-row INSERT statement
+    /// * `batch` - Converted rows
+    /// * `key_index` - Position of the primary key column, to log skipped rows by
+    ///
+    /// # Returns
+    /// Number of rows that violated a unique constraint and were skipped
+    async fn insert_rows_skipping_conflicts(
+        &self,
+        insert_sql: &str,
+        batch: &[Vec<libsql::Value>],
+        key_index: Option<usize>,
+    ) -> Result<usize> {
+        let mut skipped = 0;
+        for row in batch {
+            if let Err(e) = self.backend.execute_with_params(insert_sql, row.clone()).await {
+                if !is_unique_violation(&e) {
+                    return Err(e);
+                }
+                let key = key_index
+                    .and_then(|index| lookup_value(&row[index]))
+                    .unwrap_or_else(|| "(no key)".to_string());
+                warn!("Skipped row with key {}: {:#}", key, e);
+                skipped += 1;
+            }
+        }
+        Ok(skipped)
+    }
+
+    /// Inner function to insert batch rows
+    async fn insert_batch_inner(
+        &self,
+        insert_sql: &str,
+        batch: &[Vec<libsql::Value>],
+    ) -> Result<()> {
+        // The statement is prepared once and reused for every row
+        self.backend.execute_many(insert_sql, batch).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libsql_client::LibSqlClient;
+    use async_trait::async_trait;
+
+    #[test]
+    fn test_migration_mode_from_args() {
+        assert_eq!(
+            MigrationMode::from_args(false, false),
             MigrationMode::Full
         );
         assert_eq!(
@@ -364,5 +3962,998 @@ mod tests {
             MigrationMode::DataOnly
         );
     }
+
+    #[test]
+    fn test_check_document_size() {
+        assert!(check_document_size(100, None, OversizePolicy::Skip).unwrap());
+        assert!(check_document_size(100, Some(100), OversizePolicy::Skip).unwrap());
+        assert!(!check_document_size(101, Some(100), OversizePolicy::Skip).unwrap());
+        assert!(check_document_size(101, Some(100), OversizePolicy::Abort).is_err());
+        assert!(check_document_size(99, Some(100), OversizePolicy::Abort).unwrap());
+    }
+
+    #[test]
+    fn test_should_flush_batch() {
+        // Replays a stream of document sizes, returning the batch sizes flushed
+        let flushes = |sizes: &[usize], batch_size: usize, batch_bytes: Option<usize>| {
+            let mut flushed = Vec::new();
+            let (mut rows, mut bytes) = (0, 0);
+            for size in sizes {
+                rows += 1;
+                bytes += size;
+                if should_flush_batch(rows, bytes, batch_size, batch_bytes) {
+                    flushed.push(rows);
+                    (rows, bytes) = (0, 0);
+                }
+            }
+            flushed
+        };
+
+        let sizes = [100, 100, 5000, 100, 100, 100, 100, 100, 3000, 3000];
+        // Count only
+        assert_eq!(flushes(&sizes, 4, None), vec![4, 4]);
+        // Whichever threshold comes first
+        assert_eq!(flushes(&sizes, 4, Some(4096)), vec![3, 4, 3]);
+        // A single document over the byte limit is a batch on its own
+        assert_eq!(flushes(&[10_000, 10], 100, Some(1024)), vec![1]);
+    }
+
+    #[test]
+    fn test_should_flush_for_memory() {
+        // Replays a stream of row sizes, returning the batch sizes flushed
+        let flushes = |sizes: &[usize], max_memory: Option<usize>| {
+            let mut flushed = Vec::new();
+            let (mut rows, mut memory) = (0, 0);
+            for size in sizes {
+                rows += 1;
+                memory += size;
+                if should_flush_for_memory(rows, memory, max_memory) {
+                    flushed.push(rows);
+                    (rows, memory) = (0, 0);
+                }
+            }
+            flushed
+        };
+
+        // Small rows fill the budget slowly, large ones quickly
+        let sizes = [100, 100, 100, 100, 1000, 1000, 1000, 100];
+        assert_eq!(flushes(&sizes, Some(1000)), vec![5, 1, 1]);
+        assert_eq!(flushes(&sizes, None), Vec::<usize>::new());
+        // A single row over the budget is flushed on its own
+        assert_eq!(flushes(&[5000, 10], Some(1000)), vec![1]);
+
+        let row = vec![
+            libsql::Value::Integer(1),
+            libsql::Value::Text("abcd".to_string()),
+            libsql::Value::Blob(vec![0; 10]),
+        ];
+        assert_eq!(estimated_row_bytes(&row), 3 * 16 + 4 + 10);
+    }
+
+    fn change_event(event: Document) -> ChangeStreamEvent<Document> {
+        let mut event = event;
+        event.insert("_id", doc! { "_data": "8265A1B2C3000000012B022C0100296E5A1004" });
+        bson::from_document(event).unwrap()
+    }
+
+    #[test]
+    fn test_change_events_to_sql() {
+        let schema = CollectionSchema {
+            collection_name: "users".to_string(),
+            fields: vec![
+                Field {
+                    name: "_id".to_string(),
+                    sql_type: "TEXT".to_string(),
+                    nullable: false,
+                    is_primary_key: true,
+                    source: None,
+                    element_type: None,
+                    references: None,
+                    mixed_key: false,
+                },
+                Field {
+                    name: "name".to_string(),
+                    sql_type: "TEXT".to_string(),
+                    nullable: true,
+                    is_primary_key: false,
+                    source: None,
+                    element_type: None,
+                    references: None,
+                    mixed_key: false,
+                },
+            ],
+        };
+        let statements = ChangeStatements::new(&schema, QuoteStyle::Ansi).unwrap();
+        let options = ConverterOptions::default();
+        let mut warned_fields = HashSet::new();
+
+        let insert = change_event(doc! {
+            "operationType": "insert",
+            "documentKey": { "_id": "u1" },
+            "fullDocument": { "_id": "u1", "name": "Ada" },
+        });
+        let action = change_action(&insert).unwrap();
+        let (sql, values) = statements
+            .statement(&action, &schema, &options, &mut warned_fields)
+            .unwrap();
+        assert_eq!(
+            sql,
+            "INSERT INTO \"users\" (\"_id\", \"name\") VALUES (?, ?) ON CONFLICT(\"_id\") DO UPDATE SET \"name\" = excluded.\"name\""
+        );
+        assert!(matches!(&values[..], [libsql::Value::Text(id), libsql::Value::Text(name)] if id == "u1" && name == "Ada"));
+
+        let delete = change_event(doc! {
+            "operationType": "delete",
+            "documentKey": { "_id": "u1" },
+        });
+        let action = change_action(&delete).unwrap();
+        assert_eq!(action, ChangeAction::Delete(Bson::String("u1".to_string())));
+        let (sql, values) = statements
+            .statement(&action, &schema, &options, &mut warned_fields)
+            .unwrap();
+        assert_eq!(sql, "DELETE FROM \"users\" WHERE \"_id\" = ?");
+        assert!(matches!(&values[..], [libsql::Value::Text(id)] if id == "u1"));
+
+        // An update whose document was deleted before the lookup has nothing to apply
+        let stale_update = change_event(doc! {
+            "operationType": "update",
+            "documentKey": { "_id": "u1" },
+        });
+        assert_eq!(change_action(&stale_update).unwrap(), ChangeAction::Ignore);
+
+        let drop = change_event(doc! { "operationType": "drop" });
+        assert!(change_action(&drop).is_err());
+    }
+
+    #[test]
+    fn test_resume_token_round_trip() {
+        let event = change_event(doc! { "operationType": "insert" });
+        let text = encode_resume_token(&event.id).unwrap();
+        assert_eq!(decode_resume_token(&text).unwrap(), event.id);
+    }
+
+    #[test]
+    fn test_split_file_path() {
+        assert_eq!(split_file_path("out/shop.db", "orders"), "out/shop_orders.db");
+        assert_eq!(split_file_path("shop.sqlite", "users"), "shop_users.sqlite");
+        assert_eq!(split_file_path("shop", "users"), "shop_users.db");
+        assert_eq!(split_file_path("shop.db", "logs/2024"), "shop_logs_2024.db");
+    }
+
+    #[test]
+    fn test_continuation_token_round_trip() {
+        let oid = bson::oid::ObjectId::parse_str("507f1f77bcf86cd799439011").unwrap();
+        let mut token = ContinuationToken::default();
+        token.record("users", Bson::ObjectId(oid));
+        token.record("events", Bson::Int64(42));
+
+        let text = token.to_json();
+        assert_eq!(
+            text,
+            r#"{"events":{"$numberLong":"42"},"users":{"$oid":"507f1f77bcf86cd799439011"}}"#
+        );
+        let parsed = ContinuationToken::parse(&text).unwrap();
+        assert_eq!(parsed, token);
+
+        // Ids keep their BSON type, so the next chunk starts right after them
+        assert_eq!(parsed.filter("users"), doc! { "_id": { "$gt": oid } });
+        assert_eq!(parsed.filter("events"), doc! { "_id": { "$gt": 42_i64 } });
+        assert_eq!(parsed.filter("orders"), doc! {});
+
+        assert!(ContinuationToken::parse("[1, 2]").is_err());
+        assert!(ContinuationToken::parse("not json").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_filter_skips_repeated_keys() {
+        let docs = [
+            doc! { "_id": 1, "email": "a@example.com" },
+            doc! { "_id": 2, "email": "b@example.com" },
+            doc! { "_id": 3, "email": "a@example.com" },
+            doc! { "_id": 4 },
+            doc! { "_id": 5 },
+            doc! { "_id": 6, "email": 42 },
+            doc! { "_id": 7, "email": "42" },
+            doc! { "_id": 8, "email": 42 },
+        ];
+
+        let mut filter = DuplicateFilter::new("email");
+        let kept: Vec<i32> = docs
+            .iter()
+            .filter(|doc| filter.keep(doc))
+            .map(|doc| doc.get_i32("_id").unwrap())
+            .collect();
+
+        // Missing keys are kept; 42 and "42" are different values
+        assert_eq!(kept, vec![1, 2, 4, 5, 6, 7]);
+        assert_eq!("last-wins-sql".parse::<DedupStrategy>().unwrap(), DedupStrategy::LastWinsSql);
+        assert!("disk".parse::<DedupStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_merge_spec() {
+        let merge: MergeSpec = "events_*=events".parse().unwrap();
+        assert_eq!(merge.target, "events");
+        assert!(merge.matches("events_2023"));
+        assert!(merge.matches("events_"));
+        assert!(!merge.matches("events"));
+        assert!(!merge.matches("old_events_2023"));
+
+        let merge: MergeSpec = "log?_*_v*=logs".parse().unwrap();
+        assert!(merge.matches("log1_app_v2"));
+        assert!(!merge.matches("log12_app_v2"));
+
+        assert!("events_*".parse::<MergeSpec>().is_err());
+        assert!("=events".parse::<MergeSpec>().is_err());
+    }
+
+    #[test]
+    fn test_phase_timings_accumulate() {
+        let mut timings = PhaseTimings::default();
+        assert!(timings.is_empty());
+
+        timings.add("users", TimedPhase::Sampling, Duration::from_millis(20));
+        timings.add("users", TimedPhase::Schema, Duration::from_millis(5));
+        timings.add("orders", TimedPhase::Data, Duration::from_millis(300));
+        timings.add("users", TimedPhase::Sampling, Duration::from_millis(30));
+        timings.add("users", TimedPhase::Data, Duration::from_millis(100));
+
+        let collections = timings.collections();
+        assert_eq!(collections.len(), 2);
+        assert_eq!(collections[0].collection, "users");
+        assert_eq!(collections[0].sampling, Duration::from_millis(50));
+        assert_eq!(collections[0].schema, Duration::from_millis(5));
+        assert_eq!(collections[0].data, Duration::from_millis(100));
+        assert_eq!(collections[0].total(), Duration::from_millis(155));
+        assert_eq!(collections[1].collection, "orders");
+        assert_eq!(collections[1].total(), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_lookup_cache_assigns_stable_ids() {
+        let mut cache = LookupCache::default();
+        assert_eq!(cache.id_for("active"), (1, true));
+        assert_eq!(cache.id_for("banned"), (2, true));
+        assert_eq!(cache.id_for("active"), (1, false));
+
+        // A cache seeded from an existing lookup table keeps its ids
+        let mut cache = LookupCache::from_rows([(1, "active".to_string()), (4, "banned".to_string())]);
+        assert_eq!(cache.id_for("banned"), (4, false));
+        assert_eq!(cache.id_for("pending"), (5, true));
+
+        assert_eq!(lookup_value(&libsql::Value::Null), None);
+        assert_eq!(lookup_value(&libsql::Value::Integer(3)), Some("3".to_string()));
+    }
+
+    #[test]
+    fn test_select_top_collections() {
+        let counts: HashMap<String, u64> = [
+            ("logs".to_string(), 5_000),
+            ("users".to_string(), 120),
+            ("orders".to_string(), 900),
+            ("audit".to_string(), 900),
+            ("tags".to_string(), 3),
+        ]
+        .into();
+
+        assert_eq!(select_top_collections(&counts, 3), vec!["logs", "audit", "orders"]);
+        assert_eq!(select_top_collections(&counts, 10).len(), 5);
+        assert!(select_top_collections(&counts, 0).is_empty());
+    }
+
+    #[test]
+    fn test_column_size_sql() {
+        assert_eq!(
+            column_size_sql("users", "avatar", QuoteStyle::Ansi),
+            "SELECT COALESCE(SUM(LENGTH(\"avatar\")), 0) FROM \"users\""
+        );
+        assert_eq!(
+            column_size_sql("order items", "my\"col", QuoteStyle::Ansi),
+            "SELECT COALESCE(SUM(LENGTH(\"my\"\"col\")), 0) FROM \"order items\""
+        );
+    }
+
+    #[test]
+    fn test_column_stats_sql() {
+        assert_eq!(
+            column_stats_sql("users", "age", "INTEGER", QuoteStyle::Ansi),
+            "SELECT COUNT(\"age\"), COUNT(DISTINCT \"age\"), \
+             MIN(CASE WHEN typeof(\"age\") IN ('integer', 'real') THEN \"age\" END), \
+             MAX(CASE WHEN typeof(\"age\") IN ('integer', 'real') THEN \"age\" END) \
+             FROM \"users\""
+        );
+        // Only numeric columns get a range
+        assert_eq!(
+            column_stats_sql("order items", "my\"col", "TEXT", QuoteStyle::Ansi),
+            "SELECT COUNT(\"my\"\"col\"), COUNT(DISTINCT \"my\"\"col\"), NULL, NULL FROM \"order items\""
+        );
+
+        assert_eq!(stat_number(&libsql::Value::Integer(42)), Some(42.0));
+        assert_eq!(stat_number(&libsql::Value::Real(-1.5)), Some(-1.5));
+        assert_eq!(stat_number(&libsql::Value::Text("3.25".to_string())), Some(3.25));
+        assert_eq!(stat_number(&libsql::Value::Text("n/a".to_string())), None);
+        assert_eq!(stat_number(&libsql::Value::Null), None);
+    }
+
+    #[test]
+    fn test_pending_schema_collections() {
+        let collections = vec![
+            "users".to_string(),
+            "orders".to_string(),
+            "products".to_string(),
+        ];
+        let completed: HashSet<String> = ["users".to_string(), "products".to_string()].into();
+
+        assert_eq!(
+            pending_schema_collections(&collections, &completed, true),
+            vec!["orders".to_string()]
+        );
+        assert_eq!(
+            pending_schema_collections(&collections, &completed, false),
+            collections
+        );
+        assert_eq!(
+            pending_schema_collections(&collections, &HashSet::new(), true),
+            collections
+        );
+    }
+
+    #[test]
+    fn test_empty_collection_policy() {
+        let create = EmptyCollectionPolicy::from_args(false, false);
+        let skip = EmptyCollectionPolicy::from_args(true, false);
+        let error = EmptyCollectionPolicy::from_args(false, true);
+
+        assert!(create.keep("logs", 0).unwrap());
+        assert!(!skip.keep("logs", 0).unwrap());
+        assert!(error.keep("logs", 0).is_err());
+
+        for policy in [create, skip, error] {
+            assert!(policy.keep("users", 3).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_validate_document() {
+        assert_eq!(validate_document(&doc! { "_id": 1, "name": "Ada" }, true), None);
+        assert_eq!(validate_document(&doc! { "name": "Ada" }, false), Some("missing _id".to_string()));
+        assert_eq!(validate_document(&doc! { "_id": Bson::Null }, false), Some("_id is null".to_string()));
+        assert_eq!(validate_document(&doc! { "_id": [1, 2] }, false), Some("_id is an array".to_string()));
+
+        // Unconvertible values are only rejected in strict mode
+        let doc = doc! { "_id": 1, "meta": { "tags": ["a", Bson::Undefined] } };
+        assert_eq!(validate_document(&doc, false), None);
+        assert_eq!(validate_document(&doc, true), Some("meta.tags.1 is undefined".to_string()));
+
+        assert_eq!("ABORT".parse::<InvalidDocumentPolicy>().unwrap(), InvalidDocumentPolicy::Abort);
+        assert!("ignore".parse::<InvalidDocumentPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_oversize_policy_from_str() {
+        assert_eq!("skip".parse::<OversizePolicy>().unwrap(), OversizePolicy::Skip);
+        assert_eq!("ABORT".parse::<OversizePolicy>().unwrap(), OversizePolicy::Abort);
+        assert!("truncate".parse::<OversizePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_max_rows_per_statement() {
+        assert_eq!(max_rows_per_statement(DEFAULT_SQLITE_VARIABLE_LIMIT, 1), 32766);
+        assert_eq!(max_rows_per_statement(DEFAULT_SQLITE_VARIABLE_LIMIT, 10), 3276);
+        assert_eq!(max_rows_per_statement(999, 10), 99);
+        assert_eq!(max_rows_per_statement(999, 999), 1);
+        assert_eq!(max_rows_per_statement(999, 1000), 0);
+        // A table without columns never exceeds the limit
+        assert_eq!(max_rows_per_statement(999, 0), 999);
+    }
+
+    #[test]
+    fn test_should_update_progress() {
+        let updates: Vec<usize> = (1..=10)
+            .filter(|&converted| should_update_progress(converted, Some(3)))
+            .collect();
+        assert_eq!(updates, vec![3, 6, 9]);
+
+        assert!(!should_update_progress(1000, None));
+        assert!(!should_update_progress(5, Some(0)));
+        assert!(should_update_progress(5, Some(1)));
+    }
+
+    #[test]
+    fn test_should_log_progress() {
+        // Batches of 300 rows with a line every 1000
+        let mut logged = 0;
+        let mut lines = Vec::new();
+        for position in (300..=3000).step_by(300) {
+            if should_log_progress(logged, position, 1000) {
+                lines.push(position);
+                logged = position;
+            }
+        }
+        assert_eq!(lines, vec![1200, 2100, 3000]);
+
+        assert!(should_log_progress(0, 1000, 1000));
+        assert!(!should_log_progress(1000, 1999, 1000));
+        assert!(!should_log_progress(0, 5000, 0));
+    }
+
+    #[test]
+    fn test_confirmation_decide() {
+        assert_eq!(Confirmation::decide(true, false), Confirmation::Prompt);
+        assert_eq!(Confirmation::decide(true, true), Confirmation::Skip);
+        assert_eq!(Confirmation::decide(false, true), Confirmation::Skip);
+        // Never wait for an answer that can't come
+        assert_eq!(Confirmation::decide(false, false), Confirmation::Refuse);
+    }
+
+    #[test]
+    fn test_diff_columns() {
+        let field = |name: &str| Field {
+            name: name.to_string(),
+            sql_type: "TEXT".to_string(),
+            nullable: true,
+            is_primary_key: false,
+            source: None,
+            element_type: None,
+            references: None,
+            mixed_key: false,
+        };
+        let fields = vec![field("_id"), field("email"), field("name")];
+        let table_columns = vec!["_id".to_string(), "name".to_string(), "legacy".to_string()];
+
+        assert_eq!(
+            diff_columns(&fields, &table_columns),
+            ColumnDiff {
+                missing_in_table: vec!["email".to_string()],
+                missing_in_source: vec!["legacy".to_string()],
+            }
+        );
+
+        let table_columns = vec!["name".to_string(), "email".to_string(), "_id".to_string()];
+        assert!(diff_columns(&fields, &table_columns).is_empty());
+    }
+
+    #[test]
+    fn test_map_fields_to_columns() {
+        let field = |name: &str, source: Option<&str>| Field {
+            name: name.to_string(),
+            sql_type: "TEXT".to_string(),
+            nullable: true,
+            is_primary_key: false,
+            source: source.map(str::to_string),
+            element_type: None,
+            references: None,
+            mixed_key: false,
+        };
+        let fields = vec![
+            field("id", Some("_id")),
+            field("email", None),
+            field("firstName", None),
+            field("tags", None),
+        ];
+        let table_columns = vec![
+            "FirstName".to_string(),
+            "id".to_string(),
+            "email".to_string(),
+            "createdAt".to_string(),
+        ];
+
+        let mapping = map_fields_to_columns(&fields, &table_columns);
+        assert_eq!(
+            mapping,
+            ColumnMapping {
+                // Table order and spelling, still reading the MongoDB keys
+                fields: vec![
+                    field("FirstName", Some("firstName")),
+                    field("id", Some("_id")),
+                    field("email", None),
+                ],
+                unmapped_fields: vec!["tags".to_string()],
+                unfilled_columns: vec!["createdAt".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_columns_to_add() {
+        let field = |name: &str, is_primary_key: bool| Field {
+            name: name.to_string(),
+            sql_type: "TEXT".to_string(),
+            nullable: !is_primary_key,
+            is_primary_key,
+            source: None,
+            element_type: None,
+            references: None,
+            mixed_key: false,
+        };
+        let fields = vec![field("id", true), field("email", false), field("Name", false), field("tags", false)];
+        let table_columns = vec!["name".to_string(), "legacy".to_string()];
+
+        let added: Vec<&str> = columns_to_add(&fields, &table_columns)
+            .into_iter()
+            .map(|field| field.name.as_str())
+            .collect();
+        assert_eq!(added, vec!["email", "tags"]);
+
+        let table_columns = vec!["id".to_string(), "EMAIL".to_string(), "name".to_string(), "tags".to_string()];
+        assert!(columns_to_add(&fields, &table_columns).is_empty());
+    }
+
+    #[test]
+    fn test_classify_schema_changes() {
+        let field = |name: &str, sql_type: &str, nullable: bool| Field {
+            name: name.to_string(),
+            sql_type: sql_type.to_string(),
+            nullable,
+            is_primary_key: false,
+            source: None,
+            element_type: None,
+            references: None,
+            mixed_key: false,
+        };
+        let column = |name: &str, sql_type: &str| (name.to_string(), sql_type.to_string());
+        let fields = vec![
+            field("_id", "TEXT", false),
+            field("age", "REAL", true),
+            field("email", "TEXT", true),
+            field("score", "INTEGER", false),
+            field("notes", "TEXT", true),
+        ];
+        let table_columns = vec![
+            column("_id", "text"),
+            column("age", "INTEGER"),
+            column("notes", ""),
+            column("legacy", "TEXT"),
+        ];
+
+        let changes = classify_schema_changes(&fields, &table_columns);
+        assert_eq!(
+            changes,
+            vec![
+                SchemaChange::TypeChanged {
+                    column: "age".to_string(),
+                    table_type: "INTEGER".to_string(),
+                    inferred_type: "REAL".to_string(),
+                },
+                SchemaChange::Added {
+                    column: "email".to_string(),
+                    sql_type: "TEXT".to_string(),
+                    nullable: true,
+                },
+                SchemaChange::Added {
+                    column: "score".to_string(),
+                    sql_type: "INTEGER".to_string(),
+                    nullable: false,
+                },
+                SchemaChange::Removed {
+                    column: "legacy".to_string(),
+                    table_type: "TEXT".to_string(),
+                },
+            ]
+        );
+        let breaking: Vec<bool> = changes.iter().map(SchemaChange::is_breaking).collect();
+        assert_eq!(breaking, vec![true, false, true, true]);
+
+        // Only new nullable columns: safe
+        let check = SchemaCheck {
+            table: "users".to_string(),
+            new_table: false,
+            changes: classify_schema_changes(&fields[..3], &table_columns[..1]),
+        };
+        assert_eq!(check.changes.len(), 2);
+        assert!(!check.is_breaking());
+    }
+
+    /// Source serving the same documents for every collection, except one
+    /// collection that fails to count
+    struct FailingSource {
+        documents: Vec<Document>,
+        failing: &'static str,
+    }
+
+    #[async_trait]
+    impl DocumentSource for FailingSource {
+        async fn sample_documents(
+            &self,
+            _database_name: &str,
+            _collection_name: &str,
+            _sample_size: usize,
+        ) -> Result<Vec<Document>> {
+            Ok(self.documents.clone())
+        }
+
+        async fn count_documents(&self, _database_name: &str, collection_name: &str) -> Result<u64> {
+            if collection_name == self.failing {
+                bail!("simulated failure");
+            }
+            Ok(self.documents.len() as u64)
+        }
+
+        async fn stream_documents(
+            &self,
+            _database_name: &str,
+            _collection_name: &str,
+            _sort: Option<Document>,
+        ) -> Result<futures::stream::BoxStream<'static, Result<Document>>> {
+            use futures::stream::{self, StreamExt};
+
+            Ok(stream::iter(self.documents.clone().into_iter().map(Ok)).boxed())
+        }
+    }
+
+    async fn failing_migrator(continue_on_error: bool) -> Migrator<FailingSource> {
+        let source = FailingSource {
+            documents: vec![doc! { "_id": 1, "n": 1 }, doc! { "_id": 2, "n": 2 }],
+            failing: "broken",
+        };
+        let backend = Box::new(LibSqlClient::new(Some(":memory:")).await.unwrap());
+
+        Migrator::new(
+            source,
+            backend,
+            "test".to_string(),
+            100,
+            100,
+            ConverterOptions::default(),
+        )
+        .with_continue_on_error(continue_on_error)
+    }
+
+    #[tokio::test]
+    async fn test_continue_on_error() {
+        let collections = vec!["users".to_string(), "broken".to_string(), "orders".to_string()];
+
+        let report = failing_migrator(true)
+            .await
+            .migrate(collections.clone(), MigrationMode::Full, false, false)
+            .await
+            .unwrap();
+        assert_eq!(report.total_documents, 4);
+        assert_eq!(
+            report.failures,
+            vec![CollectionFailure {
+                collection: "broken".to_string(),
+                phase: MigrationPhase::Data,
+                error: "simulated failure".to_string(),
+            }]
+        );
+
+        // Fail-fast stays the default
+        assert!(failing_migrator(false)
+            .await
+            .migrate(collections, MigrationMode::Full, false, false)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_data_only_missing_tables() {
+        let migrator = failing_migrator(false).await;
+        migrator
+            .backend
+            .execute("CREATE TABLE users (_id INTEGER PRIMARY KEY, n INTEGER)")
+            .await
+            .unwrap();
+
+        let collections = vec!["users".to_string(), "orders".to_string(), "items".to_string()];
+        let table_names = migrator.resolve_table_names(&collections);
+        assert_eq!(
+            migrator.missing_tables(&collections, &table_names).await.unwrap(),
+            vec!["orders".to_string(), "items".to_string()]
+        );
+
+        // Nothing is inserted, not even into the existing table
+        let error = migrator
+            .migrate(collections, MigrationMode::DataOnly, false, false)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("orders, items"));
+        assert_eq!(count_rows(&migrator, "users").await, 0);
+    }
+
+    /// Backend that records the statements it executes
+    struct RecordingBackend {
+        inner: LibSqlClient,
+        statements: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl SqliteBackend for RecordingBackend {
+        async fn execute(&self, sql: &str) -> Result<u64> {
+            self.statements.lock().unwrap().push(sql.to_string());
+            self.inner.execute(sql).await
+        }
+
+        async fn execute_with_params(&self, sql: &str, params: Vec<libsql::Value>) -> Result<u64> {
+            self.statements.lock().unwrap().push(sql.to_string());
+            SqliteBackend::execute_with_params(&self.inner, sql, params).await
+        }
+
+        async fn query_with_params(
+            &self,
+            sql: &str,
+            params: Vec<libsql::Value>,
+        ) -> Result<Vec<crate::sqlite_backend::Row>> {
+            SqliteBackend::query_with_params(&self.inner, sql, params).await
+        }
+
+        fn is_local(&self) -> bool {
+            self.inner.is_local()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_analyze() {
+        for analyze in [false, true] {
+            let statements = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+            let backend = RecordingBackend {
+                inner: LibSqlClient::new(Some(":memory:")).await.unwrap(),
+                statements: statements.clone(),
+            };
+            let source = FailingSource {
+                documents: vec![doc! { "_id": 1, "n": 1 }],
+                failing: "",
+            };
+            let migrator = Migrator::new(
+                source,
+                Box::new(backend),
+                "test".to_string(),
+                100,
+                100,
+                ConverterOptions::default(),
+            )
+            .with_analyze(analyze);
+
+            migrator
+                .migrate(vec!["items".to_string()], MigrationMode::Full, false, false)
+                .await
+                .unwrap();
+
+            let statements = statements.lock().unwrap();
+            assert_eq!(statements.iter().any(|sql| sql == "ANALYZE"), analyze);
+            if analyze {
+                assert_eq!(statements.last().unwrap(), "ANALYZE");
+            }
+        }
+    }
+
+    /// Capped collection counting how often its settings are looked up
+    struct CappedSource {
+        inner: FailingSource,
+        lookups: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl DocumentSource for CappedSource {
+        async fn sample_documents(
+            &self,
+            database_name: &str,
+            collection_name: &str,
+            sample_size: usize,
+        ) -> Result<Vec<Document>> {
+            self.inner.sample_documents(database_name, collection_name, sample_size).await
+        }
+
+        async fn count_documents(&self, database_name: &str, collection_name: &str) -> Result<u64> {
+            self.inner.count_documents(database_name, collection_name).await
+        }
+
+        async fn stream_documents(
+            &self,
+            database_name: &str,
+            collection_name: &str,
+            sort: Option<Document>,
+        ) -> Result<futures::stream::BoxStream<'static, Result<Document>>> {
+            assert_eq!(sort, Some(doc! { "$natural": 1 }));
+            self.inner.stream_documents(database_name, collection_name, sort).await
+        }
+
+        async fn capped_info(
+            &self,
+            _database_name: &str,
+            _collection_name: &str,
+        ) -> Result<Option<CappedInfo>> {
+            self.lookups.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Ok(Some(CappedInfo { size: Some(4096), max: None }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_capped_info_looked_up_once() {
+        let source = CappedSource {
+            inner: FailingSource {
+                documents: vec![doc! { "_id": 1, "n": 1 }],
+                failing: "",
+            },
+            lookups: Default::default(),
+        };
+        let migrator = Migrator::new(
+            source,
+            Box::new(LibSqlClient::new(Some(":memory:")).await.unwrap()),
+            "test".to_string(),
+            100,
+            100,
+            ConverterOptions::default(),
+        );
+
+        let report = migrator
+            .migrate(vec!["events".to_string()], MigrationMode::Full, false, false)
+            .await
+            .unwrap();
+        assert_eq!(report.total_documents, 1);
+        assert_eq!(migrator.source.lookups.load(std::sync::atomic::Ordering::Relaxed), 1);
+        assert_eq!(
+            migrator.get_meta("events", "capped_size").await.unwrap().as_deref(),
+            Some("4096")
+        );
+    }
+
+    async fn count_rows(migrator: &Migrator<FailingSource>, table: &str) -> i64 {
+        let rows = migrator
+            .backend
+            .query(&format!("SELECT COUNT(*) FROM \"{}\"", table))
+            .await
+            .unwrap();
+        rows[0].integer(0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_no_transaction_keeps_rows_before_failure() {
+        // The third document repeats _id 1, so the batch fails on it
+        let documents = vec![
+            doc! { "_id": 1, "n": 1 },
+            doc! { "_id": 2, "n": 2 },
+            doc! { "_id": 1, "n": 3 },
+        ];
+
+        for (transactions, expected_rows) in [(true, 0), (false, 2)] {
+            let source = FailingSource {
+                documents: documents.clone(),
+                failing: "",
+            };
+            let migrator = Migrator::new(
+                source,
+                Box::new(LibSqlClient::new(Some(":memory:")).await.unwrap()),
+                "test".to_string(),
+                100,
+                100,
+                ConverterOptions::default(),
+            )
+            .with_transactions(transactions);
+
+            let result = migrator
+                .migrate(vec!["items".to_string()], MigrationMode::Full, false, false)
+                .await;
+            assert!(result.is_err());
+
+            // Without BEGIN/COMMIT the rows before the failure were autocommitted
+            assert_eq!(count_rows(&migrator, "items").await, expected_rows);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skip_conflicts() {
+        // The third document repeats _id 1, so its batch hits the primary key
+        let documents = vec![
+            doc! { "_id": 1, "n": 1 },
+            doc! { "_id": 2, "n": 2 },
+            doc! { "_id": 1, "n": 3 },
+            doc! { "_id": 3, "n": 4 },
+        ];
+
+        for transactions in [true, false] {
+            let source = FailingSource {
+                documents: documents.clone(),
+                failing: "",
+            };
+            let migrator = Migrator::new(
+                source,
+                Box::new(LibSqlClient::new(Some(":memory:")).await.unwrap()),
+                "test".to_string(),
+                100,
+                100,
+                ConverterOptions::default(),
+            )
+            .with_transactions(transactions)
+            .with_skip_conflicts(true);
+
+            let report = migrator
+                .migrate(vec!["items".to_string()], MigrationMode::Full, false, false)
+                .await
+                .unwrap();
+            assert_eq!(report.total_documents, 3);
+            assert_eq!(count_rows(&migrator, "items").await, 3);
+
+            // The first row with a key wins
+            let rows = migrator
+                .backend
+                .query("SELECT \"n\" FROM \"items\" WHERE \"_id\" = 1")
+                .await
+                .unwrap();
+            assert_eq!(rows[0].integer(0).unwrap(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_progress_events() {
+        use std::sync::Arc;
+
+        let source = FailingSource {
+            documents: (0..5).map(|i| doc! { "_id": i, "n": i }).collect(),
+            failing: "",
+        };
+        let backend = Box::new(LibSqlClient::new(Some(":memory:")).await.unwrap());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+
+        let migrator = Migrator::new(
+            source,
+            backend,
+            "test".to_string(),
+            2,
+            100,
+            ConverterOptions::default(),
+        )
+        .on_progress(move |event| recorded.lock().unwrap().push(event));
+
+        let report = migrator
+            .migrate(vec!["progress_events".to_string()], MigrationMode::Full, false, false)
+            .await
+            .unwrap();
+        assert_eq!(report.total_documents, 5);
+
+        let collection = "progress_events".to_string();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                ProgressEvent::CollectionStarted { collection: collection.clone(), total: 5 },
+                ProgressEvent::BatchInserted { collection: collection.clone(), count: 2 },
+                ProgressEvent::BatchInserted { collection: collection.clone(), count: 2 },
+                ProgressEvent::BatchInserted { collection: collection.clone(), count: 1 },
+                ProgressEvent::CollectionFinished { collection, total: 5 },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_progress_events_count_filtered_documents() {
+        use std::sync::Arc;
+
+        let source = FailingSource {
+            documents: (0..5).map(|i| doc! { "_id": i, "n": i }).collect(),
+            failing: "",
+        };
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+        let migrator = Migrator::new(
+            source,
+            Box::new(LibSqlClient::new(Some(":memory:")).await.unwrap()),
+            "test".to_string(),
+            2,
+            100,
+            ConverterOptions::default(),
+        )
+        .with_post_filters(HashMap::from([("items".to_string(), "n != 1".parse().unwrap())]))
+        .on_progress(move |event| recorded.lock().unwrap().push(event));
+
+        let report = migrator
+            .migrate(vec!["items".to_string()], MigrationMode::Full, false, false)
+            .await
+            .unwrap();
+        assert_eq!(report.total_documents, 4);
+
+        // Inserted and skipped documents together reach the total
+        let mut position = 0;
+        for event in events.lock().unwrap().iter() {
+            match event {
+                ProgressEvent::BatchInserted { count, .. } | ProgressEvent::DocumentsSkipped { count, .. } => {
+                    position += count
+                }
+                ProgressEvent::CollectionFinished { total, .. } => assert_eq!(*total, 4),
+                _ => {}
+            }
+        }
+        assert_eq!(position, 5);
+    }
 }
 
@@ -0,0 +1,302 @@
+use anyhow::{bail, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::str::FromStr;
+use tracing::warn;
+
+/// How collection and field names are turned into table and column names
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NamingStyle {
+    /// Keep names exactly as they are in MongoDB
+    #[default]
+    Preserve,
+    /// Convert camelCase, PascalCase and kebab-case to snake_case
+    Snake,
+    /// Lowercase names without inserting separators
+    Lower,
+}
+
+impl FromStr for NamingStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "preserve" => Ok(NamingStyle::Preserve),
+            "snake" => Ok(NamingStyle::Snake),
+            "lower" => Ok(NamingStyle::Lower),
+            _ => bail!(
+                "Unknown naming style '{}': expected preserve, snake or lower",
+                s
+            ),
+        }
+    }
+}
+
+impl NamingStyle {
+    /// Apply the naming style to a single name
+    ///
+    /// # Arguments
+    /// * `name` - Collection or field name as stored in MongoDB
+    ///
+    /// # Returns
+    /// The table or column name, before collision handling
+    pub fn apply(&self, name: &str) -> String {
+        match self {
+            NamingStyle::Preserve => name.to_string(),
+            NamingStyle::Snake => to_snake_case(name),
+            NamingStyle::Lower => name.to_lowercase(),
+        }
+    }
+}
+
+/// Convert a camelCase, PascalCase or kebab-case name to snake_case
+///
+/// Acronyms stay together (`HTTPServer` becomes `http_server`), leading
+/// underscores are kept (`_id` stays `_id`) and runs of separators collapse
+/// into a single underscore.
+///
+/// # Arguments
+/// * `name` - The name to convert
+///
+/// # Returns
+/// The snake_case name
+pub fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::with_capacity(name.len() + 4);
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '-' || c == ' ' || c == '.' || c == '_' {
+            // Keep leading underscores, collapse all other separator runs
+            if snake.chars().all(|s| s == '_') || !snake.ends_with('_') {
+                snake.push('_');
+            }
+            continue;
+        }
+
+        if c.is_uppercase() && i > 0 && !snake.is_empty() && !snake.ends_with('_') {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_is_lower) {
+                snake.push('_');
+            }
+        }
+
+        snake.extend(c.to_lowercase());
+    }
+
+    // A trailing separator would only come from input like "name-"
+    if snake.len() > 1 && snake.ends_with('_') && !snake.chars().all(|s| s == '_') {
+        snake.pop();
+    }
+
+    snake
+}
+
+/// Apply a naming style to a list of names, keeping the results unique
+///
+/// SQLite identifiers are case-insensitive, so results are compared without
+/// case. When two names map to the same result, later ones get a numeric
+/// suffix (`user_id`, `user_id_2`, ...). With `Preserve` names are returned
+/// unchanged.
+///
+/// # Arguments
+/// * `names` - Original names, in a stable order
+/// * `style` - Naming style to apply
+///
+/// # Returns
+/// The new names, in the same order as `names`
+pub fn assign_names<S: AsRef<str>>(names: &[S], style: NamingStyle) -> Vec<String> {
+    if style == NamingStyle::Preserve {
+        return names.iter().map(|name| name.as_ref().to_string()).collect();
+    }
+
+    let mut taken = HashSet::new();
+
+    names
+        .iter()
+        .map(|name| {
+            let base = style.apply(name.as_ref());
+            let mut candidate = base.clone();
+            let mut suffix = 2;
+            while !taken.insert(candidate.to_lowercase()) {
+                candidate = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            candidate
+        })
+        .collect()
+}
+
+/// Assign table names to collections, replacing dots when sanitizing
+///
+/// MongoDB allows dots in collection names (`foo.bar`), which are legal but
+/// awkward in SQLite. With `sanitize`, dots become underscores, warning for
+/// each renamed collection. Names without dots keep priority, so a renamed
+/// collection colliding with another table gets a numeric suffix
+/// (`foo_bar_2`).
+///
+/// # Arguments
+/// * `names` - Collection names, in a stable order
+/// * `style` - Naming style to apply
+/// * `sanitize` - Whether to replace dots in table names
+///
+/// # Returns
+/// The table names, in the same order as `names`
+pub fn assign_table_names<S: AsRef<str>>(names: &[S], style: NamingStyle, sanitize: bool) -> Vec<String> {
+    let styled = assign_names(names, style);
+    if !sanitize {
+        return styled;
+    }
+
+    let mut taken: HashSet<String> = styled
+        .iter()
+        .filter(|name| !name.contains('.'))
+        .map(|name| name.to_lowercase())
+        .collect();
+
+    names
+        .iter()
+        .zip(styled)
+        .map(|(original, name)| {
+            if !name.contains('.') {
+                return name;
+            }
+
+            let base = name.replace('.', "_");
+            let mut candidate = base.clone();
+            let mut suffix = 2;
+            while !taken.insert(candidate.to_lowercase()) {
+                candidate = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            warn!(
+                "Collection '{}' is migrated into table '{}'",
+                original.as_ref(),
+                candidate
+            );
+            candidate
+        })
+        .collect()
+}
+
+/// Shortest `--max-identifier-length`, leaving room for the hash suffix
+pub const MIN_IDENTIFIER_LENGTH: usize = 16;
+
+/// Hex digits of the hash appended to truncated names
+const HASH_SUFFIX_LENGTH: usize = 8;
+
+/// Truncate names longer than a maximum length, keeping them distinct
+///
+/// A long name keeps its first characters and ends in `_` and the first
+/// hex digits of the SHA-256 of the whole name, so names sharing a long
+/// prefix still differ. Each truncated name is logged with a warning.
+///
+/// # Arguments
+/// * `names` - Table or column names, after naming and sanitization
+/// * `max_length` - Longest name in characters, at least [`MIN_IDENTIFIER_LENGTH`]; `None` keeps every name
+///
+/// # Returns
+/// The names, in the same order
+pub fn truncate_names(names: Vec<String>, max_length: Option<usize>) -> Vec<String> {
+    let Some(max_length) = max_length else {
+        return names;
+    };
+
+    names
+        .into_iter()
+        .map(|name| {
+            if name.chars().count() <= max_length {
+                return name;
+            }
+
+            let hash = hex::encode(Sha256::digest(name.as_bytes()));
+            let prefix: String = name
+                .chars()
+                .take(max_length.saturating_sub(HASH_SUFFIX_LENGTH + 1))
+                .collect();
+            let truncated = format!("{}_{}", prefix, &hash[..HASH_SUFFIX_LENGTH]);
+            warn!("Name '{}' is longer than {} characters; using '{}'", name, max_length, truncated);
+            truncated
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_snake_case() {
+        assert_eq!(to_snake_case("camelCase"), "camel_case");
+        assert_eq!(to_snake_case("PascalCase"), "pascal_case");
+        assert_eq!(to_snake_case("kebab-case-name"), "kebab_case_name");
+        assert_eq!(to_snake_case("userID"), "user_id");
+        assert_eq!(to_snake_case("HTTPServer"), "http_server");
+        assert_eq!(to_snake_case("address2Line"), "address2_line");
+        assert_eq!(to_snake_case("already_snake"), "already_snake");
+        assert_eq!(to_snake_case("_id"), "_id");
+        assert_eq!(to_snake_case("__v"), "__v");
+        assert_eq!(to_snake_case("order--Items"), "order_items");
+        assert_eq!(to_snake_case("v2"), "v2");
+    }
+
+    #[test]
+    fn test_naming_style_apply() {
+        assert_eq!(NamingStyle::Preserve.apply("userId"), "userId");
+        assert_eq!(NamingStyle::Snake.apply("userId"), "user_id");
+        assert_eq!(NamingStyle::Lower.apply("userId"), "userid");
+        assert_eq!("SNAKE".parse::<NamingStyle>().unwrap(), NamingStyle::Snake);
+        assert!("kebab".parse::<NamingStyle>().is_err());
+    }
+
+    #[test]
+    fn test_assign_names_collisions() {
+        let names = ["userId", "user_id", "UserID", "name"];
+
+        assert_eq!(
+            assign_names(&names, NamingStyle::Snake),
+            vec!["user_id", "user_id_2", "user_id_3", "name"]
+        );
+        assert_eq!(
+            assign_names(&names, NamingStyle::Lower),
+            vec!["userid", "user_id", "userid_2", "name"]
+        );
+        assert_eq!(assign_names(&names, NamingStyle::Preserve), names.to_vec());
+    }
+
+    #[test]
+    fn test_truncate_names() {
+        let names = vec![
+            "address_billing_contact_primary_email".to_string(),
+            "address_billing_contact_primary_phone".to_string(),
+            "name".to_string(),
+        ];
+
+        let truncated = truncate_names(names.clone(), Some(24));
+        assert!(truncated[0].starts_with("address_billing_"));
+        assert!(truncated[1].starts_with("address_billing_"));
+        assert_ne!(truncated[0], truncated[1]);
+        assert!(truncated.iter().all(|name| name.chars().count() <= 24));
+        assert_eq!(truncated[2], "name");
+
+        // Truncation is stable across runs
+        assert_eq!(truncate_names(names.clone(), Some(24)), truncated);
+        assert_eq!(truncate_names(names.clone(), None), names);
+    }
+
+    #[test]
+    fn test_assign_table_names() {
+        assert_eq!(assign_table_names(&["foo.bar"], NamingStyle::Preserve, true), vec!["foo_bar"]);
+        assert_eq!(assign_table_names(&["foo.bar"], NamingStyle::Preserve, false), vec!["foo.bar"]);
+
+        // The collection already named foo_bar keeps its table
+        assert_eq!(
+            assign_table_names(&["foo.bar", "foo_bar", "a.b.c"], NamingStyle::Preserve, true),
+            vec!["foo_bar_2", "foo_bar", "a_b_c"]
+        );
+        assert_eq!(
+            assign_table_names(&["Foo.Bar", "foo_bar"], NamingStyle::Lower, true),
+            vec!["foo_bar_2", "foo_bar"]
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use anyhow::{bail, Result};
+use arrow::array::{
+    ArrayRef, BinaryBuilder, Float64Builder, Int64Builder, NullArray, StringBuilder,
+};
+use arrow::datatypes::{DataType, Field as ArrowField, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use libsql::Value as SqlValue;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::schema::CollectionSchema;
+
+/// Compression codec for Parquet files
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ParquetCompression {
+    /// No compression
+    None,
+    /// Snappy, fast with a moderate ratio
+    #[default]
+    Snappy,
+    /// Gzip, slower with a better ratio
+    Gzip,
+    /// Zstandard, a good ratio at reasonable speed
+    Zstd,
+}
+
+impl FromStr for ParquetCompression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" | "uncompressed" => Ok(ParquetCompression::None),
+            "snappy" => Ok(ParquetCompression::Snappy),
+            "gzip" => Ok(ParquetCompression::Gzip),
+            "zstd" => Ok(ParquetCompression::Zstd),
+            _ => bail!(
+                "Unknown Parquet compression '{}': expected none, snappy, gzip or zstd",
+                s
+            ),
+        }
+    }
+}
+
+impl ParquetCompression {
+    /// The codec as a `parquet` writer setting
+    fn codec(self) -> Compression {
+        match self {
+            ParquetCompression::None => Compression::UNCOMPRESSED,
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+            ParquetCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+        }
+    }
+}
+
+/// Map an inferred SQLite column type to an Arrow type
+///
+/// Embedded documents and arrays are TEXT columns holding JSON, so they
+/// become Utf8. Unknown types (e.g. from a schema file) are written as Utf8.
+///
+/// # Arguments
+/// * `sql_type` - SQLite type of the column
+///
+/// # Returns
+/// The Arrow data type
+pub fn arrow_type(sql_type: &str) -> DataType {
+    match sql_type.to_ascii_uppercase().as_str() {
+        "INTEGER" => DataType::Int64,
+        "REAL" => DataType::Float64,
+        "BLOB" => DataType::Binary,
+        "NULL" => DataType::Null,
+        _ => DataType::Utf8,
+    }
+}
+
+/// Build the Arrow schema of a collection's Parquet file
+///
+/// All columns are nullable: documents outside the sample may lack a field
+/// or hold a value that doesn't fit its column type.
+///
+/// # Arguments
+/// * `schema` - Inferred schema of the collection
+///
+/// # Returns
+/// The Arrow schema, one field per column
+pub fn arrow_schema(schema: &CollectionSchema) -> Schema {
+    Schema::new(
+        schema
+            .fields
+            .iter()
+            .map(|field| ArrowField::new(&field.name, arrow_type(&field.sql_type), true))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Writes converted rows of one collection to a Parquet file
+pub struct ParquetSink {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+    rows: usize,
+    mismatched: usize,
+}
+
+impl ParquetSink {
+    /// Create a Parquet file for a collection
+    ///
+    /// # Arguments
+    /// * `path` - File to create, e.g. `out/users.parquet`
+    /// * `schema` - Inferred schema of the collection
+    /// * `compression` - Compression codec, from `--parquet-compression`
+    ///
+    /// # Returns
+    /// A sink ready for `write` calls
+    pub fn create(path: &Path, schema: &CollectionSchema, compression: ParquetCompression) -> Result<Self> {
+        let schema = Arc::new(arrow_schema(schema));
+        let properties = WriterProperties::builder()
+            .set_compression(compression.codec())
+            .build();
+        let writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(properties))?;
+
+        Ok(Self {
+            writer,
+            schema,
+            rows: 0,
+            mismatched: 0,
+        })
+    }
+
+    /// Write a batch of rows as one Arrow record batch
+    ///
+    /// # Arguments
+    /// * `rows` - Rows converted with `document_to_sql_values`, in schema order
+    ///
+    /// # Returns
+    /// Ok once the batch is buffered in the writer
+    pub fn write(&mut self, rows: &[Vec<SqlValue>]) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let (batch, mismatched) = record_batch(self.schema.clone(), rows)?;
+        self.writer.write(&batch)?;
+        self.rows += rows.len();
+        self.mismatched += mismatched;
+        Ok(())
+    }
+
+    /// Finish the file by writing its footer
+    ///
+    /// # Returns
+    /// The number of rows written
+    pub fn close(self) -> Result<usize> {
+        self.writer.close()?;
+        if self.mismatched > 0 {
+            warn!(
+                "{} value(s) did not match their column type and were written as null",
+                self.mismatched
+            );
+        }
+        Ok(self.rows)
+    }
+}
+
+/// Build an Arrow record batch from converted rows
+///
+/// Values are cast to their column's type where that is lossless (e.g. an
+/// integer in a REAL column); other values become null.
+///
+/// # Arguments
+/// * `schema` - Arrow schema, as built by [`arrow_schema`]
+/// * `rows` - Rows in schema column order
+///
+/// # Returns
+/// The record batch and the number of values written as null because they
+/// didn't fit their column type
+pub fn record_batch(schema: SchemaRef, rows: &[Vec<SqlValue>]) -> Result<(RecordBatch, usize)> {
+    let mut mismatched = 0;
+    let columns: Vec<ArrayRef> = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(index, field)| column_array(field.data_type(), rows, index, &mut mismatched))
+        .collect();
+
+    Ok((RecordBatch::try_new(schema, columns)?, mismatched))
+}
+
+/// Build the Arrow array of one column
+fn column_array(data_type: &DataType, rows: &[Vec<SqlValue>], index: usize, mismatched: &mut usize) -> ArrayRef {
+    let values = rows.iter().map(|row| &row[index]);
+    let mut count_mismatch = |value: &SqlValue, converted: bool| {
+        if !converted && !matches!(value, SqlValue::Null) {
+            *mismatched += 1;
+        }
+    };
+
+    match data_type {
+        DataType::Int64 => {
+            let mut builder = Int64Builder::with_capacity(rows.len());
+            for value in values {
+                let converted = as_i64(value);
+                count_mismatch(value, converted.is_some());
+                builder.append_option(converted);
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::with_capacity(rows.len());
+            for value in values {
+                let converted = as_f64(value);
+                count_mismatch(value, converted.is_some());
+                builder.append_option(converted);
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Binary => {
+            let mut builder = BinaryBuilder::new();
+            for value in values {
+                match value {
+                    SqlValue::Blob(bytes) => builder.append_value(bytes),
+                    SqlValue::Text(text) => builder.append_value(text.as_bytes()),
+                    other => {
+                        count_mismatch(other, false);
+                        builder.append_null();
+                    }
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Null => Arc::new(NullArray::new(rows.len())),
+        _ => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                match value {
+                    SqlValue::Null => builder.append_null(),
+                    SqlValue::Integer(i) => builder.append_value(i.to_string()),
+                    SqlValue::Real(r) => builder.append_value(r.to_string()),
+                    SqlValue::Text(text) => builder.append_value(text),
+                    SqlValue::Blob(bytes) => builder.append_value(hex::encode(bytes)),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+/// Read a value as an integer, if it is one (or a whole REAL)
+fn as_i64(value: &SqlValue) -> Option<i64> {
+    match value {
+        SqlValue::Integer(i) => Some(*i),
+        SqlValue::Real(r) if r.fract() == 0.0 && r.abs() < i64::MAX as f64 => Some(*r as i64),
+        _ => None,
+    }
+}
+
+/// Read a value as a float, if it is numeric
+fn as_f64(value: &SqlValue) -> Option<f64> {
+    match value {
+        SqlValue::Real(r) => Some(*r),
+        SqlValue::Integer(i) => Some(*i as f64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{InferenceOptions, SchemaInferrer};
+    use arrow::array::{Array, Float64Array, Int64Array, StringArray};
+    use bson::doc;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn test_parquet_compression_from_str() {
+        assert_eq!("ZSTD".parse::<ParquetCompression>().unwrap(), ParquetCompression::Zstd);
+        assert_eq!("none".parse::<ParquetCompression>().unwrap(), ParquetCompression::None);
+        assert!("lzo".parse::<ParquetCompression>().is_err());
+    }
+
+    #[test]
+    fn test_parquet_round_trip() {
+        let docs = vec![
+            doc! { "_id": "a", "age": 36, "score": 1.5, "address": { "city": "Paris" } },
+            doc! { "_id": "b", "age": 41, "score": 2.0 },
+        ];
+        let schema = SchemaInferrer::infer_schema("users", &docs, &InferenceOptions::default()).unwrap();
+        let rows: Vec<Vec<SqlValue>> = docs
+            .iter()
+            .map(|doc| {
+                crate::converter::document_to_sql_values(
+                    doc,
+                    &schema.fields,
+                    &Default::default(),
+                    &mut Default::default(),
+                )
+            })
+            .collect();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("users.parquet");
+        let mut sink = ParquetSink::create(&path, &schema, ParquetCompression::Zstd).unwrap();
+        sink.write(&rows).unwrap();
+        assert_eq!(sink.close().unwrap(), 2);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(|batch| batch.unwrap()).collect();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+
+        let column = |name: &str| batch.column(batch.schema().index_of(name).unwrap()).clone();
+
+        let ids = column("_id");
+        let ids = ids.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(ids.value(0), "a");
+        assert_eq!(ids.value(1), "b");
+
+        let ages = column("age");
+        let ages = ages.as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(ages.values().to_vec(), vec![36, 41]);
+
+        let scores = column("score");
+        let scores = scores.as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(scores.values().to_vec(), vec![1.5, 2.0]);
+
+        // Embedded documents stay JSON text
+        let addresses = column("address");
+        let addresses = addresses.as_any().downcast_ref::<StringArray>().unwrap();
+        assert!(addresses.value(0).contains("Paris"));
+        assert!(addresses.is_null(1));
+    }
+
+    #[test]
+    fn test_record_batch_casts_or_nulls_mismatched_values() {
+        let schema = Arc::new(Schema::new(vec![
+            ArrowField::new("count", DataType::Int64, true),
+            ArrowField::new("ratio", DataType::Float64, true),
+        ]));
+        let rows = vec![
+            vec![SqlValue::Real(3.0), SqlValue::Integer(2)],
+            vec![SqlValue::Text("many".to_string()), SqlValue::Null],
+        ];
+
+        let (batch, mismatched) = record_batch(schema, &rows).unwrap();
+        let counts = batch.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(counts.value(0), 3);
+        assert!(counts.is_null(1));
+        let ratios = batch.column(1).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(ratios.value(0), 2.0);
+        assert!(ratios.is_null(1));
+        // NULL stays NULL without counting as a mismatch
+        assert_eq!(mismatched, 1);
+    }
+}
@@ -1,20 +1,223 @@
 use bson::{Bson, Document};
-use std::collections::HashMap;
-use tracing::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use tracing::{debug, info, warn};
 
-use crate::converter::{escape_identifier, infer_sqlite_type};
+use crate::computed::ComputedColumn;
+use crate::converter::{
+    escape_identifier, infer_sqlite_type, infer_sqlite_type_with, DecimalFormat, ObjectIdFormat, QuoteStyle,
+};
+use crate::error::{MigrationError, MigrationResult};
+use crate::naming::{assign_names, truncate_names, NamingStyle};
+
+/// Name of the rowid-backed primary key synthesized when documents lack `_id`
+pub const SYNTHETIC_ID_FIELD: &str = "_rowid";
+
+/// Column holding the whole document as canonical Extended JSON under `--keep-raw-json`
+pub const RAW_JSON_FIELD: &str = "_raw";
+
+/// Replace control characters in column names with underscores
+///
+/// BSON keys may contain newlines, tabs and other control characters, which
+/// quoting keeps valid but makes the generated SQL hard to read and edit.
+/// Replaced names that collide with another column get a numeric suffix.
+///
+/// # Arguments
+/// * `names` - Column names, in field order
+/// * `collection_name` - Collection the fields belong to, for the warning
+///
+/// # Returns
+/// The names without control characters, in the same order
+fn replace_control_chars(names: Vec<String>, collection_name: &str) -> Vec<String> {
+    if !names.iter().any(|name| name.chars().any(char::is_control)) {
+        return names;
+    }
+
+    let mut taken: HashSet<String> = names
+        .iter()
+        .filter(|name| !name.chars().any(char::is_control))
+        .map(|name| name.to_lowercase())
+        .collect();
+
+    names
+        .into_iter()
+        .map(|name| {
+            if !name.chars().any(char::is_control) {
+                return name;
+            }
+
+            let base: String = name.chars().map(|c| if c.is_control() { '_' } else { c }).collect();
+            let mut candidate = base.clone();
+            let mut suffix = 2;
+            while !taken.insert(candidate.to_lowercase()) {
+                candidate = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            warn!(
+                "Field \"{}\" in {} contains control characters; it is stored in column {}",
+                name.escape_debug(),
+                collection_name,
+                candidate
+            );
+            candidate
+        })
+        .collect()
+}
 
 /// Represents a field in a MongoDB collection
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Field {
+    /// Column name in the target table
     pub name: String,
     pub sql_type: String,
     pub nullable: bool,
     pub is_primary_key: bool,
+    /// Key of the value in the MongoDB document, when it differs from `name`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Dominant type of the elements of an array field (e.g. `INTEGER` or `DOCUMENT`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub element_type: Option<String>,
+    /// Lookup table whose `id` this column stores, under `--lookup-table`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub references: Option<String>,
+    /// Primary key holding `_id` values of several BSON types, stored as
+    /// relaxed Extended JSON text so that e.g. `3` and `"3"` stay distinct
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub mixed_key: bool,
+}
+
+impl Field {
+    /// Key to read this field's value from in a MongoDB document
+    pub fn source_key(&self) -> &str {
+        self.source.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Foreign key clause for a lookup column, e.g. ` REFERENCES "users_status_lookup" ("id")`
+    ///
+    /// # Arguments
+    /// * `quote_style` - Quoting style for identifiers
+    ///
+    /// # Returns
+    /// The clause with a leading space, or an empty string for other columns
+    pub fn references_clause(&self, quote_style: QuoteStyle) -> String {
+        match &self.references {
+            Some(table) => format!(
+                " REFERENCES {} ({})",
+                escape_identifier(table, quote_style),
+                escape_identifier("id", quote_style)
+            ),
+            None => String::new(),
+        }
+    }
+}
+
+/// Name of the lookup table for a `--lookup-table` field
+///
+/// # Arguments
+/// * `table_name` - Name of the table holding the field
+/// * `field` - MongoDB field name
+///
+/// # Returns
+/// `<table>_<field>_lookup`
+pub fn lookup_table_name(table_name: &str, field: &str) -> String {
+    format!("{}_{}_lookup", table_name, field)
+}
+
+/// Generate the CREATE TABLE statement for a lookup table
+///
+/// # Arguments
+/// * `lookup_table` - Name of the lookup table
+/// * `quote_style` - Quoting style for identifiers
+///
+/// # Returns
+/// SQL creating `(id INTEGER PRIMARY KEY, value TEXT UNIQUE)` if it doesn't exist
+pub fn lookup_table_sql(lookup_table: &str, quote_style: QuoteStyle) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} ({} INTEGER PRIMARY KEY, {} TEXT UNIQUE)",
+        escape_identifier(lookup_table, quote_style),
+        escape_identifier("id", quote_style),
+        escape_identifier("value", quote_style)
+    )
+}
+
+/// SQLite collating sequence for comparing and sorting TEXT values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collation {
+    /// Byte-wise comparison (SQLite's default)
+    Binary,
+    /// Case-insensitive for ASCII letters
+    NoCase,
+    /// Like binary, ignoring trailing spaces
+    Rtrim,
+}
+
+impl Collation {
+    /// SQL name of the collating sequence
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Collation::Binary => "BINARY",
+            Collation::NoCase => "NOCASE",
+            Collation::Rtrim => "RTRIM",
+        }
+    }
+}
+
+impl FromStr for Collation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "binary" => Ok(Collation::Binary),
+            "nocase" => Ok(Collation::NoCase),
+            "rtrim" => Ok(Collation::Rtrim),
+            other => anyhow::bail!(
+                "Unknown collation '{}' (expected one of: binary, nocase, rtrim)",
+                other
+            ),
+        }
+    }
+}
+
+/// Collations declared on the TEXT columns of generated tables
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextCollation {
+    /// Collation for TEXT columns other than the primary key
+    pub columns: Option<Collation>,
+    /// Collation for a TEXT primary key column
+    pub primary_key: Option<Collation>,
+}
+
+impl TextCollation {
+    /// The ` COLLATE ...` clause for a column, or an empty string
+    ///
+    /// # Arguments
+    /// * `field` - The column being defined
+    ///
+    /// # Returns
+    /// The clause to append after the column type
+    pub fn clause(&self, field: &Field) -> String {
+        if field.sql_type != "TEXT" {
+            return String::new();
+        }
+
+        let collation = if field.is_primary_key {
+            self.primary_key
+        } else {
+            self.columns
+        };
+
+        match collation {
+            Some(collation) => format!(" COLLATE {}", collation.as_sql()),
+            None => String::new(),
+        }
+    }
 }
 
 /// Represents the schema of a MongoDB collection
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CollectionSchema {
     pub collection_name: String,
     pub fields: Vec<Field>,
@@ -23,26 +226,59 @@ pub struct CollectionSchema {
 impl CollectionSchema {
     /// Generate a CREATE TABLE statement for this schema
     ///
+    /// # Arguments
+    /// * `quote_style` - Quoting style for identifiers
+    ///
     /// # Returns
     /// SQL CREATE TABLE statement
-    pub fn to_create_table_sql(&self) -> String {
-        let table_name = escape_identifier(&self.collection_name);
-        
-        let field_defs: Vec<String> = self.fields.iter().map(|field| {
-            let field_name = escape_identifier(&field.name);
-            let mut def = format!("{} {}", field_name, field.sql_type);
-            
-            if field.is_primary_key {
-                def.push_str(" PRIMARY KEY");
-            }
-            
-            if !field.nullable && !field.is_primary_key {
-                def.push_str(" NOT NULL");
-            }
-            
-            def
-        }).collect();
-        
+    pub fn to_create_table_sql(&self, quote_style: QuoteStyle) -> String {
+        self.to_create_table_sql_with_collation(quote_style, TextCollation::default())
+    }
+
+    /// Generate a CREATE TABLE statement declaring collations on TEXT columns
+    ///
+    /// # Arguments
+    /// * `quote_style` - Quoting style for identifiers
+    /// * `collation` - Collations for TEXT columns and a TEXT primary key
+    ///
+    /// # Returns
+    /// SQL CREATE TABLE statement
+    pub fn to_create_table_sql_with_collation(
+        &self,
+        quote_style: QuoteStyle,
+        collation: TextCollation,
+    ) -> String {
+        self.to_create_table_sql_with_generated(quote_style, collation, &[])
+    }
+
+    /// Generate a CREATE TABLE statement with extra generated columns
+    ///
+    /// Generated columns, e.g. from [`json_generated_columns`], come after
+    /// the schema's fields. They are not part of `fields`, so INSERT
+    /// statements never list them; SQLite rejects values for them.
+    ///
+    /// # Arguments
+    /// * `quote_style` - Quoting style for identifiers
+    /// * `collation` - Collations for TEXT columns and a TEXT primary key
+    /// * `generated_columns` - Complete generated column definitions
+    ///
+    /// # Returns
+    /// SQL CREATE TABLE statement
+    pub fn to_create_table_sql_with_generated(
+        &self,
+        quote_style: QuoteStyle,
+        collation: TextCollation,
+        generated_columns: &[String],
+    ) -> String {
+        let table_name = escape_identifier(&self.collection_name, quote_style);
+
+        let field_defs: Vec<String> = self
+            .fields
+            .iter()
+            .map(|field| Self::column_definition(field, quote_style, collation))
+            .chain(generated_columns.iter().cloned())
+            .collect();
+
         format!(
             "CREATE TABLE IF NOT EXISTS {} (\n  {}\n)",
             table_name,
@@ -50,34 +286,535 @@ impl CollectionSchema {
         )
     }
 
-    /// Get ordered list of field names
+    /// Generate a CREATE TABLE statement with a comment line above each column
+    ///
+    /// Each comment records the inferred type and how much of the sample had
+    /// it, e.g. `-- column: age (inferred INTEGER, 95% of sample)`. Columns
+    /// without inference stats (e.g. a synthesized key) get no comment.
+    ///
+    /// # Arguments
+    /// * `quote_style` - Quoting style for identifiers
+    /// * `field_infos` - Inference stats per field, as returned by
+    ///   `SchemaInferrer::infer_schema_explained`
+    ///
+    /// # Returns
+    /// SQL CREATE TABLE statement with `--` column comments
+    pub fn to_commented_create_table_sql(
+        &self,
+        quote_style: QuoteStyle,
+        field_infos: &[(String, FieldInfo)],
+    ) -> String {
+        let table_name = escape_identifier(&self.collection_name, quote_style);
+        let last = self.fields.len().saturating_sub(1);
+
+        let mut lines = Vec::new();
+        for (i, field) in self.fields.iter().enumerate() {
+            if let Some((_, info)) = field_infos.iter().find(|(name, _)| *name == field.name) {
+                lines.push(format!("  {}", format_column_comment(&field.name, info)));
+            }
+
+            let separator = if i < last { "," } else { "" };
+            lines.push(format!(
+                "  {}{}",
+                Self::column_definition(field, quote_style, TextCollation::default()),
+                separator
+            ));
+        }
+
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (\n{}\n)",
+            table_name,
+            lines.join("\n")
+        )
+    }
+
+    /// Render a single column definition, e.g. `"age" INTEGER NOT NULL`
+    fn column_definition(field: &Field, quote_style: QuoteStyle, collation: TextCollation) -> String {
+        let field_name = escape_identifier(&field.name, quote_style);
+        let mut def = format!("{} {}{}", field_name, field.sql_type, collation.clause(field));
+
+        if field.is_primary_key {
+            def.push_str(" PRIMARY KEY");
+        }
+
+        if !field.nullable && !field.is_primary_key {
+            def.push_str(" NOT NULL");
+        }
+
+        def.push_str(&field.references_clause(quote_style));
+        def
+    }
+
+    /// Rename the table and its columns according to a naming style
+    ///
+    /// Renamed fields remember their original MongoDB key in `source`, so
+    /// values are still read from the right key. Control characters such as
+    /// newlines and tabs in keys become underscores, with a warning. Columns
+    /// that would collide get a numeric suffix.
+    ///
+    /// # Arguments
+    /// * `table_name` - Name of the target table
+    /// * `naming` - Naming style for column names
+    ///
+    /// # Returns
+    /// The schema with table and column names applied
+    pub fn renamed(self, table_name: &str, naming: NamingStyle) -> CollectionSchema {
+        let source_keys: Vec<String> = self
+            .fields
+            .iter()
+            .map(|field| field.source_key().to_string())
+            .collect();
+        let column_names = replace_control_chars(assign_names(&source_keys, naming), &self.collection_name);
+
+        let fields = self
+            .fields
+            .into_iter()
+            .zip(source_keys)
+            .zip(column_names)
+            .map(|((field, source_key), column_name)| Field {
+                source: (column_name != source_key).then_some(source_key),
+                name: column_name,
+                ..field
+            })
+            .collect();
+
+        CollectionSchema {
+            collection_name: table_name.to_string(),
+            fields,
+        }
+    }
+
+    /// Truncate column names longer than `--max-identifier-length`
+    ///
+    /// Truncated fields remember their original MongoDB key in `source`, so
+    /// values are still read from the right key.
+    ///
+    /// # Arguments
+    /// * `max_length` - Longest column name in characters, or `None` to keep every name
+    ///
+    /// # Returns
+    /// The schema with long column names truncated
+    pub fn with_max_identifier_length(mut self, max_length: Option<usize>) -> CollectionSchema {
+        let names = self.fields.iter().map(|field| field.name.clone()).collect();
+        for (field, name) in self.fields.iter_mut().zip(truncate_names(names, max_length)) {
+            if name != field.name {
+                field.source = Some(field.source_key().to_string());
+                field.name = name;
+            }
+        }
+        self
+    }
+
+    /// Store the `_id` primary key in a differently named column
+    ///
+    /// Values are still read from the document's `_id`. Schemas without an
+    /// `_id` key (e.g. with a synthesized key) are returned unchanged.
+    ///
+    /// # Arguments
+    /// * `id_column` - Column name for `_id`, from `--id-column`
+    ///
+    /// # Returns
+    /// The schema with the key column renamed, or a configuration error if
+    /// another column already has that name
+    pub fn with_id_column(mut self, id_column: &str) -> MigrationResult<CollectionSchema> {
+        if id_column == "_id" {
+            return Ok(self);
+        }
+
+        if let Some(other) = self
+            .fields
+            .iter()
+            .find(|field| field.source_key() != "_id" && field.name.eq_ignore_ascii_case(id_column))
+        {
+            return Err(MigrationError::config(format!(
+                "--id-column '{}' collides with column '{}' of {}",
+                id_column, other.name, self.collection_name
+            )));
+        }
+
+        if let Some(field) = self
+            .fields
+            .iter_mut()
+            .find(|field| field.is_primary_key && field.source_key() == "_id")
+        {
+            field.name = id_column.to_string();
+            field.source = Some("_id".to_string());
+        }
+
+        Ok(self)
+    }
+
+    /// Store `_id` in an ordinary nullable column instead of the primary key
+    ///
+    /// Without the key constraint, re-runs and duplicate documents insert
+    /// additional rows instead of failing. A synthesized rowid key is kept.
+    ///
+    /// # Returns
+    /// The schema with `_id` no longer the primary key
+    pub fn without_primary_key(mut self) -> CollectionSchema {
+        if let Some(field) = self
+            .fields
+            .iter_mut()
+            .find(|field| field.is_primary_key && field.source_key() == "_id")
+        {
+            field.is_primary_key = false;
+            field.nullable = true;
+        }
+        self
+    }
+
+    /// Declare base64-encoded fields as BLOB columns
+    ///
+    /// The primary key keeps its type.
+    ///
+    /// # Arguments
+    /// * `blob_fields` - MongoDB field names from `--base64-to-blob`
+    ///
+    /// # Returns
+    /// The schema with matching columns typed BLOB
+    pub fn with_blob_fields(mut self, blob_fields: &HashSet<String>) -> CollectionSchema {
+        for field in self.fields.iter_mut() {
+            if !field.is_primary_key && blob_fields.contains(field.source_key()) {
+                field.sql_type = "BLOB".to_string();
+            }
+        }
+        self
+    }
+
+    /// Replace `--lookup-table` fields with integer keys into lookup tables
+    ///
+    /// Each matching column becomes `INTEGER REFERENCES <table>_<field>_lookup (id)`.
+    /// The primary key is left alone.
+    ///
+    /// # Arguments
+    /// * `lookup_fields` - MongoDB field names from `--lookup-table`
     ///
     /// # Returns
-    /// Vector of field names in the order they appear in the schema
-    pub fn field_names(&self) -> Vec<String> {
-        self.fields.iter().map(|f| f.name.clone()).collect()
+    /// The schema with lookup columns typed INTEGER and referencing their lookup table
+    pub fn with_lookup_fields(mut self, lookup_fields: &HashSet<String>) -> CollectionSchema {
+        for field in self.fields.iter_mut() {
+            if !field.is_primary_key && lookup_fields.contains(field.source_key()) {
+                field.sql_type = "INTEGER".to_string();
+                field.references = Some(lookup_table_name(&self.collection_name, field.source_key()));
+            }
+        }
+        self
+    }
+
+    /// Add a nullable column for each `--computed` expression
+    ///
+    /// Column types are inferred from the expressions over the existing fields.
+    ///
+    /// # Arguments
+    /// * `columns` - Computed columns, in the order given
+    ///
+    /// # Returns
+    /// The schema with the columns appended, or a configuration error if a
+    /// column of that name already exists
+    pub fn with_computed_columns(mut self, columns: &[ComputedColumn]) -> MigrationResult<CollectionSchema> {
+        for column in columns {
+            if self
+                .fields
+                .iter()
+                .any(|field| field.name.eq_ignore_ascii_case(&column.name))
+            {
+                return Err(MigrationError::config(format!(
+                    "--computed '{}' collides with a column of {}",
+                    column.name, self.collection_name
+                )));
+            }
+
+            let sql_type = column.sql_type(&self.fields);
+            self.fields.push(Field {
+                name: column.name.clone(),
+                sql_type: sql_type.to_string(),
+                nullable: true,
+                is_primary_key: false,
+                source: None,
+                element_type: None,
+                references: None,
+                mixed_key: false,
+            });
+        }
+        Ok(self)
+    }
+
+    /// Find the column a MongoDB field is stored in
+    ///
+    /// # Arguments
+    /// * `source_key` - Key of the field in MongoDB documents
+    ///
+    /// # Returns
+    /// The column name, or `None` if the schema has no such field
+    pub fn column_for(&self, source_key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|field| field.source_key() == source_key)
+            .map(|field| field.name.as_str())
     }
 
     /// Generate INSERT statement template with placeholders
     ///
+    /// # Arguments
+    /// * `quote_style` - Quoting style for identifiers
+    ///
     /// # Returns
     /// SQL INSERT statement with ? placeholders
-    pub fn to_insert_sql(&self) -> String {
-        let table_name = escape_identifier(&self.collection_name);
+    pub fn to_insert_sql(&self, quote_style: QuoteStyle) -> String {
+        self.insert_statement("INSERT", quote_style)
+    }
+
+    /// Generate an INSERT OR REPLACE statement template with placeholders
+    ///
+    /// Rows conflicting with a unique index or the primary key replace the
+    /// existing row.
+    ///
+    /// # Arguments
+    /// * `quote_style` - Quoting style for identifiers
+    ///
+    /// # Returns
+    /// SQL INSERT OR REPLACE statement with ? placeholders
+    pub fn to_insert_or_replace_sql(&self, quote_style: QuoteStyle) -> String {
+        self.insert_statement("INSERT OR REPLACE", quote_style)
+    }
+
+    /// Build an insert statement starting with the given verb
+    fn insert_statement(&self, verb: &str, quote_style: QuoteStyle) -> String {
+        let table_name = escape_identifier(&self.collection_name, quote_style);
         let field_names: Vec<String> = self.fields
             .iter()
-            .map(|f| escape_identifier(&f.name))
+            .map(|f| escape_identifier(&f.name, quote_style))
             .collect();
         
         let placeholders = vec!["?"; self.fields.len()].join(", ");
         
         format!(
-            "INSERT INTO {} ({}) VALUES ({})",
+            "{} INTO {} ({}) VALUES ({})",
+            verb,
             table_name,
             field_names.join(", "),
             placeholders
         )
     }
+
+    /// Generate an upsert statement that updates existing rows in place
+    ///
+    /// Rows whose primary key already exists are updated column by column
+    /// (`ON CONFLICT(pk) DO UPDATE SET col = excluded.col, ...`), so columns
+    /// of the target table that are not part of this schema keep their values.
+    ///
+    /// # Arguments
+    /// * `quote_style` - Quoting style for identifiers
+    ///
+    /// # Returns
+    /// SQL upsert statement with ? placeholders, or an error if the schema
+    /// has no primary key
+    pub fn to_upsert_sql(&self, quote_style: QuoteStyle) -> MigrationResult<String> {
+        let (keys, columns): (Vec<&Field>, Vec<&Field>) =
+            self.fields.iter().partition(|f| f.is_primary_key);
+
+        if keys.is_empty() {
+            return Err(MigrationError::config(format!(
+                "Cannot upsert into {}: the schema has no primary key",
+                self.collection_name
+            )));
+        }
+
+        let key_names: Vec<String> = keys
+            .iter()
+            .map(|f| escape_identifier(&f.name, quote_style))
+            .collect();
+
+        let action = if columns.is_empty() {
+            "DO NOTHING".to_string()
+        } else {
+            let assignments: Vec<String> = columns
+                .iter()
+                .map(|f| {
+                    let column = escape_identifier(&f.name, quote_style);
+                    format!("{} = excluded.{}", column, column)
+                })
+                .collect();
+            format!("DO UPDATE SET {}", assignments.join(", "))
+        };
+
+        Ok(format!(
+            "{} ON CONFLICT({}) {}",
+            self.to_insert_sql(quote_style),
+            key_names.join(", "),
+            action
+        ))
+    }
+
+    /// Get the primary key column holding `_id`
+    ///
+    /// # Returns
+    /// The key field, or an error if `_id` is not the primary key column
+    pub fn id_key(&self) -> MigrationResult<&Field> {
+        self.fields
+            .iter()
+            .find(|f| f.is_primary_key && f.source_key() == "_id")
+            .ok_or_else(|| {
+                MigrationError::config(format!(
+                    "Cannot delete from {}: _id is not its primary key",
+                    self.collection_name
+                ))
+            })
+    }
+
+    /// Generate a DELETE statement removing the row with a given `_id`
+    ///
+    /// # Arguments
+    /// * `quote_style` - Quoting style for identifiers
+    ///
+    /// # Returns
+    /// SQL DELETE statement with a single ? placeholder for the `_id`, or an
+    /// error if `_id` is not the primary key column
+    pub fn to_delete_sql(&self, quote_style: QuoteStyle) -> MigrationResult<String> {
+        let key = self.id_key()?;
+        Ok(format!(
+            "DELETE FROM {} WHERE {} = ?",
+            escape_identifier(&self.collection_name, quote_style),
+            escape_identifier(&key.name, quote_style)
+        ))
+    }
+}
+
+/// Parse collection schemas from a JSON array of `CollectionSchema` objects
+///
+/// # Arguments
+/// * `json` - JSON text to parse
+///
+/// # Returns
+/// Schemas keyed by collection name
+pub fn parse_schema_json(json: &str) -> MigrationResult<HashMap<String, CollectionSchema>> {
+    let schemas: Vec<CollectionSchema> = serde_json::from_str(json)
+        .map_err(|e| MigrationError::config(format!("Invalid schema JSON: {}", e)))?;
+
+    let mut by_name = HashMap::new();
+    for schema in schemas {
+        if schema.fields.is_empty() {
+            return Err(MigrationError::config(format!(
+                "Schema for collection '{}' has no fields",
+                schema.collection_name
+            )));
+        }
+        let name = schema.collection_name.clone();
+        if by_name.insert(name.clone(), schema).is_some() {
+            return Err(MigrationError::config(format!(
+                "Schema for collection '{}' is defined more than once",
+                name
+            )));
+        }
+    }
+
+    Ok(by_name)
+}
+
+/// Load collection schemas from a `--schema-file` JSON file
+///
+/// # Arguments
+/// * `path` - Path to the schema file
+///
+/// # Returns
+/// Schemas keyed by collection name
+pub fn load_schema_file(path: &Path) -> MigrationResult<HashMap<String, CollectionSchema>> {
+    let json = std::fs::read_to_string(path)?;
+    parse_schema_json(&json)
+}
+
+/// Pick a column type that can hold the values of two conflicting types
+///
+/// INTEGER and REAL widen to REAL, a NULL type gives way to the other type
+/// and any other conflict falls back to TEXT.
+///
+/// # Arguments
+/// * `a` - SQLite type of the first column
+/// * `b` - SQLite type of the second column
+///
+/// # Returns
+/// The widened SQLite type
+pub fn widen_sql_type(a: &str, b: &str) -> String {
+    match (a, b) {
+        _ if a == b => a.to_string(),
+        ("NULL", other) | (other, "NULL") => other.to_string(),
+        ("INTEGER", "REAL") | ("REAL", "INTEGER") => "REAL".to_string(),
+        _ => "TEXT".to_string(),
+    }
+}
+
+/// Merge the schemas of several collections into one table schema
+///
+/// The result has the union of all fields, in the order they are first
+/// seen. A field is nullable if it is nullable in any schema or missing
+/// from one, and conflicting types are widened with [`widen_sql_type`].
+///
+/// # Arguments
+/// * `table_name` - Name of the merged table
+/// * `schemas` - Schemas of the collections being merged
+///
+/// # Returns
+/// The merged schema
+pub fn merge_schemas(table_name: &str, schemas: &[CollectionSchema]) -> CollectionSchema {
+    let mut fields: Vec<Field> = Vec::new();
+
+    for schema in schemas {
+        for field in &schema.fields {
+            match fields.iter_mut().find(|f| f.source_key() == field.source_key()) {
+                Some(merged) => {
+                    merged.sql_type = widen_sql_type(&merged.sql_type, &field.sql_type);
+                    merged.nullable |= field.nullable;
+                    merged.is_primary_key |= field.is_primary_key;
+                }
+                None => fields.push(field.clone()),
+            }
+        }
+    }
+
+    for field in fields.iter_mut().filter(|field| !field.is_primary_key) {
+        let in_every_schema = schemas
+            .iter()
+            .all(|schema| schema.fields.iter().any(|f| f.source_key() == field.source_key()));
+        if !in_every_schema {
+            field.nullable = true;
+        }
+    }
+
+    CollectionSchema {
+        collection_name: table_name.to_string(),
+        fields,
+    }
+}
+
+/// Share of string values that must parse as numbers before a field is
+/// coerced to a numeric type under `--coerce-numeric-strings`
+const NUMERIC_STRING_THRESHOLD: f64 = 0.9;
+
+/// Options controlling schema inference
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InferenceOptions {
+    /// Whether a sampled document without `_id` is an error
+    pub require_id: bool,
+    /// Whether string fields that hold numbers get a numeric column type
+    pub coerce_numeric_strings: bool,
+    /// Whether to add a [`RAW_JSON_FIELD`] column holding the whole document
+    pub keep_raw_json: bool,
+    /// Representation of ObjectId values, which decides their column type
+    pub objectid_format: ObjectIdFormat,
+    /// Representation of Decimal128 values, which decides their column type
+    pub decimal_format: DecimalFormat,
+}
+
+impl Default for InferenceOptions {
+    fn default() -> Self {
+        Self {
+            require_id: true,
+            coerce_numeric_strings: false,
+            keep_raw_json: false,
+            objectid_format: ObjectIdFormat::Hex,
+            decimal_format: DecimalFormat::Text,
+        }
+    }
 }
 
 /// Schema inference engine
@@ -89,58 +826,159 @@ impl SchemaInferrer {
     /// # Arguments
     /// * `collection_name` - Name of the collection
     /// * `documents` - Sample documents to analyze
+    /// * `options` - Inference options
     ///
     /// # Returns
     /// Inferred schema for the collection
     pub fn infer_schema(
         collection_name: &str,
         documents: &[Document],
-    ) -> CollectionSchema {
+        options: &InferenceOptions,
+    ) -> MigrationResult<CollectionSchema> {
+        Ok(Self::infer_schema_explained(collection_name, documents, options)?.0)
+    }
+
+    /// Infer schema and keep the per-field analysis behind each column type
+    ///
+    /// # Arguments
+    /// * `collection_name` - Name of the collection
+    /// * `documents` - Sample documents to analyze
+    /// * `options` - Inference options
+    ///
+    /// If some sampled documents lack `_id` and `require_id` is false, an
+    /// `INTEGER PRIMARY KEY` column named [`SYNTHETIC_ID_FIELD`] is added as the
+    /// key instead, and `_id` (if seen at all) becomes a regular column.
+    ///
+    /// # Returns
+    /// Inferred schema and the field information for each column, in schema order
+    pub fn infer_schema_explained(
+        collection_name: &str,
+        documents: &[Document],
+        options: &InferenceOptions,
+    ) -> MigrationResult<(CollectionSchema, Vec<(String, FieldInfo)>)> {
         info!("Inferring schema for collection: {}", collection_name);
         
         if documents.is_empty() {
             debug!("No documents to analyze, creating minimal schema");
-            return Self::create_empty_schema(collection_name);
+            return Ok((Self::create_empty_schema(collection_name), Vec::new()));
+        }
+
+        let missing_id = documents.iter().filter(|doc| !doc.contains_key("_id")).count();
+        if missing_id > 0 && options.require_id {
+            return Err(MigrationError::schema_inference(format!(
+                "{} of {} sampled documents in '{}' have no _id field; \
+                 use --require-id false to synthesize a rowid-based key",
+                missing_id,
+                documents.len(),
+                collection_name
+            )));
         }
 
         // Collect field information across all documents
-        let mut field_info = Self::analyze_documents(documents);
+        let mut field_info = Self::analyze_documents(documents, options);
         
         // Build field definitions
         let mut fields = Vec::new();
+        let mut infos = Vec::new();
         
-        // MongoDB's _id is always present and becomes the primary key
-        if let Some(info) = field_info.remove("_id") {
+        if missing_id > 0 {
+            warn!(
+                "{} of {} sampled documents in '{}' have no _id field; using synthesized '{}' primary key",
+                missing_id,
+                documents.len(),
+                collection_name,
+                SYNTHETIC_ID_FIELD
+            );
+            // Inserting NULL into an INTEGER PRIMARY KEY lets SQLite assign the rowid
+            fields.push(Field {
+                name: SYNTHETIC_ID_FIELD.to_string(),
+                sql_type: "INTEGER".to_string(),
+                nullable: false,
+                is_primary_key: true,
+                source: None,
+                element_type: None,
+                references: None,
+                mixed_key: false,
+            });
+        } else if let Some(info) = field_info.remove("_id") {
+            // MongoDB's _id becomes the primary key; mixed key types are all
+            // stored as typed text so no value needs a type the column doesn't
+            // have and no two keys of different types collide
+            let id_types = mixed_id_types(&info);
+            if !id_types.is_empty() {
+                warn!(
+                    "_id values in '{}' mix types ({}); storing them all as TEXT",
+                    collection_name,
+                    id_types.join(", ")
+                );
+            }
             fields.push(Field {
                 name: "_id".to_string(),
-                sql_type: info.most_common_type,
+                sql_type: if id_types.is_empty() {
+                    info.most_common_type.clone()
+                } else {
+                    "TEXT".to_string()
+                },
                 nullable: false,
                 is_primary_key: true,
+                source: None,
+                element_type: None,
+                references: None,
+                mixed_key: !id_types.is_empty(),
             });
+            infos.push(("_id".to_string(), info));
         }
         
+        // The raw document column takes precedence over a field of the same name
+        if options.keep_raw_json && field_info.remove(RAW_JSON_FIELD).is_some() {
+            warn!(
+                "Collection '{}' has a '{}' field; it is replaced by the raw document column",
+                collection_name, RAW_JSON_FIELD
+            );
+        }
+
         // Add remaining fields, sorted by name for consistency
         let mut field_names: Vec<_> = field_info.keys().cloned().collect();
         field_names.sort();
         
         for field_name in field_names {
-            let info = &field_info[&field_name];
+            let info = field_info.remove(&field_name).expect("field name taken from map keys");
             fields.push(Field {
                 name: field_name.clone(),
                 sql_type: info.most_common_type.clone(),
-                // Always nullable except for _id - MongoDB is schema-less
+                // Always nullable except for the key - MongoDB is schema-less
                 // and fields can be missing in documents outside our sample
                 nullable: true,
                 is_primary_key: false,
+                source: None,
+                element_type: info.element_type.clone(),
+                references: None,
+                mixed_key: false,
+            });
+            infos.push((field_name, info));
+        }
+
+        if options.keep_raw_json {
+            fields.push(Field {
+                name: RAW_JSON_FIELD.to_string(),
+                sql_type: "TEXT".to_string(),
+                nullable: true,
+                is_primary_key: false,
+                source: None,
+                element_type: None,
+                references: None,
+                mixed_key: false,
             });
         }
         
         debug!("Inferred {} fields for {}", fields.len(), collection_name);
         
-        CollectionSchema {
+        let schema = CollectionSchema {
             collection_name: collection_name.to_string(),
             fields,
-        }
+        };
+
+        Ok((schema, infos))
     }
 
     /// Create an empty schema with just _id field
@@ -152,12 +990,19 @@ impl SchemaInferrer {
                 sql_type: "TEXT".to_string(),
                 nullable: false,
                 is_primary_key: true,
+                source: None,
+                element_type: None,
+                references: None,
+                mixed_key: false,
             }],
         }
     }
 
     /// Analyze documents to collect field information
-    fn analyze_documents(documents: &[Document]) -> HashMap<String, FieldInfo> {
+    fn analyze_documents(
+        documents: &[Document],
+        options: &InferenceOptions,
+    ) -> HashMap<String, FieldInfo> {
         let mut field_info: HashMap<String, FieldInfo> = HashMap::new();
         
         for doc in documents {
@@ -165,26 +1010,115 @@ impl SchemaInferrer {
                 let info = field_info.entry(key.clone()).or_insert_with(|| {
                     FieldInfo::new()
                 });
-                
-                info.record_value(value);
+
+                // _id ObjectIds are never stored as timestamps, see id_sql_value
+                let objectid_format = if key == "_id" {
+                    ObjectIdFormat::Hex
+                } else {
+                    options.objectid_format
+                };
+                info.record_value_as(value, objectid_format, options.decimal_format);
             }
         }
         
         // Determine most common type for each field
         for info in field_info.values_mut() {
-            info.finalize();
+            info.finalize(options.coerce_numeric_strings);
         }
         
         field_info
     }
 }
 
+/// BSON types of a collection's `_id` values, if they are mixed
+///
+/// Int32 and Int64 keys count as one type, since both are stored as INTEGER.
+///
+/// # Arguments
+/// * `info` - Field information gathered for `_id`
+///
+/// # Returns
+/// The sorted BSON type names, or an empty list if all keys share a type
+fn mixed_id_types(info: &FieldInfo) -> Vec<String> {
+    let kinds: HashSet<&str> = info
+        .bson_type_counts
+        .keys()
+        .map(|name| match name.as_str() {
+            "Int32" | "Int64" => "Int64",
+            other => other,
+        })
+        .collect();
+    if kinds.len() < 2 {
+        return Vec::new();
+    }
+
+    let mut types: Vec<String> = info.bson_type_counts.keys().cloned().collect();
+    types.sort();
+    types
+}
+
+/// Why a particular SQL type was chosen for a field
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeReason {
+    /// The chosen type was observed more often than any other
+    Majority,
+    /// Several types were observed equally often; the tie was broken by
+    /// the INTEGER > REAL > TEXT > BLOB > NULL priority order
+    TieBreak,
+    /// Only NULLs (or nothing) dominated the sample, so a non-NULL fallback was used
+    NullFallback,
+    /// String values that parse as numbers were coerced to a numeric type
+    NumericText,
+}
+
+impl std::fmt::Display for TypeReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            TypeReason::Majority => "majority",
+            TypeReason::TieBreak => "tie-break",
+            TypeReason::NullFallback => "null-fallback",
+            TypeReason::NumericText => "numeric-text",
+        };
+        f.pad(reason)
+    }
+}
+
+/// Element types of array fields, in tie-break order
+const ELEMENT_TYPE_PRIORITY: [&str; 6] = ["INTEGER", "REAL", "TEXT", "BLOB", "DOCUMENT", "ARRAY"];
+
+/// Classify an array element for [`FieldInfo::element_type`]
+///
+/// Scalars get their SQLite type; embedded documents and nested arrays are
+/// reported as `DOCUMENT` and `ARRAY`.
+fn element_type_name(value: &Bson) -> &'static str {
+    match value {
+        Bson::Document(_) => "DOCUMENT",
+        Bson::Array(_) => "ARRAY",
+        other => infer_sqlite_type(other),
+    }
+}
+
 /// Information collected about a field during analysis
 #[derive(Debug)]
-struct FieldInfo {
-    type_counts: HashMap<String, usize>,
-    presence_count: usize,
-    most_common_type: String,
+pub struct FieldInfo {
+    pub type_counts: HashMap<String, usize>,
+    /// Occurrences of each original BSON type (e.g. `ObjectId`, `Decimal128`)
+    pub bson_type_counts: HashMap<String, usize>,
+    pub presence_count: usize,
+    /// Number of string values seen
+    pub string_count: usize,
+    /// String values that parse as integers
+    pub integer_string_count: usize,
+    /// String values that parse as reals but not integers
+    pub real_string_count: usize,
+    pub most_common_type: String,
+    pub reason: TypeReason,
+    /// Occurrences of each element type across the field's array values
+    pub element_type_counts: HashMap<String, usize>,
+    /// Most common element type, if the field held non-empty arrays
+    pub element_type: Option<String>,
+    /// Top-level keys of the field's embedded documents, in order of first appearance
+    pub document_keys: Vec<String>,
 }
 
 impl FieldInfo {
@@ -192,23 +1126,84 @@ impl FieldInfo {
     fn new() -> Self {
         Self {
             type_counts: HashMap::new(),
+            bson_type_counts: HashMap::new(),
             presence_count: 0,
+            string_count: 0,
+            integer_string_count: 0,
+            real_string_count: 0,
             most_common_type: "TEXT".to_string(), // Default fallback
+            reason: TypeReason::NullFallback,
+            element_type_counts: HashMap::new(),
+            element_type: None,
+            document_keys: Vec::new(),
         }
     }
 
-    /// Record a value occurrence
-    fn record_value(&mut self, value: &Bson) {
+    /// Record a value occurrence, typing ObjectIds and Decimal128s by their representation
+    fn record_value_as(&mut self, value: &Bson, objectid_format: ObjectIdFormat, decimal_format: DecimalFormat) {
         self.presence_count += 1;
-        
-        let sql_type = infer_sqlite_type(value);
+
+        let sql_type = match value {
+            Bson::Decimal128(_) => decimal_format.sql_type(),
+            other => infer_sqlite_type_with(other, objectid_format),
+        };
         *self.type_counts.entry(sql_type.to_string()).or_insert(0) += 1;
+        *self
+            .bson_type_counts
+            .entry(bson_type_name(value).to_string())
+            .or_insert(0) += 1;
+
+        // Track the element types of arrays; NULL elements say nothing
+        if let Bson::Array(elements) = value {
+            for element in elements.iter().filter(|e| !matches!(e, Bson::Null)) {
+                *self
+                    .element_type_counts
+                    .entry(element_type_name(element).to_string())
+                    .or_insert(0) += 1;
+            }
+        }
+
+        // Track the keys of embedded documents for --create-json-views
+        if let Bson::Document(doc) = value {
+            for key in doc.keys() {
+                if !self.document_keys.contains(key) {
+                    self.document_keys.push(key.clone());
+                }
+            }
+        }
+
+        // Track whether string values would parse as numbers
+        if let Bson::String(s) = value {
+            self.string_count += 1;
+            let s = s.trim();
+            if s.parse::<i64>().is_ok() {
+                self.integer_string_count += 1;
+            } else if s.parse::<f64>().is_ok_and(f64::is_finite) {
+                self.real_string_count += 1;
+            }
+        }
     }
 
     /// Finalize analysis and determine most common type
-    fn finalize(&mut self) {
+    ///
+    /// # Arguments
+    /// * `coerce_numeric_strings` - Whether a TEXT field made of numeric
+    ///   strings should get a numeric type
+    fn finalize(&mut self, coerce_numeric_strings: bool) {
+        // Dominant array element type, ties broken by ELEMENT_TYPE_PRIORITY
+        let mut max_element_count = 0;
+        for name in ELEMENT_TYPE_PRIORITY {
+            if let Some(&count) = self.element_type_counts.get(name) {
+                if count > max_element_count {
+                    max_element_count = count;
+                    self.element_type = Some(name.to_string());
+                }
+            }
+        }
+
         if self.type_counts.is_empty() {
             self.most_common_type = "TEXT".to_string();
+            self.reason = TypeReason::NullFallback;
             return;
         }
 
@@ -236,18 +1231,338 @@ impl FieldInfo {
             }
         }
         
+        let tied_types = self.type_counts.values().filter(|&&c| c == max_count).count();
+        self.reason = if tied_types > 1 {
+            TypeReason::TieBreak
+        } else {
+            TypeReason::Majority
+        };
+
         // Special case: if we see NULL and other types, prefer the non-NULL type
         if most_common == "NULL" && self.type_counts.len() > 1 {
             for (type_name, &count) in &self.type_counts {
                 if type_name != "NULL" && count > 0 {
                     most_common = type_name.clone();
+                    self.reason = TypeReason::NullFallback;
                     break;
                 }
             }
         }
         
         self.most_common_type = most_common;
+
+        if coerce_numeric_strings {
+            self.coerce_numeric_strings();
+        }
+    }
+
+    /// Switch a TEXT field to INTEGER/REAL if enough of its strings are numeric
+    ///
+    /// Only applies when every TEXT value was a string (not e.g. a date or
+    /// ObjectId) and at least `NUMERIC_STRING_THRESHOLD` of them parse.
+    fn coerce_numeric_strings(&mut self) {
+        let text_count = self.type_counts.get("TEXT").copied().unwrap_or(0);
+        if self.most_common_type != "TEXT" || text_count != self.string_count {
+            return;
+        }
+
+        let numeric = self.integer_string_count + self.real_string_count;
+        if (numeric as f64) < self.string_count as f64 * NUMERIC_STRING_THRESHOLD {
+            return;
+        }
+
+        self.most_common_type = if self.real_string_count == 0 {
+            "INTEGER".to_string()
+        } else {
+            "REAL".to_string()
+        };
+        self.reason = TypeReason::NumericText;
+    }
+}
+
+/// Name of a value's BSON type, as used in the `_bson_types` metadata table
+///
+/// # Arguments
+/// * `value` - The BSON value
+///
+/// # Returns
+/// The BSON type name, e.g. `ObjectId` or `Int64`
+pub fn bson_type_name(value: &Bson) -> &'static str {
+    match value {
+        Bson::Double(_) => "Double",
+        Bson::String(_) => "String",
+        Bson::Array(_) => "Array",
+        Bson::Document(_) => "Document",
+        Bson::Boolean(_) => "Boolean",
+        Bson::Null => "Null",
+        Bson::RegularExpression(_) => "RegularExpression",
+        Bson::JavaScriptCode(_) => "JavaScriptCode",
+        Bson::JavaScriptCodeWithScope(_) => "JavaScriptCodeWithScope",
+        Bson::Int32(_) => "Int32",
+        Bson::Int64(_) => "Int64",
+        Bson::Timestamp(_) => "Timestamp",
+        Bson::Binary(_) => "Binary",
+        Bson::ObjectId(_) => "ObjectId",
+        Bson::DateTime(_) => "DateTime",
+        Bson::Symbol(_) => "Symbol",
+        Bson::Decimal128(_) => "Decimal128",
+        Bson::Undefined => "Undefined",
+        Bson::MaxKey => "MaxKey",
+        Bson::MinKey => "MinKey",
+        Bson::DbPointer(_) => "DbPointer",
+    }
+}
+
+/// One row of the `_bson_types` metadata table
+#[derive(Debug, Clone, PartialEq)]
+pub struct BsonTypeRow {
+    pub column: String,
+    pub bson_type: String,
+    pub count: usize,
+}
+
+/// Build the `_bson_types` rows describing the original BSON types of each column
+///
+/// # Arguments
+/// * `field_infos` - Inference stats per field, in schema order
+///
+/// # Returns
+/// One row per column and observed BSON type, in schema order and most
+/// frequent type first
+pub fn bson_type_rows(field_infos: &[(String, FieldInfo)]) -> Vec<BsonTypeRow> {
+    let mut rows = Vec::new();
+
+    for (column, info) in field_infos {
+        let mut counts: Vec<_> = info.bson_type_counts.iter().collect();
+        counts.sort_by(|(a_type, a_count), (b_type, b_count)| {
+            b_count.cmp(a_count).then_with(|| a_type.cmp(b_type))
+        });
+
+        rows.extend(counts.into_iter().map(|(bson_type, count)| BsonTypeRow {
+            column: column.clone(),
+            bson_type: bson_type.clone(),
+            count: *count,
+        }));
+    }
+
+    rows
+}
+
+/// Name of the companion view created by `--create-json-views`
+///
+/// # Arguments
+/// * `table_name` - Name of the table the view selects from
+///
+/// # Returns
+/// `<table>_json`
+pub fn json_view_name(table_name: &str) -> String {
+    format!("{}_json", table_name)
+}
+
+/// JSON path selecting a top-level key, quoting keys that aren't plain identifiers
+fn json_key_path(key: &str) -> String {
+    let plain = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let path = if plain {
+        format!("$.{}", key)
+    } else {
+        format!("$.\"{}\"", key.replace('"', "\\\""))
+    };
+    format!("'{}'", path.replace('\'', "''"))
+}
+
+/// Name the columns extracting the top-level keys of JSON columns
+///
+/// Embedded documents are stored as JSON TEXT. Every key seen in a TEXT
+/// column's sampled documents gets a `<column>_<key>` name; names that would
+/// collide with another column get a numeric suffix.
+///
+/// # Returns
+/// `(name, json_extract expression)` pairs, in column and key order
+fn json_key_columns(
+    schema: &CollectionSchema,
+    field_infos: &[(String, FieldInfo)],
+    quote_style: QuoteStyle,
+) -> Vec<(String, String)> {
+    let mut taken: HashSet<String> = schema
+        .fields
+        .iter()
+        .map(|field| field.name.to_lowercase())
+        .collect();
+    let mut columns = Vec::new();
+
+    for field in schema.fields.iter().filter(|field| field.sql_type == "TEXT") {
+        let Some((_, info)) = field_infos.iter().find(|(name, _)| *name == field.name) else {
+            continue;
+        };
+        for key in &info.document_keys {
+            let base = format!("{}_{}", field.name, key);
+            let mut alias = base.clone();
+            let mut suffix = 2;
+            while !taken.insert(alias.to_lowercase()) {
+                alias = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            let expression = format!(
+                "json_extract({}, {})",
+                escape_identifier(&field.name, quote_style),
+                json_key_path(key)
+            );
+            columns.push((alias, expression));
+        }
+    }
+
+    columns
+}
+
+/// Generate a view exposing the top-level keys of JSON columns as columns
+///
+/// For every JSON column whose sampled documents had keys, the view adds
+/// `json_extract(<column>, '$.<key>') AS <column>_<key>` next to all table
+/// columns.
+///
+/// # Arguments
+/// * `schema` - Schema of the table
+/// * `field_infos` - Inference stats per column, as returned by
+///   `SchemaInferrer::infer_schema_explained`
+/// * `quote_style` - Quoting style for identifiers
+///
+/// # Returns
+/// The CREATE VIEW statement, or `None` if the table has no JSON columns with keys
+pub fn json_view_sql(
+    schema: &CollectionSchema,
+    field_infos: &[(String, FieldInfo)],
+    quote_style: QuoteStyle,
+) -> Option<String> {
+    let expressions: Vec<String> = json_key_columns(schema, field_infos, quote_style)
+        .into_iter()
+        .map(|(alias, expression)| {
+            format!("{} AS {}", expression, escape_identifier(&alias, quote_style))
+        })
+        .collect();
+
+    if expressions.is_empty() {
+        return None;
     }
+
+    Some(format!(
+        "CREATE VIEW {} AS SELECT *, {} FROM {}",
+        escape_identifier(&json_view_name(&schema.collection_name), quote_style),
+        expressions.join(", "),
+        escape_identifier(&schema.collection_name, quote_style)
+    ))
+}
+
+/// Define generated columns exposing the top-level keys of JSON columns
+///
+/// The same columns as [`json_view_sql`] adds, declared in the table itself
+/// as `<column>_<key> GENERATED ALWAYS AS (json_extract(...)) VIRTUAL`, so
+/// they can be indexed. Virtual columns take no storage.
+///
+/// # Arguments
+/// * `schema` - Schema of the table
+/// * `field_infos` - Inference stats per column, as returned by
+///   `SchemaInferrer::infer_schema_explained`
+/// * `quote_style` - Quoting style for identifiers
+///
+/// # Returns
+/// Column definitions for `to_create_table_sql_with_generated`
+pub fn json_generated_columns(
+    schema: &CollectionSchema,
+    field_infos: &[(String, FieldInfo)],
+    quote_style: QuoteStyle,
+) -> Vec<String> {
+    json_key_columns(schema, field_infos, quote_style)
+        .into_iter()
+        .map(|(name, expression)| {
+            format!(
+                "{} GENERATED ALWAYS AS ({}) VIRTUAL",
+                escape_identifier(&name, quote_style),
+                expression
+            )
+        })
+        .collect()
+}
+
+/// Format the observed type distribution of a field, most frequent first
+///
+/// # Arguments
+/// * `info` - The analyzed field information
+///
+/// # Returns
+/// A string like `INTEGER: 95, TEXT: 5`
+pub fn format_type_counts(info: &FieldInfo) -> String {
+    let mut counts: Vec<_> = info.type_counts.iter().collect();
+    counts.sort_by(|(a_type, a_count), (b_type, b_count)| {
+        b_count.cmp(a_count).then_with(|| a_type.cmp(b_type))
+    });
+
+    counts
+        .iter()
+        .map(|(type_name, count)| format!("{}: {}", type_name, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format the SQL comment line documenting a column's inferred type
+///
+/// The percentage is the share of sampled values that had the chosen type;
+/// for coerced numeric strings it is the share of strings that parsed.
+///
+/// # Arguments
+/// * `field_name` - Name of the field
+/// * `info` - The analyzed field information
+///
+/// # Returns
+/// A line like `-- column: age (inferred INTEGER, 95% of sample)`
+pub fn format_column_comment(field_name: &str, info: &FieldInfo) -> String {
+    let (matching, total) = match info.reason {
+        TypeReason::NumericText => (
+            info.integer_string_count + info.real_string_count,
+            info.string_count,
+        ),
+        _ => (
+            info.type_counts
+                .get(&info.most_common_type)
+                .copied()
+                .unwrap_or(0),
+            info.presence_count,
+        ),
+    };
+    let percent = if total == 0 {
+        0
+    } else {
+        (matching as f64 * 100.0 / total as f64).round() as usize
+    };
+
+    // Keep the comment on one line whatever the field name contains
+    let field_name = field_name.replace(['\n', '\r'], " ");
+
+    format!(
+        "-- column: {} (inferred {}, {}% of sample)",
+        field_name, info.most_common_type, percent
+    )
+}
+
+/// Format one row of the `--explain-schema` table
+///
+/// # Arguments
+/// * `field_name` - Name of the field
+/// * `info` - The analyzed field information
+/// * `name_width` - Column width to pad the field name to
+///
+/// # Returns
+/// An aligned row with the field name, chosen type, reason and type distribution
+pub fn format_field_explanation(field_name: &str, info: &FieldInfo, name_width: usize) -> String {
+    format!(
+        "{:<name_width$}  {:<7}  {:<13}  {}",
+        field_name,
+        info.most_common_type,
+        info.reason,
+        format_type_counts(info),
+        name_width = name_width
+    )
 }
 
 #[cfg(test)]
@@ -270,7 +1585,7 @@ mod tests {
             },
         ];
 
-        let schema = SchemaInferrer::infer_schema("users", &docs);
+        let schema = SchemaInferrer::infer_schema("users", &docs, &InferenceOptions::default()).unwrap();
         
         assert_eq!(schema.collection_name, "users");
         assert_eq!(schema.fields.len(), 3); // _id, name, age
@@ -295,13 +1610,18 @@ mod tests {
             },
         ];
 
-        let schema = SchemaInferrer::infer_schema("users", &docs);
+        let schema = SchemaInferrer::infer_schema("users", &docs, &InferenceOptions::default()).unwrap();
         
         let email_field = schema.fields.iter().find(|f| f.name == "email").unwrap();
         assert!(email_field.nullable);
         
+        // Fields present in every sample are still nullable, since documents
+        // outside the sample may omit them
         let name_field = schema.fields.iter().find(|f| f.name == "name").unwrap();
-        assert!(!name_field.nullable);
+        assert!(name_field.nullable);
+
+        let id_field = schema.fields.iter().find(|f| f.name == "_id").unwrap();
+        assert!(!id_field.nullable);
     }
 
     #[test]
@@ -314,26 +1634,84 @@ mod tests {
                     sql_type: "TEXT".to_string(),
                     nullable: false,
                     is_primary_key: true,
+                    source: None,
+                    element_type: None,
+                    references: None,
+                    mixed_key: false,
                 },
                 Field {
                     name: "name".to_string(),
                     sql_type: "TEXT".to_string(),
                     nullable: false,
                     is_primary_key: false,
+                    source: None,
+                    element_type: None,
+                    references: None,
+                    mixed_key: false,
                 },
                 Field {
                     name: "age".to_string(),
                     sql_type: "INTEGER".to_string(),
                     nullable: true,
                     is_primary_key: false,
+                    source: None,
+                    element_type: None,
+                    references: None,
+                    mixed_key: false,
                 },
             ],
         };
 
-        let sql = schema.to_create_table_sql();
+        let sql = schema.to_create_table_sql(QuoteStyle::Ansi);
         assert!(sql.contains("CREATE TABLE"));
-        assert!(sql.contains("users"));
+        assert!(sql.contains("\"users\""));
         assert!(sql.contains("PRIMARY KEY"));
+
+        let sql = schema.to_create_table_sql(QuoteStyle::Mysql);
+        assert!(sql.contains("`users`"));
+        assert!(sql.contains("`_id` TEXT PRIMARY KEY"));
+    }
+
+    #[test]
+    fn test_create_table_sql_with_collation() {
+        let field = |name: &str, sql_type: &str, is_primary_key: bool| Field {
+            name: name.to_string(),
+            sql_type: sql_type.to_string(),
+            nullable: !is_primary_key,
+            is_primary_key,
+            source: None,
+            element_type: None,
+            references: None,
+            mixed_key: false,
+        };
+        let schema = CollectionSchema {
+            collection_name: "users".to_string(),
+            fields: vec![
+                field("_id", "TEXT", true),
+                field("email", "TEXT", false),
+                field("age", "INTEGER", false),
+            ],
+        };
+
+        let collation = TextCollation {
+            columns: Some("nocase".parse().unwrap()),
+            primary_key: None,
+        };
+        let sql = schema.to_create_table_sql_with_collation(QuoteStyle::Ansi, collation);
+        assert!(sql.contains("\"email\" TEXT COLLATE NOCASE"));
+        assert!(sql.contains("\"_id\" TEXT PRIMARY KEY"));
+        assert!(sql.contains("\"age\" INTEGER\n"));
+
+        let collation = TextCollation {
+            columns: Some(Collation::Rtrim),
+            primary_key: Some(Collation::Binary),
+        };
+        let sql = schema.to_create_table_sql_with_collation(QuoteStyle::Ansi, collation);
+        assert!(sql.contains("\"_id\" TEXT COLLATE BINARY PRIMARY KEY"));
+        assert!(sql.contains("\"email\" TEXT COLLATE RTRIM"));
+
+        assert!(!schema.to_create_table_sql(QuoteStyle::Ansi).contains("COLLATE"));
+        assert!("ucs".parse::<Collation>().is_err());
     }
 
     #[test]
@@ -346,29 +1724,567 @@ mod tests {
                     sql_type: "TEXT".to_string(),
                     nullable: false,
                     is_primary_key: true,
+                    source: None,
+                    element_type: None,
+                    references: None,
+                    mixed_key: false,
                 },
                 Field {
                     name: "name".to_string(),
                     sql_type: "TEXT".to_string(),
                     nullable: false,
                     is_primary_key: false,
+                    source: None,
+                    element_type: None,
+                    references: None,
+                    mixed_key: false,
                 },
             ],
         };
 
-        let sql = schema.to_insert_sql();
+        let sql = schema.to_insert_sql(QuoteStyle::Ansi);
         assert!(sql.contains("INSERT INTO"));
         assert!(sql.contains("VALUES"));
         assert!(sql.contains("?"));
+
+        let sql = schema.to_insert_or_replace_sql(QuoteStyle::Ansi);
+        assert!(sql.starts_with("INSERT OR REPLACE INTO \"users\""));
+    }
+
+    #[test]
+    fn test_to_upsert_sql() {
+        let field = |name: &str, is_primary_key: bool| Field {
+            name: name.to_string(),
+            sql_type: "TEXT".to_string(),
+            nullable: !is_primary_key,
+            is_primary_key,
+            source: None,
+            element_type: None,
+            references: None,
+            mixed_key: false,
+        };
+        let schema = CollectionSchema {
+            collection_name: "users".to_string(),
+            fields: vec![field("_id", true), field("name", false), field("email", false)],
+        };
+
+        assert_eq!(
+            schema.to_upsert_sql(QuoteStyle::Ansi).unwrap(),
+            "INSERT INTO \"users\" (\"_id\", \"name\", \"email\") VALUES (?, ?, ?) \
+             ON CONFLICT(\"_id\") DO UPDATE SET \"name\" = excluded.\"name\", \"email\" = excluded.\"email\""
+        );
+
+        let keys_only = CollectionSchema {
+            collection_name: "tags".to_string(),
+            fields: vec![field("_id", true)],
+        };
+        assert!(keys_only.to_upsert_sql(QuoteStyle::Ansi).unwrap().ends_with("DO NOTHING"));
+
+        let no_key = CollectionSchema {
+            collection_name: "logs".to_string(),
+            fields: vec![field("message", false)],
+        };
+        assert!(no_key.to_upsert_sql(QuoteStyle::Ansi).is_err());
     }
 
     #[test]
     fn test_empty_schema() {
         let docs: Vec<Document> = vec![];
-        let schema = SchemaInferrer::infer_schema("empty", &docs);
+        let schema = SchemaInferrer::infer_schema("empty", &docs, &InferenceOptions::default()).unwrap();
         
         assert_eq!(schema.fields.len(), 1); // Just _id
         assert_eq!(schema.fields[0].name, "_id");
     }
-}
 
+    #[test]
+    fn test_format_field_explanation() {
+        let mut info = FieldInfo::new();
+        for _ in 0..95 {
+            info.record_value_as(&Bson::Int32(1), ObjectIdFormat::Hex, DecimalFormat::Text);
+        }
+        for _ in 0..5 {
+            info.record_value_as(&Bson::String("x".to_string()), ObjectIdFormat::Hex, DecimalFormat::Text);
+        }
+        info.finalize(false);
+
+        assert_eq!(info.reason, TypeReason::Majority);
+        assert_eq!(format_type_counts(&info), "INTEGER: 95, TEXT: 5");
+        assert_eq!(
+            format_field_explanation("age", &info, 5),
+            "age    INTEGER  majority       INTEGER: 95, TEXT: 5"
+        );
+    }
+
+    #[test]
+    fn test_type_reason_tie_break_and_null_fallback() {
+        let mut tied = FieldInfo::new();
+        tied.record_value_as(&Bson::Int32(1), ObjectIdFormat::Hex, DecimalFormat::Text);
+        tied.record_value_as(&Bson::Double(1.5), ObjectIdFormat::Hex, DecimalFormat::Text);
+        tied.finalize(false);
+        assert_eq!(tied.most_common_type, "INTEGER");
+        assert_eq!(tied.reason, TypeReason::TieBreak);
+
+        let mut mostly_null = FieldInfo::new();
+        mostly_null.record_value_as(&Bson::Null, ObjectIdFormat::Hex, DecimalFormat::Text);
+        mostly_null.record_value_as(&Bson::Null, ObjectIdFormat::Hex, DecimalFormat::Text);
+        mostly_null.record_value_as(&Bson::String("x".to_string()), ObjectIdFormat::Hex, DecimalFormat::Text);
+        mostly_null.finalize(false);
+        assert_eq!(mostly_null.most_common_type, "TEXT");
+        assert_eq!(mostly_null.reason, TypeReason::NullFallback);
+    }
+
+    #[test]
+    fn test_infer_schema_mixed_id_types() {
+        let oid = bson::oid::ObjectId::parse_str("507f1f77bcf86cd799439011").unwrap();
+        let docs = vec![
+            doc! { "_id": oid, "name": "Alice" },
+            doc! { "_id": "legacy-2", "name": "Bob" },
+            doc! { "_id": 3, "name": "Carol" },
+            doc! { "_id": 4, "name": "Dave" },
+        ];
+        let schema = SchemaInferrer::infer_schema("users", &docs, &InferenceOptions::default()).unwrap();
+        let id_field = &schema.fields[0];
+        assert_eq!(id_field.name, "_id");
+        assert_eq!(id_field.sql_type, "TEXT");
+
+        // Every key is stored as relaxed Extended JSON, which keeps its type
+        assert!(id_field.mixed_key);
+        let options = crate::converter::ConverterOptions::default();
+        let ids: Vec<libsql::Value> = docs
+            .iter()
+            .map(|doc| {
+                crate::converter::document_to_sql_values(doc, &schema.fields, &options, &mut HashSet::new())
+                    .remove(0)
+            })
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                libsql::Value::Text(r#"{"$oid":"507f1f77bcf86cd799439011"}"#.to_string()),
+                libsql::Value::Text(r#""legacy-2""#.to_string()),
+                libsql::Value::Text("3".to_string()),
+                libsql::Value::Text("4".to_string()),
+            ]
+        );
+
+        // A number and a string with the same digits are different keys
+        let docs = vec![doc! { "_id": 3 }, doc! { "_id": "3" }];
+        let schema = SchemaInferrer::infer_schema("codes", &docs, &InferenceOptions::default()).unwrap();
+        let keys: Vec<libsql::Value> = docs
+            .iter()
+            .map(|doc| crate::converter::key_sql_value(doc.get("_id").unwrap(), &schema.fields[0], &options))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![libsql::Value::Text("3".to_string()), libsql::Value::Text(r#""3""#.to_string())]
+        );
+
+        // Int32 and Int64 keys are not mixed
+        let docs = vec![doc! { "_id": 1_i32 }, doc! { "_id": 2_i64 }];
+        let schema = SchemaInferrer::infer_schema("counters", &docs, &InferenceOptions::default()).unwrap();
+        assert_eq!(schema.fields[0].sql_type, "INTEGER");
+    }
+
+    #[test]
+    fn test_infer_schema_missing_id() {
+        let docs = vec![
+            doc! { "_id": "1", "name": "Alice" },
+            doc! { "name": "Bob" },
+        ];
+
+        let err = SchemaInferrer::infer_schema("users", &docs, &InferenceOptions::default()).unwrap_err();
+        assert!(matches!(err, MigrationError::SchemaInferenceError(_)));
+
+        let schema = SchemaInferrer::infer_schema(
+            "users",
+            &docs,
+            &InferenceOptions {
+                require_id: false,
+                ..InferenceOptions::default()
+            },
+        ).unwrap();
+        assert_eq!(schema.fields[0].name, SYNTHETIC_ID_FIELD);
+        assert_eq!(schema.fields[0].sql_type, "INTEGER");
+        assert!(schema.fields[0].is_primary_key);
+
+        let id_field = schema.fields.iter().find(|f| f.name == "_id").unwrap();
+        assert!(!id_field.is_primary_key);
+        assert!(id_field.nullable);
+        assert!(schema.to_create_table_sql(QuoteStyle::Ansi).contains("\"_rowid\" INTEGER PRIMARY KEY"));
+    }
+
+    #[test]
+    fn test_schema_json_round_trip() {
+        let docs = vec![
+            doc! { "_id": "1", "name": "Alice", "age": 30 },
+            doc! { "_id": "2", "name": "Bob", "score": 1.5 },
+        ];
+        let schema = SchemaInferrer::infer_schema("users", &docs, &InferenceOptions::default()).unwrap();
+
+        let json = serde_json::to_string_pretty(&vec![schema.clone()]).unwrap();
+        let schemas = parse_schema_json(&json).unwrap();
+
+        assert_eq!(schemas.len(), 1);
+        assert_eq!(schemas["users"], schema);
+    }
+
+    #[test]
+    fn test_parse_schema_json_invalid() {
+        assert!(parse_schema_json("not json").is_err());
+        assert!(parse_schema_json(r#"[{"collection_name": "users", "fields": []}]"#).is_err());
+    }
+
+    fn infer_coerced_type(values: &[&str]) -> String {
+        let docs: Vec<Document> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| doc! { "_id": i as i32, "value": *value })
+            .collect();
+        let options = InferenceOptions {
+            coerce_numeric_strings: true,
+            ..InferenceOptions::default()
+        };
+
+        let schema = SchemaInferrer::infer_schema("items", &docs, &options).unwrap();
+        schema
+            .fields
+            .into_iter()
+            .find(|f| f.name == "value")
+            .unwrap()
+            .sql_type
+    }
+
+    #[test]
+    fn test_coerce_all_numeric_strings() {
+        assert_eq!(infer_coerced_type(&["1", "42", " -7 "]), "INTEGER");
+        assert_eq!(infer_coerced_type(&["1", "3.14", "2e3"]), "REAL");
+    }
+
+    #[test]
+    fn test_coerce_mostly_numeric_strings() {
+        let mut values = vec!["1"; 9];
+        values.push("n/a");
+        assert_eq!(infer_coerced_type(&values), "INTEGER");
+
+        let mut values = vec!["1"; 8];
+        values.extend(["n/a", "unknown"]);
+        assert_eq!(infer_coerced_type(&values), "TEXT");
+    }
+
+    #[test]
+    fn test_coerce_non_numeric_strings() {
+        assert_eq!(infer_coerced_type(&["alice", "bob"]), "TEXT");
+
+        let docs = vec![doc! { "_id": 1, "value": "42" }];
+        let schema = SchemaInferrer::infer_schema("items", &docs, &InferenceOptions::default())
+            .unwrap();
+        assert_eq!(schema.fields[1].sql_type, "TEXT");
+    }
+
+    #[test]
+    fn test_commented_create_table_sql() {
+        let mut docs: Vec<Document> = (0..19).map(|i| doc! { "_id": i, "age": i }).collect();
+        docs.push(doc! { "_id": 19, "age": "unknown" });
+
+        let (schema, field_infos) =
+            SchemaInferrer::infer_schema_explained("users", &docs, &InferenceOptions::default())
+                .unwrap();
+        let sql = schema.to_commented_create_table_sql(QuoteStyle::Ansi, &field_infos);
+
+        assert_eq!(
+            sql,
+            "CREATE TABLE IF NOT EXISTS \"users\" (\n  \
+             -- column: _id (inferred INTEGER, 100% of sample)\n  \
+             \"_id\" INTEGER PRIMARY KEY,\n  \
+             -- column: age (inferred INTEGER, 95% of sample)\n  \
+             \"age\" INTEGER\n\
+             )"
+        );
+
+        // Every comment line must be a well-formed SQL line comment
+        for line in sql.lines().filter(|line| line.contains("--")) {
+            assert!(line.trim_start().starts_with("-- column: "));
+            assert!(line.ends_with("% of sample)"));
+        }
+    }
+
+    #[test]
+    fn test_json_view_sql() {
+        let docs = vec![
+            doc! { "_id": 1, "address": { "city": "Paris", "zip": "75001" }, "address_city": "x" },
+            doc! { "_id": 2, "address": { "city": "Lyon", "geo.lat": 45.7 }, "address_city": "y" },
+        ];
+        let (schema, field_infos) =
+            SchemaInferrer::infer_schema_explained("users", &docs, &InferenceOptions::default())
+                .unwrap();
+
+        assert_eq!(
+            json_view_sql(&schema, &field_infos, QuoteStyle::Ansi).unwrap(),
+            "CREATE VIEW \"users_json\" AS SELECT *, \
+             json_extract(\"address\", '$.city') AS \"address_city_2\", \
+             json_extract(\"address\", '$.zip') AS \"address_zip\", \
+             json_extract(\"address\", '$.\"geo.lat\"') AS \"address_geo.lat\" \
+             FROM \"users\""
+        );
+
+        // Tables without embedded documents get no view
+        let docs = vec![doc! { "_id": 1, "name": "Ada" }];
+        let (schema, field_infos) =
+            SchemaInferrer::infer_schema_explained("users", &docs, &InferenceOptions::default())
+                .unwrap();
+        assert!(json_view_sql(&schema, &field_infos, QuoteStyle::Ansi).is_none());
+    }
+
+    #[test]
+    fn test_json_generated_columns() {
+        let docs = vec![
+            doc! { "_id": 1, "address": { "city": "Paris", "zip": "75001" } },
+            doc! { "_id": 2, "address": { "city": "Lyon" } },
+        ];
+        let (schema, field_infos) =
+            SchemaInferrer::infer_schema_explained("users", &docs, &InferenceOptions::default())
+                .unwrap();
+        let generated = json_generated_columns(&schema, &field_infos, QuoteStyle::Ansi);
+
+        assert_eq!(
+            schema.to_create_table_sql_with_generated(
+                QuoteStyle::Ansi,
+                TextCollation::default(),
+                &generated
+            ),
+            "CREATE TABLE IF NOT EXISTS \"users\" (\n  \
+             \"_id\" INTEGER PRIMARY KEY,\n  \
+             \"address\" TEXT,\n  \
+             \"address_city\" GENERATED ALWAYS AS (json_extract(\"address\", '$.city')) VIRTUAL,\n  \
+             \"address_zip\" GENERATED ALWAYS AS (json_extract(\"address\", '$.zip')) VIRTUAL\n)"
+        );
+        // Generated columns can't take values, so inserts leave them out
+        assert_eq!(
+            schema.to_insert_sql(QuoteStyle::Ansi),
+            "INSERT INTO \"users\" (\"_id\", \"address\") VALUES (?, ?)"
+        );
+    }
+
+    #[test]
+    fn test_bson_type_rows_mixed_field() {
+        let docs = vec![
+            doc! { "_id": bson::oid::ObjectId::new(), "amount": 1 },
+            doc! { "_id": bson::oid::ObjectId::new(), "amount": Bson::Int64(2) },
+            doc! { "_id": bson::oid::ObjectId::new(), "amount": Bson::Int64(3) },
+            doc! { "_id": bson::oid::ObjectId::new(), "amount": Bson::Null },
+        ];
+
+        let (_, field_infos) =
+            SchemaInferrer::infer_schema_explained("payments", &docs, &InferenceOptions::default())
+                .unwrap();
+        let row = |column: &str, bson_type: &str, count| BsonTypeRow {
+            column: column.to_string(),
+            bson_type: bson_type.to_string(),
+            count,
+        };
+
+        assert_eq!(
+            bson_type_rows(&field_infos),
+            vec![
+                row("_id", "ObjectId", 4),
+                row("amount", "Int64", 2),
+                row("amount", "Int32", 1),
+                row("amount", "Null", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_infer_array_element_type() {
+        let docs = vec![
+            doc! { "_id": 1, "scores": [1, 2, 3], "items": [{ "sku": "a" }, { "sku": "b" }] },
+            doc! { "_id": 2, "scores": [4, Bson::Null], "items": [{ "sku": "c" }, "loose"], "name": "x" },
+        ];
+
+        let schema = SchemaInferrer::infer_schema("orders", &docs, &InferenceOptions::default()).unwrap();
+        let element_type = |name: &str| {
+            schema
+                .fields
+                .iter()
+                .find(|field| field.name == name)
+                .and_then(|field| field.element_type.as_deref())
+        };
+
+        assert_eq!(element_type("scores"), Some("INTEGER"));
+        assert_eq!(element_type("items"), Some("DOCUMENT"));
+        assert_eq!(element_type("name"), None);
+    }
+
+    #[test]
+    fn test_merge_schemas() {
+        let field = |name: &str, sql_type: &str, nullable: bool| Field {
+            name: name.to_string(),
+            sql_type: sql_type.to_string(),
+            nullable,
+            is_primary_key: name == "_id",
+            source: None,
+            element_type: None,
+            references: None,
+            mixed_key: false,
+        };
+        let events_2023 = CollectionSchema {
+            collection_name: "events_2023".to_string(),
+            fields: vec![
+                field("_id", "TEXT", false),
+                field("amount", "INTEGER", false),
+                field("kind", "TEXT", false),
+            ],
+        };
+        let events_2024 = CollectionSchema {
+            collection_name: "events_2024".to_string(),
+            fields: vec![
+                field("_id", "TEXT", false),
+                field("amount", "REAL", false),
+                field("tags", "TEXT", true),
+                field("kind", "BLOB", false),
+            ],
+        };
+
+        let merged = merge_schemas("events", &[events_2023, events_2024]);
+
+        assert_eq!(merged.collection_name, "events");
+        let columns: Vec<(&str, &str, bool, bool)> = merged
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.sql_type.as_str(), f.nullable, f.is_primary_key))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("_id", "TEXT", false, true),
+                ("amount", "REAL", false, false),
+                ("kind", "TEXT", false, false),
+                ("tags", "TEXT", true, false),
+            ]
+        );
+        assert_eq!(widen_sql_type("NULL", "INTEGER"), "INTEGER");
+    }
+
+    #[test]
+    fn test_with_id_column() {
+        let docs = vec![doc! { "_id": "u1", "name": "Ada" }];
+        let schema = SchemaInferrer::infer_schema("users", &docs, &InferenceOptions::default())
+            .unwrap()
+            .with_id_column("id")
+            .unwrap();
+
+        let create_sql = schema.to_create_table_sql(QuoteStyle::Ansi);
+        assert!(create_sql.contains("\"id\" TEXT PRIMARY KEY"));
+        assert!(!create_sql.contains("_id"));
+        assert!(schema
+            .to_insert_sql(QuoteStyle::Ansi)
+            .starts_with("INSERT INTO \"users\" (\"id\", \"name\")"));
+
+        let values = crate::converter::document_to_sql_values(
+            &docs[0],
+            &schema.fields,
+            &Default::default(),
+            &mut Default::default(),
+        );
+        assert_eq!(values[0], libsql::Value::Text("u1".to_string()));
+        assert_eq!(schema.column_for("_id"), Some("id"));
+
+        // An existing column of the same name is not silently shadowed
+        let docs = vec![doc! { "_id": 1, "ID": 2 }];
+        let schema = SchemaInferrer::infer_schema("users", &docs, &InferenceOptions::default()).unwrap();
+        assert!(schema.with_id_column("id").is_err());
+    }
+
+    #[test]
+    fn test_without_primary_key() {
+        let docs = vec![doc! { "_id": "u1", "name": "Ada" }];
+        let schema = SchemaInferrer::infer_schema("users", &docs, &InferenceOptions::default())
+            .unwrap()
+            .without_primary_key();
+
+        let create_sql = schema.to_create_table_sql(QuoteStyle::Ansi);
+        assert!(create_sql.contains("\"_id\" TEXT"));
+        assert!(!create_sql.contains("PRIMARY KEY"));
+        assert!(!create_sql.contains("NOT NULL"));
+        assert!(schema.to_upsert_sql(QuoteStyle::Ansi).is_err());
+    }
+
+    #[test]
+    fn test_with_blob_fields() {
+        let docs = vec![doc! { "_id": "u1", "avatar": "aGVsbG8=", "name": "Ada" }];
+        let blob_fields: HashSet<String> = ["avatar".to_string()].into_iter().collect();
+        let schema = SchemaInferrer::infer_schema("users", &docs, &InferenceOptions::default())
+            .unwrap()
+            .with_blob_fields(&blob_fields);
+
+        let create_sql = schema.to_create_table_sql(QuoteStyle::Ansi);
+        assert!(create_sql.contains("\"avatar\" BLOB"));
+        assert!(create_sql.contains("\"name\" TEXT"));
+    }
+
+    #[test]
+    fn test_with_lookup_fields() {
+        let docs = vec![doc! { "_id": "u1", "status": "active", "name": "Ada" }];
+        let lookup_fields: HashSet<String> = ["status".to_string()].into_iter().collect();
+        let schema = SchemaInferrer::infer_schema("users", &docs, &InferenceOptions::default())
+            .unwrap()
+            .with_lookup_fields(&lookup_fields);
+
+        let create_sql = schema.to_create_table_sql(QuoteStyle::Ansi);
+        assert!(create_sql.contains("\"status\" INTEGER REFERENCES \"users_status_lookup\" (\"id\")"));
+        assert!(create_sql.contains("\"name\" TEXT"));
+        assert_eq!(
+            lookup_table_sql("users_status_lookup", QuoteStyle::Ansi),
+            "CREATE TABLE IF NOT EXISTS \"users_status_lookup\" (\"id\" INTEGER PRIMARY KEY, \"value\" TEXT UNIQUE)"
+        );
+    }
+
+    #[test]
+    fn test_renamed_schema() {
+        let docs = vec![doc! { "_id": 1, "firstName": "Ada", "first_name": "A", "zip-code": "1" }];
+        let schema = SchemaInferrer::infer_schema("userProfiles", &docs, &InferenceOptions::default())
+            .unwrap()
+            .renamed("user_profiles", NamingStyle::Snake);
+
+        assert_eq!(schema.collection_name, "user_profiles");
+        let columns: Vec<(&str, &str)> = schema
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.source_key()))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("_id", "_id"),
+                ("first_name", "firstName"),
+                ("first_name_2", "first_name"),
+                ("zip_code", "zip-code"),
+            ]
+        );
+        assert_eq!(schema.fields[0].source, None);
+        assert_eq!(schema.column_for("zip-code"), Some("zip_code"));
+    }
+
+    #[test]
+    fn test_renamed_schema_control_characters() {
+        let docs = vec![doc! { "_id": 1, "line\nbreak": "a", "tab\there": 2, "tab_here": 3 }];
+        let schema = SchemaInferrer::infer_schema("notes", &docs, &InferenceOptions::default())
+            .unwrap()
+            .renamed("notes", NamingStyle::Preserve);
+
+        assert_eq!(schema.column_for("line\nbreak"), Some("line_break"));
+        assert_eq!(schema.column_for("tab\there"), Some("tab_here_2"));
+        assert_eq!(schema.column_for("tab_here"), Some("tab_here"));
+
+        // Values are still read from the original keys
+        let values = crate::converter::document_to_sql_values(
+            &docs[0],
+            &schema.fields,
+            &crate::converter::ConverterOptions::default(),
+            &mut HashSet::new(),
+        );
+        let index = |column: &str| schema.fields.iter().position(|field| field.name == column).unwrap();
+        assert_eq!(values[index("line_break")], libsql::Value::Text("a".to_string()));
+        assert_eq!(values[index("tab_here_2")], libsql::Value::Integer(2));
+    }
+}
@@ -0,0 +1,521 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use bson::{doc, Bson, Document, Timestamp};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use std::io::{ErrorKind, Read, Write};
+use std::sync::Mutex;
+use tracing::debug;
+
+use mongodb::change_stream::event::{ChangeStreamEvent, ResumeToken};
+
+use crate::mongodb_client::{CappedInfo, CollectionStats, MongoClient};
+
+/// A source of documents that can be migrated
+///
+/// Implemented by `MongoClient` for live databases and by `BsonStreamSource`
+/// for length-prefixed BSON read from another process.
+#[async_trait]
+pub trait DocumentSource: Send + Sync {
+    /// Sample documents from a collection for schema inference
+    async fn sample_documents(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        sample_size: usize,
+    ) -> Result<Vec<Document>>;
+
+    /// Count documents in a collection
+    async fn count_documents(&self, database_name: &str, collection_name: &str) -> Result<u64>;
+
+    /// Whether `count_documents` is the exact number of documents streamed
+    ///
+    /// Streams that can't be counted ahead only report whether they are empty.
+    fn count_is_exact(&self) -> bool {
+        true
+    }
+
+    /// Stream all documents from a collection, optionally sorted
+    async fn stream_documents(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        sort: Option<Document>,
+    ) -> Result<BoxStream<'static, Result<Document>>>;
+
+    /// Stream all documents from a collection using up to `parallelism` readers
+    ///
+    /// Documents may arrive in any order. Sources that cannot split their
+    /// reads stream with a single reader.
+    async fn stream_documents_parallel(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        _parallelism: usize,
+    ) -> Result<BoxStream<'static, Result<Document>>> {
+        self.stream_documents(database_name, collection_name, None).await
+    }
+
+    /// Stream documents of a collection in `_id` order, after a given `_id`
+    ///
+    /// Used by continuation mode (`--continue-from`), which needs a stable
+    /// order to pick up where a previous chunk stopped. Only live MongoDB
+    /// collections can be read this way; other sources fail.
+    async fn stream_documents_after(
+        &self,
+        _database_name: &str,
+        collection_name: &str,
+        _after: Option<Bson>,
+        _limit: Option<usize>,
+    ) -> Result<BoxStream<'static, Result<Document>>> {
+        anyhow::bail!(
+            "Cannot continue {} by _id: continuation tokens need a MongoDB collection as the source",
+            collection_name
+        )
+    }
+
+    /// Look up the capped-collection settings of a collection
+    async fn capped_info(
+        &self,
+        _database_name: &str,
+        _collection_name: &str,
+    ) -> Result<Option<CappedInfo>> {
+        Ok(None)
+    }
+
+    /// Look up the size statistics of a collection
+    ///
+    /// Only live MongoDB collections have statistics; other sources return `None`.
+    async fn collection_stats(
+        &self,
+        _database_name: &str,
+        _collection_name: &str,
+    ) -> Result<Option<CollectionStats>> {
+        Ok(None)
+    }
+
+    /// Read the current operation time, to start watching changes from
+    ///
+    /// Only live MongoDB deployments have one; other sources return `None`.
+    async fn operation_time(&self, _database_name: &str) -> Result<Option<Timestamp>> {
+        Ok(None)
+    }
+
+    /// Stream change events of a collection until the stream is closed
+    ///
+    /// Events start after `resume_after` if given, else at `start_at`, else
+    /// now. Only live MongoDB collections can be watched; other sources fail.
+    async fn watch_changes(
+        &self,
+        _database_name: &str,
+        collection_name: &str,
+        _resume_after: Option<ResumeToken>,
+        _start_at: Option<Timestamp>,
+    ) -> Result<BoxStream<'static, Result<ChangeStreamEvent<Document>>>> {
+        anyhow::bail!(
+            "Cannot watch {}: change streams need a MongoDB collection as the source",
+            collection_name
+        )
+    }
+}
+
+#[async_trait]
+impl DocumentSource for MongoClient {
+    async fn sample_documents(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        sample_size: usize,
+    ) -> Result<Vec<Document>> {
+        MongoClient::sample_documents(self, database_name, collection_name, sample_size).await
+    }
+
+    async fn count_documents(&self, database_name: &str, collection_name: &str) -> Result<u64> {
+        MongoClient::count_documents(self, database_name, collection_name).await
+    }
+
+    async fn stream_documents(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        sort: Option<Document>,
+    ) -> Result<BoxStream<'static, Result<Document>>> {
+        // Unsorted reads of collections with monotonic ids go page by page
+        if let (None, Some(page_size)) = (&sort, self.keyset_page_size()) {
+            if let Some(kind) = self.detect_id_kind(database_name, collection_name).await? {
+                return Ok(self.stream_documents_keyset(database_name, collection_name, kind, page_size));
+            }
+            debug!(
+                "{}.{} has no consistent ObjectId or integer _id; reading with a single cursor",
+                database_name, collection_name
+            );
+        }
+
+        let cursor =
+            MongoClient::stream_documents(self, database_name, collection_name, sort).await?;
+        Ok(cursor.map_err(anyhow::Error::from).boxed())
+    }
+
+    async fn stream_documents_parallel(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        parallelism: usize,
+    ) -> Result<BoxStream<'static, Result<Document>>> {
+        MongoClient::stream_documents_parallel(self, database_name, collection_name, parallelism)
+            .await
+    }
+
+    async fn stream_documents_after(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        after: Option<Bson>,
+        limit: Option<usize>,
+    ) -> Result<BoxStream<'static, Result<Document>>> {
+        let cursor = MongoClient::stream_documents_after(
+            self,
+            database_name,
+            collection_name,
+            after.as_ref(),
+            limit,
+        )
+        .await?;
+        Ok(cursor.map_err(anyhow::Error::from).boxed())
+    }
+
+    async fn capped_info(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+    ) -> Result<Option<CappedInfo>> {
+        MongoClient::capped_info(self, database_name, collection_name).await
+    }
+
+    async fn collection_stats(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+    ) -> Result<Option<CollectionStats>> {
+        MongoClient::collection_stats(self, database_name, collection_name)
+            .await
+            .map(Some)
+    }
+
+    async fn operation_time(&self, database_name: &str) -> Result<Option<Timestamp>> {
+        MongoClient::operation_time(self, database_name).await
+    }
+
+    async fn watch_changes(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        resume_after: Option<ResumeToken>,
+        start_at: Option<Timestamp>,
+    ) -> Result<BoxStream<'static, Result<ChangeStreamEvent<Document>>>> {
+        let stream = MongoClient::watch_collection(
+            self,
+            database_name,
+            collection_name,
+            resume_after,
+            start_at,
+        )
+        .await?;
+        Ok(stream.map_err(anyhow::Error::from).boxed())
+    }
+}
+
+/// The output of an aggregation pipeline, migrated as a single table
+///
+/// Every collection name runs the same pipeline on that collection, so it is
+/// meant for a single `--table`.
+pub struct AggregationSource {
+    client: MongoClient,
+    pipeline: Vec<Document>,
+}
+
+impl AggregationSource {
+    /// Create a source running `pipeline` on the migrated collection
+    ///
+    /// # Arguments
+    /// * `client` - Connected MongoDB client
+    /// * `pipeline` - Aggregation stages, e.g. from `--pipeline`
+    ///
+    /// # Returns
+    /// A new AggregationSource
+    pub fn new(client: MongoClient, pipeline: Vec<Document>) -> Self {
+        Self { client, pipeline }
+    }
+}
+
+#[async_trait]
+impl DocumentSource for AggregationSource {
+    async fn sample_documents(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        sample_size: usize,
+    ) -> Result<Vec<Document>> {
+        self.client
+            .sample_aggregation(database_name, collection_name, &self.pipeline, sample_size)
+            .await
+    }
+
+    async fn count_documents(&self, database_name: &str, collection_name: &str) -> Result<u64> {
+        let mut pipeline = self.pipeline.clone();
+        pipeline.push(doc! { "$count": "count" });
+
+        let mut cursor = self
+            .client
+            .stream_aggregation(database_name, collection_name, pipeline)
+            .await?;
+
+        // $count emits no document at all when the pipeline output is empty
+        let count = match cursor.try_next().await? {
+            Some(result) => match result.get("count") {
+                Some(Bson::Int32(n)) => *n as u64,
+                Some(Bson::Int64(n)) => *n as u64,
+                _ => anyhow::bail!("Unexpected $count result: {}", result),
+            },
+            None => 0,
+        };
+
+        Ok(count)
+    }
+
+    async fn stream_documents(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        sort: Option<Document>,
+    ) -> Result<BoxStream<'static, Result<Document>>> {
+        let mut pipeline = self.pipeline.clone();
+        if let Some(sort) = sort {
+            pipeline.push(doc! { "$sort": sort });
+        }
+
+        let cursor = self
+            .client
+            .stream_aggregation(database_name, collection_name, pipeline)
+            .await?;
+        Ok(cursor.map_err(anyhow::Error::from).boxed())
+    }
+}
+
+/// Documents read from a length-prefixed BSON stream (e.g. stdin)
+///
+/// The stream is read lazily and only once: schema sampling reads ahead
+/// into a buffer of at most the sample size, and data migration streams
+/// that buffer followed by the rest of the stream. Every collection name
+/// resolves to the same documents, so it is meant for a single `--table`.
+pub struct BsonStreamSource {
+    /// Documents read ahead for sampling, not yet streamed
+    prefix: Mutex<Vec<Document>>,
+    /// The unread rest of the stream, taken by `stream_documents`
+    reader: Mutex<Option<Box<dyn Read + Send>>>,
+}
+
+impl BsonStreamSource {
+    /// Wrap a length-prefixed BSON stream
+    ///
+    /// # Arguments
+    /// * `reader` - Stream of concatenated BSON documents
+    ///
+    /// # Returns
+    /// A source reading documents from the stream on demand
+    pub fn from_reader<R: Read + Send + 'static>(reader: R) -> Self {
+        Self {
+            prefix: Mutex::new(Vec::new()),
+            reader: Mutex::new(Some(Box::new(reader))),
+        }
+    }
+
+    /// Read ahead until the prefix holds `count` documents or the stream ends
+    fn fill_prefix(&self, count: usize) -> Result<std::sync::MutexGuard<'_, Vec<Document>>> {
+        let mut prefix = self.prefix.lock().unwrap_or_else(|e| e.into_inner());
+        let mut reader = self.reader.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(reader) = reader.as_mut() {
+            while prefix.len() < count {
+                match read_bson_document(reader)? {
+                    Some(doc) => prefix.push(doc),
+                    None => break,
+                }
+            }
+        }
+        Ok(prefix)
+    }
+}
+
+#[async_trait]
+impl DocumentSource for BsonStreamSource {
+    async fn sample_documents(
+        &self,
+        _database_name: &str,
+        _collection_name: &str,
+        sample_size: usize,
+    ) -> Result<Vec<Document>> {
+        let prefix = self.fill_prefix(sample_size)?;
+        Ok(prefix.iter().take(sample_size).cloned().collect())
+    }
+
+    /// A stream can't be counted without reading it, so this only tells an
+    /// empty stream (0) from one with documents (the documents read ahead)
+    async fn count_documents(&self, _database_name: &str, _collection_name: &str) -> Result<u64> {
+        Ok(self.fill_prefix(1)?.len() as u64)
+    }
+
+    fn count_is_exact(&self) -> bool {
+        false
+    }
+
+    async fn stream_documents(
+        &self,
+        _database_name: &str,
+        _collection_name: &str,
+        sort: Option<Document>,
+    ) -> Result<BoxStream<'static, Result<Document>>> {
+        if sort.is_some() {
+            debug!("Ignoring sort for BSON stream input; documents keep stream order");
+        }
+
+        let prefix = std::mem::take(&mut *self.prefix.lock().unwrap_or_else(|e| e.into_inner()));
+        let Some(reader) = self.reader.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+            anyhow::bail!("The BSON stream can only be migrated once");
+        };
+
+        let rest = stream::try_unfold(reader, |mut reader| async move {
+            Ok(read_bson_document(&mut reader)?.map(|doc| (doc, reader)))
+        });
+        Ok(stream::iter(prefix.into_iter().map(Ok)).chain(rest).boxed())
+    }
+}
+
+/// Write a document as length-prefixed BSON
+///
+/// BSON documents start with their own little-endian int32 length, so
+/// documents written back to back form a self-delimiting stream.
+///
+/// # Arguments
+/// * `writer` - Destination stream
+/// * `doc` - The document to write
+pub fn write_bson_document<W: Write>(writer: &mut W, doc: &Document) -> Result<()> {
+    doc.to_writer(writer)?;
+    Ok(())
+}
+
+/// Read the next length-prefixed BSON document from a stream
+///
+/// # Arguments
+/// * `reader` - Source stream
+///
+/// # Returns
+/// The next document, or `None` at a clean end of stream
+pub fn read_bson_document<R: Read>(reader: &mut R) -> Result<Option<Document>> {
+    // Only a stream ending before the first length byte ends cleanly; a
+    // partial length prefix is a truncated document
+    let mut length_bytes = [0u8; 4];
+    let mut filled = 0;
+    while filled < length_bytes.len() {
+        match reader.read(&mut length_bytes[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => anyhow::bail!(
+                "BSON stream ended inside a document length ({} of 4 bytes)",
+                filled
+            ),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let length = i32::from_le_bytes(length_bytes);
+    if length < 5 {
+        anyhow::bail!("Invalid BSON document length {} in stream", length);
+    }
+
+    let mut bytes = length_bytes.to_vec();
+    bytes.resize(length as usize, 0);
+    reader.read_exact(&mut bytes[4..])?;
+
+    Ok(Some(Document::from_reader(bytes.as_slice())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn sample_docs() -> Vec<Document> {
+        vec![
+            doc! { "_id": 1, "name": "Alice" },
+            doc! { "_id": 2, "name": "Bob", "tags": ["a", "b"] },
+            doc! { "_id": 3, "profile": { "age": 30 } },
+        ]
+    }
+
+    #[test]
+    fn test_bson_stream_round_trip() {
+        let mut buffer = Vec::new();
+        for doc in sample_docs() {
+            write_bson_document(&mut buffer, &doc).unwrap();
+        }
+
+        let mut reader = Cursor::new(buffer);
+        let mut read_back = Vec::new();
+        while let Some(doc) = read_bson_document(&mut reader).unwrap() {
+            read_back.push(doc);
+        }
+
+        assert_eq!(read_back, sample_docs());
+    }
+
+    #[test]
+    fn test_read_truncated_bson_stream() {
+        let mut buffer = Vec::new();
+        write_bson_document(&mut buffer, &sample_docs()[0]).unwrap();
+        buffer.truncate(buffer.len() - 2);
+
+        assert!(read_bson_document(&mut Cursor::new(buffer)).is_err());
+    }
+
+    #[test]
+    fn test_read_truncated_bson_length() {
+        let mut buffer = Vec::new();
+        write_bson_document(&mut buffer, &sample_docs()[0]).unwrap();
+        buffer.extend_from_slice(&[0x10, 0x00]);
+
+        let mut reader = Cursor::new(buffer);
+        assert!(read_bson_document(&mut reader).unwrap().is_some());
+        assert!(read_bson_document(&mut reader).is_err());
+
+        assert!(read_bson_document(&mut Cursor::new(Vec::new())).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bson_stream_source() {
+        let mut buffer = Vec::new();
+        for doc in sample_docs() {
+            write_bson_document(&mut buffer, &doc).unwrap();
+        }
+
+        let source = BsonStreamSource::from_reader(Cursor::new(buffer));
+        assert!(!source.count_is_exact());
+        assert_eq!(source.count_documents("db", "users").await.unwrap(), 1);
+        assert_eq!(source.sample_documents("db", "users", 2).await.unwrap().len(), 2);
+        // Sampling only reads ahead as far as the sample
+        assert_eq!(source.prefix.lock().unwrap().len(), 2);
+
+        let streamed: Vec<Document> = source
+            .stream_documents("db", "users", None)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(streamed, sample_docs());
+        assert!(source.stream_documents("db", "users", None).await.is_err());
+
+        let empty = BsonStreamSource::from_reader(Cursor::new(Vec::new()));
+        assert_eq!(empty.count_documents("db", "users").await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,518 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use libsql::Value;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::libsql_client::{
+    integrity_problems, pragma_setting, remote_configured, Heartbeat, LibSqlClient, SavedPragmas,
+    FAST_LOAD_PRAGMAS,
+};
+
+/// Driver used to write the SQLite database
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Backend {
+    /// libsql, for local files and Turso
+    #[default]
+    LibSql,
+    /// rusqlite, for local files only
+    Rusqlite,
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "libsql" => Ok(Backend::LibSql),
+            "rusqlite" => Ok(Backend::Rusqlite),
+            other => bail!("Unknown backend '{}' (expected libsql or rusqlite)", other),
+        }
+    }
+}
+
+/// A row returned by [`SqliteBackend::query`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row(Vec<Value>);
+
+impl Row {
+    /// Get a column value
+    ///
+    /// # Arguments
+    /// * `index` - Zero-based column index
+    ///
+    /// # Returns
+    /// The value, or an error if the row has no such column
+    pub fn value(&self, index: usize) -> Result<&Value> {
+        match self.0.get(index) {
+            Some(value) => Ok(value),
+            None => bail!("Row has no column {}", index),
+        }
+    }
+
+    /// Get an INTEGER column value
+    pub fn integer(&self, index: usize) -> Result<i64> {
+        match self.value(index)? {
+            Value::Integer(i) => Ok(*i),
+            other => bail!("Column {} is not an integer: {:?}", index, other),
+        }
+    }
+
+    /// Get a TEXT column value
+    pub fn text(&self, index: usize) -> Result<String> {
+        self.optional_text(index)?
+            .ok_or_else(|| anyhow::anyhow!("Column {} is NULL", index))
+    }
+
+    /// Get a TEXT column value that may be NULL
+    pub fn optional_text(&self, index: usize) -> Result<Option<String>> {
+        match self.value(index)? {
+            Value::Text(s) => Ok(Some(s.clone())),
+            Value::Null => Ok(None),
+            other => bail!("Column {} is not text: {:?}", index, other),
+        }
+    }
+}
+
+/// Operations the migrator needs from a SQLite driver
+///
+/// Query results are read into memory, which suits the metadata and
+/// statistics queries the migrator runs; documents are only ever written.
+#[async_trait]
+pub trait SqliteBackend: Send + Sync {
+    /// Execute a SQL statement without returning results
+    ///
+    /// # Returns
+    /// Number of rows affected
+    async fn execute(&self, sql: &str) -> Result<u64>;
+
+    /// Execute a SQL statement with `?` placeholders bound to `params`
+    ///
+    /// # Returns
+    /// Number of rows affected
+    async fn execute_with_params(&self, sql: &str, params: Vec<Value>) -> Result<u64>;
+
+    /// Execute one statement once per parameter set
+    ///
+    /// Drivers override this to prepare the statement only once.
+    ///
+    /// # Returns
+    /// Total number of rows affected
+    async fn execute_many(&self, sql: &str, rows: &[Vec<Value>]) -> Result<u64> {
+        let mut total_affected = 0;
+        for values in rows {
+            total_affected += self.execute_with_params(sql, values.clone()).await?;
+        }
+        Ok(total_affected)
+    }
+
+    /// Run a query with `?` placeholders bound to `params`
+    ///
+    /// # Returns
+    /// All result rows
+    async fn query_with_params(&self, sql: &str, params: Vec<Value>) -> Result<Vec<Row>>;
+
+    /// Check if the database is a local file
+    fn is_local(&self) -> bool;
+
+    /// Run a query without parameters
+    ///
+    /// # Returns
+    /// All result rows
+    async fn query(&self, sql: &str) -> Result<Vec<Row>> {
+        self.query_with_params(sql, Vec::new()).await
+    }
+
+    /// Roll back the open transaction after a failed statement
+    ///
+    /// A failing ROLLBACK is only logged, see [`LibSqlClient::rollback`].
+    async fn rollback(&self) {
+        if let Err(e) = self.execute("ROLLBACK").await {
+            warn!("ROLLBACK failed, the transaction may already be rolled back: {}", e);
+        }
+    }
+
+    /// Start a background task that keeps a remote connection warm
+    ///
+    /// # Returns
+    /// The heartbeat handle, or `None` if the driver has no use for one
+    fn start_heartbeat(&self, _interval: Duration) -> Option<Heartbeat> {
+        None
+    }
+
+    /// List the column names of a table with their declared types
+    ///
+    /// # Returns
+    /// `(name, declared type)` pairs in table order, or an empty list if the
+    /// table doesn't exist
+    async fn table_column_types(&self, table: &str) -> Result<Vec<(String, String)>> {
+        let sql = format!("PRAGMA table_info(\"{}\")", table.replace('"', "\"\""));
+        self.query(&sql)
+            .await?
+            .iter()
+            .map(|row| Ok((row.text(1)?, row.text(2)?)))
+            .collect()
+    }
+
+    /// Execute statements in a transaction, rolling back if one fails
+    ///
+    /// # Returns
+    /// Total number of rows affected
+    async fn execute_batch(&self, statements: Vec<String>) -> Result<u64> {
+        self.execute("BEGIN TRANSACTION").await?;
+        let mut affected = 0;
+        for statement in &statements {
+            match self.execute(statement).await {
+                Ok(rows) => affected += rows,
+                Err(e) => {
+                    self.rollback().await;
+                    return Err(e);
+                }
+            }
+        }
+        self.execute("COMMIT").await?;
+        Ok(affected)
+    }
+
+    /// Check whether a table exists
+    async fn table_exists(&self, table: &str) -> Result<bool> {
+        let rows = self
+            .query_with_params(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?",
+                vec![Value::Text(table.to_string())],
+            )
+            .await?;
+        Ok(!rows.is_empty())
+    }
+
+    /// List the column names of a table
+    async fn table_columns(&self, table: &str) -> Result<Vec<String>> {
+        let columns = self.table_column_types(table).await?;
+        Ok(columns.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// Run `PRAGMA integrity_check` on the database
+    ///
+    /// # Returns
+    /// The problems reported, empty if the database is intact
+    async fn integrity_check(&self) -> Result<Vec<String>> {
+        let results = self
+            .query("PRAGMA integrity_check")
+            .await?
+            .iter()
+            .map(|row| row.text(0))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(integrity_problems(results))
+    }
+
+    /// Speed up a bulk load into a local file, see [`LibSqlClient::enable_fast_load`]
+    async fn enable_fast_load(&self) -> Result<SavedPragmas> {
+        let mut saved = SavedPragmas::default();
+        if !self.is_local() {
+            warn!("Ignoring --fast-load: only local database files can be tuned");
+            return Ok(saved);
+        }
+
+        warn!("Fast load enabled: a crash during the load can corrupt the output file");
+        for (name, value) in FAST_LOAD_PRAGMAS {
+            if let Some(row) = self.query(&format!("PRAGMA {}", name)).await?.first() {
+                saved.push(name, pragma_setting(name, row.value(0)?)?);
+            }
+            self.query(&format!("PRAGMA {} = {}", name, value)).await?;
+        }
+        Ok(saved)
+    }
+
+    /// Restore the settings `enable_fast_load` changed
+    async fn finish_fast_load(&self, saved: &SavedPragmas) -> Result<()> {
+        for statement in saved.restore_statements() {
+            self.query(&statement).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SqliteBackend for LibSqlClient {
+    async fn execute(&self, sql: &str) -> Result<u64> {
+        LibSqlClient::execute(self, sql).await
+    }
+
+    async fn execute_with_params(&self, sql: &str, params: Vec<Value>) -> Result<u64> {
+        LibSqlClient::execute_with_params(self, sql, libsql::params_from_iter(params)).await
+    }
+
+    async fn execute_many(&self, sql: &str, rows: &[Vec<Value>]) -> Result<u64> {
+        self.execute_prepared(sql, rows).await
+    }
+
+    async fn query_with_params(&self, sql: &str, params: Vec<Value>) -> Result<Vec<Row>> {
+        let mut rows = LibSqlClient::query_with_params(self, sql, libsql::params_from_iter(params)).await?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let values = (0..rows.column_count())
+                .map(|index| row.get_value(index))
+                .collect::<libsql::Result<Vec<_>>>()?;
+            result.push(Row(values));
+        }
+        Ok(result)
+    }
+
+    fn is_local(&self) -> bool {
+        LibSqlClient::is_local(self)
+    }
+
+    async fn rollback(&self) {
+        LibSqlClient::rollback(self).await
+    }
+
+    async fn execute_batch(&self, statements: Vec<String>) -> Result<u64> {
+        LibSqlClient::execute_batch(self, statements).await
+    }
+
+    fn start_heartbeat(&self, interval: Duration) -> Option<Heartbeat> {
+        Some(LibSqlClient::start_heartbeat(self, interval))
+    }
+}
+
+/// rusqlite client for local SQLite files
+///
+/// rusqlite is synchronous, so statements run on tokio's blocking thread
+/// pool and don't stall the runtime's worker threads.
+pub struct RusqliteClient {
+    connection: Arc<Mutex<rusqlite::Connection>>,
+}
+
+impl RusqliteClient {
+    /// Open or create a local SQLite file
+    ///
+    /// # Arguments
+    /// * `output_path` - Path of the SQLite file, `output.db` if not given
+    ///
+    /// # Returns
+    /// A new RusqliteClient instance
+    pub fn new(output_path: Option<&str>) -> Result<Self> {
+        let path = output_path.unwrap_or("output.db");
+        info!("Using local SQLite file via rusqlite: {}", path);
+
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(rusqlite::Connection::open(path)?)),
+        })
+    }
+
+    /// Run a closure on the connection in a blocking thread
+    ///
+    /// # Arguments
+    /// * `f` - Work to do with the locked connection
+    ///
+    /// # Returns
+    /// The closure's result
+    async fn with_connection<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&rusqlite::Connection) -> Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            // A panic while holding the lock leaves the connection usable
+            let connection = connection.lock().unwrap_or_else(|e| e.into_inner());
+            f(&connection)
+        })
+        .await?
+    }
+}
+
+/// Convert a libsql value into a rusqlite parameter
+fn to_rusqlite(value: Value) -> rusqlite::types::Value {
+    match value {
+        Value::Null => rusqlite::types::Value::Null,
+        Value::Integer(i) => rusqlite::types::Value::Integer(i),
+        Value::Real(r) => rusqlite::types::Value::Real(r),
+        Value::Text(s) => rusqlite::types::Value::Text(s),
+        Value::Blob(b) => rusqlite::types::Value::Blob(b),
+    }
+}
+
+/// Convert a rusqlite column value into a libsql value
+fn from_rusqlite(value: rusqlite::types::ValueRef<'_>) -> Value {
+    match value {
+        rusqlite::types::ValueRef::Null => Value::Null,
+        rusqlite::types::ValueRef::Integer(i) => Value::Integer(i),
+        rusqlite::types::ValueRef::Real(r) => Value::Real(r),
+        rusqlite::types::ValueRef::Text(s) => Value::Text(String::from_utf8_lossy(s).into_owned()),
+        rusqlite::types::ValueRef::Blob(b) => Value::Blob(b.to_vec()),
+    }
+}
+
+#[async_trait]
+impl SqliteBackend for RusqliteClient {
+    async fn execute(&self, sql: &str) -> Result<u64> {
+        self.execute_with_params(sql, Vec::new()).await
+    }
+
+    async fn execute_with_params(&self, sql: &str, params: Vec<Value>) -> Result<u64> {
+        debug!("Executing SQL: {}", sql);
+        let sql = sql.to_string();
+        let params: Vec<_> = params.into_iter().map(to_rusqlite).collect();
+        self.with_connection(move |connection| {
+            Ok(connection.execute(&sql, rusqlite::params_from_iter(params))? as u64)
+        })
+        .await
+    }
+
+    async fn execute_many(&self, sql: &str, rows: &[Vec<Value>]) -> Result<u64> {
+        debug!("Executing SQL for {} row(s): {}", rows.len(), sql);
+        let sql = sql.to_string();
+        let rows: Vec<Vec<_>> = rows
+            .iter()
+            .map(|values| values.iter().cloned().map(to_rusqlite).collect())
+            .collect();
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare_cached(&sql)?;
+
+            let mut total_affected = 0;
+            for values in rows {
+                total_affected += statement.execute(rusqlite::params_from_iter(values))? as u64;
+            }
+            Ok(total_affected)
+        })
+        .await
+    }
+
+    async fn query_with_params(&self, sql: &str, params: Vec<Value>) -> Result<Vec<Row>> {
+        debug!("Querying: {}", sql);
+        let sql = sql.to_string();
+        let params: Vec<_> = params.into_iter().map(to_rusqlite).collect();
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(&sql)?;
+            let column_count = statement.column_count();
+
+            let mut rows = statement.query(rusqlite::params_from_iter(params))?;
+            let mut result = Vec::new();
+            while let Some(row) = rows.next()? {
+                let values = (0..column_count)
+                    .map(|index| row.get_ref(index).map(from_rusqlite))
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                result.push(Row(values));
+            }
+            Ok(result)
+        })
+        .await
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+}
+
+/// Connect to the target database with the selected driver
+///
+/// # Arguments
+/// * `backend` - Driver to use
+/// * `output_path` - Path for a local SQLite file
+///
+/// # Returns
+/// The connected backend
+pub async fn connect_backend(backend: Backend, output_path: Option<&str>) -> Result<Box<dyn SqliteBackend>> {
+    match backend {
+        Backend::LibSql => Ok(Box::new(LibSqlClient::new(output_path).await?)),
+        Backend::Rusqlite => {
+            if remote_configured() {
+                bail!("--backend rusqlite only writes local files; unset TURSO_DATABASE_URL to use it");
+            }
+            Ok(Box::new(RusqliteClient::new(output_path)?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_backend_from_str() {
+        assert_eq!("libsql".parse::<Backend>().unwrap(), Backend::LibSql);
+        assert_eq!("Rusqlite".parse::<Backend>().unwrap(), Backend::Rusqlite);
+        assert!("postgres".parse::<Backend>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rusqlite_create_and_query() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let backend: Box<dyn SqliteBackend> =
+            Box::new(RusqliteClient::new(temp_file.path().to_str()).unwrap());
+
+        backend
+            .execute("CREATE TABLE test (id INTEGER PRIMARY KEY, name TEXT, score REAL)")
+            .await
+            .unwrap();
+        let inserted = backend
+            .execute_with_params(
+                "INSERT INTO test (id, name, score) VALUES (?, ?, ?)",
+                vec![Value::Integer(1), Value::Text("Alice".to_string()), Value::Real(2.5)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(inserted, 1);
+        backend
+            .execute_with_params(
+                "INSERT INTO test (id, name, score) VALUES (?, ?, ?)",
+                vec![Value::Integer(2), Value::Null, Value::Null],
+            )
+            .await
+            .unwrap();
+
+        let rows = backend
+            .query_with_params("SELECT id, name, score FROM test WHERE id >= ? ORDER BY id", vec![Value::Integer(1)])
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].integer(0).unwrap(), 1);
+        assert_eq!(rows[0].text(1).unwrap(), "Alice");
+        assert_eq!(rows[0].value(2).unwrap(), &Value::Real(2.5));
+        assert_eq!(rows[1].optional_text(1).unwrap(), None);
+
+        assert_eq!(
+            backend.table_column_types("test").await.unwrap(),
+            vec![
+                ("id".to_string(), "INTEGER".to_string()),
+                ("name".to_string(), "TEXT".to_string()),
+                ("score".to_string(), "REAL".to_string()),
+            ]
+        );
+        assert!(backend.integrity_check().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rusqlite_fast_load_and_rollback() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let backend = RusqliteClient::new(temp_file.path().to_str()).unwrap();
+
+        backend.query("PRAGMA synchronous = NORMAL").await.unwrap();
+        let saved = backend.enable_fast_load().await.unwrap();
+        let rows = backend.query("PRAGMA synchronous").await.unwrap();
+        assert_eq!(rows[0].integer(0).unwrap(), 0);
+        backend.finish_fast_load(&saved).await.unwrap();
+        let rows = backend.query("PRAGMA synchronous").await.unwrap();
+        assert_eq!(rows[0].integer(0).unwrap(), 1);
+        let rows = backend.query("PRAGMA journal_mode").await.unwrap();
+        assert_eq!(rows[0].text(0).unwrap(), "delete");
+
+        backend.execute("CREATE TABLE test (id INTEGER)").await.unwrap();
+        backend.execute("BEGIN TRANSACTION").await.unwrap();
+        backend.execute("INSERT INTO test VALUES (1)").await.unwrap();
+        backend.rollback().await;
+        let rows = backend.query("SELECT COUNT(*) FROM test").await.unwrap();
+        assert_eq!(rows[0].integer(0).unwrap(), 0);
+    }
+}